@@ -0,0 +1,69 @@
+//! Compares two workload specs, e.g. the neighbouring points of a parameter sweep.
+use anyhow::{Context, Result};
+use std::path::Path;
+use workload_gen::spec::{SpecFormat, WorkloadSpec};
+
+/// Parses the specs at `a_path` and `b_path` and prints how the operations of every type, the
+/// bytes, and the groups of every section change from `a` to `b`.
+pub fn diff_specs(a_path: &Path, b_path: &Path) -> Result<()> {
+    let (a, b) = (parse(a_path)?, parse(b_path)?);
+    let row = |name: &str, a: usize, b: usize| {
+        println!("{name}: {a} -> {b} ({:+})", b as i128 - a as i128);
+    };
+
+    println!("{} -> {}", a_path.display(), b_path.display());
+    row("ops", a.operation_count(), b.operation_count());
+    row("bytes", a.bytes_count(), b.bytes_count());
+    let (a_counts, b_counts) = (a.operation_counts(), b.operation_counts());
+    row("inserts", a_counts.inserts, b_counts.inserts);
+    row("updates", a_counts.updates, b_counts.updates);
+    row("deletes", a_counts.deletes, b_counts.deletes);
+    row(
+        "point queries",
+        a_counts.point_queries,
+        b_counts.point_queries,
+    );
+    row(
+        "empty point queries",
+        a_counts.empty_point_queries,
+        b_counts.empty_point_queries,
+    );
+    row(
+        "range queries",
+        a_counts.range_queries,
+        b_counts.range_queries,
+    );
+    row("seek queries", a_counts.seek_queries, b_counts.seek_queries);
+    row(
+        "range deletes",
+        a_counts.range_deletes,
+        b_counts.range_deletes,
+    );
+    row("think times", a_counts.think_times, b_counts.think_times);
+    row("flushes", a_counts.flushes, b_counts.flushes);
+    row(
+        "read-modify-writes",
+        a_counts.read_modify_writes,
+        b_counts.read_modify_writes,
+    );
+
+    let (a_groups, b_groups) = (a.group_counts(), b.group_counts());
+    row("sections", a_groups.len(), b_groups.len());
+    for i in 0..a_groups.len().max(b_groups.len()) {
+        match (a_groups.get(i), b_groups.get(i)) {
+            (Some(&a), Some(&b)) => row(&format!("section {i} groups"), a, b),
+            (Some(a), None) => println!("section {i}: removed, with {a} groups"),
+            (None, Some(b)) => println!("section {i}: added, with {b} groups"),
+            (None, None) => unreachable!("section {i} is in neither spec"),
+        }
+    }
+
+    return Ok(());
+}
+
+fn parse(path: &Path) -> Result<WorkloadSpec> {
+    let contents = crate::read_spec(path)?;
+    return SpecFormat::from_path(path)
+        .parse(&contents)
+        .with_context(|| format!("parsing spec {}", path.display()));
+}
@@ -0,0 +1,239 @@
+//! Summarizes an existing ASCII workload file.
+use anyhow::{bail, Result};
+use log::warn;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::BufReader;
+use std::path::Path;
+use workload_gen::writer::{AsciiFormat, AsciiReader, Footer, Header};
+
+/// Malformed lines reported individually, the rest are only counted.
+const MAX_REPORTED_MALFORMED: usize = 10;
+
+/// Distinct keys counted exactly, beyond this they are estimated to bound the memory of large
+/// workloads.
+const MAX_EXACT_DISTINCT_KEYS: usize = 1 << 20;
+
+/// Bits of a key hash that select a HyperLogLog register.
+const HLL_PRECISION: u32 = 14;
+
+/// Number of distinct keys, exact up to [`MAX_EXACT_DISTINCT_KEYS`] and a HyperLogLog estimate
+/// with an error of about 1% beyond.
+enum DistinctKeys {
+    Exact(HashSet<Vec<u8>>),
+    Estimated(Vec<u8>),
+}
+
+impl DistinctKeys {
+    fn insert(&mut self, key: &[u8]) {
+        match self {
+            DistinctKeys::Exact(keys) => {
+                if !keys.contains(key) {
+                    keys.insert(key.to_vec());
+                }
+                if keys.len() > MAX_EXACT_DISTINCT_KEYS {
+                    let mut registers = vec![0; 1 << HLL_PRECISION];
+                    for key in keys.iter() {
+                        hll_insert(&mut registers, key);
+                    }
+                    *self = DistinctKeys::Estimated(registers);
+                }
+            }
+            DistinctKeys::Estimated(registers) => hll_insert(registers, key),
+        }
+    }
+
+    /// The count, prefixed with `~` if it is an estimate.
+    fn display(&self) -> String {
+        let registers = match self {
+            DistinctKeys::Exact(keys) => return keys.len().to_string(),
+            DistinctKeys::Estimated(registers) => registers,
+        };
+        let m = registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = registers.iter().map(|&r| 2f64.powi(-i32::from(r))).sum();
+        // Estimates start above a million keys, far above the range that needs a correction.
+        return format!("~{:.0}", alpha * m * m / sum);
+    }
+}
+
+/// Records `key` in the register its hash selects, as the longest run of leading zeros seen there.
+fn hll_insert(registers: &mut [u8], key: &[u8]) {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    let hash = hasher.finish();
+    let idx = (hash >> (64 - HLL_PRECISION)) as usize;
+    let rank = ((hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1))).leading_zeros() + 1;
+    registers[idx] = registers[idx].max(rank as u8);
+}
+
+/// Operation counts of a workload, by leading marker.
+#[derive(Default)]
+struct OpCounts {
+    inserts: usize,
+    updates: usize,
+    deletes: usize,
+    range_deletes: usize,
+    point_queries: usize,
+    range_queries: usize,
+    seek_queries: usize,
+    think_times: usize,
+    flushes: usize,
+}
+
+/// Scans the workload at `workload_path`, written with the separators of `format`, and prints the
+/// number of operations of every type, the number of distinct keys, the longest key and value, the
+/// total bytes, and the line numbers of malformed lines. Fails if a footer doesn't match the
+/// operations before it, e.g. of a truncated or corrupted copy, or if a key or value is longer
+/// than its header allows.
+pub fn inspect(workload_path: &Path, format: AsciiFormat) -> Result<()> {
+    let mut reader = BufReader::new(File::open(workload_path)?);
+    let ascii_reader = AsciiReader::new(format);
+
+    let mut counts = OpCounts::default();
+    let mut keys = DistinctKeys::Exact(HashSet::new());
+    let mut malformed = 0;
+    // Appended workloads have one footer each, covering the lines since the previous one.
+    let mut footers = 0;
+    let mut footer_ops = 0;
+    let mut hasher = crc32fast::Hasher::new();
+    // Appended workloads have one header each, covering the lines until the next one.
+    let mut headers = 0;
+    let mut header: Option<Header> = None;
+    let mut max_key_len = 0;
+    let mut max_val_len = 0;
+    let mut column_families: HashSet<Vec<u8>> = HashSet::new();
+    let mut line = Vec::new();
+    let mut line_number = 0;
+    while ascii_reader.read_record(&mut reader, &mut line)? {
+        line_number += 1;
+        if let Some(footer) = Footer::parse(&line) {
+            let found = Footer {
+                ops: footer_ops,
+                crc: hasher.finalize(),
+            };
+            if found != footer {
+                bail!(
+                    "Footer on line {} doesn't match the workload: expected `{}`, found `{}`",
+                    line_number,
+                    footer,
+                    found
+                );
+            }
+            footers += 1;
+            footer_ops = 0;
+            hasher = crc32fast::Hasher::new();
+            continue;
+        }
+        hasher.update(&line);
+        hasher.update(&[format.record_sep]);
+        if let Some(found) = Header::parse(&line) {
+            headers += 1;
+            header = Some(found);
+            continue;
+        }
+        // Comments, e.g. the fences around warmup operations, aren't operations.
+        if line.starts_with(b"#") {
+            continue;
+        }
+        let fields = ascii_reader.split_fields(&line);
+        // Column family directives route the operations after them, they aren't operations.
+        if let [cf, name] = fields.as_slice() {
+            if cf == b"CF" {
+                column_families.insert(name.clone());
+                continue;
+            }
+        }
+
+        // Count range queries end in `+count` instead of a key.
+        let (count, key_fields) = match (fields[0].as_slice(), fields.len()) {
+            (b"I", 3) => (&mut counts.inserts, 1),
+            (b"U", 3) => (&mut counts.updates, 1),
+            (b"D", 2) => (&mut counts.deletes, 1),
+            (b"DR", 3) => (&mut counts.range_deletes, 2),
+            (b"P", 2) => (&mut counts.point_queries, 1),
+            (b"SK", 2) => (&mut counts.seek_queries, 1),
+            (b"R", 3) if fields[2].starts_with(b"+") => (&mut counts.range_queries, 1),
+            (b"R", 3) => (&mut counts.range_queries, 2),
+            (b"T", 2) => (&mut counts.think_times, 0),
+            (b"F", 1) => (&mut counts.flushes, 0),
+            _ => {
+                malformed += 1;
+                if malformed <= MAX_REPORTED_MALFORMED {
+                    warn!("Malformed operation on line {line_number}");
+                }
+                continue;
+            }
+        };
+        *count += 1;
+        footer_ops += 1;
+        let key_len = fields[1..=key_fields]
+            .iter()
+            .map(|key| key.len())
+            .max()
+            .unwrap_or(0);
+        let val_len = match fields[0].as_slice() {
+            b"I" | b"U" => fields[2].len(),
+            _ => 0,
+        };
+        if let Some(header) = header {
+            if key_len > header.max_key || val_len > header.max_val {
+                bail!(
+                    "Operation on line {} has a key of {} and a value of {} bytes, longer than its header `{}`",
+                    line_number,
+                    key_len,
+                    val_len,
+                    header
+                );
+            }
+        }
+        max_key_len = max_key_len.max(key_len);
+        max_val_len = max_val_len.max(val_len);
+        for key in &fields[1..=key_fields] {
+            keys.insert(key);
+        }
+    }
+
+    let bytes = fs::metadata(workload_path)?.len();
+    let op_count = counts.inserts
+        + counts.updates
+        + counts.deletes
+        + counts.range_deletes
+        + counts.point_queries
+        + counts.range_queries
+        + counts.seek_queries
+        + counts.think_times
+        + counts.flushes;
+    println!(
+        "{}: {} ops, {} bytes",
+        workload_path.display(),
+        op_count,
+        bytes
+    );
+    println!("inserts: {}", counts.inserts);
+    println!("updates: {}", counts.updates);
+    println!("deletes: {}", counts.deletes);
+    println!("range deletes: {}", counts.range_deletes);
+    println!("point queries: {}", counts.point_queries);
+    println!("range queries: {}", counts.range_queries);
+    println!("seek queries: {}", counts.seek_queries);
+    println!("think times: {}", counts.think_times);
+    println!("flushes: {}", counts.flushes);
+    println!("distinct keys: {}", keys.display());
+    println!("longest key: {max_key_len}");
+    println!("longest value: {max_val_len}");
+    println!("malformed lines: {malformed}");
+    if !column_families.is_empty() {
+        println!("column families: {}", column_families.len());
+    }
+    if footers > 0 {
+        println!("verified footers: {footers}");
+    }
+    if headers > 0 {
+        println!("verified headers: {headers}");
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,41 @@
+//! Checks specs without generating them, e.g. every spec of a sweep before a long run.
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+use workload_gen::spec::SpecFormat;
+use workload_gen::validate_workload_spec_value;
+
+/// Parses and validates the spec at `path`, or every spec of the folder at `path` as found by
+/// `generate`, printing the problems of every failing spec. Fails if any spec does.
+pub fn lint(path: &Path) -> Result<()> {
+    let spec_paths: Vec<PathBuf> = if path.is_dir() {
+        crate::spec_files(path)
+            .map(|file| file.into_path())
+            .collect()
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut failed = 0;
+    for spec_path in &spec_paths {
+        if let Err(err) = lint_spec(spec_path) {
+            failed += 1;
+            println!("{}: {err:#}", spec_path.display());
+        }
+    }
+    println!("{} specs, {failed} failed", spec_paths.len());
+
+    if failed > 0 {
+        bail!("{failed} of {} specs failed", spec_paths.len());
+    }
+    return Ok(());
+}
+
+/// Checks the spec against the schema, which reports every mismatch at once, then against the
+/// checks of [`workload_gen::spec::WorkloadSpec::validate`].
+fn lint_spec(path: &Path) -> Result<()> {
+    let contents = crate::read_spec(path)?;
+    let format = SpecFormat::from_path(path);
+    validate_workload_spec_value(&format.to_json(&contents)?)?;
+    format.parse(&contents)?.validate()?;
+    return Ok(());
+}
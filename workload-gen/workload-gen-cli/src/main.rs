@@ -1,52 +1,605 @@
 #![allow(clippy::needless_return)]
+mod diff;
+mod inspect;
+mod lint;
+#[cfg(feature = "replay")]
+mod replay;
+
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
+use flate2::read::GzDecoder;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{info, log_enabled, Level, LevelFilter};
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::{fs, path::PathBuf};
-use walkdir::WalkDir;
-use workload_gen::{generate_workload, generate_workload_spec_schema};
+use walkdir::{DirEntry, WalkDir};
+use workload_gen::presets::Ycsb;
+use workload_gen::spec::SpecFormat;
+use workload_gen::writer::{
+    convert, AsciiFormat, CountingWriter, FieldEscaping, MmapWriter, OutputFormat,
+};
+use workload_gen::{
+    generate_phases_into, generate_shards_into, generate_workload_into,
+    generate_workload_spec_schema, GenerateOptions, WorkloadStats,
+};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Command,
+
+    /// Only log warnings and errors. Otherwise progress is logged to stderr, filtered by
+    /// `RUST_LOG`.
+    #[arg(short = 'q', long = "quiet", global = true)]
+    quiet: bool,
 }
 
 #[derive(Debug, Subcommand)]
 enum Command {
     /// Generate workload(s) from a file or folder of workload specifications.
     Generate {
-        /// File or folder of workload spec files
+        /// File or folder of workload spec files, in json, toml, or yaml by their extension and
+        /// optionally gzipped, or `-` to read a single json spec from stdin.
         #[arg(short = 'w', long = "workload")]
         workload_path: String,
 
-        /// Output folder for workloads.
+        /// Output folder for workloads, or the output file when reading the spec from stdin. `-`
+        /// writes a single workload to stdout.
         #[arg(short = 'o', long = "output")]
         output: Option<String>,
+
+        /// Output format of the workloads, `ascii`, `jsonl`, or `msgpack`.
+        #[arg(short = 'f', long = "format", default_value = "ascii")]
+        format: OutputFormat,
+
+        /// Separator between the fields of an ascii operation, e.g. `\t` for tab separated output.
+        #[arg(long = "field-sep", default_value = " ", value_parser = parse_separator)]
+        field_sep: u8,
+
+        /// Separator after every ascii operation, e.g. `\0` for null delimited records.
+        #[arg(long = "record-sep", default_value = "\\n", value_parser = parse_separator)]
+        record_sep: u8,
+
+        /// Escape separators and backslashes in ascii keys and values with a backslash, e.g. for
+        /// custom charsets with a space. Otherwise such keys are an error.
+        #[arg(long = "escape-separators")]
+        escape_separators: bool,
+
+        /// Number of threads used to generate large insert-only groups.
+        #[arg(short = 't', long = "threads", default_value_t = 1)]
+        threads: usize,
+
+        /// Stop after this many operations, e.g. for quick smoke tests.
+        #[arg(long = "max-ops")]
+        max_ops: Option<usize>,
+
+        /// Validate specs against the workload spec schema, reporting the path of every error.
+        #[arg(long = "validate")]
+        validate: bool,
+
+        /// Write the workloads of every spec, in file name order, into the single `--output` file.
+        #[arg(long = "append", requires = "output")]
+        append: bool,
+
+        /// Write the distinct keys inserted by every workload, one per line, to this file.
+        #[arg(long = "dump-keys")]
+        dump_keys: Option<PathBuf>,
+
+        /// Capacity in bytes of the buffer in front of every output file, e.g. smaller for
+        /// low-memory environments or larger for spinning disks.
+        #[arg(long = "buffer-size", default_value_t = 1024 * 1024)]
+        buffer_size: usize,
+
+        /// End every ascii workload with a `# ops=<count> crc=<crc32>` line, which `inspect`
+        /// verifies.
+        #[arg(long = "footer")]
+        footer: bool,
+
+        /// Replace output files that already exist instead of refusing to write them.
+        #[arg(long = "overwrite")]
+        overwrite: bool,
+
+        /// Name output files with this template instead of `{stem}.<extension>`, e.g.
+        /// `{stem}-{ops}.txt`. Placeholders are `{stem}`, `{ops}` for the operation count of the
+        /// spec, `{seed}`, `random` without one, and `{date}`, today in UTC as `YYYY-MM-DD`.
+        #[arg(long = "name-template", conflicts_with = "append")]
+        name_template: Option<String>,
+
+        /// Write every workload as a `<name>.load.<extension>` file with the inserts that start
+        /// each section and a `<name>.run.<extension>` file with everything after them.
+        #[arg(long = "split-phases", conflicts_with_all = ["append", "footer"])]
+        split_phases: bool,
+
+        /// Write ascii workloads into memory-mapped files, pre-sized to their exact length. Falls
+        /// back to buffered writes for workloads whose length isn't known up front, e.g. with
+        /// sampled value lengths, and for footers and headers.
+        #[arg(long = "mmap", conflicts_with_all = ["append", "split_phases"])]
+        mmap: bool,
+
+        /// Write the operations of every section in reverse order, reversing runs of operations
+        /// on distinct keys so no operation comes before the insert of its key or after its
+        /// delete. Buffers every section in memory.
+        #[arg(long = "reverse")]
+        reverse: bool,
+
+        /// Write every workload as `<name>.shard<i>.<extension>` files, each operation to shard
+        /// `hash(key) % shards` of its key, so all the operations on a key land in the same shard.
+        /// Range queries, seeks, and range deletes, whose keys span every shard, and operations
+        /// without a key go to every shard.
+        #[arg(
+            long = "shards",
+            conflicts_with_all = ["append", "footer", "split_phases", "mmap"]
+        )]
+        shards: Option<usize>,
+
+        /// Write operations without a key, think times, flushes, and comments, only to this shard
+        /// instead of to every shard.
+        #[arg(long = "keyless-shard", requires = "shards")]
+        keyless_shard: Option<usize>,
+
+        /// Start every ascii workload with a `# max_key=<len> max_val=<len> ops=<count>` line
+        /// derived from the spec, e.g. for replay tools that pre-allocate their buffers, which
+        /// `inspect` checks the keys and values against.
+        #[arg(long = "header", conflicts_with_all = ["split_phases", "shards"])]
+        header: bool,
+
+        /// Write at most this many operations per second, sleeping between them, e.g. to pipe a
+        /// workload live into a system under test with `-o -`. Generates on one thread.
+        #[arg(long = "ops-per-sec")]
+        ops_per_sec: Option<f64>,
+
+        /// Scale every spec to this many operations, keeping the ratios of its operation types,
+        /// e.g. to try a large spec quickly.
+        #[arg(long = "scale", value_name = "TOTAL")]
+        scale: Option<usize>,
     },
     /// Prints the json schema for IDE integration.
-    Schema,
+    Schema {
+        /// Write the schema to this file instead of stdout.
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+    },
+    /// Prints the spec of a standard workload, `ycsb-a` to `ycsb-f`, to generate or to edit.
+    Preset {
+        /// Name of the workload
+        name: Ycsb,
+
+        /// Records inserted by the load phase.
+        #[arg(long = "record-count", default_value_t = 1000)]
+        record_count: usize,
+
+        /// Operations of the run phase.
+        #[arg(long = "operation-count", default_value_t = 1000)]
+        operation_count: usize,
+
+        /// Write the spec to this file instead of stdout.
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+    },
+    /// Summarizes a generated ascii workload: operations by type, distinct keys, and bytes.
+    Inspect {
+        /// Generated workload file
+        #[arg(short = 'w', long = "workload")]
+        workload_path: PathBuf,
+
+        /// Separator between the fields of an ascii operation, as the workload was generated.
+        #[arg(long = "field-sep", default_value = " ", value_parser = parse_separator)]
+        field_sep: u8,
+
+        /// Separator after every ascii operation, as the workload was generated.
+        #[arg(long = "record-sep", default_value = "\\n", value_parser = parse_separator)]
+        record_sep: u8,
+
+        /// Ascii keys and values escape separators and backslashes with a backslash.
+        #[arg(long = "escape-separators")]
+        escape_separators: bool,
+    },
+    /// Converts a generated workload into another output format, e.g. ascii to msgpack, without
+    /// generating it again.
+    Convert {
+        /// Generated workload file
+        #[arg(short = 'i', long = "input")]
+        input: PathBuf,
+
+        /// Format of the input workload, `ascii`, `jsonl`, or `msgpack`.
+        #[arg(long = "from")]
+        from: OutputFormat,
+
+        /// Format to convert the workload to, `ascii`, `jsonl`, or `msgpack`.
+        #[arg(long = "to")]
+        to: OutputFormat,
+
+        /// Write the converted workload to this file instead of stdout.
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+
+        /// Separator between the fields of an ascii operation, on either side.
+        #[arg(long = "field-sep", default_value = " ", value_parser = parse_separator)]
+        field_sep: u8,
+
+        /// Separator after every ascii operation, on either side.
+        #[arg(long = "record-sep", default_value = "\\n", value_parser = parse_separator)]
+        record_sep: u8,
+
+        /// Ascii keys and values escape separators and backslashes with a backslash, on either
+        /// side.
+        #[arg(long = "escape-separators")]
+        escape_separators: bool,
+
+        /// Replace the output file if it already exists instead of refusing to write it.
+        #[arg(long = "overwrite")]
+        overwrite: bool,
+    },
+    /// Compares two workload specs: operations by type, bytes, and groups of every section.
+    DiffSpecs {
+        /// Workload spec to compare against
+        a: PathBuf,
+        /// Workload spec compared to `a`
+        b: PathBuf,
+    },
+    /// Parses and validates every spec of a file or folder without generating anything,
+    /// reporting every problem. Fails if any spec is invalid.
+    Lint {
+        /// Spec file, or folder searched for spec files like `generate`
+        path: PathBuf,
+    },
+    /// Replays a generated ascii workload against a RocksDB instance.
+    #[cfg(feature = "replay")]
+    Replay {
+        /// Generated workload file
+        #[arg(short = 'w', long = "workload")]
+        workload_path: PathBuf,
+
+        /// RocksDB database directory, created if it doesn't exist.
+        #[arg(short = 'd', long = "db")]
+        db_path: PathBuf,
+
+        /// Separator between the fields of an ascii operation, as the workload was generated.
+        #[arg(long = "field-sep", default_value = " ", value_parser = parse_separator)]
+        field_sep: u8,
+
+        /// Separator after every ascii operation, as the workload was generated.
+        #[arg(long = "record-sep", default_value = "\\n", value_parser = parse_separator)]
+        record_sep: u8,
+
+        /// Ascii keys and values escape separators and backslashes with a backslash.
+        #[arg(long = "escape-separators")]
+        escape_separators: bool,
+    },
 }
 
 fn main() -> Result<()> {
     let args = Cli::parse();
+    init_logger(args.quiet);
 
     match args.command {
         Command::Generate {
             workload_path,
             output,
-        } => invoke_generate(workload_path, output),
-        Command::Schema => invoke_schema(),
+            format,
+            field_sep,
+            record_sep,
+            escape_separators,
+            threads,
+            max_ops,
+            validate,
+            append,
+            dump_keys,
+            buffer_size,
+            footer,
+            overwrite,
+            name_template,
+            split_phases,
+            mmap,
+            reverse,
+            shards,
+            keyless_shard,
+            header,
+            ops_per_sec,
+            scale,
+        } => invoke_generate(
+            workload_path,
+            output,
+            dump_keys,
+            OutputFiles {
+                append,
+                overwrite,
+                name_template,
+                split: match (split_phases, shards) {
+                    (true, _) => Split::Phases,
+                    (false, Some(shards)) => Split::Shards {
+                        shards,
+                        keyless_shard,
+                    },
+                    (false, None) => Split::None,
+                },
+            },
+            GenerateOptions {
+                format,
+                ascii_format: AsciiFormat {
+                    field_sep,
+                    record_sep,
+                    escaping: match escape_separators {
+                        true => FieldEscaping::Backslash,
+                        false => FieldEscaping::Reject,
+                    },
+                },
+                threads,
+                max_ops,
+                validate,
+                collect_keys: false,
+                range_width_histogram: false,
+                spec_format: SpecFormat::Json,
+                buffer_size,
+                footer,
+                mmap,
+                reverse,
+                header,
+                ops_per_sec,
+                total_operations: scale,
+            },
+        ),
+        Command::Schema { output } => invoke_schema(output),
+        Command::Preset {
+            name,
+            record_count,
+            operation_count,
+            output,
+        } => invoke_preset(name, record_count, operation_count, output),
+        Command::Inspect {
+            workload_path,
+            field_sep,
+            record_sep,
+            escape_separators,
+        } => inspect::inspect(
+            &workload_path,
+            AsciiFormat {
+                field_sep,
+                record_sep,
+                escaping: match escape_separators {
+                    true => FieldEscaping::Backslash,
+                    false => FieldEscaping::Reject,
+                },
+            },
+        ),
+        Command::Convert {
+            input,
+            from,
+            to,
+            output,
+            field_sep,
+            record_sep,
+            escape_separators,
+            overwrite,
+        } => invoke_convert(
+            &input,
+            from,
+            to,
+            output,
+            overwrite,
+            AsciiFormat {
+                field_sep,
+                record_sep,
+                escaping: match escape_separators {
+                    true => FieldEscaping::Backslash,
+                    false => FieldEscaping::Reject,
+                },
+            },
+        ),
+        Command::DiffSpecs { a, b } => diff::diff_specs(&a, &b),
+        Command::Lint { path } => lint::lint(&path),
+        #[cfg(feature = "replay")]
+        Command::Replay {
+            workload_path,
+            db_path,
+            field_sep,
+            record_sep,
+            escape_separators,
+        } => replay::replay(
+            &workload_path,
+            &db_path,
+            AsciiFormat {
+                field_sep,
+                record_sep,
+                escaping: match escape_separators {
+                    true => FieldEscaping::Backslash,
+                    false => FieldEscaping::Reject,
+                },
+            },
+        ),
+    }
+}
+
+/// Logs to stderr without decorations, so stdout only carries the output of a command.
+fn init_logger(quiet: bool) {
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+    builder.format(|buf, record| writeln!(buf, "{}", record.args()));
+    if quiet {
+        builder.filter_level(LevelFilter::Warn);
     }
+    builder.init();
+}
+
+/// Parses a single byte separator, either a printable ascii character or one of the escapes `\t`,
+/// `\n`, `\r`, and `\0`. Other control characters are rejected, they are easily passed by
+/// accident and invisible in the output. So is a backslash, which escapes separators.
+fn parse_separator(s: &str) -> Result<u8> {
+    return match s {
+        "\\t" => Ok(b'\t'),
+        "\\n" => Ok(b'\n'),
+        "\\r" => Ok(b'\r'),
+        "\\0" => Ok(b'\0'),
+        "\\" => anyhow::bail!("a backslash escapes separators, it can't be one"),
+        _ if s.len() == 1 && s.is_ascii() && !s.as_bytes()[0].is_ascii_control() => {
+            Ok(s.as_bytes()[0])
+        }
+        _ => anyhow::bail!(
+            "expected a single printable ascii character or one of \\t, \\n, \\r, \\0"
+        ),
+    };
+}
+
+/// How `generate` names and writes the workload files.
+struct OutputFiles {
+    append: bool,
+    overwrite: bool,
+    name_template: Option<String>,
+    split: Split,
+}
+
+/// Which files `generate` splits every workload into.
+#[derive(Debug, Copy, Clone)]
+enum Split {
+    /// A single file.
+    None,
+    /// A load and a run file, see `--split-phases`.
+    Phases,
+    /// A file per shard, see `--shards`.
+    Shards {
+        shards: usize,
+        keyless_shard: Option<usize>,
+    },
 }
 
 /// Generate workload(s) from a file or folder of workload specifications.
-fn invoke_generate(workload_path: String, output: Option<String>) -> Result<()> {
+fn invoke_generate(
+    workload_path: String,
+    output: Option<String>,
+    dump_keys: Option<PathBuf>,
+    output_files: OutputFiles,
+    mut options: GenerateOptions,
+) -> Result<()> {
+    let OutputFiles {
+        append,
+        overwrite,
+        name_template,
+        split,
+    } = output_files;
+    if options.threads == 0 {
+        anyhow::bail!("--threads must be at least 1");
+    }
+    if options.buffer_size == 0 {
+        anyhow::bail!("--buffer-size must be at least 1");
+    }
+    if options.footer && options.format != OutputFormat::Ascii {
+        anyhow::bail!("--footer is only supported by the ascii format");
+    }
+    if options.header && options.format != OutputFormat::Ascii {
+        anyhow::bail!("--header is only supported by the ascii format");
+    }
+    if name_template.is_some() && (workload_path == "-" || output.as_deref() == Some("-")) {
+        anyhow::bail!("--name-template names the files of an output folder, not stdin or stdout");
+    }
+    if matches!(split, Split::Phases) && output.as_deref() == Some("-") {
+        anyhow::bail!("--split-phases writes a load and a run file, not stdout");
+    }
+    if let Split::Shards {
+        shards,
+        keyless_shard,
+    } = split
+    {
+        if shards == 0 {
+            anyhow::bail!("--shards must be at least 1");
+        }
+        if keyless_shard.is_some_and(|shard| shard >= shards) {
+            anyhow::bail!("--keyless-shard must be below --shards");
+        }
+        if output.as_deref() == Some("-") {
+            anyhow::bail!("--shards writes a file per shard, not stdout");
+        }
+    }
+    if options.mmap && output.as_deref() == Some("-") {
+        anyhow::bail!("--mmap maps output files, not stdout");
+    }
+
+    let mut keys_writer = match dump_keys {
+        Some(path) => {
+            let file = create_output(&path, overwrite)
+                .with_context(|| format!("creating keys file {}", path.display()))?;
+            Some(BufWriter::new(file))
+        }
+        None => None,
+    };
+    options.collect_keys = keys_writer.is_some();
+    let keys_writer = &mut keys_writer;
+    if output.as_deref() == Some("-") {
+        let contents = if workload_path == "-" {
+            read_stdin()?
+        } else {
+            let workload_path = PathBuf::from(&workload_path);
+            if workload_path.is_dir() {
+                anyhow::bail!(
+                    "--output - writes a single workload, but {} is a directory",
+                    workload_path.display()
+                );
+            }
+            options.spec_format = SpecFormat::from_path(&workload_path);
+            read_spec(&workload_path)?
+        };
+        let mut writer = BufWriter::with_capacity(options.buffer_size, io::stdout().lock());
+        write_with_progress_bar(&contents, Writers::One(&mut writer), options, keys_writer)?;
+        writer.flush()?;
+        return Ok(());
+    }
+    if workload_path == "-" {
+        let Some(output) = output else {
+            anyhow::bail!("--output is required when reading the spec from stdin");
+        };
+        let output_file_path = PathBuf::from(output);
+        if output_file_path.is_dir() {
+            anyhow::bail!(
+                "--output must be a file when reading the spec from stdin, but {} is a directory",
+                output_file_path.display()
+            );
+        }
+        let contents = read_stdin()?;
+        return generate_with_progress_bar(
+            &contents,
+            output_file_path,
+            overwrite,
+            split,
+            options,
+            keys_writer,
+        );
+    }
+
     let workload_path = PathBuf::from(&workload_path);
     if !workload_path.exists() {
         anyhow::bail!("File or folder does not exist {}", workload_path.display());
     }
 
+    if append {
+        let output_file_path = PathBuf::from(output.context("--append requires --output")?);
+        let spec_paths: Vec<PathBuf> = if workload_path.is_dir() {
+            spec_files(&workload_path)
+                .map(|entry| entry.into_path())
+                .collect()
+        } else {
+            vec![workload_path]
+        };
+        let mut writer = BufWriter::with_capacity(
+            options.buffer_size,
+            create_output(&output_file_path, overwrite)?,
+        );
+        for path in spec_paths {
+            info!("Generating workload for: {}", path.display());
+            let contents = read_spec(&path)?;
+            let options = GenerateOptions {
+                spec_format: SpecFormat::from_path(&path),
+                ..options
+            };
+            write_with_progress_bar(&contents, Writers::One(&mut writer), options, keys_writer)?;
+        }
+        writer.flush()?;
+        return Ok(());
+    }
+
     let output_path = if let Some(output) = output {
         // Directory that didn't exist.
         let output_path = PathBuf::from(output);
@@ -63,55 +616,66 @@ fn invoke_generate(workload_path: String, output: Option<String>) -> Result<()>
     };
 
     if workload_path.is_dir() {
-        for entry in WalkDir::new(&workload_path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|file| {
-                file.file_type().is_file()
-                    && file
-                        .path()
-                        .file_name()
-                        .and_then(|name| name.to_str())
-                        .map(|name| name.ends_with(".spec.json"))
-                        .unwrap_or(false)
-            })
-        {
+        for entry in spec_files(&workload_path) {
             let path = entry.path();
-            println!("Generating workload for: {}", path.display());
-            let contents = fs::read_to_string(path)?;
+            info!("Generating workload for: {}", path.display());
+            let contents = read_spec(path)?;
+            let options = GenerateOptions {
+                spec_format: SpecFormat::from_path(path),
+                ..options
+            };
 
-            let output_file = path
+            let stem = path
                 .file_name()
                 .and_then(|stem| stem.to_str())
-                .map(|stem| stem.rsplitn(3, '.').collect::<Vec<_>>()[2]) // file.spec.json -> file
-                .map(|stem| format!("{}.txt", stem)) // file -> file.txt
+                .map(|stem| stem.strip_suffix(".gz").unwrap_or(stem))
+                .map(|stem| stem.rsplitn(3, '.').collect::<Vec<_>>()[2].to_string()) // file.spec.json -> file
                 .unwrap_or_else(|| {
                     let filename = path.file_name().unwrap().to_string_lossy();
                     let basename = filename
                         .rsplit_once('.')
                         .map_or(filename.as_ref(), |(base, _)| base);
-                    format!("{}.txt", basename)
+                    basename.to_string()
                 });
+            let output_file = output_name(&stem, &contents, name_template.as_deref(), &options)?;
 
             let mut output_file_path = output_path.clone();
             output_file_path.push(output_file);
 
-            generate_workload(&contents, output_file_path)?;
+            generate_with_progress_bar(
+                &contents,
+                output_file_path,
+                overwrite,
+                split,
+                options,
+                keys_writer,
+            )?;
         }
     } else if workload_path.is_file() {
-        let contents = fs::read_to_string(&workload_path)?;
+        let contents = read_spec(&workload_path)?;
+        options.spec_format = SpecFormat::from_path(&workload_path);
 
-        let output_file = workload_path
-            .file_stem()
+        let stem = workload_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.strip_suffix(".gz").unwrap_or(name))
+            .and_then(|name| Path::new(name).file_stem())
             .and_then(|stem| stem.to_str())
-            .map(|stem| format!("{}.txt", stem))
-            .unwrap_or_else(|| format!("{}.txt", workload_path.display()));
+            .map(|stem| stem.to_string())
+            .unwrap_or_else(|| workload_path.display().to_string());
+        let output_file = output_name(&stem, &contents, name_template.as_deref(), &options)?;
 
         let mut output_file_path = output_path.clone();
         output_file_path.push(output_file);
 
-        generate_workload(&contents, output_file_path)?;
+        generate_with_progress_bar(
+            &contents,
+            output_file_path,
+            overwrite,
+            split,
+            options,
+            keys_writer,
+        )?;
     } else {
         unreachable!("Path is neither a file nor a directory");
     };
@@ -119,9 +683,413 @@ fn invoke_generate(workload_path: String, output: Option<String>) -> Result<()>
     return Ok(());
 }
 
-/// Prints the json schema for IDE integration.
-fn invoke_schema() -> Result<()> {
+fn read_stdin() -> Result<String> {
+    let mut contents = String::new();
+    io::stdin()
+        .read_to_string(&mut contents)
+        .context("reading spec from stdin")?;
+    return Ok(contents);
+}
+
+/// Reads a spec file, decompressing it first if it ends in `.gz`.
+fn read_spec(path: &Path) -> Result<String> {
+    let mut contents = String::new();
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        GzDecoder::new(File::open(path)?)
+            .read_to_string(&mut contents)
+            .with_context(|| format!("decompressing spec {}", path.display()))?;
+    } else {
+        contents = fs::read_to_string(path)?;
+    }
+    return Ok(contents);
+}
+
+/// Extensions of spec files, optionally followed by `.gz`.
+const SPEC_EXTENSIONS: [&str; 4] = [".spec.json", ".spec.toml", ".spec.yaml", ".spec.yml"];
+
+/// The `*.spec.json`, `*.spec.toml`, `*.spec.yaml`, and `*.spec.yml` files in a folder and its
+/// subfolders, optionally gzipped, sorted by file name.
+fn spec_files(dir: &Path) -> impl Iterator<Item = DirEntry> {
+    return WalkDir::new(dir)
+        .follow_links(true)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|file| {
+            file.file_type().is_file()
+                && file
+                    .path()
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map(|name| {
+                        let name = name.strip_suffix(".gz").unwrap_or(name);
+                        SPEC_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+                    })
+                    .unwrap_or(false)
+        });
+}
+
+/// Name of the output file of the spec `contents`, `{stem}.<extension>` unless there is a
+/// `--name-template`.
+fn output_name(
+    stem: &str,
+    contents: &str,
+    name_template: Option<&str>,
+    options: &GenerateOptions,
+) -> Result<String> {
+    let Some(template) = name_template else {
+        return Ok(format!("{}.{}", stem, options.format.extension()));
+    };
+    let spec = options.parse_spec(contents)?;
+    let mut name = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            anyhow::bail!("unclosed `{{` in --name-template {template}");
+        };
+        match &rest[start + 1..start + len] {
+            "stem" => name.push_str(stem),
+            "ops" => name.push_str(&spec.operation_count().to_string()),
+            "seed" => match spec.seed() {
+                Some(seed) => name.push_str(&seed.to_string()),
+                None => name.push_str("random"),
+            },
+            "date" => name.push_str(&utc_date()),
+            placeholder => {
+                anyhow::bail!("unknown placeholder {{{placeholder}}} in --name-template")
+            }
+        }
+        rest = &rest[start + len + 1..];
+    }
+    name.push_str(rest);
+    return Ok(name);
+}
+
+/// Today in UTC as `YYYY-MM-DD`.
+fn utc_date() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    // Howard Hinnant's `civil_from_days`, with years starting in March so leap days come last.
+    let days = secs / 86_400 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    return format!("{year:04}-{month:02}-{day:02}");
+}
+
+/// Creates an output file, refusing to replace an existing file unless `overwrite` is set. The file
+/// is readable too, so `--mmap` can map it.
+fn create_output(path: &Path, overwrite: bool) -> Result<File> {
+    let mut options = File::options();
+    options.read(true).write(true);
+    if overwrite {
+        return Ok(options.create(true).truncate(true).open(path)?);
+    }
+    return match options.create_new(true).open(path) {
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => anyhow::bail!(
+            "{} already exists, pass --overwrite to replace it",
+            path.display()
+        ),
+        file => Ok(file?),
+    };
+}
+
+/// Generates a workload while rendering a progress bar of the operations written. Split into
+/// phases or shards, writes them next to `output_file_path` instead.
+fn generate_with_progress_bar(
+    contents: &str,
+    output_file_path: PathBuf,
+    overwrite: bool,
+    split: Split,
+    options: GenerateOptions,
+    keys_writer: &mut Option<BufWriter<File>>,
+) -> Result<()> {
+    match split {
+        Split::None => {}
+        Split::Phases => {
+            let mut load = BufWriter::with_capacity(
+                options.buffer_size,
+                create_output(&phase_path(&output_file_path, "load"), overwrite)?,
+            );
+            let mut run = BufWriter::with_capacity(
+                options.buffer_size,
+                create_output(&phase_path(&output_file_path, "run"), overwrite)?,
+            );
+            let writers = Writers::Phases {
+                load: &mut load,
+                run: &mut run,
+            };
+            write_with_progress_bar(contents, writers, options, keys_writer)?;
+            load.flush()?;
+            run.flush()?;
+            return Ok(());
+        }
+        Split::Shards {
+            shards,
+            keyless_shard,
+        } => {
+            let mut shards = (0..shards)
+                .map(|shard| {
+                    let path = phase_path(&output_file_path, &format!("shard{shard}"));
+                    let file = create_output(&path, overwrite)?;
+                    return Ok(BufWriter::with_capacity(options.buffer_size, file));
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let writers = Writers::Shards {
+                shards: &mut shards,
+                keyless_shard,
+            };
+            write_with_progress_bar(contents, writers, options, keys_writer)?;
+            for shard in &mut shards {
+                shard.flush()?;
+            }
+            return Ok(());
+        }
+    }
+    // The file is sized from the spec, so an invalid spec has to fail before it is created.
+    let mmap_len = match options.mmap {
+        true => {
+            let spec = options.parse_spec(contents)?;
+            spec.validate()?;
+            options.mmap_len(&spec)
+        }
+        false => None,
+    };
+    if let Some(len) = mmap_len {
+        let file = create_output(&output_file_path, overwrite)?;
+        let result = write_mmap(contents, file, len, options, keys_writer);
+        // A failed workload leaves the file at its full length, mostly zeroes.
+        if result.is_err() {
+            let _ = fs::remove_file(&output_file_path);
+        }
+        return result;
+    }
+    let mut writer = BufWriter::with_capacity(
+        options.buffer_size,
+        create_output(&output_file_path, overwrite)?,
+    );
+    write_with_progress_bar(contents, Writers::One(&mut writer), options, keys_writer)?;
+    writer.flush()?;
+    return Ok(());
+}
+
+/// Writes the workload into `file` memory-mapped at `len` bytes, see [`MmapWriter`].
+fn write_mmap(
+    contents: &str,
+    file: File,
+    len: usize,
+    options: GenerateOptions,
+    keys_writer: &mut Option<BufWriter<File>>,
+) -> Result<()> {
+    let mut writer = MmapWriter::create(file, len)?;
+    write_with_progress_bar(contents, Writers::One(&mut writer), options, keys_writer)?;
+    writer.finish()?;
+    return Ok(());
+}
+
+/// `workload.txt` -> `workload.<phase>.txt`, also for shards.
+fn phase_path(path: &Path, phase: &str) -> PathBuf {
+    return match path.extension() {
+        Some(ext) => path.with_extension(format!("{phase}.{}", ext.to_string_lossy())),
+        None => path.with_extension(phase),
+    };
+}
+
+/// Where [`write_with_progress_bar`] writes a workload.
+enum Writers<'a, W: Write> {
+    One(&'a mut W),
+    /// The load phase to `load` and the run phase to `run`.
+    Phases {
+        load: &'a mut W,
+        run: &'a mut W,
+    },
+    /// Every operation to the shard of its key.
+    Shards {
+        shards: &'a mut [W],
+        keyless_shard: Option<usize>,
+    },
+}
+
+/// Same as [`generate_with_progress_bar`], writing to `writers`.
+fn write_with_progress_bar(
+    contents: &str,
+    writers: Writers<'_, impl Write>,
+    options: GenerateOptions,
+    keys_writer: &mut Option<BufWriter<File>>,
+) -> Result<()> {
+    // Ascii workloads know their size up front, so their progress and time left are tracked in
+    // bytes. Specs that don't parse are left for `generate_workload_into` to report.
+    let total_bytes = match options.format {
+        OutputFormat::Ascii if options.max_ops.is_none() => options
+            .parse_spec(contents)
+            .ok()
+            .map(|spec| spec.bytes_count()),
+        _ => None,
+    };
+    let template = match total_bytes {
+        Some(_) => "{wide_bar} {binary_bytes}/{binary_total_bytes} ({eta})",
+        None => "{wide_bar} {human_pos}/{human_len} ops ({eta})",
+    };
+    // The progress bar is progress logging too, so it is hidden whenever that is.
+    let progress_bar = if log_enabled!(Level::Info) {
+        ProgressBar::new(0).with_style(ProgressStyle::with_template(template)?)
+    } else {
+        ProgressBar::hidden()
+    };
+    let bytes = Cell::new(0);
+    let counting = |inner| CountingWriter {
+        inner,
+        bytes: &bytes,
+    };
+    let mut on_progress = |done: usize, total: usize| {
+        if let Some(total_bytes) = total_bytes {
+            progress_bar.set_length(total_bytes as u64);
+            progress_bar.set_position(bytes.get() as u64);
+        } else {
+            progress_bar.set_length(total as u64);
+            progress_bar.set_position(done as u64);
+        }
+    };
+    let stats = match writers {
+        Writers::One(writer) => {
+            generate_workload_into(contents, &mut counting(writer), options, &mut on_progress)?
+        }
+        Writers::Phases { load, run } => generate_phases_into(
+            contents,
+            &mut counting(load),
+            &mut counting(run),
+            options,
+            &mut on_progress,
+        )?,
+        Writers::Shards {
+            shards,
+            keyless_shard,
+        } => {
+            let mut shards: Vec<_> = shards.iter_mut().map(counting).collect();
+            generate_shards_into(
+                contents,
+                &mut shards,
+                options,
+                keyless_shard,
+                &mut on_progress,
+            )?
+        }
+    };
+    progress_bar.finish();
+    print_stats(&stats);
+    if let Some(keys_writer) = keys_writer.as_mut() {
+        for key in &stats.keys {
+            keys_writer.write_all(key)?;
+            keys_writer.write_all(b"\n")?;
+        }
+        keys_writer.flush()?;
+    }
+    return Ok(());
+}
+
+/// Logs a summary of a generated workload.
+fn print_stats(stats: &WorkloadStats) {
+    info!(
+        "{} ops: {} inserts, {} updates, {} deletes, {} point queries, {} empty point queries, {} range queries, {} seek queries, {} range deletes, {} think times, {} flushes",
+        stats.operation_count(),
+        stats.inserts,
+        stats.updates,
+        stats.deletes,
+        stats.point_queries,
+        stats.empty_point_queries,
+        stats.range_queries,
+        stats.seek_queries,
+        stats.range_deletes,
+        stats.think_times,
+        stats.flushes,
+    );
+    if stats.read_modify_writes > 0 {
+        info!(
+            "{} of the point queries and updates are read-modify-writes",
+            stats.read_modify_writes
+        );
+    }
+    info!(
+        "{} distinct keys, {} live keys",
+        stats.distinct_keys, stats.live_keys
+    );
+    if stats.key_len_histogram.len() > 1 {
+        let histogram: Vec<String> = stats
+            .key_len_histogram
+            .iter()
+            .map(|(key_len, count)| format!("{key_len}: {count}"))
+            .collect();
+        info!("Inserts by key length: {}", histogram.join(", "));
+    }
+}
+
+/// Prints the spec of a standard workload, or writes it to `output`.
+fn invoke_preset(
+    name: Ycsb,
+    record_count: usize,
+    operation_count: usize,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let spec_str = serde_json::to_string_pretty(&name.spec(record_count, operation_count))?;
+    match output {
+        Some(output) => fs::write(&output, format!("{spec_str}\n"))
+            .with_context(|| format!("writing spec to {}", output.display()))?,
+        None => println!("{spec_str}"),
+    }
+    return Ok(());
+}
+
+/// Converts the workload at `input` from one output format into another, writing it to `output`
+/// or stdout.
+fn invoke_convert(
+    input: &Path,
+    from: OutputFormat,
+    to: OutputFormat,
+    output: Option<PathBuf>,
+    overwrite: bool,
+    ascii_format: AsciiFormat,
+) -> Result<()> {
+    let file = File::open(input).with_context(|| format!("opening {}", input.display()))?;
+    let mut reader = BufReader::new(file);
+    let ops = match output {
+        Some(path) => {
+            let file = create_output(&path, overwrite)
+                .with_context(|| format!("creating output file {}", path.display()))?;
+            let mut writer = BufWriter::new(file);
+            let ops = convert(&mut reader, from, &mut writer, to, ascii_format)?;
+            writer.flush()?;
+            ops
+        }
+        None => {
+            let mut writer = BufWriter::new(io::stdout().lock());
+            let ops = convert(&mut reader, from, &mut writer, to, ascii_format)?;
+            writer.flush()?;
+            ops
+        }
+    };
+    info!("Converted {ops} operations of {}", input.display());
+    return Ok(());
+}
+
+/// Prints the json schema for IDE integration, or writes it to `output`.
+fn invoke_schema(output: Option<PathBuf>) -> Result<()> {
     let schema_str = generate_workload_spec_schema().context("Schema generation failed.")?;
-    println!("{schema_str}");
+    match output {
+        Some(output) => fs::write(&output, format!("{schema_str}\n"))
+            .with_context(|| format!("writing schema to {}", output.display()))?,
+        None => println!("{schema_str}"),
+    }
     return Ok(());
 }
@@ -0,0 +1,172 @@
+//! Replays a generated ASCII workload against a RocksDB instance.
+//!
+//! Only the default column family is opened, so `CF name` directives are rejected.
+use anyhow::{bail, Context, Result};
+use rocksdb::{Direction, IteratorMode, WriteBatch, DB};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use workload_gen::writer::{AsciiFormat, AsciiReader};
+
+/// Latencies of every replayed operation of one type.
+struct OpLatencies {
+    name: &'static str,
+    latencies: Vec<Duration>,
+}
+
+impl OpLatencies {
+    fn new(name: &'static str) -> Self {
+        return Self {
+            name,
+            latencies: Vec::new(),
+        };
+    }
+
+    fn report(&mut self) {
+        if self.latencies.is_empty() {
+            return;
+        }
+        self.latencies.sort();
+        let count = self.latencies.len();
+        let mean = self.latencies.iter().sum::<Duration>() / count as u32;
+        let percentile = |p: usize| self.latencies[(count - 1) * p / 100];
+        println!(
+            "{:<12} {:>10} ops  mean {:>10.2?}  p50 {:>10.2?}  p99 {:>10.2?}  max {:>10.2?}",
+            self.name,
+            count,
+            mean,
+            percentile(50),
+            percentile(99),
+            self.latencies[count - 1],
+        );
+    }
+}
+
+/// Applies every operation of the workload at `workload_path`, written with the separators of
+/// `format`, to the database at `db_path`, creating it if it doesn't exist, and prints the
+/// throughput and per-operation latencies.
+pub fn replay(workload_path: &Path, db_path: &Path, format: AsciiFormat) -> Result<()> {
+    let db = DB::open_default(db_path).context("opening database")?;
+    let mut reader = BufReader::new(File::open(workload_path)?);
+    let ascii_reader = AsciiReader::new(format);
+
+    let mut puts = OpLatencies::new("put");
+    let mut deletes = OpLatencies::new("delete");
+    let mut gets = OpLatencies::new("get");
+    let mut scans = OpLatencies::new("range scan");
+    let mut seeks = OpLatencies::new("seek");
+
+    let start = Instant::now();
+    let mut line = Vec::new();
+    let mut line_number = 0;
+    while ascii_reader.read_record(&mut reader, &mut line)? {
+        line_number += 1;
+        // Headers and footers are checked by `inspect`, there is nothing to replay.
+        if line.starts_with(b"#") {
+            continue;
+        }
+        if line == b"F" {
+            // Flushes aren't operations either, but their cost shows up in the total time.
+            db.flush()?;
+            continue;
+        }
+        let fields = ascii_reader.split_fields(&line);
+        let (op, key, arg) = match fields.as_slice() {
+            [op, key] => (op.as_slice(), key.as_slice(), None),
+            [op, key, arg] => (op.as_slice(), key.as_slice(), Some(arg.as_slice())),
+            _ => bail!("Malformed operation on line {}", line_number),
+        };
+
+        let op_start = Instant::now();
+        let latencies = match (op, arg) {
+            (b"I" | b"U", Some(val)) => {
+                db.put(key, val)?;
+                &mut puts
+            }
+            (b"D", None) => {
+                db.delete(key)?;
+                &mut deletes
+            }
+            (b"DR", Some(end_key)) => {
+                let mut batch = WriteBatch::default();
+                batch.delete_range(key, end_key);
+                db.write(batch)?;
+                &mut deletes
+            }
+            (b"P", None) => {
+                db.get(key)?;
+                &mut gets
+            }
+            (b"SK", None) => {
+                let mut iter = db.raw_iterator();
+                iter.seek(key);
+                iter.status()?;
+                &mut seeks
+            }
+            (b"R", Some(count)) if count.starts_with(b"+") => {
+                let count: usize = std::str::from_utf8(&count[1..])?
+                    .parse()
+                    .with_context(|| format!("Malformed count on line {}", line_number))?;
+                for entry in db
+                    .iterator(IteratorMode::From(key, Direction::Forward))
+                    .take(count)
+                {
+                    entry?;
+                }
+                &mut scans
+            }
+            (b"T", None) => {
+                // Pauses aren't operations, so they are neither timed nor counted.
+                let micros: u64 = std::str::from_utf8(key)?
+                    .parse()
+                    .with_context(|| format!("Malformed think time on line {}", line_number))?;
+                std::thread::sleep(Duration::from_micros(micros));
+                continue;
+            }
+            (b"R", Some(end_key)) => {
+                // Endpoints in descending order signal a backward scan.
+                let direction = if key <= end_key {
+                    Direction::Forward
+                } else {
+                    Direction::Reverse
+                };
+                for entry in db.iterator(IteratorMode::From(key, direction)) {
+                    let (entry_key, _) = entry?;
+                    let past_end = match direction {
+                        Direction::Forward => &*entry_key > end_key,
+                        Direction::Reverse => &*entry_key < end_key,
+                    };
+                    if past_end {
+                        break;
+                    }
+                }
+                &mut scans
+            }
+            (b"CF", None) => bail!(
+                "Column family directive on line {}, replaying column families isn't supported",
+                line_number
+            ),
+            _ => bail!("Unknown operation on line {}", line_number),
+        };
+        latencies.latencies.push(op_start.elapsed());
+    }
+    let elapsed = start.elapsed();
+
+    let op_count = puts.latencies.len()
+        + deletes.latencies.len()
+        + gets.latencies.len()
+        + scans.latencies.len()
+        + seeks.latencies.len();
+    println!(
+        "Replayed {} ops in {:.2?} ({:.0} ops/s)",
+        op_count,
+        elapsed,
+        op_count as f64 / elapsed.as_secs_f64()
+    );
+    for latencies in [&mut puts, &mut deletes, &mut gets, &mut scans, &mut seeks] {
+        latencies.report();
+    }
+
+    return Ok(());
+}
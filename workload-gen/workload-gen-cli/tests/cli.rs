@@ -0,0 +1,952 @@
+#![allow(clippy::needless_return)]
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+/// A fresh scratch directory for one test, unique per test name and process.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("workload-gen-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    return dir;
+}
+
+/// Writes `spec` to `name` in `dir`, returning its path.
+fn write_spec(dir: &Path, name: &str, spec: &str) -> PathBuf {
+    let path = dir.join(name);
+    fs::write(&path, spec).unwrap();
+    return path;
+}
+
+fn cli() -> Command {
+    return Command::new(env!("CARGO_BIN_EXE_workload-gen-cli"));
+}
+
+/// `generate` of the spec file or folder `specs`, or `-` for stdin.
+fn generate(specs: impl AsRef<OsStr>) -> Command {
+    let mut command = cli();
+    command.arg("generate").arg("-w").arg(specs);
+    return command;
+}
+
+/// `inspect` of the workload at `path`.
+fn inspect(path: &Path) -> Command {
+    let mut command = cli();
+    command.arg("inspect").arg("-w").arg(path);
+    return command;
+}
+
+/// Runs `command` with `input` on its stdin.
+fn output_with_stdin(command: &mut Command, input: &[u8]) -> Output {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    return child.wait_with_output().unwrap();
+}
+
+/// Number of lines of the file at `path`.
+fn line_count(path: &Path) -> usize {
+    return fs::read_to_string(path).unwrap().lines().count();
+}
+
+/// Number of lines of `workload` starting with `prefix`.
+fn count_lines(workload: &str, prefix: &str) -> usize {
+    return workload
+        .lines()
+        .filter(|line| line.starts_with(prefix))
+        .count();
+}
+
+#[test]
+fn generate_from_stdin() {
+    let dir = scratch_dir("stdin");
+    let output_path = dir.join("workload.txt");
+    let spec = r#"{ "sections": [{ "groups": [{ "inserts": { "amount": 100, "key_len": 8, "val_len": 8 } }] }] }"#;
+
+    let output = output_with_stdin(generate("-").arg("-o").arg(&output_path), spec.as_bytes());
+    assert!(output.status.success(), "{output:?}");
+
+    let workload = fs::read_to_string(&output_path).unwrap();
+    assert_eq!(workload.lines().count(), 100);
+    assert_eq!(count_lines(&workload, "I "), 100);
+
+    // A directory can't be the output of a single spec.
+    let output = generate("-")
+        .arg("-o")
+        .arg(&dir)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn generate_to_stdout() {
+    let dir = scratch_dir("stdout");
+    let spec_path = write_spec(
+        &dir,
+        "workload.spec.json",
+        r#"{ "sections": [{ "groups": [{ "inserts": { "amount": 100, "key_len": 8, "val_len": 8 } }] }] }"#,
+    );
+
+    let generate_with = |args: &[&str]| {
+        let output = generate(&spec_path)
+            .args(["-o", "-"])
+            .args(args)
+            .env_remove("RUST_LOG")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        return (
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+        );
+    };
+
+    // Only the workload goes to stdout, the stats are logged to stderr.
+    let (stdout, stderr) = generate_with(&[]);
+    assert_eq!(stdout.lines().count(), 100);
+    assert_eq!(count_lines(&stdout, "I "), 100);
+    assert!(stderr.contains("100 ops"));
+
+    let (stdout, stderr) = generate_with(&["--quiet"]);
+    assert_eq!(stdout.lines().count(), 100);
+    assert_eq!(stderr, "");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn empty_groups_fail_cleanly() {
+    for (spec, message) in [
+        (r#"{ "sections": [] }"#, "has no sections"),
+        (
+            r#"{ "sections": [{ "groups": [] }] }"#,
+            "Section 0 has no groups",
+        ),
+        (
+            r#"{ "sections": [{ "groups": [{}] }] }"#,
+            "Group 0 of section 0 has no operations",
+        ),
+    ] {
+        let output = output_with_stdin(generate("-").args(["-o", "-"]), spec.as_bytes());
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(message), "{stderr}");
+        assert!(!stderr.contains("panicked"), "{stderr}");
+    }
+}
+
+#[test]
+fn existing_output_needs_overwrite() {
+    let dir = scratch_dir("overwrite");
+    let spec_path = write_spec(
+        &dir,
+        "overwrite.spec.json",
+        r#"{ "sections": [{ "groups": [{ "inserts": { "amount": 100, "key_len": 8, "val_len": 8 } }] }] }"#,
+    );
+    let workload_path = dir.join("overwrite.spec.txt");
+    fs::write(&workload_path, "hand-curated\n").unwrap();
+
+    let generate_with = |extra: &[&str]| {
+        return generate(&spec_path).args(extra).output().unwrap();
+    };
+    let output = generate_with(&[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("already exists"), "{stderr}");
+    assert!(stderr.contains("overwrite.spec.txt"), "{stderr}");
+    assert_eq!(
+        fs::read_to_string(&workload_path).unwrap(),
+        "hand-curated\n"
+    );
+
+    assert!(generate_with(&["--overwrite"]).status.success());
+    assert_eq!(line_count(&workload_path), 100);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn append_specs_to_one_file() {
+    let dir = scratch_dir("append");
+    let specs_dir = dir.join("specs");
+    fs::create_dir_all(&specs_dir).unwrap();
+    write_spec(
+        &specs_dir,
+        "a.spec.json",
+        r#"{ "sections": [{ "groups": [{ "inserts": { "amount": 100, "key_len": 8, "val_len": 8 } }] }] }"#,
+    );
+    write_spec(
+        &specs_dir,
+        "b.spec.json",
+        r#"{ "sections": [{ "groups": [
+            { "inserts": { "amount": 50, "key_len": 8, "val_len": 8 } },
+            { "point_queries": { "amount": 10 } }
+        ] }] }"#,
+    );
+
+    let generate_with = |args: &[&str]| {
+        let status = generate(&specs_dir).args(args).status().unwrap();
+        assert!(status.success());
+    };
+    let separate_dir = dir.join("separate");
+    generate_with(&["-o", separate_dir.to_str().unwrap()]);
+    let combined_path = dir.join("combined.txt");
+    generate_with(&["--append", "-o", combined_path.to_str().unwrap()]);
+
+    let separate =
+        line_count(&separate_dir.join("a.txt")) + line_count(&separate_dir.join("b.txt"));
+    assert_eq!(separate, 160);
+    assert_eq!(line_count(&combined_path), separate);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn buffer_size_does_not_change_output() {
+    let dir = scratch_dir("buffer");
+    let spec_path = write_spec(
+        &dir,
+        "a.json",
+        r#"{ "seed": 3, "sections": [{ "groups": [
+            { "inserts": { "amount": 1000, "key_len": 8, "val_len": 64 } },
+            { "updates": { "amount": 200, "val_len": 64 }, "range_queries": { "amount": 200, "selectivity": 0.1 } }
+        ] }] }"#,
+    );
+
+    let generate_with = |buffer_size: &str| {
+        let output_dir = dir.join(buffer_size);
+        let status = generate(&spec_path)
+            .arg("-o")
+            .arg(&output_dir)
+            .args(["--buffer-size", buffer_size])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        return fs::read(output_dir.join("a.txt")).unwrap();
+    };
+    let tiny = generate_with("1");
+    assert_eq!(tiny.iter().filter(|&&b| b == b'\n').count(), 1400);
+    assert_eq!(tiny, generate_with("16777216"));
+
+    let output = generate(&spec_path)
+        .args(["--buffer-size", "0"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--buffer-size must be at least 1"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn gzipped_spec_matches_uncompressed() {
+    const SPEC: &str = r#"{ "sections": [{ "groups": [
+        { "inserts": { "amount": 100, "key_len": 8, "val_len": 8 } },
+        { "updates": { "amount": 20, "val_len": 8 }, "point_queries": { "amount": 20 } }
+    ] }] }"#;
+    // Keys and values are random, so the operations are compared by type.
+    let markers = |path: &Path| {
+        let mut markers: Vec<String> = fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| line.split(' ').next().unwrap().to_string())
+            .collect();
+        markers.sort();
+        return markers;
+    };
+
+    let dir = scratch_dir("gzip");
+    let plain_dir = dir.join("plain");
+    let gzip_dir = dir.join("gzip");
+    fs::create_dir_all(&plain_dir).unwrap();
+    fs::create_dir_all(&gzip_dir).unwrap();
+    write_spec(&plain_dir, "a.spec.json", SPEC);
+    let mut encoder = GzEncoder::new(
+        fs::File::create(gzip_dir.join("a.spec.json.gz")).unwrap(),
+        Compression::default(),
+    );
+    encoder.write_all(SPEC.as_bytes()).unwrap();
+    encoder.finish().unwrap();
+
+    for specs_dir in [&plain_dir, &gzip_dir] {
+        assert!(generate(specs_dir).status().unwrap().success());
+    }
+
+    let plain = markers(&plain_dir.join("a.txt"));
+    assert_eq!(plain.len(), 140);
+    assert_eq!(plain, markers(&gzip_dir.join("a.txt")));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn toml_and_yaml_specs_match_json() {
+    let dir = scratch_dir("formats");
+    let specs = Path::new(env!("CARGO_MANIFEST_DIR")).join("../workload-gen/test_specs");
+    for ext in ["json", "toml", "yaml"] {
+        fs::copy(
+            specs.join(format!("spec_formats.{ext}")),
+            dir.join(format!("{ext}.spec.{ext}")),
+        )
+        .unwrap();
+    }
+
+    let status = generate(&dir).arg("--validate").status().unwrap();
+    assert!(status.success());
+
+    // The specs are seeded, so every format generates the same workload.
+    let json = fs::read(dir.join("json.txt")).unwrap();
+    assert_eq!(json.iter().filter(|&&b| b == b'\n').count(), 2500);
+    assert_eq!(json, fs::read(dir.join("toml.txt")).unwrap());
+    assert_eq!(json, fs::read(dir.join("yaml.txt")).unwrap());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn name_template_renders_placeholders() {
+    let dir = scratch_dir("name-template");
+    let output_dir = dir.join("out");
+    write_spec(
+        &dir,
+        "sweep.spec.json",
+        r#"{ "seed": 3, "sections": [{ "groups": [
+            { "inserts": { "amount": 100, "key_len": 8, "val_len": 8 } },
+            { "point_queries": { "amount": 50 } }
+        ] }] }"#,
+    );
+
+    let generate_with = |template: &str| {
+        return generate(&dir)
+            .arg("-o")
+            .arg(&output_dir)
+            .args(["--name-template", template])
+            .output()
+            .unwrap();
+    };
+    let output = generate_with("{stem}-{ops}.txt");
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(line_count(&output_dir.join("sweep-150.txt")), 150);
+
+    assert!(generate_with("{stem}-seed{seed}.txt").status.success());
+    assert!(output_dir.join("sweep-seed3.txt").exists());
+
+    let output = generate_with("{stem}-{threads}.txt");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("unknown placeholder {threads}"), "{stderr}");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dump_keys_matches_distinct_inserts() {
+    let dir = scratch_dir("dump-keys");
+    let spec_path = write_spec(
+        &dir,
+        "keys.spec.json",
+        r#"{ "sections": [{ "groups": [
+            { "inserts": { "amount": 100, "key_len": 16, "val_len": 8, "allow_duplicate_keys": false } },
+            { "deletes": { "amount": 30 } },
+            { "inserts": { "amount": 20, "key_len": 16, "val_len": 8, "allow_duplicate_keys": false } }
+        ] }] }"#,
+    );
+    let keys_path = dir.join("keys.txt");
+
+    let status = generate(&spec_path)
+        .arg("-o")
+        .arg(&dir)
+        .arg("--dump-keys")
+        .arg(&keys_path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let workload = fs::read_to_string(dir.join("keys.spec.txt")).unwrap();
+    let inserted: HashSet<&str> = workload
+        .lines()
+        .filter(|line| line.starts_with("I "))
+        .map(|line| line.split(' ').nth(1).unwrap())
+        .collect();
+    let dumped = fs::read_to_string(&keys_path).unwrap();
+    let dumped: Vec<&str> = dumped.lines().collect();
+    assert_eq!(inserted.len(), 120);
+    assert_eq!(dumped.len(), inserted.len());
+    assert!(dumped.iter().all(|key| inserted.contains(key)));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn scale_keeps_the_operation_mix() {
+    let dir = scratch_dir("scale");
+    let spec_path = write_spec(
+        &dir,
+        "workload.spec.json",
+        r#"{ "sections": [{ "groups": [
+            { "inserts": { "amount": 3000, "key_len": 8, "val_len": 8 } },
+            { "point_queries": { "amount": 1000 } }
+        ] }] }"#,
+    );
+
+    let status = generate(&spec_path)
+        .args(["--quiet", "--scale", "400"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let workload = fs::read_to_string(dir.join("workload.spec.txt")).unwrap();
+    assert_eq!(workload.lines().count(), 400);
+    assert_eq!(count_lines(&workload, "I "), 300);
+    assert_eq!(count_lines(&workload, "P "), 100);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn ycsb_c_only_reads_after_load() {
+    let preset = cli()
+        .args(["preset", "ycsb-c", "--record-count", "100"])
+        .args(["--operation-count", "500"])
+        .output()
+        .unwrap();
+    assert!(preset.status.success(), "{preset:?}");
+
+    let output = output_with_stdin(generate("-").args(["-o", "-"]), &preset.stdout);
+    assert!(output.status.success());
+
+    let workload = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = workload.lines().collect();
+    assert_eq!(lines.len(), 600);
+    let (load, run) = lines.split_at(100);
+    assert!(load.iter().all(|line| line.starts_with("I user")));
+    assert!(run.iter().all(|line| line.starts_with("P user")));
+}
+
+#[test]
+fn split_phases_writes_load_and_run_files() {
+    let dir = scratch_dir("split-phases");
+    let output_dir = dir.join("out");
+    write_spec(
+        &dir,
+        "phases.spec.json",
+        r#"{ "sections": [{ "groups": [
+            { "inserts": { "amount": 100, "key_len": 8, "val_len": 8 } },
+            { "updates": { "amount": 30, "val_len": 8 }, "point_queries": { "amount": 20 } }
+        ] }] }"#,
+    );
+
+    let output = generate(&dir)
+        .arg("-o")
+        .arg(&output_dir)
+        .arg("--split-phases")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert!(!output_dir.join("phases.txt").exists());
+
+    let load = fs::read_to_string(output_dir.join("phases.load.txt")).unwrap();
+    assert_eq!(load.lines().count(), 100);
+    assert_eq!(count_lines(&load, "I "), 100);
+    let run = fs::read_to_string(output_dir.join("phases.run.txt")).unwrap();
+    assert_eq!(run.lines().count(), 50);
+    assert_eq!(count_lines(&run, "I "), 0);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn mmap_matches_buffered_output() {
+    let dir = scratch_dir("mmap");
+    write_spec(
+        &dir,
+        "mapped.spec.json",
+        r#"{ "seed": 5, "sections": [{ "groups": [
+            { "inserts": { "amount": 1000, "key_len": 8, "val_len": 8 } },
+            { "point_queries": { "amount": 500 }, "range_queries": { "amount": 10, "selectivity": 0.01 } }
+        ] }] }"#,
+    );
+
+    let generate_with = |output: &str, mmap: bool| {
+        let mut command = generate(&dir);
+        command.arg("-o").arg(dir.join(output));
+        if mmap {
+            command.arg("--mmap");
+        }
+        let output = command.output().unwrap();
+        assert!(output.status.success(), "{output:?}");
+    };
+    generate_with("buffered", false);
+    generate_with("mapped", true);
+
+    let buffered = fs::read(dir.join("buffered").join("mapped.txt")).unwrap();
+    let mapped = fs::read(dir.join("mapped").join("mapped.txt")).unwrap();
+    assert_eq!(buffered.len(), 1000 * 20 + 500 * 11 + 10 * 20);
+    assert_eq!(mapped, buffered);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn mmap_leaves_no_file_for_invalid_specs() {
+    let dir = scratch_dir("mmap-invalid");
+    let output_dir = dir.join("out");
+    write_spec(
+        &dir,
+        "bad.spec.json",
+        r#"{ "sections": [{ "groups": [
+            { "inserts": { "amount": 10, "key_len": 8, "val_len": 8 } },
+            { "deletes": { "amount": 11 } }
+        ] }] }"#,
+    );
+
+    // A rerun doesn't trip over a file left by the first run.
+    for _ in 0..2 {
+        let output = generate(&dir)
+            .arg("-o")
+            .arg(&output_dir)
+            .args(["--quiet", "--mmap"])
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{output:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(!stderr.contains("already exists"), "{stderr}");
+        assert!(!output_dir.join("bad.spec.txt").exists());
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn shards_by_key() {
+    let dir = scratch_dir("shards");
+    let spec_path = write_spec(
+        &dir,
+        "shards.json",
+        r#"{ "sections": [{ "groups": [
+            { "inserts": { "amount": 1000, "key_len": 16, "val_len": 8 } },
+            { "updates": { "amount": 200, "val_len": 8 }, "deletes": { "amount": 100 } },
+            { "point_queries": { "amount": 300 }, "flushes": { "amount": 2 } }
+        ] }] }"#,
+    );
+    let status = generate(&spec_path)
+        .args(["--shards", "4"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let mut key_shards: HashMap<String, usize> = HashMap::new();
+    let mut ops = 0;
+    for shard in 0..4 {
+        let workload = fs::read_to_string(dir.join(format!("shards.shard{shard}.txt"))).unwrap();
+        let mut flushes = 0;
+        for line in workload.lines() {
+            let Some(key) = line.split(' ').nth(1) else {
+                flushes += 1;
+                continue;
+            };
+            ops += 1;
+            // Every operation on a key is in the shard of its first operation.
+            let first = *key_shards.entry(key.to_string()).or_insert(shard);
+            assert_eq!(first, shard, "{line}");
+        }
+        assert_eq!(flushes, 2);
+    }
+    assert_eq!(ops, 1600);
+    assert!(key_shards.len() > 900);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn throttled_operations_arrive_live() {
+    let dir = scratch_dir("ops-per-sec");
+    let spec_path = write_spec(
+        &dir,
+        "workload.spec.json",
+        r#"{ "sections": [{ "groups": [{ "inserts": { "amount": 50, "key_len": 8, "val_len": 8 } }] }] }"#,
+    );
+
+    let start = Instant::now();
+    let mut child = generate(&spec_path)
+        .args(["-o", "-", "--quiet", "--ops-per-sec", "50"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+
+    // The first operations are flushed while the rest are still held back.
+    assert!(lines.next().unwrap().unwrap().starts_with("I "));
+    let first = start.elapsed();
+    assert_eq!(lines.count(), 49);
+    let elapsed = start.elapsed();
+    assert!(child.wait().unwrap().success());
+    assert!(elapsed >= Duration::from_millis(950), "{elapsed:?}");
+    // The other 49 operations are due over the 980ms after the first, however long it took to
+    // start, so the first one arrived well before they were written.
+    assert!(
+        elapsed - first >= Duration::from_millis(500),
+        "{first:?} of {elapsed:?}"
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn inspect_generated_workload() {
+    let dir = scratch_dir("inspect");
+    let spec_path = write_spec(
+        &dir,
+        "inspect.json",
+        r#"{ "sections": [{ "groups": [
+            { "inserts": { "amount": 1000, "key_len": 16, "val_len": 8, "allow_duplicate_keys": false } },
+            { "deletes": { "amount": 10 }, "point_queries": { "amount": 20 } },
+            { "range_queries": { "amount": 5, "selectivity": 0.1 } }
+        ] }] }"#,
+    );
+    assert!(generate(&spec_path).status().unwrap().success());
+
+    let workload_path = dir.join("inspect.txt");
+    let inspect_ok = |path: &Path| {
+        let output = inspect(path).output().unwrap();
+        assert!(output.status.success());
+        return (
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+        );
+    };
+    let (stdout, _) = inspect_ok(&workload_path);
+    assert!(stdout.contains("1035 ops"), "{stdout}");
+    assert!(stdout.contains("inserts: 1000\n"), "{stdout}");
+    assert!(stdout.contains("deletes: 10\n"), "{stdout}");
+    assert!(stdout.contains("point queries: 20\n"), "{stdout}");
+    assert!(stdout.contains("range queries: 5\n"), "{stdout}");
+    assert!(stdout.contains("distinct keys: 1000\n"), "{stdout}");
+    assert!(stdout.contains("malformed lines: 0\n"), "{stdout}");
+
+    let mut workload = fs::read_to_string(&workload_path).unwrap();
+    workload.push_str("X bogus\n");
+    fs::write(&workload_path, workload).unwrap();
+    let (stdout, stderr) = inspect_ok(&workload_path);
+    assert!(stdout.contains("malformed lines: 1\n"), "{stdout}");
+    assert!(stderr.contains("line 1036"), "{stderr}");
+    // The malformed lines are warnings, which the log filter can silence.
+    let output = inspect(&workload_path)
+        .env("RUST_LOG", "error")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(
+        output.stderr.is_empty(),
+        "{:?}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn inspect_custom_separators() {
+    let dir = scratch_dir("inspect-separators");
+    let spec_path = write_spec(
+        &dir,
+        "separators.json",
+        r#"{ "sections": [{ "groups": [
+            { "inserts": { "amount": 100, "key_len": 16, "val_len": 8, "allow_duplicate_keys": false } },
+            { "point_queries": { "amount": 20 } }
+        ] }] }"#,
+    );
+    let separators = ["--field-sep", "\\t", "--record-sep", "\\0"];
+    let status = generate(&spec_path)
+        .args(separators)
+        .arg("--footer")
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let workload_path = dir.join("separators.txt");
+    let output = inspect(&workload_path).args(separators).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("120 ops"), "{stdout}");
+    assert!(stdout.contains("inserts: 100\n"), "{stdout}");
+    assert!(stdout.contains("point queries: 20\n"), "{stdout}");
+    assert!(stdout.contains("distinct keys: 100\n"), "{stdout}");
+    assert!(stdout.contains("malformed lines: 0\n"), "{stdout}");
+    assert!(stdout.contains("verified footers: 1\n"), "{stdout}");
+
+    // Control characters other than the escapes, and backslashes, aren't separators.
+    for sep in ["\u{1}", "\\"] {
+        let output = inspect(&workload_path)
+            .args(["--field-sep", sep])
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn header_is_checked_by_inspect() {
+    let dir = scratch_dir("header");
+    let spec_path = write_spec(
+        &dir,
+        "header.json",
+        r#"{ "sections": [{ "groups": [
+            { "inserts": { "amount": 500, "key_len": 8, "val_len": 8 } },
+            { "point_queries": { "amount": 100 }, "updates": { "amount": 50, "val_len": 20 } }
+        ] }] }"#,
+    );
+    let status = generate(&spec_path)
+        .args(["--header", "--footer"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let workload_path = dir.join("header.txt");
+    let workload = fs::read_to_string(&workload_path).unwrap();
+    assert_eq!(
+        workload.lines().next().unwrap(),
+        "# max_key=8 max_val=20 ops=650"
+    );
+    let output = inspect(&workload_path).output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("verified headers: 1\n"), "{stdout}");
+    assert!(stdout.contains("verified footers: 1\n"), "{stdout}");
+    assert!(stdout.contains("longest value: 20\n"), "{stdout}");
+
+    // A header promising shorter values than the workload has is caught.
+    let understated = workload.replacen("max_val=20", "max_val=10", 1);
+    fs::write(&workload_path, understated).unwrap();
+    let output = inspect(&workload_path).output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("longer than its header"), "{stderr}");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn footer_is_verified_by_inspect() {
+    let dir = scratch_dir("footer");
+    let spec_path = write_spec(
+        &dir,
+        "footer.json",
+        r#"{ "sections": [{ "groups": [
+            { "inserts": { "amount": 500, "key_len": 8, "val_len": 8 } },
+            { "point_queries": { "amount": 100 }, "updates": { "amount": 50, "val_len": 8 } }
+        ] }] }"#,
+    );
+    let generate_with = |extra: &[&str]| {
+        return generate(&spec_path)
+            .arg("--footer")
+            .args(extra)
+            .output()
+            .unwrap();
+    };
+    assert!(generate_with(&[]).status.success());
+
+    let workload_path = dir.join("footer.txt");
+    let workload = fs::read_to_string(&workload_path).unwrap();
+    let footer = workload.lines().last().unwrap();
+    assert!(footer.starts_with("# ops=650 crc="), "{footer}");
+    assert_eq!(workload.lines().count(), 651);
+
+    let output = inspect(&workload_path).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("verified footers: 1\n"), "{stdout}");
+
+    // Turning an insert into an update keeps the op count but changes the crc.
+    let corrupted = workload.replacen("I ", "U ", 1);
+    fs::write(&workload_path, corrupted).unwrap();
+    let output = inspect(&workload_path).output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("doesn't match the workload"), "{stderr}");
+
+    // Only ascii workloads can carry a footer.
+    assert!(!generate_with(&["-f", "jsonl"]).status.success());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn convert_ascii_to_msgpack_and_back() {
+    let dir = scratch_dir("convert");
+    let spec_path = write_spec(
+        &dir,
+        "convert.json",
+        r#"{ "seed": 3, "sections": [{ "warmup_point_queries": 10, "groups": [
+            { "inserts": { "amount": 1000, "key_len": 16, "val_len": 8 } },
+            { "updates": { "amount": 50, "val_len": 8 }, "deletes": { "amount": 10 } },
+            { "point_queries": { "amount": 20 }, "range_queries": { "amount": 5, "selectivity": 0.1 } }
+        ] }] }"#,
+    );
+    assert!(generate(&spec_path).status().unwrap().success());
+
+    let ascii_path = dir.join("convert.txt");
+    let msgpack_path = dir.join("convert.msgpack");
+    let status = cli()
+        .args(["convert", "--from", "ascii", "--to", "msgpack", "-i"])
+        .arg(&ascii_path)
+        .arg("-o")
+        .arg(&msgpack_path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let output = cli()
+        .args(["convert", "--from", "msgpack", "--to", "ascii", "-i"])
+        .arg(&msgpack_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, fs::read(&ascii_path).unwrap());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn lint_reports_invalid_specs() {
+    let dir = scratch_dir("lint");
+    write_spec(
+        &dir,
+        "good.spec.json",
+        r#"{ "sections": [{ "groups": [
+            { "inserts": { "amount": 100, "key_len": 8, "val_len": 8 } }
+        ] }] }"#,
+    );
+    write_spec(
+        &dir,
+        "bad.spec.json",
+        r#"{ "sections": [{ "groups": [
+            { "inserts": { "amount": 100, "key_len": 8, "val_len": 8 }, "deletes": { "amount": "many" } }
+        ] }] }"#,
+    );
+
+    let output = cli().arg("lint").arg(&dir).output().unwrap();
+    assert!(!output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("bad.spec.json"), "{stdout}");
+    assert!(!stdout.contains("good.spec.json"), "{stdout}");
+    assert!(stdout.contains("2 specs, 1 failed"), "{stdout}");
+    assert_eq!(
+        fs::read_dir(&dir).unwrap().count(),
+        2,
+        "lint generated files"
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn diff_specs_reports_deltas() {
+    let specs = Path::new(env!("CARGO_MANIFEST_DIR")).join("../workload-gen/test_specs");
+    let output = cli()
+        .arg("diff-specs")
+        .arg(specs.join("1m_i.json"))
+        .arg(specs.join("1m_i-1m_rq.json"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("range queries: 0 -> 1000000 (+1000000)"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("inserts: 1000000 -> 1000000 (+0)"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("section 0 groups: 1 -> 2 (+1)"), "{stdout}");
+}
+
+#[test]
+fn schema_to_file() {
+    let dir = scratch_dir("schema");
+    let schema_path = dir.join("workload.schema.json");
+
+    let status = cli()
+        .arg("schema")
+        .arg("-o")
+        .arg(&schema_path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let schema: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&schema_path).unwrap()).unwrap();
+    assert_eq!(schema["title"], "WorkloadSpec");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "replay")]
+mod replay {
+    use super::*;
+
+    /// `replay` of the workload at `workload_path` into the database at `db_path`.
+    fn replay(workload_path: &Path, db_path: &Path) -> Command {
+        let mut command = cli();
+        command
+            .arg("replay")
+            .arg("-w")
+            .arg(workload_path)
+            .arg("-d")
+            .arg(db_path);
+        return command;
+    }
+
+    #[test]
+    fn replay_tiny_workload() {
+        let dir = scratch_dir("replay");
+        let workload_path = dir.join("tiny.txt");
+        let db_path = dir.join("db");
+        fs::write(
+            &workload_path,
+            "I a 1\nI b 2\nI c 3\nU a 4\nD b\nP a\nP b\nR a c\nR c a\n",
+        )
+        .unwrap();
+
+        let status = replay(&workload_path, &db_path).status().unwrap();
+        assert!(status.success());
+
+        let db = rocksdb::DB::open_default(&db_path).unwrap();
+        assert_eq!(db.iterator(rocksdb::IteratorMode::Start).count(), 2);
+        assert_eq!(db.get(b"a").unwrap().as_deref(), Some(&b"4"[..]));
+        drop(db);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn replay_escaped_separators() {
+        let dir = scratch_dir("replay-escaped");
+        let workload_path = dir.join("escaped.txt");
+        let db_path = dir.join("db");
+        fs::write(&workload_path, "I\ta\\\tb\t1\0I\tc\\\\\t2\0P\ta\\\tb\0").unwrap();
+
+        let status = replay(&workload_path, &db_path)
+            .args([
+                "--field-sep",
+                "\\t",
+                "--record-sep",
+                "\\0",
+                "--escape-separators",
+            ])
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let db = rocksdb::DB::open_default(&db_path).unwrap();
+        assert_eq!(db.get(b"a\tb").unwrap().as_deref(), Some(&b"1"[..]));
+        assert_eq!(db.get(b"c\\").unwrap().as_deref(), Some(&b"2"[..]));
+        drop(db);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
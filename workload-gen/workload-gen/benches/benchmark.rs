@@ -1,8 +1,55 @@
 #![allow(non_snake_case)]
 
 use criterion::{criterion_group, criterion_main, Criterion};
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256Plus;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
 use std::io::sink;
-use workload_gen::{spec::WorkloadSpec, write_operations};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use workload_gen::keyset::{
+    KeySet, OrderStatKeySet, RoaringKeySet, VecBloomFilterKeySet, VecCuckooFilterKeySet,
+    VecHashSetKeySet,
+};
+use workload_gen::{
+    generate_to_vec, generate_workload_with_progress,
+    spec::{Inserts, KeyEviction, KeySpace, WorkloadSpec, WorkloadSpecGroup},
+    write_operations, write_operations_with_options, GenerateOptions, Key,
+};
+
+/// Allocator that counts the heap bytes allocated while `COUNTING` is set, to measure key set
+/// memory. The other benchmarks only pay for the check of the flag.
+struct CountingAlloc;
+
+static COUNTING: AtomicBool = AtomicBool::new(false);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.load(Ordering::Relaxed) {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if COUNTING.load(Ordering::Relaxed) {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        unsafe { System.dealloc(ptr, layout) };
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if COUNTING.load(Ordering::Relaxed) {
+            ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
 
 fn bench_1m_i__1m_i_1m_u() {
     let spec_str = include_str!("../test_specs/benchmarks/1m_i-1m_i_1m_u.json");
@@ -35,6 +82,12 @@ fn bench_10k_i__100_i_10k_rq() {
     write_operations(&mut sink(), &spec).unwrap();
 }
 
+fn bench_1m_i__100k_d() {
+    let spec_str = include_str!("../test_specs/deletes.json");
+    let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+    write_operations(&mut sink(), &spec).unwrap();
+}
+
 fn bench_10k_i__10k_rq() {
     let spec_str = include_str!("../test_specs/benchmarks/1m_i-1m_rq.json");
     let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
@@ -46,23 +99,193 @@ fn bench_100k_i__1k_eqp() {
     write_operations(&mut sink(), &spec).unwrap();
 }
 
+fn bench_100k_i__100k_eqp_numeric() {
+    let spec_str = include_str!("../test_specs/benchmarks/100k_i-100k_eqp_numeric.json");
+    let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+    write_operations(&mut sink(), &spec).unwrap();
+}
+
+fn bench_1m_i(threads: usize) {
+    let spec_str = include_str!("../test_specs/1m_i.json");
+    let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+    let options = GenerateOptions {
+        threads,
+        ..GenerateOptions::default()
+    };
+    write_operations_with_options(&mut sink(), &spec, options, |_, _| {}).unwrap();
+}
+
+/// Writes the inserts of `1m_i.json` while tracking their keys, which a section of only inserts
+/// otherwise skips. A cap that is never reached forces the tracking without evicting keys.
+fn bench_1m_i_tracked() {
+    let spec = WorkloadSpec::builder()
+        .key_space(KeySpace::Alphanumeric)
+        .max_tracked_keys(1_000_000)
+        .key_eviction(KeyEviction::Random)
+        .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1_000_000, 16, 48)))
+        .build();
+    write_operations(&mut sink(), &spec).unwrap();
+}
+
+/// Writes `1m_i.json` to a file, through a memory map or a buffered writer.
+fn bench_1m_i_to_file(mmap: bool) {
+    let spec_str = include_str!("../test_specs/1m_i.json");
+    let options = GenerateOptions {
+        mmap,
+        ..GenerateOptions::default()
+    };
+    let path = std::env::temp_dir().join(format!("workload-gen-bench-{}.txt", std::process::id()));
+    generate_workload_with_progress(spec_str, path.clone(), options, |_, _| {}).unwrap();
+    std::fs::remove_file(path).unwrap();
+}
+
+fn bench_1m_i_to_vec() {
+    let spec_str = include_str!("../test_specs/1m_i.json");
+    let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+    black_box(generate_to_vec(&spec).unwrap());
+}
+
+fn gen_numeric_key(rng: &mut Xoshiro256Plus, len: usize) -> Key {
+    (0..len).map(|_| rng.random_range(b'0'..=b'9')).collect()
+}
+
+/// Fills a key set with 100k numeric keys, matching `100k_i-100k_eqp_numeric.json`.
+fn build_numeric_keyset<K: KeySet>() -> K {
+    let mut rng = Xoshiro256Plus::seed_from_u64(0);
+    let mut key_set = K::new(100_000);
+    for _ in 0..100_000 {
+        key_set.push(gen_numeric_key(&mut rng, 12));
+    }
+    key_set
+}
+
+/// Measures the heap bytes held by a key set of 100k numeric keys, including the keys, next to its
+/// own `memory_bytes`, and times building it.
+fn bench_numeric_keyset_memory<K: KeySet>(c: &mut Criterion, name: &str) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    COUNTING.store(true, Ordering::Relaxed);
+    let key_set = build_numeric_keyset::<K>();
+    COUNTING.store(false, Ordering::Relaxed);
+    let measured = ALLOCATED.load(Ordering::Relaxed).wrapping_sub(before);
+    println!(
+        "{name}: {measured} bytes measured, {} bytes from memory_bytes",
+        key_set.memory_bytes()
+    );
+    drop(key_set);
+    c.bench_function(name, |b| b.iter(build_numeric_keyset::<K>));
+}
+
+fn bench_contains<K: KeySet>(c: &mut Criterion, name: &str) {
+    let key_set = build_numeric_keyset::<K>();
+    let mut rng = Xoshiro256Plus::seed_from_u64(1);
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            let key = gen_numeric_key(&mut rng, 12);
+            black_box(key_set.contains(&key))
+        })
+    });
+}
+
+/// Replays the key set operations of 10k inserts followed by 20 groups of 500 inserts, 50 deletes,
+/// and 50 range queries with a selectivity of 0.1.
+fn bench_interleaved_i_d_rq<K: KeySet>() {
+    let mut rng = Xoshiro256Plus::seed_from_u64(0);
+    let mut key_set = K::new(20_000);
+    for _ in 0..10_000 {
+        key_set.push(gen_numeric_key(&mut rng, 16));
+    }
+    for _ in 0..20 {
+        for _ in 0..500 {
+            key_set.push(gen_numeric_key(&mut rng, 16));
+        }
+        for _ in 0..50 {
+            let idx = rng.random_range(0..key_set.len());
+            black_box(key_set.remove(idx));
+        }
+        for _ in 0..50 {
+            key_set.sort();
+            let num_items = key_set.len() / 10;
+            let start_idx = rng.random_range(0..key_set.len() - num_items);
+            black_box(key_set.get(start_idx));
+            black_box(key_set.get(start_idx + num_items));
+        }
+    }
+}
+
+/// Replays the key set operations of 100k inserts followed by 20 groups of 1k inserts, 2k deletes,
+/// and 2k empty point queries, which regenerate keys until one isn't contained.
+fn bench_interleaved_d_epq<K: KeySet>() {
+    let mut rng = Xoshiro256Plus::seed_from_u64(0);
+    let mut key_set = K::new(100_000);
+    for _ in 0..100_000 {
+        key_set.push(gen_numeric_key(&mut rng, 16));
+    }
+    for _ in 0..20 {
+        for _ in 0..1_000 {
+            key_set.push(gen_numeric_key(&mut rng, 16));
+        }
+        for _ in 0..2_000 {
+            let idx = rng.random_range(0..key_set.len());
+            black_box(key_set.remove(idx));
+        }
+        for _ in 0..2_000 {
+            let key = std::iter::repeat_with(|| gen_numeric_key(&mut rng, 16))
+                .find(|key| !key_set.contains(key));
+            black_box(key);
+        }
+    }
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
-    c.bench_function("insert + update", |b| b.iter(|| bench_1m_i__1m_i_1m_u()));
-    c.bench_function("insert + delete", |b| b.iter(|| bench_1m_i__1m_i_1m_d()));
-    c.bench_function("insert + point query", |b| {
-        b.iter(|| bench_1m_i__1m_i_1m_pq())
+    c.bench_function("insert (1 thread)", |b| b.iter(|| bench_1m_i(1)));
+    c.bench_function("insert (4 threads)", |b| b.iter(|| bench_1m_i(4)));
+    c.bench_function("insert (tracked keys)", |b| b.iter(bench_1m_i_tracked));
+    c.bench_function("insert (to vec)", |b| b.iter(bench_1m_i_to_vec));
+    c.bench_function("insert (to file, buffered)", |b| {
+        b.iter(|| bench_1m_i_to_file(false))
+    });
+    c.bench_function("insert (to file, mmap)", |b| {
+        b.iter(|| bench_1m_i_to_file(true))
     });
+    c.bench_function("insert + update", |b| b.iter(bench_1m_i__1m_i_1m_u));
+    c.bench_function("insert + delete", |b| b.iter(bench_1m_i__1m_i_1m_d));
+    c.bench_function("insert + delete (100k)", |b| b.iter(bench_1m_i__100k_d));
+    c.bench_function("insert + point query", |b| b.iter(bench_1m_i__1m_i_1m_pq));
     c.bench_function("insert + range query (even)", |b| {
-        b.iter(|| bench_10k_i__10k_i_10k_rq())
+        b.iter(bench_10k_i__10k_i_10k_rq)
     });
     c.bench_function("insert + range query (heavy i)", |b| {
-        b.iter(|| bench_10k_i__100k_i_100_rq())
+        b.iter(bench_10k_i__100k_i_100_rq)
     });
     c.bench_function("insert + range query (heavy rq)", |b| {
-        b.iter(|| bench_10k_i__100_i_10k_rq())
+        b.iter(bench_10k_i__100_i_10k_rq)
+    });
+    c.bench_function("range query", |b| b.iter(bench_10k_i__10k_rq));
+    c.bench_function("empty point query", |b| b.iter(bench_100k_i__1k_eqp));
+    c.bench_function("empty point query (numeric)", |b| {
+        b.iter(bench_100k_i__100k_eqp_numeric)
+    });
+    bench_numeric_keyset_memory::<VecHashSetKeySet>(c, "build 100k numeric (hash set)");
+    bench_numeric_keyset_memory::<RoaringKeySet>(c, "build 100k numeric (roaring)");
+    bench_numeric_keyset_memory::<VecCuckooFilterKeySet>(c, "build 100k numeric (cuckoo filter)");
+    bench_contains::<VecHashSetKeySet>(c, "contains (hash set)");
+    bench_contains::<RoaringKeySet>(c, "contains (roaring)");
+    bench_contains::<VecCuckooFilterKeySet>(c, "contains (cuckoo filter)");
+    c.bench_function("interleaved d/epq (hash set)", |b| {
+        b.iter(bench_interleaved_d_epq::<VecHashSetKeySet>)
+    });
+    c.bench_function("interleaved d/epq (bloom filter)", |b| {
+        b.iter(bench_interleaved_d_epq::<VecBloomFilterKeySet>)
+    });
+    c.bench_function("interleaved d/epq (cuckoo filter)", |b| {
+        b.iter(bench_interleaved_d_epq::<VecCuckooFilterKeySet>)
+    });
+    c.bench_function("interleaved i/d/rq (bloom filter)", |b| {
+        b.iter(bench_interleaved_i_d_rq::<VecBloomFilterKeySet>)
+    });
+    c.bench_function("interleaved i/d/rq (order statistic tree)", |b| {
+        b.iter(bench_interleaved_i_d_rq::<OrderStatKeySet>)
     });
-    c.bench_function("range query", |b| b.iter(|| bench_10k_i__10k_rq()));
-    c.bench_function("empty point query", |b| b.iter(|| bench_100k_i__1k_eqp()));
 }
 
 criterion_group!(
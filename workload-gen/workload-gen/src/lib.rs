@@ -1,27 +1,178 @@
 #![allow(clippy::needless_return)]
 #![allow(dead_code)]
 
-use anyhow::{bail, Context, Result};
-use rand::distr::Alphanumeric;
-use rand::{Rng, SeedableRng};
-use rand_xoshiro::Xoshiro256Plus;
+use rand::Rng;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fs::File;
-use std::io::{BufWriter, Write};
-use std::path::PathBuf;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicUsize;
+use std::sync::Mutex;
+
+/// Errors of parsing, validating, and generating a workload.
+pub mod error {
+    /// Why a workload couldn't be generated.
+    #[derive(thiserror::Error, Debug)]
+    pub enum WorkloadError {
+        /// The spec isn't valid json or doesn't deserialize into a spec.
+        #[error("parsing json file: {0}")]
+        Parse(#[from] serde_json::Error),
+        /// Same as [`WorkloadError::Parse`], pointing at the `line` and `column` of the json spec
+        /// and the `path` of the offending value, e.g. `sections[0].groups[1].inserts.amount`.
+        #[error("parsing json spec at line {line}, column {column}, in `{path}`: {message}")]
+        ParseSpec {
+            line: usize,
+            column: usize,
+            path: String,
+            message: String,
+        },
+        /// The spec isn't valid toml or doesn't deserialize into a spec.
+        #[error("parsing toml file: {0}")]
+        ParseToml(#[from] toml::de::Error),
+        /// The spec isn't valid yaml or doesn't deserialize into a spec.
+        #[error("parsing yaml file: {0}")]
+        ParseYaml(#[from] serde_yaml::Error),
+        /// The spec doesn't match the json schema, one message per error.
+        #[error("Invalid workload spec.\n{}", .0.join("\n"))]
+        Schema(Vec<String>),
+        /// A parameter of the spec is out of range or inconsistent with another one.
+        #[error("Invalid workload spec. {0}")]
+        InvalidSpec(String),
+        /// A workload without sections.
+        #[error("Invalid workload spec. The workload has no sections.")]
+        EmptyWorkload,
+        /// A section without groups.
+        #[error("Invalid workload spec. Section {section} has no groups.")]
+        EmptySection { section: usize },
+        /// A group without any operations.
+        #[error("Invalid workload spec. Group {group} of section {section} has no operations.")]
+        EmptyGroup { section: usize, group: usize },
+        /// A group deletes more keys than are valid by then.
+        #[error("Invalid workload spec. Group {group} deletes {deletes} keys, but only {live_keys} valid keys exist by then.")]
+        TooManyDeletes {
+            group: usize,
+            deletes: usize,
+            live_keys: usize,
+        },
+        /// Interleaved groups delete more keys than they insert in total.
+        #[error("Invalid workload spec. The interleaved groups delete {deletes} keys, but only {inserted} keys are inserted.")]
+        TooManyInterleavedDeletes { deletes: usize, inserted: usize },
+        /// An operation needs a valid key, but there is none.
+        #[error("Invalid workload spec. {0}")]
+        NoValidKeys(String),
+        /// No new key could be generated, the key space is too small for the keys in use.
+        #[error("{0}")]
+        KeySpaceExhausted(String),
+        /// JSON Lines output requires utf8 keys.
+        #[error("JSON Lines output requires utf8 keys")]
+        NonUtf8Key(#[from] std::str::Utf8Error),
+        #[error("Unknown output format `{0}`, expected `ascii`, `jsonl`, or `msgpack`")]
+        UnknownFormat(String),
+        /// No format of this name is registered in a [`crate::writer::FormatRegistry`].
+        #[error("Unknown output format `{name}`, expected one of {}", .registered.join(", "))]
+        UnregisteredFormat {
+            name: String,
+            registered: Vec<String>,
+        },
+        #[error("Unknown preset `{0}`, expected one of `ycsb-a` to `ycsb-f`")]
+        UnknownPreset(String),
+        /// Keys of [`crate::spec::KeySpace::Binary`] can't be written in a text format.
+        #[error("Binary keys can't be written in the {format} format, use msgpack")]
+        BinaryKeys { format: &'static str },
+        /// Sharded output needs at least one shard, and operations without a key can only go to
+        /// one of them.
+        #[error("Sharded output needs at least 1 shard and a keyless shard below the number of shards, got {shards} shards and keyless shard {keyless_shard:?}")]
+        InvalidShards {
+            shards: usize,
+            keyless_shard: Option<usize>,
+        },
+        /// Throttled generation needs a rate it can keep.
+        #[error("ops_per_sec must be a positive finite number, but is {0}")]
+        InvalidOpsPerSec(f64),
+        /// A key or value contains a separator of the ascii format, which would split it.
+        #[error("The ascii field {field:?} contains the separator {sep:?}, escape separators or use other ones")]
+        SeparatorInField { field: String, sep: char },
+        /// A record of a workload being read back isn't an operation of its format. Records
+        /// are counted from 1, lines for ascii and JSON Lines.
+        #[error("Malformed operation in record {record}: {message}")]
+        MalformedOperation { record: usize, message: String },
+        #[error(transparent)]
+        Io(#[from] std::io::Error),
+        #[error(transparent)]
+        Msgpack(#[from] rmp_serde::encode::Error),
+        #[cfg(feature = "sst")]
+        #[error(transparent)]
+        RocksDb(#[from] rocksdb::Error),
+    }
+
+    pub type Result<T, E = WorkloadError> = std::result::Result<T, E>;
+}
 
 /// Workload specification.
 pub mod spec {
+    use crate::error::{Result, WorkloadError};
+    use crate::keygen::{
+        AlphanumericKeyGen, BinaryKeyGen, CharsetKeyGen, KeyGen, NumericKeyGen, NumericRangeKeyGen,
+        UuidKeyGen, ALPHANUMERIC,
+    };
+    use crate::keyset::{KeySet, KeySetKind};
+    use crate::rng::WorkloadRng;
+    use crate::writer::{AsciiFormat, FieldEscaping};
+    use crate::{Key, WorkloadStats};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use rand_distr::Zipf;
+    use rand_pcg::Pcg64;
+    use rand_xoshiro::Xoshiro256Plus;
     use schemars::JsonSchema;
+    use std::borrow::Cow;
+    use std::path::Path;
 
     /// Specification for inserts in a workload group.
     #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
     pub struct Inserts {
         /// Number of inserts
         pub(crate) amount: usize,
-        /// Key length
+        /// Key length, unless `key_lens` is set.
+        #[serde(default)]
         pub(crate) key_len: usize,
+        /// Draws the key length of every insert from these classes, in proportion to their
+        /// weights, instead of using `key_len`, e.g. `[{"key_len": 8, "weight": 3}, {"key_len":
+        /// 32, "weight": 1}]`. At most 8 classes.
+        #[serde(default)]
+        #[schemars(with = "Option<Vec<KeyLenWeight>>")]
+        pub(crate) key_lens: Option<KeyLenClasses>,
         /// Value length
-        pub(crate) val_len: usize,
+        pub(crate) val_len: ValLen,
+        /// Content of the values
+        #[serde(default)]
+        pub(crate) val_pattern: ValPattern,
+        /// Allow inserting a key that is already valid. Otherwise, colliding keys are regenerated.
+        /// Duplicates stay valid once per insert, so a duplicated key has to be deleted as many
+        /// times as it was inserted.
+        #[serde(default = "default_true")]
+        pub(crate) allow_duplicate_keys: bool,
+        /// Fraction of the inserts that overwrite a valid key instead of creating a new one,
+        /// spread evenly over the inserts. The first insert always creates a key.
+        #[serde(default)]
+        pub(crate) overwrite_ratio: f32,
+        /// Fraction of the inserts that reinsert a key deleted earlier in the section, picked at
+        /// random, instead of creating a new one, spread evenly over the inserts that don't
+        /// overwrite. Creates a new key while no deleted key is left. With `max_tracked_keys`,
+        /// at most that many deleted keys are remembered for reinserting.
+        #[serde(default)]
+        pub(crate) reinsert_ratio: f32,
+        /// Insert the new keys of the group in ascending order instead of in random order. Only
+        /// the keys within the group are ordered, not relative to the keys of other groups. With
+        /// interleaved groups and no duplicate keys, a key another group inserted first is
+        /// replaced, rarely out of order.
+        #[serde(default)]
+        pub(crate) sorted: bool,
+        /// Leave the inserts out of the `bulk_load` of their section and write them with the other
+        /// operations of their group instead, like the inserts of YCSB's run phase.
+        #[serde(default)]
+        pub(crate) after_bulk_load: bool,
     }
 
     /// Specification for updates in a workload group.
@@ -30,734 +181,11880 @@ pub mod spec {
         /// Number of updates
         pub(crate) amount: usize,
         /// Value length
-        pub(crate) val_len: usize,
+        pub(crate) val_len: ValLen,
+        /// Content of the values
+        #[serde(default)]
+        pub(crate) val_pattern: ValPattern,
+        /// Update an existing valid key. Otherwise, do a blind update to a new random key with the
+        /// section's insert key length, which then becomes a valid key just like an insert.
+        #[serde(default = "default_true")]
+        pub(crate) existing: bool,
+        /// How updates of existing keys pick a key. Defaults to the section's `key_distribution`.
+        #[serde(default)]
+        pub(crate) key_distribution: Option<KeyDistribution>,
     }
 
-    /// Specification for point deletes in a workload group.
-    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
-    pub struct Deletes {
-        /// Number of deletes
-        pub(crate) amount: usize,
+    fn default_true() -> bool {
+        return true;
     }
 
-    /// Specification for point queries in a workload group.
-    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
-    pub struct PointQueries {
-        /// Number of point queries
-        pub(crate) amount: usize,
+    fn default_hit_ratio() -> f32 {
+        return 1.0;
     }
 
-    /// Specification for empty point queries in a workload group.
-    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
-    pub struct EmptyPointQueries {
-        /// Number of point queries
-        pub(crate) amount: usize,
-        /// Key length
-        pub(crate) key_len: usize,
+    /// A key length class of inserts, drawn in proportion to its weight.
+    #[derive(serde::Deserialize, JsonSchema, Default, Copy, Clone, Debug)]
+    pub struct KeyLenWeight {
+        pub key_len: usize,
+        pub weight: f64,
     }
 
-    /// Specification for range queries in a workload group.
-    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
-    pub struct RangeQueries {
-        /// Number of range queries
-        pub(crate) amount: usize,
-        /// Selectivity of range queries. Based off of the range of valid keys, not the full
-        /// key-space.
-        pub(crate) selectivity: f32,
+    /// Maximum number of classes in [`Inserts::key_lens`].
+    pub const MAX_KEY_LEN_CLASSES: usize = 8;
+
+    /// Weighted key length classes, stored inline so [`Inserts`] stays `Copy`.
+    #[derive(serde::Deserialize, Copy, Clone, Debug)]
+    #[serde(try_from = "Vec<KeyLenWeight>")]
+    pub struct KeyLenClasses {
+        classes: [KeyLenWeight; MAX_KEY_LEN_CLASSES],
+        len: usize,
     }
 
-    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
-    pub(crate) struct WorkloadSpecGroup {
-        pub(crate) inserts: Option<Inserts>,
-        pub(crate) updates: Option<Updates>,
-        pub(crate) deletes: Option<Deletes>,
-        pub(crate) point_queries: Option<PointQueries>,
-        pub(crate) empty_point_queries: Option<EmptyPointQueries>,
-        pub(crate) range_queries: Option<RangeQueries>,
+    impl TryFrom<Vec<KeyLenWeight>> for KeyLenClasses {
+        type Error = String;
+
+        fn try_from(classes: Vec<KeyLenWeight>) -> Result<Self, Self::Error> {
+            if classes.is_empty() || classes.len() > MAX_KEY_LEN_CLASSES {
+                return Err(format!(
+                    "key_lens must have between 1 and {} classes, but has {}",
+                    MAX_KEY_LEN_CLASSES,
+                    classes.len()
+                ));
+            }
+            if let Some(class) = classes
+                .iter()
+                .find(|class| !class.weight.is_finite() || class.weight <= 0.0)
+            {
+                return Err(format!(
+                    "key_lens weights must be positive and finite, but the weight of key_len {} is {}",
+                    class.key_len, class.weight
+                ));
+            }
+            let mut inline = [KeyLenWeight::default(); MAX_KEY_LEN_CLASSES];
+            inline[..classes.len()].copy_from_slice(&classes);
+            return Ok(KeyLenClasses {
+                classes: inline,
+                len: classes.len(),
+            });
+        }
     }
 
-    impl WorkloadSpecGroup {
-        pub fn operation_count(&self) -> usize {
-            let operation_count = self.inserts.map_or(0, |s| s.amount)
-                + self.updates.map_or(0, |us| us.amount)
-                + self.point_queries.map_or(0, |is| is.amount)
-                + self.empty_point_queries.map_or(0, |is| is.amount)
-                + self.range_queries.map_or(0, |is| is.amount)
-                + self.deletes.map_or(0, |is| is.amount);
-            return operation_count;
+    impl KeyLenClasses {
+        pub fn classes(&self) -> &[KeyLenWeight] {
+            return &self.classes[..self.len];
         }
 
-        pub fn bytes_count(&self, insert_key_len: usize) -> usize {
-            let bytes_insert = self.inserts.map_or(0, |is| {
-                (b"I ".len() + is.key_len + b" ".len() + is.val_len + b"\n".len()) * is.amount
-            });
-            let bytes_update = self.updates.map_or(0, |us| {
-                (b"U ".len() + insert_key_len + b" ".len() + us.val_len + b"\n".len()) * us.amount
-            });
-            let bytes_delete = self.deletes.map_or(0, |ds| {
-                (b"D ".len() + insert_key_len + b"\n".len()) * ds.amount
-            });
-            let bytes_point_queries = self.point_queries.map_or(0, |pq| {
-                (b"P ".len() + insert_key_len + b"\n".len()) * pq.amount
-            });
-            let bytes_empty_point_queries = self.empty_point_queries.map_or(0, |epq| {
-                (b"P ".len() + epq.key_len + b"\n".len()) * epq.amount
-            });
-            let bytes_range_queries = self.range_queries.map_or(0, |rq| {
-                (b"R ".len() + insert_key_len + b" ".len() + insert_key_len + b"\n".len())
-                    * rq.amount
-            });
-            return bytes_insert
-                + bytes_update
-                + bytes_delete
-                + bytes_point_queries
-                + bytes_empty_point_queries
-                + bytes_range_queries;
+        fn total_weight(&self) -> f64 {
+            return self.classes().iter().map(|class| class.weight).sum();
         }
 
-        // pub fn needs_static_sorted_keys(&self) -> bool {
-        //     return self.range_queries.is_some();
-        // }
-        //
-        // pub fn needs_dynamic_sorted_keys(&self) -> bool {
-        //     return (self.inserts.is_some() || self.deletes.is_some())
-        //         && self.range_queries.is_some();
-        // }
+        pub(crate) fn sample(&self, rng: &mut WorkloadRng) -> usize {
+            let mut pick = rng.random_range(0.0..self.total_weight());
+            for class in self.classes() {
+                if pick < class.weight {
+                    return class.key_len;
+                }
+                pick -= class.weight;
+            }
+            // Rounding can leave a sliver past the last class.
+            return self.classes()[self.len - 1].key_len;
+        }
+
+        /// Expected key length.
+        pub fn mean(&self) -> f64 {
+            let weighted: f64 = self
+                .classes()
+                .iter()
+                .map(|class| class.key_len as f64 * class.weight)
+                .sum();
+            return weighted / self.total_weight();
+        }
     }
 
-    #[derive(serde::Deserialize, JsonSchema, Default, Clone, Debug)]
+    /// Length of generated values, either fixed or sampled for every value.
+    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
+    #[serde(untagged)]
+    pub enum ValLen {
+        Fixed(usize),
+        Distribution(ValLenDistribution),
+    }
+
+    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
     #[serde(rename_all = "snake_case")]
-    pub(crate) enum KeySpace {
-        #[default]
-        Alphanumeric,
+    pub enum ValLenDistribution {
+        /// Heavy-tailed value lengths from an exponential distribution with mean `mean`, clamped
+        /// to `max`.
+        Exponential { mean: usize, max: usize },
     }
-    #[derive(serde::Deserialize, JsonSchema, Default, Clone, Debug)]
+
+    impl ValLen {
+        pub(crate) fn sample(&self, rng: &mut WorkloadRng) -> usize {
+            return match *self {
+                ValLen::Fixed(len) => len,
+                ValLen::Distribution(ValLenDistribution::Exponential { mean, max }) => {
+                    let u: f64 = rng.random();
+                    let len = -(mean as f64) * (1.0 - u).ln();
+                    (len as usize).min(max)
+                }
+            };
+        }
+
+        /// Longest value length that can be sampled.
+        pub fn max(&self) -> usize {
+            return match *self {
+                ValLen::Fixed(len) => len,
+                ValLen::Distribution(ValLenDistribution::Exponential { max, .. }) => max,
+            };
+        }
+
+        /// Expected value length, exact for fixed lengths and an estimate for distributions.
+        pub fn mean(&self) -> usize {
+            return match *self {
+                ValLen::Fixed(len) => len,
+                // Clamping at `max` cuts off the tail beyond it.
+                ValLen::Distribution(ValLenDistribution::Exponential { mean, max }) => {
+                    if mean == 0 {
+                        return 0;
+                    }
+                    let mean = mean as f64;
+                    (mean * (1.0 - (-(max as f64) / mean).exp())).round() as usize
+                }
+            };
+        }
+    }
+
+    /// Content of generated values, which decides how well they compress. Only the bytes differ,
+    /// lengths still come from `val_len`.
+    #[derive(serde::Deserialize, JsonSchema, Default, Copy, Clone, Debug, PartialEq, Eq)]
     #[serde(rename_all = "snake_case")]
-    pub(crate) enum KeyDistribution {
+    pub enum ValPattern {
+        /// Random alphanumeric bytes, which barely compress.
         #[default]
-        Uniform,
+        Random,
+        /// One byte repeated, e.g. `{"repeated": 97}` for `aaaa...`. Must be printable ascii.
+        Repeated(u8),
+        /// Random bytes drawn from the first `distinct_bytes` of `0-9A-Za-z`, between 1 and 62.
+        LowEntropy { distinct_bytes: u8 },
     }
 
-    #[derive(serde::Deserialize, JsonSchema, Clone, Debug)]
-    pub(crate) struct WorkloadSpecSection {
-        /// A list of operation groups that share keys between operations.
-        ///
-        /// E.g., non-empty point queries will use a key from an insert in this group.
-        pub(crate) groups: Vec<WorkloadSpecGroup>,
-        /// The domain from which the keys will be created from.
-        #[serde(default = "KeySpace::default")]
-        pub(crate) key_space: KeySpace,
-        /// The domain from which the keys will be created from.
-        #[serde(default = "KeyDistribution::default")]
-        pub(crate) key_distribution: KeyDistribution,
+    impl ValPattern {
+        pub(crate) fn validate(&self) -> Result<()> {
+            return match *self {
+                ValPattern::Repeated(byte) if !byte.is_ascii_graphic() => {
+                    Err(WorkloadError::InvalidSpec(format!(
+                        "Repeated values need a printable ascii byte, but the byte is {byte}."
+                    )))
+                }
+                ValPattern::LowEntropy { distinct_bytes }
+                    if !(1..=ALPHANUMERIC.len()).contains(&usize::from(distinct_bytes)) =>
+                {
+                    Err(WorkloadError::InvalidSpec(format!(
+                        "distinct_bytes must be between 1 and {}, but is {}.",
+                        ALPHANUMERIC.len(),
+                        distinct_bytes
+                    )))
+                }
+                _ => Ok(()),
+            };
+        }
     }
 
-    impl WorkloadSpecSection {
-        pub fn operation_count(&self) -> usize {
-            return self.groups.iter().map(|g| g.operation_count()).sum();
+    impl From<usize> for ValLen {
+        fn from(len: usize) -> Self {
+            return ValLen::Fixed(len);
         }
+    }
 
-        pub fn bytes_count(&self) -> usize {
-            let insert_key_len = self
-                .groups
-                .iter()
-                .map(|g| g.inserts.map_or(0, |is| is.key_len))
-                .max()
-                .expect("No groups in workload spec");
-            return self
-                .groups
-                .iter()
-                .map(|g| g.bytes_count(insert_key_len))
-                .sum();
+    impl Inserts {
+        /// Inserts that allow duplicate keys, like the json default.
+        pub fn new(amount: usize, key_len: usize, val_len: usize) -> Self {
+            return Inserts {
+                amount,
+                key_len,
+                key_lens: None,
+                val_len: ValLen::Fixed(val_len),
+                val_pattern: ValPattern::Random,
+                allow_duplicate_keys: true,
+                overwrite_ratio: 0.0,
+                reinsert_ratio: 0.0,
+                sorted: false,
+                after_bulk_load: false,
+            };
         }
 
-        pub fn insert_count(&self) -> usize {
-            return self
-                .groups
-                .iter()
-                .map(|g| g.inserts.map_or(0, |is| is.amount))
-                .sum();
+        pub fn val_len(self, val_len: ValLen) -> Self {
+            return Inserts { val_len, ..self };
         }
 
-        pub fn has_inserts(&self) -> bool {
-            return self.groups.iter().any(|g| g.inserts.is_some());
+        pub fn val_pattern(self, val_pattern: ValPattern) -> Self {
+            return Inserts {
+                val_pattern,
+                ..self
+            };
         }
 
-        pub fn has_updates(&self) -> bool {
-            return self.groups.iter().any(|g| g.updates.is_some());
-        }
-        pub fn has_deletes(&self) -> bool {
-            return self.groups.iter().any(|g| g.deletes.is_some());
-        }
-        pub fn has_point_queries(&self) -> bool {
-            return self.groups.iter().any(|g| g.point_queries.is_some());
+        pub fn allow_duplicate_keys(self, allow_duplicate_keys: bool) -> Self {
+            return Inserts {
+                allow_duplicate_keys,
+                ..self
+            };
         }
-        pub fn has_empty_point_queries(&self) -> bool {
-            return self.groups.iter().any(|g| g.empty_point_queries.is_some());
+
+        pub fn overwrite_ratio(self, overwrite_ratio: f32) -> Self {
+            return Inserts {
+                overwrite_ratio,
+                ..self
+            };
         }
 
-        pub fn has_range_queries(&self) -> bool {
-            return self.groups.iter().any(|g| g.range_queries.is_some());
+        pub fn reinsert_ratio(self, reinsert_ratio: f32) -> Self {
+            return Inserts {
+                reinsert_ratio,
+                ..self
+            };
         }
-    }
 
-    #[derive(serde::Deserialize, JsonSchema, Debug, Clone)]
-    pub struct WorkloadSpec {
-        /// Sections of a workload where a key from one will (probably) not appear in another.
-        pub(crate) sections: Vec<WorkloadSpecSection>,
-    }
+        pub fn sorted(self, sorted: bool) -> Self {
+            return Inserts { sorted, ..self };
+        }
 
-    impl WorkloadSpec {
-        pub fn operation_count(&self) -> usize {
-            return self.sections.iter().map(|s| s.operation_count()).sum();
+        pub fn after_bulk_load(self, after_bulk_load: bool) -> Self {
+            return Inserts {
+                after_bulk_load,
+                ..self
+            };
         }
 
-        pub fn bytes_count(&self) -> usize {
-            return self.sections.iter().map(|s| s.bytes_count()).sum();
+        /// Draws key lengths from `(key_len, weight)` classes, see [`Inserts::key_lens`].
+        ///
+        /// # Panics
+        ///
+        /// If there are no classes, more than [`MAX_KEY_LEN_CLASSES`], or a weight isn't positive.
+        pub fn key_lens(self, key_lens: &[(usize, f64)]) -> Self {
+            let classes = key_lens
+                .iter()
+                .map(|&(key_len, weight)| KeyLenWeight { key_len, weight })
+                .collect::<Vec<_>>();
+            return Inserts {
+                key_lens: Some(KeyLenClasses::try_from(classes).unwrap()),
+                ..self
+            };
         }
-    }
-}
 
-/// Json schema generation
-mod schema {
-    use crate::spec::WorkloadSpec;
-    use schemars::schema_for;
+        /// Length of the next inserted key.
+        pub(crate) fn sample_key_len(&self, rng: &mut WorkloadRng) -> usize {
+            return self
+                .key_lens
+                .map_or(self.key_len, |key_lens| key_lens.sample(rng));
+        }
 
-    pub fn generate_workload_spec_schema() -> serde_json::Result<String> {
-        let schema = schema_for!(WorkloadSpec);
-        return serde_json::to_string_pretty(&schema);
-    }
-}
+        /// Expected key length, exact unless `key_lens` is set.
+        pub fn mean_key_len(&self) -> usize {
+            return self
+                .key_lens
+                .map_or(self.key_len, |key_lens| key_lens.mean().round() as usize);
+        }
 
-mod keyset {
-    use crate::Key;
-    use bloom::{BloomFilter, ASMS};
-    use rand::Rng;
-    use rand_xoshiro::Xoshiro256Plus;
-    use std::collections::{HashMap, HashSet};
+        /// Every length an inserted key can have.
+        pub fn possible_key_lens(&self) -> impl Iterator<Item = usize> + '_ {
+            let classes = self.key_lens.as_ref().map_or(&[][..], |c| c.classes());
+            let fixed = self.key_lens.is_none().then_some(self.key_len);
+            return classes.iter().map(|class| class.key_len).chain(fixed);
+        }
 
-    pub trait KeySet {
-        fn new(capacity: usize) -> Self;
+        /// Whether the `i`th insert overwrites a valid key, i.e. whether the running count of
+        /// overwrites, `overwrite_ratio` per insert, reaches a new integer at it.
+        pub(crate) fn is_overwrite(&self, i: usize) -> bool {
+            return i > 0 && reaches_integer(self.overwrite_ratio, i);
+        }
 
-        fn len(&self) -> usize;
+        /// Whether the `i`th insert reinserts a deleted key, the same way as
+        /// [`Inserts::is_overwrite`] with `reinsert_ratio`.
+        pub(crate) fn is_reinsert(&self, i: usize) -> bool {
+            return !self.is_overwrite(i) && reaches_integer(self.reinsert_ratio, i);
+        }
 
-        fn is_empty(&self) -> bool;
+        /// Number of inserts that create a new key.
+        pub fn new_key_count(&self) -> usize {
+            let ratio = f64::from(self.overwrite_ratio);
+            // The overwrites of `is_overwrite` telescope to this.
+            let overwrites = (self.amount as f64 * ratio).floor() - ratio.floor();
+            return self.amount - overwrites as usize;
+        }
+    }
 
-        fn push(&mut self, key: Key);
+    /// Whether the running count of `ratio` per item reaches a new integer at the `i`th item.
+    fn reaches_integer(ratio: f32, i: usize) -> bool {
+        let ratio = f64::from(ratio);
+        return ((i + 1) as f64 * ratio).floor() > (i as f64 * ratio).floor();
+    }
 
-        fn remove(&mut self, idx: usize) -> Key;
+    impl Updates {
+        /// Updates of existing keys, like the json default.
+        pub fn new(amount: usize, val_len: usize) -> Self {
+            return Updates {
+                amount,
+                val_len: ValLen::Fixed(val_len),
+                val_pattern: ValPattern::Random,
+                existing: true,
+                key_distribution: None,
+            };
+        }
 
-        fn get(&self, idx: usize) -> Option<&Key>;
+        pub fn existing(self, existing: bool) -> Self {
+            return Updates { existing, ..self };
+        }
 
-        fn get_random(&self, rng: &mut Xoshiro256Plus) -> &Key;
+        pub fn val_len(self, val_len: ValLen) -> Self {
+            return Updates { val_len, ..self };
+        }
 
-        fn contains(&self, key: &Key) -> bool;
+        pub fn val_pattern(self, val_pattern: ValPattern) -> Self {
+            return Updates {
+                val_pattern,
+                ..self
+            };
+        }
 
-        fn sort(&mut self);
+        pub fn key_distribution(self, key_distribution: KeyDistribution) -> Self {
+            return Updates {
+                key_distribution: Some(key_distribution),
+                ..self
+            };
+        }
     }
 
-    pub struct VecKeySet {
-        keys: Vec<Key>,
-        sorted: bool,
+    impl Deletes {
+        pub fn new(amount: usize) -> Self {
+            return Deletes { amount };
+        }
     }
 
-    impl KeySet for VecKeySet {
-        fn new(capacity: usize) -> Self {
-            return Self {
-                keys: Vec::with_capacity(capacity),
-                sorted: true,
+    impl PointQueries {
+        pub fn new(amount: usize) -> Self {
+            return PointQueries {
+                amount,
+                key_distribution: None,
+                hit_ratio: 1.0,
             };
         }
 
-        fn len(&self) -> usize {
-            return self.keys.len();
+        pub fn key_distribution(self, key_distribution: KeyDistribution) -> Self {
+            return PointQueries {
+                key_distribution: Some(key_distribution),
+                ..self
+            };
         }
 
-        fn is_empty(&self) -> bool {
-            return self.keys.is_empty();
+        pub fn hit_ratio(self, hit_ratio: f32) -> Self {
+            return PointQueries { hit_ratio, ..self };
         }
 
-        fn push(&mut self, key: Key) {
-            if self.sorted && self.keys.last().is_some_and(|last_key| last_key > &key) {
-                self.sorted = false;
-            }
-            self.keys.push(key);
+        /// Whether the `i`th point query misses, the same way as [`Inserts::is_overwrite`] with
+        /// the fraction of misses.
+        pub(crate) fn is_miss(&self, i: usize) -> bool {
+            return reaches_integer(1.0 - self.hit_ratio, i);
         }
 
-        fn remove(&mut self, idx: usize) -> Key {
-            let key = self.keys.remove(idx);
-            return key;
+        /// Number of point queries that miss.
+        pub fn miss_count(&self) -> usize {
+            // The misses of `is_miss` telescope to this.
+            return (self.amount as f64 * f64::from(1.0 - self.hit_ratio)).floor() as usize;
         }
+    }
 
-        fn get(&self, idx: usize) -> Option<&Key> {
-            return self.keys.get(idx);
+    impl SeekQueries {
+        pub fn new(amount: usize) -> Self {
+            return SeekQueries {
+                amount,
+                key_distribution: None,
+                hit_ratio: 1.0,
+            };
         }
 
-        fn get_random(&self, rng: &mut Xoshiro256Plus) -> &Key {
-            return self
-                .keys
-                .get(rng.random_range(0..self.keys.len()))
-                .expect("KeySet to not be empty");
+        pub fn key_distribution(self, key_distribution: KeyDistribution) -> Self {
+            return SeekQueries {
+                key_distribution: Some(key_distribution),
+                ..self
+            };
         }
 
-        fn contains(&self, key: &Key) -> bool {
-            return self.keys.contains(key);
+        pub fn hit_ratio(self, hit_ratio: f32) -> Self {
+            return SeekQueries { hit_ratio, ..self };
         }
 
-        fn sort(&mut self) {
-            if !self.sorted {
-                self.keys.sort();
-                self.sorted = true;
-            }
+        /// Whether the `i`th seek targets an absent key, like [`PointQueries::is_miss`].
+        pub(crate) fn is_miss(&self, i: usize) -> bool {
+            return reaches_integer(1.0 - self.hit_ratio, i);
         }
-    }
+
+        /// Number of seeks that target an absent key.
+        pub fn miss_count(&self) -> usize {
+            return (self.amount as f64 * f64::from(1.0 - self.hit_ratio)).floor() as usize;
+        }
+    }
+
+    impl EmptyPointQueries {
+        pub fn new(amount: usize, key_len: usize) -> Self {
+            return EmptyPointQueries {
+                amount,
+                key_len,
+                near_miss: false,
+                max_attempts: None,
+            };
+        }
+
+        pub fn near_miss(self, near_miss: bool) -> Self {
+            return EmptyPointQueries { near_miss, ..self };
+        }
+
+        pub fn max_attempts(self, max_attempts: usize) -> Self {
+            let max_attempts = Some(max_attempts);
+            return EmptyPointQueries {
+                max_attempts,
+                ..self
+            };
+        }
+    }
+
+    impl RangeQueries {
+        /// Range queries with ascending endpoints, like the json default.
+        pub fn new(amount: usize, selectivity: f32) -> Self {
+            return RangeQueries {
+                amount,
+                selectivity,
+                reverse: false,
+                domain_based: false,
+                min_gap: 0.0,
+            };
+        }
+
+        pub fn reverse(self, reverse: bool) -> Self {
+            return RangeQueries { reverse, ..self };
+        }
+
+        pub fn domain_based(self, domain_based: bool) -> Self {
+            return RangeQueries {
+                domain_based,
+                ..self
+            };
+        }
+
+        pub fn min_gap(self, min_gap: f64) -> Self {
+            return RangeQueries { min_gap, ..self };
+        }
+    }
+
+    impl CountRangeQueries {
+        pub fn new(amount: usize, count: usize) -> Self {
+            return CountRangeQueries { amount, count };
+        }
+    }
+
+    impl ReadModifyWrites {
+        pub fn new(amount: usize, val_len: usize) -> Self {
+            return ReadModifyWrites {
+                amount,
+                val_len: ValLen::Fixed(val_len),
+            };
+        }
+
+        pub fn val_len(self, val_len: ValLen) -> Self {
+            return ReadModifyWrites { val_len, ..self };
+        }
+    }
+
+    impl ThinkTimes {
+        pub fn new(amount: usize, micros: u64) -> Self {
+            return ThinkTimes { amount, micros };
+        }
+    }
+
+    impl Flushes {
+        pub fn new(amount: usize) -> Self {
+            return Flushes { amount };
+        }
+    }
+
+    impl RangeDeletes {
+        pub fn new(amount: usize, selectivity: f32) -> Self {
+            return RangeDeletes {
+                amount,
+                selectivity,
+            };
+        }
+    }
+
+    /// Specification for point deletes in a workload group.
+    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
+    pub struct Deletes {
+        /// Number of deletes
+        pub(crate) amount: usize,
+    }
+
+    /// Specification for point queries in a workload group.
+    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
+    pub struct PointQueries {
+        /// Number of point queries
+        pub(crate) amount: usize,
+        /// How point queries pick a key. Defaults to the section's `key_distribution`.
+        #[serde(default)]
+        pub(crate) key_distribution: Option<KeyDistribution>,
+        /// Fraction of the point queries on valid keys, spread evenly over the queries. The rest
+        /// miss, querying generated keys that aren't valid with the key length of the section's
+        /// inserts, like empty point queries. 1 by default.
+        #[serde(default = "default_hit_ratio")]
+        pub(crate) hit_ratio: f32,
+    }
+
+    /// Specification for empty point queries in a workload group.
+    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
+    pub struct EmptyPointQueries {
+        /// Number of point queries
+        pub(crate) amount: usize,
+        /// Key length
+        pub(crate) key_len: usize,
+        /// Query near misses of valid keys instead of random keys: a valid key with one byte
+        /// changed, so queries share long prefixes with valid keys, e.g. to stress prefix bloom
+        /// filters. The queries have the length of the valid keys instead of `key_len`.
+        #[serde(default)]
+        pub(crate) near_miss: bool,
+        /// Keys to try for every query before failing, when nearly every key of the key space is
+        /// valid. 10000 by default.
+        #[serde(default)]
+        pub(crate) max_attempts: Option<usize>,
+    }
+
+    /// Specification for iterator seeks in a workload group, written as `SK key`. Replay tools
+    /// position an iterator at the first key at or after `key` without reading further, which
+    /// isolates the cost of a seek from the cost of a scan.
+    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
+    pub struct SeekQueries {
+        /// Number of seeks
+        pub(crate) amount: usize,
+        /// How seeks pick a key. Defaults to the section's `key_distribution`.
+        #[serde(default)]
+        pub(crate) key_distribution: Option<KeyDistribution>,
+        /// Fraction of the seeks targeting valid keys, spread evenly over the seeks. The rest
+        /// target generated keys that aren't valid, like point queries that miss. 1 by default.
+        #[serde(default = "default_hit_ratio")]
+        pub(crate) hit_ratio: f32,
+    }
+
+    /// Specification for range queries in a workload group.
+    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
+    pub struct RangeQueries {
+        /// Number of range queries
+        pub(crate) amount: usize,
+        /// Selectivity of range queries, between 0 and 1. Based off of the range of valid keys, not
+        /// the full key-space. Ranges span at least one key, however small the selectivity.
+        pub(crate) selectivity: f32,
+        /// Emit the endpoints in descending order, e.g. `R key2 key1` where `key2 > key1`, to
+        /// signal a backward scan.
+        #[serde(default)]
+        pub(crate) reverse: bool,
+        /// Interpret `selectivity` as a fraction of the key domain between the smallest and the
+        /// largest valid key, instead of a fraction of the valid keys. Endpoints are picked by
+        /// interpolating key values, so ranges over sparse parts of the domain hold fewer keys.
+        #[serde(default)]
+        pub(crate) domain_based: bool,
+        /// Minimum distance between the endpoints, as a fraction of the key domain between the
+        /// smallest and the largest valid key. Endpoints that are closer, e.g. in clusters of
+        /// nearly identical keys, are picked again.
+        #[serde(default)]
+        pub(crate) min_gap: f64,
+    }
+
+    /// Specification for range deletes in a workload group, written as `DR start end`. Every valid
+    /// key from `start` up to, but not including, `end` is deleted, like RocksDB's `DeleteRange`.
+    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
+    pub struct RangeDeletes {
+        /// Number of range deletes
+        pub(crate) amount: usize,
+        /// Fraction of the valid keys deleted by each range delete.
+        pub(crate) selectivity: f32,
+    }
+
+    /// Specification for range queries that read a number of keys from a start key, written as
+    /// `R start +count`, in a workload group.
+    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
+    pub struct CountRangeQueries {
+        /// Number of range queries
+        pub(crate) amount: usize,
+        /// Number of keys read by each range query, including the start key. Clamped to the
+        /// number of valid keys.
+        pub(crate) count: usize,
+    }
+
+    /// Specification for read-modify-writes in a workload group. Each is written as two
+    /// operations, a point query of a valid key followed by an update of the same key, i.e.
+    /// `P key` then `U key val`.
+    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
+    pub struct ReadModifyWrites {
+        /// Number of read-modify-writes, each counting as two operations
+        pub(crate) amount: usize,
+        /// Value length of the update
+        pub(crate) val_len: ValLen,
+    }
+
+    /// Specification for client pauses in a workload group, written as `T micros`. They don't
+    /// touch any key, replay tools sleep for `micros` microseconds instead.
+    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
+    pub struct ThinkTimes {
+        /// Number of pauses
+        pub(crate) amount: usize,
+        /// Length of each pause in microseconds
+        pub(crate) micros: u64,
+    }
+
+    /// Specification for memtable flush hints in a workload group, written as `F`. They don't
+    /// touch any key, replay tools flush the memtable instead. They are spread evenly over the
+    /// other operations of the group, the last one ending it.
+    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
+    pub struct Flushes {
+        /// Number of flushes
+        pub(crate) amount: usize,
+    }
+
+    #[derive(serde::Deserialize, JsonSchema, Default, Copy, Clone, Debug)]
+    pub struct WorkloadSpecGroup {
+        pub(crate) inserts: Option<Inserts>,
+        pub(crate) updates: Option<Updates>,
+        pub(crate) deletes: Option<Deletes>,
+        pub(crate) point_queries: Option<PointQueries>,
+        pub(crate) empty_point_queries: Option<EmptyPointQueries>,
+        pub(crate) range_queries: Option<RangeQueries>,
+        pub(crate) count_range_queries: Option<CountRangeQueries>,
+        pub(crate) range_deletes: Option<RangeDeletes>,
+        pub(crate) think_times: Option<ThinkTimes>,
+        pub(crate) flushes: Option<Flushes>,
+        pub(crate) read_modify_writes: Option<ReadModifyWrites>,
+        pub(crate) seek_queries: Option<SeekQueries>,
+    }
+
+    impl WorkloadSpecGroup {
+        /// A group without any operations, to add operations to with one setter per kind.
+        pub fn new() -> Self {
+            return WorkloadSpecGroup::default();
+        }
+
+        pub fn inserts(self, inserts: Inserts) -> Self {
+            return WorkloadSpecGroup {
+                inserts: Some(inserts),
+                ..self
+            };
+        }
+
+        pub fn updates(self, updates: Updates) -> Self {
+            return WorkloadSpecGroup {
+                updates: Some(updates),
+                ..self
+            };
+        }
+
+        pub fn deletes(self, deletes: Deletes) -> Self {
+            return WorkloadSpecGroup {
+                deletes: Some(deletes),
+                ..self
+            };
+        }
+
+        pub fn point_queries(self, point_queries: PointQueries) -> Self {
+            return WorkloadSpecGroup {
+                point_queries: Some(point_queries),
+                ..self
+            };
+        }
+
+        pub fn empty_point_queries(self, empty_point_queries: EmptyPointQueries) -> Self {
+            return WorkloadSpecGroup {
+                empty_point_queries: Some(empty_point_queries),
+                ..self
+            };
+        }
+
+        pub fn range_queries(self, range_queries: RangeQueries) -> Self {
+            return WorkloadSpecGroup {
+                range_queries: Some(range_queries),
+                ..self
+            };
+        }
+
+        pub fn count_range_queries(self, count_range_queries: CountRangeQueries) -> Self {
+            return WorkloadSpecGroup {
+                count_range_queries: Some(count_range_queries),
+                ..self
+            };
+        }
+
+        pub fn range_deletes(self, range_deletes: RangeDeletes) -> Self {
+            return WorkloadSpecGroup {
+                range_deletes: Some(range_deletes),
+                ..self
+            };
+        }
+
+        pub fn think_times(self, think_times: ThinkTimes) -> Self {
+            return WorkloadSpecGroup {
+                think_times: Some(think_times),
+                ..self
+            };
+        }
+
+        pub fn flushes(self, flushes: Flushes) -> Self {
+            return WorkloadSpecGroup {
+                flushes: Some(flushes),
+                ..self
+            };
+        }
+
+        pub fn read_modify_writes(self, read_modify_writes: ReadModifyWrites) -> Self {
+            return WorkloadSpecGroup {
+                read_modify_writes: Some(read_modify_writes),
+                ..self
+            };
+        }
+
+        pub fn seek_queries(self, seek_queries: SeekQueries) -> Self {
+            return WorkloadSpecGroup {
+                seek_queries: Some(seek_queries),
+                ..self
+            };
+        }
+
+        /// Number of operations of this group. Read-modify-writes count as two, their point
+        /// query and their update.
+        pub fn operation_count(&self) -> usize {
+            let operation_count = self.inserts.map_or(0, |s| s.amount)
+                + self.updates.map_or(0, |us| us.amount)
+                + self.point_queries.map_or(0, |is| is.amount)
+                + self.empty_point_queries.map_or(0, |is| is.amount)
+                + self.range_queries.map_or(0, |is| is.amount)
+                + self.count_range_queries.map_or(0, |is| is.amount)
+                + self.range_deletes.map_or(0, |is| is.amount)
+                + self.deletes.map_or(0, |is| is.amount)
+                + self.think_times.map_or(0, |ts| ts.amount)
+                + self.flushes.map_or(0, |fs| fs.amount)
+                + self.read_modify_writes.map_or(0, |rmws| 2 * rmws.amount)
+                + self.seek_queries.map_or(0, |sks| sks.amount);
+            return operation_count;
+        }
+
+        /// The amount of every operation type of the group, with the number of operations each
+        /// one counts as, for [`WorkloadSpec::scaled_to`].
+        fn amounts_mut(&mut self) -> impl Iterator<Item = (&mut usize, usize)> {
+            let single = [
+                self.inserts.as_mut().map(|is| &mut is.amount),
+                self.updates.as_mut().map(|us| &mut us.amount),
+                self.deletes.as_mut().map(|ds| &mut ds.amount),
+                self.point_queries.as_mut().map(|pqs| &mut pqs.amount),
+                self.empty_point_queries
+                    .as_mut()
+                    .map(|epqs| &mut epqs.amount),
+                self.range_queries.as_mut().map(|rqs| &mut rqs.amount),
+                self.count_range_queries
+                    .as_mut()
+                    .map(|crqs| &mut crqs.amount),
+                self.range_deletes.as_mut().map(|rds| &mut rds.amount),
+                self.think_times.as_mut().map(|ts| &mut ts.amount),
+                self.flushes.as_mut().map(|fs| &mut fs.amount),
+                self.seek_queries.as_mut().map(|sks| &mut sks.amount),
+            ];
+            let rmws = self
+                .read_modify_writes
+                .as_mut()
+                .map(|rmws| (&mut rmws.amount, 2));
+            return single
+                .into_iter()
+                .flatten()
+                .map(|amount| (amount, 1))
+                .chain(rmws);
+        }
+
+        /// Whether the group has no operations of any kind. Operations with an amount of 0 still
+        /// count, e.g. for the zero point of a sweep.
+        pub fn is_empty(&self) -> bool {
+            return self.inserts.is_none()
+                && self.updates.is_none()
+                && self.deletes.is_none()
+                && self.point_queries.is_none()
+                && self.empty_point_queries.is_none()
+                && self.range_queries.is_none()
+                && self.count_range_queries.is_none()
+                && self.range_deletes.is_none()
+                && self.think_times.is_none()
+                && self.flushes.is_none()
+                && self.read_modify_writes.is_none()
+                && self.seek_queries.is_none();
+        }
+
+        /// Number of updates that create their key instead of updating a valid one.
+        pub fn blind_update_count(&self) -> usize {
+            return self
+                .updates
+                .filter(|us| !us.existing)
+                .map_or(0, |us| us.amount);
+        }
+
+        /// Number of keys created by this group, by inserts that don't overwrite and by blind
+        /// updates.
+        pub fn created_key_count(&self) -> usize {
+            return self.inserts.map_or(0, |is| is.new_key_count()) + self.blind_update_count();
+        }
+
+        /// Whether the group has operations besides deletes that need a valid key, e.g. point
+        /// queries, updates of existing keys, or overwrites.
+        pub(crate) fn reads_valid_keys(&self) -> bool {
+            return self.inserts.is_some_and(|is| is.overwrite_ratio > 0.0)
+                || self.updates.is_some_and(|us| us.existing)
+                || self.point_queries.is_some()
+                || self.range_queries.is_some()
+                || self.count_range_queries.is_some()
+                || self.read_modify_writes.is_some()
+                || self.seek_queries.is_some_and(|sks| sks.hit_ratio > 0.0)
+                || self.empty_point_queries.is_some_and(|epqs| epqs.near_miss);
+        }
+
+        /// Whether the group only has inserts, which don't depend on each other and can be
+        /// generated in parallel.
+        pub fn is_insert_only(&self) -> bool {
+            return self.inserts.is_some()
+                && self.operation_count() == self.inserts.map_or(0, |is| is.amount);
+        }
+
+        /// Number of keys written by the operations of this group. Range queries and range
+        /// deletes write two, think times and flushes none.
+        pub fn key_count(&self) -> usize {
+            return self.operation_count()
+                + self.range_queries.map_or(0, |rq| rq.amount)
+                + self.range_deletes.map_or(0, |rd| rd.amount)
+                - self.think_times.map_or(0, |ts| ts.amount)
+                - self.flushes.map_or(0, |fs| fs.amount);
+        }
+
+        /// Bytes of the operations of this group, an estimate when value lengths are sampled.
+        pub fn bytes_count(&self, insert_key_len: usize) -> usize {
+            let bytes_insert = self.inserts.map_or(0, |is| {
+                (b"I ".len() + is.mean_key_len() + b" ".len() + is.val_len.mean() + b"\n".len())
+                    * is.amount
+            });
+            let bytes_update = self.updates.map_or(0, |us| {
+                (b"U ".len() + insert_key_len + b" ".len() + us.val_len.mean() + b"\n".len())
+                    * us.amount
+            });
+            let bytes_delete = self.deletes.map_or(0, |ds| {
+                (b"D ".len() + insert_key_len + b"\n".len()) * ds.amount
+            });
+            let bytes_point_queries = self.point_queries.map_or(0, |pq| {
+                (b"P ".len() + insert_key_len + b"\n".len()) * pq.amount
+            });
+            let bytes_empty_point_queries = self.empty_point_queries.map_or(0, |epq| {
+                let key_len = if epq.near_miss {
+                    insert_key_len
+                } else {
+                    epq.key_len
+                };
+                (b"P ".len() + key_len + b"\n".len()) * epq.amount
+            });
+            let bytes_range_queries = self.range_queries.map_or(0, |rq| {
+                (b"R ".len() + insert_key_len + b" ".len() + insert_key_len + b"\n".len())
+                    * rq.amount
+            });
+            // Assumes the count isn't clamped, i.e. there are at least `count` valid keys.
+            let bytes_count_range_queries = self.count_range_queries.map_or(0, |crq| {
+                (b"R ".len()
+                    + insert_key_len
+                    + b" +".len()
+                    + crq.count.to_string().len()
+                    + b"\n".len())
+                    * crq.amount
+            });
+            let bytes_range_deletes = self.range_deletes.map_or(0, |rd| {
+                (b"DR ".len() + insert_key_len + b" ".len() + insert_key_len + b"\n".len())
+                    * rd.amount
+            });
+            let bytes_think_times = self.think_times.map_or(0, |ts| {
+                (b"T ".len() + ts.micros.to_string().len() + b"\n".len()) * ts.amount
+            });
+            let bytes_flushes = self.flushes.map_or(0, |fs| b"F\n".len() * fs.amount);
+            let bytes_seek_queries = self.seek_queries.map_or(0, |sk| {
+                (b"SK ".len() + insert_key_len + b"\n".len()) * sk.amount
+            });
+            let bytes_read_modify_writes = self.read_modify_writes.map_or(0, |rmws| {
+                (b"P ".len()
+                    + insert_key_len
+                    + b"\n".len()
+                    + b"U ".len()
+                    + insert_key_len
+                    + b" ".len()
+                    + rmws.val_len.mean()
+                    + b"\n".len())
+                    * rmws.amount
+            });
+            return bytes_insert
+                + bytes_update
+                + bytes_delete
+                + bytes_point_queries
+                + bytes_empty_point_queries
+                + bytes_range_queries
+                + bytes_count_range_queries
+                + bytes_range_deletes
+                + bytes_think_times
+                + bytes_flushes
+                + bytes_read_modify_writes
+                + bytes_seek_queries;
+        }
+
+        // pub fn needs_static_sorted_keys(&self) -> bool {
+        //     return self.range_queries.is_some();
+        // }
+        //
+        // pub fn needs_dynamic_sorted_keys(&self) -> bool {
+        //     return (self.inserts.is_some() || self.deletes.is_some())
+        //         && self.range_queries.is_some();
+        // }
+    }
+
+    /// Alternative form of a workload group where the amount of each operation is derived from a
+    /// weight relative to the other operations, e.g. 70% point queries and 30% inserts. Weights
+    /// must be finite and not negative, and at least one must be positive.
+    #[derive(serde::Deserialize, JsonSchema, Copy, Clone, Debug)]
+    pub struct WorkloadSpecGroupRatio {
+        /// Total number of operations in the group
+        pub(crate) total: usize,
+        /// Weight of inserts, 0 for none.
+        #[serde(default)]
+        pub(crate) insert_weight: f64,
+        /// Weight of updates, 0 for none.
+        #[serde(default)]
+        pub(crate) update_weight: f64,
+        /// Weight of deletes, 0 for none.
+        #[serde(default)]
+        pub(crate) delete_weight: f64,
+        /// Weight of point queries, 0 for none.
+        #[serde(default)]
+        pub(crate) point_query_weight: f64,
+        /// Weight of range queries, 0 for none.
+        #[serde(default)]
+        pub(crate) range_query_weight: f64,
+        /// Key length of inserts. Required if `insert_weight` is non-zero.
+        pub(crate) key_len: Option<usize>,
+        /// Value length of inserts and updates. Required if `insert_weight` or `update_weight`
+        /// is non-zero.
+        pub(crate) val_len: Option<usize>,
+        /// Selectivity of range queries. Required if `range_query_weight` is non-zero.
+        pub(crate) selectivity: Option<f32>,
+    }
+
+    impl WorkloadSpecGroupRatio {
+        fn weights(&self) -> [f64; 5] {
+            return [
+                self.insert_weight,
+                self.update_weight,
+                self.delete_weight,
+                self.point_query_weight,
+                self.range_query_weight,
+            ];
+        }
+
+        pub(crate) fn validate(&self) -> Result<()> {
+            let names = [
+                "insert_weight",
+                "update_weight",
+                "delete_weight",
+                "point_query_weight",
+                "range_query_weight",
+            ];
+            for (name, weight) in names.iter().zip(self.weights()) {
+                if !weight.is_finite() || weight < 0.0 {
+                    return Err(WorkloadError::InvalidSpec(format!(
+                        "{name} must be finite and not negative, but is {weight}."
+                    )));
+                }
+            }
+            if self.weights().iter().all(|&weight| weight == 0.0) {
+                return Err(WorkloadError::InvalidSpec(
+                    "A ratio group needs at least one positive weight.".to_string(),
+                ));
+            }
+            return Ok(());
+        }
+
+        /// Splits `total` proportionally to the weights, in the order inserts, updates, deletes,
+        /// point queries, range queries. The rounding remainder goes to the operations with the
+        /// largest fractional parts, ties going to the earlier operation, so the amounts always
+        /// sum to `total`. All 0 unless the weights are valid.
+        pub fn amounts(&self) -> [usize; 5] {
+            let weights = self.weights();
+            let weight_sum: f64 = weights.iter().sum();
+            let mut amounts = [0; 5];
+            if self.validate().is_err() {
+                return amounts;
+            }
+
+            let exact = weights.map(|w| self.total as f64 * w / weight_sum);
+            for (amount, exact) in amounts.iter_mut().zip(exact) {
+                *amount = exact.floor() as usize;
+            }
+            // Rounding can push the floors a hair past `total`, which then leaves no remainder.
+            let remainder = self.total.saturating_sub(amounts.iter().sum::<usize>());
+            let mut by_fraction: Vec<usize> =
+                (0..amounts.len()).filter(|&i| weights[i] > 0.0).collect();
+            by_fraction.sort_by(|&a, &b| (exact[b].fract()).total_cmp(&exact[a].fract()));
+            for &i in by_fraction.iter().cycle().take(remainder) {
+                amounts[i] += 1;
+            }
+            return amounts;
+        }
+    }
+
+    impl TryFrom<WorkloadSpecGroupRatio> for WorkloadSpecGroup {
+        type Error = String;
+
+        fn try_from(ratio: WorkloadSpecGroupRatio) -> Result<Self, Self::Error> {
+            ratio.validate().map_err(|err| err.to_string())?;
+            let [inserts, updates, deletes, point_queries, range_queries] = ratio.amounts();
+            let key_len = || ratio.key_len.ok_or("`key_len` is required for inserts");
+            let val_len = || {
+                ratio
+                    .val_len
+                    .ok_or("`val_len` is required for inserts and updates")
+            };
+            let selectivity = || {
+                ratio
+                    .selectivity
+                    .ok_or("`selectivity` is required for range queries")
+            };
+
+            return Ok(WorkloadSpecGroup {
+                inserts: match inserts {
+                    0 => None,
+                    amount => Some(Inserts {
+                        amount,
+                        key_len: key_len()?,
+                        key_lens: None,
+                        val_len: ValLen::Fixed(val_len()?),
+                        val_pattern: ValPattern::Random,
+                        allow_duplicate_keys: true,
+                        overwrite_ratio: 0.0,
+                        reinsert_ratio: 0.0,
+                        sorted: false,
+                        after_bulk_load: false,
+                    }),
+                },
+                updates: match updates {
+                    0 => None,
+                    amount => Some(Updates::new(amount, val_len()?)),
+                },
+                deletes: match deletes {
+                    0 => None,
+                    amount => Some(Deletes { amount }),
+                },
+                point_queries: match point_queries {
+                    0 => None,
+                    amount => Some(PointQueries::new(amount)),
+                },
+                empty_point_queries: None,
+                range_queries: match range_queries {
+                    0 => None,
+                    amount => Some(RangeQueries::new(amount, selectivity()?)),
+                },
+                count_range_queries: None,
+                range_deletes: None,
+                think_times: None,
+                flushes: None,
+                read_modify_writes: None,
+                seek_queries: None,
+            });
+        }
+    }
+
+    /// A workload group given either by absolute amounts or by ratios.
+    #[derive(serde::Deserialize, JsonSchema)]
+    #[serde(untagged)]
+    enum WorkloadSpecGroupForm {
+        // Ratio has a required `total`, so it must be tried before the all-optional amounts form.
+        Ratio(WorkloadSpecGroupRatio),
+        Amounts(Box<WorkloadSpecGroup>),
+    }
+
+    fn deserialize_groups<'de, D>(deserializer: D) -> Result<Vec<WorkloadSpecGroup>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let groups: Vec<WorkloadSpecGroupForm> = serde::Deserialize::deserialize(deserializer)?;
+        return groups
+            .into_iter()
+            .map(|group| match group {
+                WorkloadSpecGroupForm::Ratio(ratio) => {
+                    WorkloadSpecGroup::try_from(ratio).map_err(serde::de::Error::custom)
+                }
+                WorkloadSpecGroupForm::Amounts(group) => Ok(*group),
+            })
+            .collect();
+    }
+
+    /// The random number generators a workload can be generated with. The same seed generates a
+    /// different workload with every algorithm.
+    #[derive(serde::Deserialize, JsonSchema, Default, Copy, Clone, Debug, PartialEq, Eq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum RngAlgorithm {
+        /// Xoshiro256+, the fastest.
+        #[default]
+        Xoshiro256Plus,
+        /// ChaCha with 8 rounds, as `rand_chacha::ChaCha8Rng`.
+        ChaCha8,
+        /// PCG XSL RR 128/64, as `rand_pcg::Pcg64`.
+        Pcg64,
+    }
+
+    impl RngAlgorithm {
+        /// An rng of this algorithm seeded with `seed`.
+        pub fn seed_from_u64(self, seed: u64) -> WorkloadRng {
+            return match self {
+                RngAlgorithm::Xoshiro256Plus => {
+                    WorkloadRng::Xoshiro256Plus(Xoshiro256Plus::seed_from_u64(seed))
+                }
+                RngAlgorithm::ChaCha8 => {
+                    WorkloadRng::ChaCha8(Box::new(ChaCha8Rng::seed_from_u64(seed)))
+                }
+                RngAlgorithm::Pcg64 => WorkloadRng::Pcg64(Pcg64::seed_from_u64(seed)),
+            };
+        }
+
+        /// An rng of this algorithm seeded from the operating system, different in every run.
+        pub fn from_os_rng(self) -> WorkloadRng {
+            return match self {
+                RngAlgorithm::Xoshiro256Plus => {
+                    WorkloadRng::Xoshiro256Plus(Xoshiro256Plus::from_os_rng())
+                }
+                RngAlgorithm::ChaCha8 => WorkloadRng::ChaCha8(Box::new(ChaCha8Rng::from_os_rng())),
+                RngAlgorithm::Pcg64 => WorkloadRng::Pcg64(Pcg64::from_os_rng()),
+            };
+        }
+    }
+
+    #[derive(serde::Deserialize, JsonSchema, Default, Clone, Debug)]
+    #[serde(rename_all = "snake_case")]
+    pub enum KeySpace {
+        #[default]
+        Alphanumeric,
+        /// Fixed length strings of ascii digits, e.g. `"00420"`.
+        Numeric,
+        /// Integers drawn uniformly from `min..=max`, zero-padded to the key length, e.g.
+        /// `"00420"`. Bounds the number of distinct keys to create update hotspots or collisions;
+        /// inserts beyond `max - min + 1` keys need `allow_duplicate_keys`.
+        NumericRange { min: u64, max: u64 },
+        /// Hyphenated UUID-v4 strings, e.g. `"67e55044-10b1-426f-9247-bb680e5fe0c8"`. `key_len`
+        /// must be 36.
+        Uuid,
+        /// UUID-v4 strings as 32 hex characters, e.g. `"67e5504410b1426f9247bb680e5fe0c8"`.
+        /// `key_len` must be 32.
+        UuidSimple,
+        /// Strings of characters drawn uniformly from `charset`, e.g. `"ACGT"`, to control the
+        /// number of distinct keys and how often keys share a prefix. The charset must be
+        /// non-empty ascii without control characters, and without `+` in sections with range
+        /// queries. Repeated characters count once. The ascii output format has to escape
+        /// separators in it, e.g. a space.
+        Custom { charset: String },
+        /// Uniformly random bytes, which aren't valid utf8 or ascii, so only the msgpack output
+        /// format can write them.
+        Binary,
+    }
+
+    impl KeySpace {
+        /// Number of distinct keys of length `key_len`, or `None` if it doesn't fit in a `u128`.
+        pub fn size(&self, key_len: usize) -> Option<u128> {
+            let key_len = u32::try_from(key_len).ok()?;
+            return match self {
+                KeySpace::Alphanumeric => 62u128.checked_pow(key_len),
+                KeySpace::Numeric => 10u128.checked_pow(key_len),
+                KeySpace::NumericRange { min, max } => Some(u128::from(max - min) + 1),
+                // 6 bits of a v4 UUID are fixed for the version and variant.
+                KeySpace::Uuid | KeySpace::UuidSimple => Some(1 << 122),
+                KeySpace::Custom { charset } => {
+                    (CharsetKeyGen::new(charset).charset.len() as u128).checked_pow(key_len)
+                }
+                KeySpace::Binary => 256u128.checked_pow(key_len),
+            };
+        }
+
+        /// The generator of the keys of this key space.
+        pub fn key_gen(&self) -> Box<dyn KeyGen> {
+            return match self {
+                KeySpace::Alphanumeric => Box::new(AlphanumericKeyGen),
+                KeySpace::Numeric => Box::new(NumericKeyGen),
+                KeySpace::NumericRange { min, max } => Box::new(NumericRangeKeyGen {
+                    min: *min,
+                    max: *max,
+                }),
+                KeySpace::Uuid => Box::new(UuidKeyGen { hyphenated: true }),
+                KeySpace::UuidSimple => Box::new(UuidKeyGen { hyphenated: false }),
+                KeySpace::Custom { charset } => Box::new(CharsetKeyGen::new(charset)),
+                KeySpace::Binary => Box::new(BinaryKeyGen),
+            };
+        }
+
+        /// The only key length this key space can generate, if it is fixed width.
+        pub fn fixed_key_len(&self) -> Option<usize> {
+            return match self {
+                KeySpace::Alphanumeric
+                | KeySpace::Numeric
+                | KeySpace::NumericRange { .. }
+                | KeySpace::Custom { .. }
+                | KeySpace::Binary => None,
+                KeySpace::Uuid => Some(uuid::fmt::Hyphenated::LENGTH),
+                KeySpace::UuidSimple => Some(uuid::fmt::Simple::LENGTH),
+            };
+        }
+    }
+
+    /// How updates, deletes, and point queries pick a valid key.
+    #[derive(serde::Deserialize, JsonSchema, Default, Copy, Clone, Debug)]
+    #[serde(rename_all = "snake_case")]
+    pub enum KeyDistribution {
+        #[default]
+        Uniform,
+        /// YCSB's hotspot model. With probability `hot_probability` a key is picked uniformly
+        /// from the first `hot_fraction` of the valid keys, otherwise uniformly from the rest.
+        Hotspot {
+            hot_fraction: f64,
+            hot_probability: f64,
+        },
+        /// The valid keys in sorted order, one after another, wrapping around to the smallest key
+        /// after the largest, like a full table scan. All operations of a section that use it
+        /// share one position.
+        Sequential,
+        /// YCSB's zipfian model. The i-th valid key is picked with probability proportional to
+        /// `1 / i^theta`, so the first valid keys are the hottest. YCSB uses a theta of 0.99.
+        Zipfian { theta: f64 },
+        /// YCSB's latest model, zipfian from the last valid key backwards, so the most recently
+        /// added keys are the hottest. Valid keys are in the order they were added, except in
+        /// sorted key sets and where a delete moved the last key into its gap.
+        Latest { theta: f64 },
+    }
+
+    /// Zero-based rank drawn from a zipfian distribution over `len` items.
+    fn zipf_rank(rng: &mut WorkloadRng, len: usize, theta: f64) -> usize {
+        let zipf = Zipf::new(len as f64, theta).expect("a validated theta and a valid key");
+        return (rng.sample(zipf) as usize).clamp(1, len) - 1;
+    }
+
+    /// Position of a sequential scan over the valid keys, the last key it picked.
+    #[derive(Default, Debug)]
+    pub(crate) struct KeyCursor {
+        last: Option<Key>,
+    }
+
+    impl KeyCursor {
+        /// Index of the smallest valid key after the last picked one, or of the smallest key
+        /// once the scan passed the largest. Sorts the valid keys.
+        pub(crate) fn next_index(&mut self, keys_valid: &mut impl KeySet) -> usize {
+            keys_valid.sort();
+            let idx = match &self.last {
+                Some(last) => keys_valid.partition_point(|key| key <= last),
+                None => 0,
+            };
+            let idx = if idx < keys_valid.len() { idx } else { 0 };
+            self.last = keys_valid.get(idx).cloned();
+            return idx;
+        }
+    }
+
+    /// Which valid keys are evicted once a section tracks `max_tracked_keys` of them.
+    #[derive(serde::Deserialize, JsonSchema, Default, Copy, Clone, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    pub enum KeyEviction {
+        /// The keys that became valid first, a sliding window over the inserts. Evicted in batches
+        /// of 1/16 of `max_tracked_keys`, since finding them needs the keys sorted.
+        #[default]
+        Oldest,
+        /// A random valid key, one at a time.
+        Random,
+    }
+
+    impl KeyEviction {
+        /// Number of keys evicted at once out of at most `max_tracked_keys` valid keys.
+        pub(crate) fn batch_len(&self, max_tracked_keys: usize) -> usize {
+            return match self {
+                KeyEviction::Oldest => (max_tracked_keys / 16).max(1),
+                KeyEviction::Random => 1,
+            };
+        }
+    }
+
+    impl KeyDistribution {
+        /// Picks the index of a valid key. Only sequential picks move the `cursor`.
+        pub(crate) fn sample_index(
+            &self,
+            rng: &mut WorkloadRng,
+            keys_valid: &mut impl KeySet,
+            cursor: &mut KeyCursor,
+        ) -> usize {
+            let len = keys_valid.len();
+            return match *self {
+                KeyDistribution::Uniform => rng.random_range(0..len),
+                KeyDistribution::Hotspot {
+                    hot_fraction,
+                    hot_probability,
+                } => {
+                    let hot_len = ((len as f64 * hot_fraction).ceil() as usize).min(len);
+                    if hot_len == 0 || hot_len == len {
+                        return rng.random_range(0..len);
+                    }
+                    if rng.random_bool(hot_probability) {
+                        rng.random_range(0..hot_len)
+                    } else {
+                        rng.random_range(hot_len..len)
+                    }
+                }
+                KeyDistribution::Sequential => cursor.next_index(keys_valid),
+                KeyDistribution::Zipfian { theta } => zipf_rank(rng, len, theta),
+                KeyDistribution::Latest { theta } => len - 1 - zipf_rank(rng, len, theta),
+            };
+        }
+
+        pub(crate) fn validate(&self) -> Result<()> {
+            if let KeyDistribution::Hotspot {
+                hot_fraction,
+                hot_probability,
+            } = *self
+            {
+                if !(0.0..=1.0).contains(&hot_fraction) || !(0.0..=1.0).contains(&hot_probability) {
+                    return Err(WorkloadError::InvalidSpec(format!(
+                        "hot_fraction and hot_probability must be between 0 and 1, but are {} and {}.",
+                        hot_fraction, hot_probability
+                    )));
+                }
+            }
+            if let KeyDistribution::Zipfian { theta } | KeyDistribution::Latest { theta } = *self {
+                if !(theta.is_finite() && theta >= 0.0) {
+                    return Err(WorkloadError::InvalidSpec(format!(
+                        "theta must be a finite number of at least 0, but is {theta}."
+                    )));
+                }
+            }
+            return Ok(());
+        }
+    }
+
+    /// Number of keys a range delete with `selectivity` removes out of `len` valid keys. At least
+    /// one key is left to be the exclusive end of the range.
+    pub(crate) fn range_delete_len(selectivity: f32, len: usize) -> usize {
+        let num_items = (selectivity * len as f32).floor() as usize;
+        return num_items.min(len.saturating_sub(1));
+    }
+
+    /// Column family of the sections without `column_family` when other sections have one.
+    pub const DEFAULT_COLUMN_FAMILY: &str = "default";
+
+    #[derive(serde::Deserialize, JsonSchema, Default, Clone, Debug)]
+    pub(crate) struct WorkloadSpecSection {
+        /// A list of operation groups that share keys between operations.
+        ///
+        /// E.g., non-empty point queries will use a key from an insert in this group.
+        #[serde(deserialize_with = "deserialize_groups")]
+        #[schemars(with = "Vec<WorkloadSpecGroupForm>")]
+        pub(crate) groups: Vec<WorkloadSpecGroup>,
+        /// The domain from which the keys will be created from.
+        #[serde(default = "KeySpace::default")]
+        pub(crate) key_space: KeySpace,
+        /// How operations on existing keys pick a valid key.
+        #[serde(default = "KeyDistribution::default")]
+        pub(crate) key_distribution: KeyDistribution,
+        /// When set, empty point queries are checked against a bloom filter with this
+        /// false-positive rate, strictly between 0 and 1, instead of an exact key set. Uses less
+        /// memory, but roughly this fraction of the empty point queries may hit an existing key.
+        #[serde(default)]
+        pub(crate) bloom_filter_fp_rate: Option<f64>,
+        /// When set, empty point queries are checked against a cuckoo filter instead of an exact
+        /// key set. Uses about 2 bytes per key, with a false-positive rate around 0.01%, and
+        /// removes deleted keys in constant time.
+        #[serde(default)]
+        pub(crate) cuckoo_filter: bool,
+        /// Prefix prepended to every key generated in this section, e.g. a tenant id. `key_len`
+        /// doesn't include the prefix.
+        #[serde(default)]
+        pub(crate) key_prefix: Option<String>,
+        /// Prepends the key length as zero-padded digits to every key, so keys of different
+        /// `key_len`s sort by length first instead of shorter keys sorting between longer keys
+        /// that share their prefix. `key_len` doesn't include the length prefix.
+        #[serde(default)]
+        pub(crate) length_prefixed_keys: bool,
+        /// Randomly interleave the operations of all groups instead of running the groups one
+        /// after another. Each group keeps the order of its own operations, so its inserts still
+        /// come before the operations on them, unless the other groups delete every valid key and
+        /// an insert moves ahead. The deletes must leave a valid key for the other operations in
+        /// any order, and can't be mixed with range deletes.
+        #[serde(default)]
+        pub(crate) interleave_groups: bool,
+        /// Reorder the generated operations so all operations on a key are written together,
+        /// keys in the order they are first used, as a baseline of maximal locality. Buffers the
+        /// operations of the whole section in memory. Ranges count as operations on their start
+        /// key, and think times stay after the operation they follow.
+        #[serde(default)]
+        pub(crate) group_by_key: bool,
+        /// Write the inserts of all groups first, sorted by key, as a bulk-load phase before the
+        /// rest of the groups run, like YCSB's load and run phases. Buffers the inserts of the
+        /// whole section in memory. Inserts `after_bulk_load` run with their groups instead.
+        #[serde(default)]
+        pub(crate) bulk_load: bool,
+        /// Point queries on the loaded keys, written once the inserts that start the section are
+        /// done and before the rest of its operations. They are fenced by `# warmup-start` and
+        /// `# warmup-end` comments, so a benchmark can leave them out of its timings.
+        #[serde(default)]
+        pub(crate) warmup_point_queries: Option<usize>,
+        /// Seed of the rng that generates this section, so the section is the same in every run.
+        /// Otherwise the seed is derived from the workload's `seed`.
+        #[serde(default)]
+        pub(crate) seed: Option<u64>,
+        /// Upper bound on the valid keys tracked while generating, so long-running insert
+        /// workloads don't run out of memory. Past it, `key_eviction` picks keys to forget.
+        /// Evicted keys stay in the database, but are never picked by later operations, and
+        /// inserts and empty point queries no longer know to avoid them.
+        #[serde(default)]
+        pub(crate) max_tracked_keys: Option<usize>,
+        /// Which keys are evicted past `max_tracked_keys`.
+        #[serde(default)]
+        pub(crate) key_eviction: KeyEviction,
+        /// Derive the `i`-th insert key of the section from its `seed`, or 0 without one, and `i`
+        /// instead of the section's rng, so sections with the same seed insert the same keys in
+        /// the same order, e.g. to overlap the keys of two sections on purpose.
+        #[serde(default)]
+        pub(crate) deterministic_keys: bool,
+        /// RocksDB column family of the section's operations, written as a `CF name` directive
+        /// before them, so a replay tool routes them to that column family. Sections don't share
+        /// keys, so each can stand for its own column family. Once any section has one, sections
+        /// without one are written to the `default` column family. Names can't contain
+        /// whitespace or control characters.
+        #[serde(default)]
+        pub(crate) column_family: Option<String>,
+        /// Tag prepended to every key, before `key_prefix`, when the workload partitions its
+        /// sections. Set by [`WorkloadSpec::sections`].
+        #[serde(skip)]
+        #[schemars(skip)]
+        pub(crate) section_tag: Option<String>,
+    }
+
+    impl WorkloadSpecSection {
+        pub fn operation_count(&self) -> usize {
+            let warmup = self.warmup_point_queries.unwrap_or(0);
+            return self
+                .groups
+                .iter()
+                .map(|g| g.operation_count())
+                .sum::<usize>()
+                + warmup;
+        }
+
+        /// Key length used for operations on existing keys, the longest key length of inserts. 0
+        /// in a section without inserts, which has no existing keys.
+        pub fn insert_key_len(&self) -> usize {
+            return self
+                .groups
+                .iter()
+                .flat_map(|g| g.inserts.iter().flat_map(|is| is.possible_key_lens()))
+                .max()
+                .unwrap_or(0);
+        }
+
+        /// Width of the length prefix of every key, 0 unless `length_prefixed_keys` is set.
+        pub fn length_prefix_width(&self) -> usize {
+            if !self.length_prefixed_keys {
+                return 0;
+            }
+            return self.max_key_len().to_string().len();
+        }
+
+        /// Bytes added in front of every generated key, by the section tag, `key_prefix`, and the
+        /// length prefix.
+        pub fn key_prefix_len(&self) -> usize {
+            let tag_len = self.section_tag.as_ref().map_or(0, |tag| tag.len());
+            let prefix_len = self.key_prefix.as_ref().map_or(0, |prefix| prefix.len());
+            return tag_len + prefix_len + self.length_prefix_width();
+        }
+
+        pub fn bytes_count(&self) -> usize {
+            let insert_key_len = self.insert_key_len();
+            let prefix_len = self.key_prefix_len();
+            let bytes_warmup = self.warmup_point_queries.map_or(0, |amount| {
+                b"# warmup-start\n".len()
+                    + (b"P ".len() + prefix_len + insert_key_len + b"\n".len()) * amount
+                    + b"# warmup-end\n".len()
+            });
+            let bytes_column_family = self
+                .column_family
+                .as_ref()
+                .map_or(0, |cf| b"CF ".len() + cf.len() + b"\n".len());
+            return self
+                .groups
+                .iter()
+                .map(|g| g.bytes_count(insert_key_len) + prefix_len * g.key_count())
+                .sum::<usize>()
+                + bytes_warmup
+                + bytes_column_family;
+        }
+
+        /// Whether [`WorkloadSpecSection::bytes_count`] is exact rather than an estimate, i.e. all
+        /// keys have one length and all values a fixed one. Count range queries clamped at fewer
+        /// valid keys than their count still write fewer bytes.
+        pub fn bytes_count_is_exact(&self) -> bool {
+            let mut key_lens = self
+                .groups
+                .iter()
+                .flat_map(|g| g.inserts.iter().flat_map(|is| is.possible_key_lens()));
+            let one_key_len = key_lens
+                .next()
+                .is_none_or(|key_len| key_lens.all(|other| other == key_len));
+            let fixed_val_lens = self.groups.iter().all(|g| {
+                g.inserts
+                    .is_none_or(|is| matches!(is.val_len, ValLen::Fixed(_)))
+                    && g.updates
+                        .is_none_or(|us| matches!(us.val_len, ValLen::Fixed(_)))
+                    && g.read_modify_writes
+                        .is_none_or(|rmws| matches!(rmws.val_len, ValLen::Fixed(_)))
+            });
+            return one_key_len && fixed_val_lens;
+        }
+
+        pub fn insert_count(&self) -> usize {
+            return self
+                .groups
+                .iter()
+                .map(|g| g.inserts.map_or(0, |is| is.amount))
+                .sum();
+        }
+
+        pub fn has_inserts(&self) -> bool {
+            return self.groups.iter().any(|g| g.inserts.is_some());
+        }
+
+        pub fn has_updates(&self) -> bool {
+            return self.groups.iter().any(|g| g.updates.is_some());
+        }
+        /// Whether every insert of the section may reuse a valid key.
+        pub fn allows_duplicate_keys(&self) -> bool {
+            return self
+                .groups
+                .iter()
+                .all(|g| g.inserts.is_none_or(|is| is.allow_duplicate_keys));
+        }
+        pub fn has_deletes(&self) -> bool {
+            return self.groups.iter().any(|g| g.deletes.is_some());
+        }
+        pub fn has_point_queries(&self) -> bool {
+            return self.groups.iter().any(|g| g.point_queries.is_some());
+        }
+        pub fn has_empty_point_queries(&self) -> bool {
+            return self.groups.iter().any(|g| {
+                g.empty_point_queries.is_some()
+                    || g.point_queries.is_some_and(|pqs| pqs.hit_ratio < 1.0)
+                    || g.seek_queries.is_some_and(|sks| sks.hit_ratio < 1.0)
+            });
+        }
+
+        pub fn has_range_deletes(&self) -> bool {
+            return self.groups.iter().any(|g| g.range_deletes.is_some());
+        }
+
+        /// Whether any operation picks keys sequentially, which needs the keys sorted.
+        pub fn has_sequential_keys(&self) -> bool {
+            return self.first_sequential_group().is_some();
+        }
+
+        /// Index of the first group with an operation that picks keys sequentially.
+        fn first_sequential_group(&self) -> Option<usize> {
+            let sequential =
+                |kd: Option<KeyDistribution>| matches!(kd, Some(KeyDistribution::Sequential));
+            if sequential(Some(self.key_distribution)) {
+                return (!self.groups.is_empty()).then_some(0);
+            }
+            return self.groups.iter().position(|g| {
+                sequential(g.updates.and_then(|us| us.key_distribution))
+                    || sequential(g.point_queries.and_then(|pqs| pqs.key_distribution))
+                    || sequential(g.seek_queries.and_then(|sks| sks.key_distribution))
+            });
+        }
+
+        /// Whether keys are created after keys are first picked sequentially, so the valid keys
+        /// would need sorting again between the sequential picks. Within a group, inserts come
+        /// before the picks, except for overwrites, which pick with the section's distribution.
+        pub(crate) fn creates_keys_after_sequential_keys(&self) -> bool {
+            let Some(first) = self.first_sequential_group() else {
+                return false;
+            };
+            if self.interleave_groups {
+                return self.groups.iter().any(|g| g.created_key_count() > 0);
+            }
+            let overwrites = self.groups[first]
+                .inserts
+                .is_some_and(|is| is.overwrite_ratio > 0.0 && is.new_key_count() > 0);
+            return overwrites && matches!(self.key_distribution, KeyDistribution::Sequential)
+                || self.groups[first + 1..]
+                    .iter()
+                    .any(|g| g.created_key_count() > 0);
+        }
+
+        pub fn has_range_queries(&self) -> bool {
+            return self
+                .groups
+                .iter()
+                .any(|g| g.range_queries.is_some() || g.count_range_queries.is_some());
+        }
+
+        /// Key lengths of the keys generated in this section, either by inserts or empty point
+        /// queries.
+        pub fn key_lens(&self) -> impl Iterator<Item = usize> + '_ {
+            return self.groups.iter().flat_map(|g| {
+                g.inserts
+                    .iter()
+                    .flat_map(|is| is.possible_key_lens())
+                    .chain(g.empty_point_queries.map(|epq| epq.key_len))
+            });
+        }
+
+        /// Longest key that will be generated in this section, either by inserts or empty point
+        /// queries.
+        pub fn max_key_len(&self) -> usize {
+            return self.key_lens().max().unwrap_or(0);
+        }
+
+        /// Longest value that will be generated in this section, by inserts, updates, or
+        /// read-modify-writes.
+        pub fn max_val_len(&self) -> usize {
+            return self
+                .groups
+                .iter()
+                .flat_map(|g| {
+                    [
+                        g.inserts.map(|is| is.val_len),
+                        g.updates.map(|us| us.val_len),
+                        g.read_modify_writes.map(|rmws| rmws.val_len),
+                    ]
+                })
+                .flatten()
+                .map(|val_len| val_len.max())
+                .max()
+                .unwrap_or(0);
+        }
+
+        /// Whether generating the section reads its valid keys. Blind inserts, which may
+        /// duplicate keys and neither overwrite nor reinsert, never do, so a section of only blind
+        /// inserts doesn't need to track its keys unless it evicts some or has a warmup.
+        pub(crate) fn needs_valid_keys(&self) -> bool {
+            let blind_inserts = self.groups.iter().all(|g| {
+                g.is_insert_only()
+                    && g.inserts.is_some_and(|is| {
+                        is.allow_duplicate_keys
+                            && is.overwrite_ratio == 0.0
+                            && is.reinsert_ratio == 0.0
+                    })
+            });
+            return !blind_inserts
+                || self.max_tracked_keys.is_some()
+                || self.warmup_point_queries.is_some_and(|amount| amount > 0);
+        }
+
+        /// Number of keys the key set of the section is created with room for.
+        pub(crate) fn key_set_capacity(&self) -> usize {
+            return self
+                .max_tracked_keys
+                .map_or(self.insert_count(), |max_tracked_keys| {
+                    self.insert_count().min(max_tracked_keys)
+                });
+        }
+
+        /// Estimate of the most valid keys the section tracks at once, following the keys
+        /// created and deleted by every group like [`WorkloadSpecSection::validate`]. Interleaved
+        /// groups may create all their keys before any delete, so their estimate is every created
+        /// key.
+        pub fn peak_live_keys(&self) -> usize {
+            let max_tracked_keys = self.max_tracked_keys.unwrap_or(usize::MAX);
+            let created_key_count = |g: &WorkloadSpecGroup| {
+                let inserted = g
+                    .inserts
+                    .filter(|is| !self.bulk_loads(is))
+                    .map_or(0, |is| is.new_key_count());
+                return inserted + g.blind_update_count();
+            };
+            // A bulk load creates the keys of its inserts before the groups run.
+            let mut live_keys: usize = self
+                .groups
+                .iter()
+                .filter_map(|g| g.inserts.filter(|is| self.bulk_loads(is)))
+                .map(|is| is.new_key_count())
+                .sum();
+            if self.interleave_groups {
+                live_keys += self.groups.iter().map(created_key_count).sum::<usize>();
+                return live_keys.min(max_tracked_keys);
+            }
+            let mut peak = live_keys.min(max_tracked_keys);
+            for group in &self.groups {
+                live_keys += created_key_count(group);
+                peak = peak.max(live_keys.min(max_tracked_keys));
+                if live_keys > max_tracked_keys {
+                    live_keys =
+                        max_tracked_keys - self.key_eviction.batch_len(max_tracked_keys) + 1;
+                }
+                live_keys -= group.deletes.map_or(0, |ds| ds.amount).min(live_keys);
+                if let Some(rds) = group.range_deletes {
+                    for _ in 0..rds.amount {
+                        live_keys -= range_delete_len(rds.selectivity, live_keys);
+                    }
+                }
+            }
+            return peak;
+        }
+
+        /// Whether `is` is written by the section's bulk load rather than with its group.
+        pub(crate) fn bulk_loads(&self, is: &Inserts) -> bool {
+            return self.bulk_load && !is.after_bulk_load;
+        }
+
+        /// Checks the section can be generated before any operation is written.
+        ///
+        /// Groups run in order, and within a group inserts and blind updates run before deletes,
+        /// so each delete is bounded by the keys inserted so far minus the keys deleted so far.
+        /// Interleaved groups run in a random order, so only the totals of the section are checked.
+        pub(crate) fn validate(&self) -> Result<()> {
+            if let Some(column_family) = &self.column_family {
+                if column_family.is_empty() {
+                    return Err(WorkloadError::InvalidSpec(
+                        "column_family must not be empty.".to_string(),
+                    ));
+                }
+                // The name is one field of its directive, custom separators are checked with the
+                // other fields by `WorkloadSpec::validate_separators`.
+                if column_family
+                    .chars()
+                    .any(|c| c.is_whitespace() || c.is_control())
+                {
+                    return Err(WorkloadError::InvalidSpec(format!(
+                        "column_family must not contain whitespace or control characters, but is \
+                         {:?}.",
+                        column_family
+                    )));
+                }
+            }
+            if let KeySpace::Custom { charset } = &self.key_space {
+                if charset.is_empty() || !charset.is_ascii() {
+                    return Err(WorkloadError::InvalidSpec(format!(
+                        "The charset of a custom key space must be non-empty ascii, but is {:?}.",
+                        charset
+                    )));
+                }
+                if charset.bytes().any(|b| b.is_ascii_control()) {
+                    return Err(WorkloadError::InvalidSpec(format!(
+                        "The charset of a custom key space must not contain control characters \
+                         like a newline, but is {:?}.",
+                        charset
+                    )));
+                }
+                // `R <key> +<count>` is a count range query, so the end key of a range query
+                // must not start with `+`.
+                if charset.contains('+') && self.groups.iter().any(|g| g.range_queries.is_some()) {
+                    return Err(WorkloadError::InvalidSpec(format!(
+                        "The charset of a custom key space with range queries must not contain \
+                         `+`, but is {:?}.",
+                        charset
+                    )));
+                }
+            }
+            if let KeySpace::NumericRange { min, max } = self.key_space {
+                if min > max {
+                    return Err(WorkloadError::InvalidSpec(format!(
+                        "The numeric range key space needs min <= max, but is {}..={}.",
+                        min, max
+                    )));
+                }
+                let max_len = max.to_string().len();
+                if let Some(key_len) = self.key_lens().find(|&len| len < max_len) {
+                    return Err(WorkloadError::InvalidSpec(format!(
+                        "Keys up to {} need a key_len of at least {}, but key_len is {}.",
+                        max, max_len, key_len
+                    )));
+                }
+            }
+            if let Some(fixed_key_len) = self.key_space.fixed_key_len() {
+                if let Some(key_len) = self.key_lens().find(|&len| len != fixed_key_len) {
+                    return Err(WorkloadError::InvalidSpec(format!(
+                        "Keys in the {:?} key space are {} bytes long, but key_len is {}.",
+                        self.key_space, fixed_key_len, key_len
+                    )));
+                }
+            }
+            self.key_distribution.validate()?;
+            if let Some(fp_rate) = self.bloom_filter_fp_rate {
+                if !(fp_rate > 0.0 && fp_rate < 1.0) {
+                    return Err(WorkloadError::InvalidSpec(format!(
+                        "bloom_filter_fp_rate must be between 0 and 1 exclusive, but is {}.",
+                        fp_rate
+                    )));
+                }
+            }
+            if self.cuckoo_filter && self.bloom_filter_fp_rate.is_some() {
+                return Err(WorkloadError::InvalidSpec(
+                    "A section can't use both bloom_filter_fp_rate and cuckoo_filter.".to_string(),
+                ));
+            }
+            if self.max_tracked_keys == Some(0) {
+                return Err(WorkloadError::InvalidSpec(
+                    "max_tracked_keys must be at least 1.".to_string(),
+                ));
+            }
+            if self.warmup_point_queries.is_some_and(|amount| amount > 0) {
+                if !self.has_inserts() {
+                    return Err(WorkloadError::InvalidSpec(
+                        "Warmup point queries read the keys the section loads, but it has no \
+                         inserts."
+                            .to_string(),
+                    ));
+                }
+                if self.group_by_key {
+                    return Err(WorkloadError::InvalidSpec(
+                        "Warmup point queries can't be grouped by key, it moves them out of their \
+                         fence."
+                            .to_string(),
+                    ));
+                }
+            }
+            // A count of 0 has no key to start from.
+            if self
+                .groups
+                .iter()
+                .any(|g| g.count_range_queries.is_some_and(|crqs| crqs.count == 0))
+            {
+                return Err(WorkloadError::InvalidSpec(
+                    "count of count range queries must be at least 1.".to_string(),
+                ));
+            }
+            let has_inserts = self.groups.iter().any(|g| g.inserts.is_some());
+            if !has_inserts
+                && self
+                    .groups
+                    .iter()
+                    .any(|g| g.updates.is_some_and(|us| !us.existing))
+            {
+                return Err(WorkloadError::InvalidSpec(
+                    "Blind updates take the key length of the section's inserts, but it has none."
+                        .to_string(),
+                ));
+            }
+            for group in &self.groups {
+                if let Some(is) = group.inserts {
+                    if is.key_len == 0 && is.key_lens.is_none() {
+                        return Err(WorkloadError::InvalidSpec(
+                            "Inserts need a key_len or key_lens.".to_string(),
+                        ));
+                    }
+                    if !(0.0..=1.0).contains(&is.overwrite_ratio) {
+                        return Err(WorkloadError::InvalidSpec(format!(
+                            "overwrite_ratio must be between 0 and 1, but is {}.",
+                            is.overwrite_ratio
+                        )));
+                    }
+                    if !(0.0..=1.0).contains(&is.reinsert_ratio) {
+                        return Err(WorkloadError::InvalidSpec(format!(
+                            "reinsert_ratio must be between 0 and 1, but is {}.",
+                            is.reinsert_ratio
+                        )));
+                    }
+                    is.val_pattern.validate()?;
+                }
+                if let Some(us) = group.updates {
+                    us.val_pattern.validate()?;
+                }
+                if group
+                    .empty_point_queries
+                    .is_some_and(|epqs| epqs.max_attempts == Some(0))
+                {
+                    return Err(WorkloadError::InvalidSpec(
+                        "max_attempts of empty point queries must be at least 1.".to_string(),
+                    ));
+                }
+                if let Some(pqs) = group.point_queries {
+                    if !(0.0..=1.0).contains(&pqs.hit_ratio) {
+                        return Err(WorkloadError::InvalidSpec(format!(
+                            "hit_ratio must be between 0 and 1, but is {}.",
+                            pqs.hit_ratio
+                        )));
+                    }
+                }
+                if let Some(sks) = group.seek_queries {
+                    if !(0.0..=1.0).contains(&sks.hit_ratio) {
+                        return Err(WorkloadError::InvalidSpec(format!(
+                            "hit_ratio of seek queries must be between 0 and 1, but is {}.",
+                            sks.hit_ratio
+                        )));
+                    }
+                }
+                if let Some(rs) = group.range_queries {
+                    if !(0.0..=1.0).contains(&rs.selectivity) {
+                        return Err(WorkloadError::InvalidSpec(format!(
+                            "selectivity of range queries must be between 0 and 1, but is {}.",
+                            rs.selectivity
+                        )));
+                    }
+                    if !(0.0..=1.0).contains(&rs.min_gap) {
+                        return Err(WorkloadError::InvalidSpec(format!(
+                            "min_gap must be between 0 and 1, but is {}.",
+                            rs.min_gap
+                        )));
+                    }
+                }
+                let updates = group.updates.and_then(|us| us.key_distribution);
+                let point_queries = group.point_queries.and_then(|pqs| pqs.key_distribution);
+                let seek_queries = group.seek_queries.and_then(|sks| sks.key_distribution);
+                for key_distribution in updates.iter().chain(&point_queries).chain(&seek_queries) {
+                    key_distribution.validate()?;
+                }
+            }
+
+            if self.interleave_groups {
+                let created: usize = self.groups.iter().map(|g| g.created_key_count()).sum();
+                let deleted: usize = self
+                    .groups
+                    .iter()
+                    .map(|g| g.deletes.map_or(0, |ds| ds.amount))
+                    .sum();
+                if deleted > created {
+                    return Err(WorkloadError::TooManyInterleavedDeletes {
+                        deletes: deleted,
+                        inserted: created,
+                    });
+                }
+                if self.has_range_deletes() && created == 0 {
+                    return Err(WorkloadError::NoValidKeys(
+                        "The interleaved groups have range deletes, but no keys are inserted."
+                            .to_string(),
+                    ));
+                }
+                // Operations that need a valid key yield to the ones that create keys, so the
+                // keys only run out once every key is created. These bounds hold for any order of
+                // the deletes after that.
+                if deleted == 0 {
+                    return Ok(());
+                }
+                if self.has_range_deletes() {
+                    return Err(WorkloadError::InvalidSpec(
+                        "Interleaved groups can't have both deletes and range deletes, since a \
+                         range delete may leave too few keys for the deletes."
+                            .to_string(),
+                    ));
+                }
+                // Like below, evicting a batch leaves fewer keys than the cap.
+                let tracked = match self.max_tracked_keys {
+                    Some(max_tracked_keys) if created > max_tracked_keys => {
+                        max_tracked_keys - self.key_eviction.batch_len(max_tracked_keys) + 1
+                    }
+                    _ => created,
+                };
+                if deleted > tracked {
+                    return Err(WorkloadError::NoValidKeys(format!(
+                        "The interleaved groups delete {} keys, but only {} keys may be tracked \
+                         by then.",
+                        deleted, tracked
+                    )));
+                }
+                if deleted == tracked && self.groups.iter().any(|g| g.reads_valid_keys()) {
+                    return Err(WorkloadError::NoValidKeys(
+                        "The interleaved groups may delete every key before their other \
+                         operations on valid keys."
+                            .to_string(),
+                    ));
+                }
+                return Ok(());
+            }
+
+            let mut live_keys: usize = 0;
+            for (i, group) in self.groups.iter().enumerate() {
+                live_keys += group.created_key_count();
+                if let Some(max_tracked_keys) = self.max_tracked_keys {
+                    // Evicting a batch leaves fewer keys than the cap until the next inserts.
+                    if live_keys > max_tracked_keys {
+                        live_keys =
+                            max_tracked_keys - self.key_eviction.batch_len(max_tracked_keys) + 1;
+                    }
+                }
+                if let Some(ds) = group.deletes {
+                    if ds.amount > live_keys {
+                        return Err(WorkloadError::TooManyDeletes {
+                            group: i,
+                            deletes: ds.amount,
+                            live_keys,
+                        });
+                    }
+                    live_keys -= ds.amount;
+                }
+                if let Some(rds) = group.range_deletes {
+                    if live_keys == 0 {
+                        return Err(WorkloadError::NoValidKeys(format!(
+                            "Group {} has range deletes, but no valid keys exist by then.",
+                            i
+                        )));
+                    }
+                    for _ in 0..rds.amount {
+                        live_keys -= range_delete_len(rds.selectivity, live_keys);
+                    }
+                }
+            }
+            return Ok(());
+        }
+    }
+
+    #[derive(serde::Deserialize, JsonSchema, Debug, Clone)]
+    #[schemars(example = "insert_only_example", example = "mixed_example")]
+    pub struct WorkloadSpec {
+        /// Sections of a workload where a key from one will (probably) not appear in another. Set
+        /// `partition_sections` to guarantee it.
+        pub(crate) sections: Vec<WorkloadSpecSection>,
+        /// Tag every key with the index of its section, e.g. `1~`, so a key from one section can
+        /// never appear in another. The tag comes before the section's `key_prefix`.
+        #[serde(default)]
+        pub(crate) partition_sections: bool,
+        /// Seed from which the seeds of sections without their own `seed` are derived, so the
+        /// workload is the same in every run. Random if not set.
+        #[serde(default)]
+        pub(crate) seed: Option<u64>,
+        /// Algorithm of the rng that generates the workload, e.g. to compare against a reference
+        /// implementation seeded with the same generator.
+        #[serde(default)]
+        pub(crate) rng: RngAlgorithm,
+    }
+
+    impl WorkloadSpec {
+        /// Builds a spec in code instead of json.
+        pub fn builder() -> WorkloadSpecBuilder {
+            return WorkloadSpecBuilder::default();
+        }
+
+        pub fn operation_count(&self) -> usize {
+            return self.sections.iter().map(|s| s.operation_count()).sum();
+        }
+
+        /// Longest key of the workload, with its section prefixes.
+        pub fn max_key_len(&self) -> usize {
+            return self
+                .sections()
+                .map(|s| s.key_prefix_len() + s.max_key_len())
+                .max()
+                .unwrap_or(0);
+        }
+
+        /// Longest value of the workload.
+        pub fn max_val_len(&self) -> usize {
+            return self
+                .sections
+                .iter()
+                .map(|s| s.max_val_len())
+                .max()
+                .unwrap_or(0);
+        }
+
+        /// The same workload with every amount, warmup point queries included, scaled by one
+        /// factor so the workload has `total` operations, keeping the ratios of the operation
+        /// types, e.g. to try a large spec quickly. Amounts are rounded by largest remainder, so
+        /// they add up to `total` exactly. An amount that would round to zero keeps one operation,
+        /// taken from the largest amount, unless `total` is too small to go around. Read-modify-
+        /// writes count as two operations, so `total` can come out one short when they can't be
+        /// rounded otherwise. Amounts that depend on each other, e.g. deletes of every inserted
+        /// key, can end up one apart and fail validation.
+        pub fn scaled_to(&self, total: usize) -> WorkloadSpec {
+            let mut scaled = self.clone();
+            let mut amounts: Vec<(&mut usize, usize)> = Vec::new();
+            for section in &mut scaled.sections {
+                if let Some(warmup) = &mut section.warmup_point_queries {
+                    amounts.push((warmup, 1));
+                }
+                for group in &mut section.groups {
+                    amounts.extend(group.amounts_mut());
+                }
+            }
+            scale_amounts(&mut amounts, total);
+            return scaled;
+        }
+
+        /// A random spec that generates without errors, e.g. for property tests of the generator
+        /// itself. Sections often start with only one to three keys, and ratios and selectivities
+        /// are often exactly 0 or 1, where off-by-one mistakes live. Deletes never remove the
+        /// last valid key, since the operations after them need one.
+        pub fn arbitrary(rng: &mut impl Rng) -> WorkloadSpec {
+            let mut builder = WorkloadSpec::builder()
+                .seed(rng.random())
+                .partition_sections(rng.random_bool(0.25));
+            for i in 0..rng.random_range(1..=3) {
+                if i > 0 {
+                    builder = builder.add_section();
+                }
+                let key_len = rng.random_range(8..=16);
+                let first_keys = match rng.random_range(0..4) {
+                    0 => 1,
+                    1 => 2,
+                    2 => 3,
+                    _ => rng.random_range(4..=300),
+                };
+                let group_by_key = rng.random_bool(0.25);
+                builder = builder
+                    .key_distribution(arbitrary_key_distribution(rng))
+                    .group_by_key(group_by_key)
+                    .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(
+                        first_keys,
+                        key_len,
+                        rng.random_range(0..=16),
+                    )));
+                // Grouping by key would move warmup point queries out of their fence.
+                if !group_by_key && rng.random_bool(0.25) {
+                    builder = builder.warmup_point_queries(rng.random_range(0..=10));
+                }
+                if rng.random_bool(0.25) {
+                    builder = builder.column_family(format!("cf{i}"));
+                }
+                if rng.random_bool(0.25) {
+                    builder = builder.key_prefix("tenant:");
+                }
+
+                // A lower bound on the valid keys before every group's deletes.
+                let mut live = first_keys;
+                for _ in 0..rng.random_range(0..=3) {
+                    let (group, live_after) = arbitrary_group(rng, key_len, live);
+                    builder = builder.add_group(group);
+                    live = live_after;
+                }
+            }
+            return builder.build();
+        }
+
+        /// Seed of the workload, `None` if it is random.
+        pub fn seed(&self) -> Option<u64> {
+            return self.seed;
+        }
+
+        /// Number of operations of every type, counted like the stats of the generated workload.
+        /// The key stats are left empty.
+        pub fn operation_counts(&self) -> WorkloadStats {
+            let mut counts = WorkloadStats::default();
+            for group in self.sections.iter().flat_map(|s| &s.groups) {
+                let rmws = group.read_modify_writes.map_or(0, |rmws| rmws.amount);
+                counts.inserts += group.inserts.map_or(0, |is| is.amount);
+                counts.updates += group.updates.map_or(0, |us| us.amount) + rmws;
+                counts.deletes += group.deletes.map_or(0, |ds| ds.amount);
+                let misses = group.point_queries.map_or(0, |pqs| pqs.miss_count());
+                counts.point_queries +=
+                    group.point_queries.map_or(0, |pqs| pqs.amount) - misses + rmws;
+                counts.empty_point_queries +=
+                    group.empty_point_queries.map_or(0, |es| es.amount) + misses;
+                counts.range_queries += group.range_queries.map_or(0, |rqs| rqs.amount)
+                    + group.count_range_queries.map_or(0, |crqs| crqs.amount);
+                counts.range_deletes += group.range_deletes.map_or(0, |rds| rds.amount);
+                counts.think_times += group.think_times.map_or(0, |ts| ts.amount);
+                counts.flushes += group.flushes.map_or(0, |fs| fs.amount);
+                counts.read_modify_writes += rmws;
+                counts.seek_queries += group.seek_queries.map_or(0, |sks| sks.amount);
+            }
+            counts.point_queries += self
+                .sections
+                .iter()
+                .map(|s| s.warmup_point_queries.unwrap_or(0))
+                .sum::<usize>();
+            return counts;
+        }
+
+        /// Number of groups of every section.
+        pub fn group_counts(&self) -> Vec<usize> {
+            return self.sections.iter().map(|s| s.groups.len()).collect();
+        }
+
+        pub fn bytes_count(&self) -> usize {
+            return self.sections().map(|s| s.bytes_count()).sum();
+        }
+
+        /// Estimate of the most valid keys every section tracks at once, see
+        /// [`WorkloadSpecSection::peak_live_keys`].
+        pub fn peak_live_keys(&self) -> Vec<usize> {
+            return self.sections().map(|s| s.peak_live_keys()).collect();
+        }
+
+        /// The key set every section is generated with.
+        pub fn key_set_kinds(&self) -> Vec<KeySetKind> {
+            return self.sections().map(|s| KeySetKind::select(&s)).collect();
+        }
+
+        /// Estimate of the heap bytes the key set of every section holds at its peak if it was
+        /// generated with `kind`, e.g. to compare `kind` with [`WorkloadSpec::key_set_kinds`]
+        /// before generating a large workload.
+        pub fn key_set_memory_bytes(&self, kind: KeySetKind) -> Vec<usize> {
+            return self.sections().map(|s| kind.memory_bytes(&s)).collect();
+        }
+
+        /// Fails if a key, value, or column family name of the workload can contain a separator
+        /// of `format`, which the ascii format rejects unless it escapes separators. Checked
+        /// before generating, so nothing is written when it fails.
+        pub fn validate_separators(&self, format: &AsciiFormat) -> Result<()> {
+            if format.escaping == FieldEscaping::Backslash {
+                return Ok(());
+            }
+            for (i, section) in self.sections().enumerate() {
+                let key_bytes: &[u8] = match &section.key_space {
+                    KeySpace::Alphanumeric => ALPHANUMERIC,
+                    KeySpace::Numeric | KeySpace::NumericRange { .. } => b"0123456789",
+                    KeySpace::Uuid => b"0123456789abcdef-",
+                    KeySpace::UuidSimple => b"0123456789abcdef",
+                    KeySpace::Custom { charset } => charset.as_bytes(),
+                    // Binary keys can't be written as ascii at all.
+                    KeySpace::Binary => b"",
+                };
+                let val_patterns = section.groups.iter().flat_map(|g| {
+                    let is = g.inserts.map(|is| is.val_pattern);
+                    let us = g.updates.map(|us| us.val_pattern);
+                    // Read-modify-writes write random values.
+                    let rmws = g.read_modify_writes.map(|_| ValPattern::Random);
+                    return [is, us, rmws].into_iter().flatten();
+                });
+                let mut fields: Vec<(&str, Cow<[u8]>)> = vec![("keys", Cow::Borrowed(key_bytes))];
+                fields.extend(val_patterns.map(|val_pattern| {
+                    return match val_pattern {
+                        ValPattern::Repeated(byte) => ("values", Cow::Owned(vec![byte])),
+                        ValPattern::Random | ValPattern::LowEntropy { .. } => {
+                            ("values", Cow::Borrowed(&ALPHANUMERIC[..]))
+                        }
+                    };
+                }));
+                if section.length_prefixed_keys {
+                    fields.push(("key length prefixes", Cow::Borrowed(b"0123456789")));
+                }
+                for (name, field) in [
+                    ("key_prefix", &section.key_prefix),
+                    ("section tag", &section.section_tag),
+                    ("column_family", &section.column_family),
+                ] {
+                    if let Some(field) = field {
+                        fields.push((name, Cow::Borrowed(field.as_bytes())));
+                    }
+                }
+                for sep in [format.field_sep, format.record_sep] {
+                    if let Some((name, _)) = fields.iter().find(|(_, field)| field.contains(&sep)) {
+                        return Err(WorkloadError::InvalidSpec(format!(
+                            "The {} of section {} can contain the ascii separator {:?}, escape \
+                             separators or use other ones.",
+                            name,
+                            i,
+                            char::from(sep)
+                        )));
+                    }
+                }
+            }
+            return Ok(());
+        }
+
+        /// Whether any section draws its keys from [`KeySpace::Binary`].
+        pub fn has_binary_keys(&self) -> bool {
+            return self
+                .sections
+                .iter()
+                .any(|s| matches!(s.key_space, KeySpace::Binary));
+        }
+
+        /// Whether [`WorkloadSpec::bytes_count`] is exact, see
+        /// [`WorkloadSpecSection::bytes_count_is_exact`].
+        pub fn bytes_count_is_exact(&self) -> bool {
+            return self.sections().all(|s| s.bytes_count_is_exact());
+        }
+
+        /// The sections to generate, tagged with their index if the workload partitions its
+        /// sections. A tag is digits followed by `~`, so no tag is a prefix of another one.
+        /// Sections without a column family are in the `default` one if any section has one, so
+        /// their operations aren't routed to the column family of the section before them.
+        pub(crate) fn sections(&self) -> impl Iterator<Item = Cow<'_, WorkloadSpecSection>> {
+            let routed = self.sections.iter().any(|s| s.column_family.is_some());
+            return self.sections.iter().enumerate().map(move |(i, section)| {
+                let default_column_family = routed && section.column_family.is_none();
+                if !self.partition_sections && !default_column_family {
+                    return Cow::Borrowed(section);
+                }
+                let mut section = section.clone();
+                if self.partition_sections {
+                    section.section_tag = Some(format!("{i}~"));
+                }
+                if default_column_family {
+                    section.column_family = Some(DEFAULT_COLUMN_FAMILY.to_string());
+                }
+                return Cow::Owned(section);
+            });
+        }
+
+        /// Checks every section can be generated, see [`WorkloadSpecSection::validate`].
+        pub fn validate(&self) -> Result<()> {
+            if self.sections.is_empty() {
+                return Err(WorkloadError::EmptyWorkload);
+            }
+            for (i, section) in self.sections.iter().enumerate() {
+                if section.groups.is_empty() {
+                    return Err(WorkloadError::EmptySection { section: i });
+                }
+                if let Some(group) = section.groups.iter().position(|g| g.is_empty()) {
+                    return Err(WorkloadError::EmptyGroup { section: i, group });
+                }
+                section.validate()?;
+            }
+            return Ok(());
+        }
+    }
+
+    /// A random key distribution for [`WorkloadSpec::arbitrary`].
+    fn arbitrary_key_distribution(rng: &mut impl Rng) -> KeyDistribution {
+        return match rng.random_range(0..5) {
+            0 => KeyDistribution::Uniform,
+            1 => KeyDistribution::Hotspot {
+                hot_fraction: rng.random(),
+                hot_probability: rng.random(),
+            },
+            2 => KeyDistribution::Sequential,
+            3 => KeyDistribution::Zipfian { theta: 0.99 },
+            _ => KeyDistribution::Latest { theta: 0.99 },
+        };
+    }
+
+    /// A random fraction for [`WorkloadSpec::arbitrary`], often exactly 0 or 1.
+    fn arbitrary_fraction(rng: &mut impl Rng) -> f32 {
+        return match rng.random_range(0..4) {
+            0 => 0.0,
+            1 => 1.0,
+            _ => rng.random(),
+        };
+    }
+
+    /// A random group for [`WorkloadSpec::arbitrary`] of a section with keys of `key_len` and at
+    /// least `live` valid keys, with the valid keys it leaves at least.
+    fn arbitrary_group(
+        rng: &mut impl Rng,
+        key_len: usize,
+        mut live: usize,
+    ) -> (WorkloadSpecGroup, usize) {
+        let mut group = WorkloadSpecGroup::new();
+        if rng.random_bool(0.5) {
+            let inserts = rng.random_range(0..=50);
+            group = group.inserts(Inserts::new(inserts, key_len, 8).sorted(rng.random_bool(0.25)));
+            live += inserts;
+        }
+        if rng.random_bool(0.5) {
+            let updates = Updates::new(rng.random_range(0..=50), 8).existing(rng.random_bool(0.75));
+            group = group.updates(updates);
+        }
+        // Deletes run after the inserts of the group and before the other operations, which need
+        // at least one valid key.
+        if rng.random_bool(0.5) {
+            let deletes = rng.random_range(0..=live - 1);
+            group = group.deletes(Deletes::new(deletes));
+            live -= deletes;
+        }
+        if rng.random_bool(0.25) {
+            let range_deletes = RangeDeletes::new(rng.random_range(0..=3), arbitrary_fraction(rng));
+            for _ in 0..range_deletes.amount {
+                live -= range_delete_len(range_deletes.selectivity, live);
+            }
+            group = group.range_deletes(range_deletes);
+        }
+        if rng.random_bool(0.5) {
+            let point_queries = PointQueries::new(rng.random_range(0..=50))
+                .key_distribution(arbitrary_key_distribution(rng))
+                .hit_ratio(arbitrary_fraction(rng));
+            group = group.point_queries(point_queries);
+        }
+        if rng.random_bool(0.25) {
+            let empty_point_queries = EmptyPointQueries::new(rng.random_range(0..=20), key_len)
+                .near_miss(rng.random_bool(0.5));
+            group = group.empty_point_queries(empty_point_queries);
+        }
+        if rng.random_bool(0.5) {
+            let range_queries =
+                RangeQueries::new(rng.random_range(0..=20), arbitrary_fraction(rng))
+                    .reverse(rng.random_bool(0.5))
+                    .domain_based(rng.random_bool(0.25));
+            group = group.range_queries(range_queries);
+        }
+        if rng.random_bool(0.25) {
+            let count = rng.random_range(1..=2 * live + 2);
+            group =
+                group.count_range_queries(CountRangeQueries::new(rng.random_range(0..=20), count));
+        }
+        if rng.random_bool(0.25) {
+            let seek_queries =
+                SeekQueries::new(rng.random_range(0..=20)).hit_ratio(arbitrary_fraction(rng));
+            group = group.seek_queries(seek_queries);
+        }
+        if rng.random_bool(0.25) {
+            group = group.read_modify_writes(ReadModifyWrites::new(rng.random_range(0..=20), 8));
+        }
+        if rng.random_bool(0.25) {
+            group = group.think_times(ThinkTimes::new(rng.random_range(0..=5), 0));
+        }
+        if rng.random_bool(0.25) {
+            group = group.flushes(Flushes::new(rng.random_range(0..=3)));
+        }
+        if group.is_empty() {
+            group = group.point_queries(PointQueries::new(rng.random_range(0..=10)));
+        }
+        return (group, live);
+    }
+
+    /// Scales `amounts`, each with the number of operations it counts as, to `total` operations,
+    /// see [`WorkloadSpec::scaled_to`].
+    fn scale_amounts(amounts: &mut [(&mut usize, usize)], total: usize) {
+        let ops: u128 = amounts.iter().map(|(a, w)| (**a * w) as u128).sum();
+        if ops == 0 {
+            return;
+        }
+        let original: Vec<usize> = amounts.iter().map(|(a, _)| **a).collect();
+        // Rounds every exact share down, remembering how much was cut off.
+        let mut remainders = Vec::with_capacity(amounts.len());
+        let mut left = total;
+        for (i, (amount, weight)) in amounts.iter_mut().enumerate() {
+            let share = total as u128 * (**amount * *weight) as u128;
+            let scaled = (share / ops) as usize / *weight;
+            remainders.push((share - (scaled * *weight) as u128 * ops, i));
+            **amount = scaled;
+            left -= scaled * *weight;
+        }
+        // The largest remainders get the operations that are left.
+        remainders.sort_by(|a, b| b.cmp(a));
+        for &(_, i) in &remainders {
+            let weight = amounts[i].1;
+            if weight <= left {
+                *amounts[i].0 += 1;
+                left -= weight;
+            }
+        }
+        // Amounts rounded to zero take theirs from the largest amount that adds up to them.
+        for i in 0..amounts.len() {
+            let weight = amounts[i].1;
+            if original[i] == 0 || *amounts[i].0 > 0 {
+                continue;
+            }
+            let donor = (0..amounts.len())
+                .filter(|&k| amounts[k].1 <= weight && *amounts[k].0 > weight / amounts[k].1)
+                .max_by_key(|&k| *amounts[k].0 * amounts[k].1);
+            if let Some(k) = donor {
+                *amounts[k].0 -= weight / amounts[k].1;
+                *amounts[i].0 = 1;
+            }
+        }
+    }
+
+    /// Builder for a [`WorkloadSpec`]. Groups and section options apply to the last added section,
+    /// which is created on first use if no section was added yet.
+    ///
+    /// ```
+    /// use workload_gen::spec::{Deletes, Inserts, WorkloadSpec, WorkloadSpecGroup};
+    ///
+    /// let spec = WorkloadSpec::builder()
+    ///     .add_section()
+    ///     .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 16, 48)))
+    ///     .add_group(WorkloadSpecGroup::new().deletes(Deletes::new(100)))
+    ///     .build();
+    /// assert_eq!(spec.operation_count(), 1100);
+    /// ```
+    #[derive(Default, Clone, Debug)]
+    pub struct WorkloadSpecBuilder {
+        sections: Vec<WorkloadSpecSection>,
+        partition_sections: bool,
+        seed: Option<u64>,
+        rng: RngAlgorithm,
+    }
+
+    impl WorkloadSpecBuilder {
+        /// Starts a new section with the default options.
+        pub fn add_section(mut self) -> Self {
+            self.sections.push(WorkloadSpecSection::default());
+            return self;
+        }
+
+        pub fn add_group(mut self, group: WorkloadSpecGroup) -> Self {
+            self.section().groups.push(group);
+            return self;
+        }
+
+        pub fn key_space(mut self, key_space: KeySpace) -> Self {
+            self.section().key_space = key_space;
+            return self;
+        }
+
+        pub fn key_distribution(mut self, key_distribution: KeyDistribution) -> Self {
+            self.section().key_distribution = key_distribution;
+            return self;
+        }
+
+        pub fn bloom_filter_fp_rate(mut self, bloom_filter_fp_rate: f64) -> Self {
+            self.section().bloom_filter_fp_rate = Some(bloom_filter_fp_rate);
+            return self;
+        }
+
+        pub fn cuckoo_filter(mut self, cuckoo_filter: bool) -> Self {
+            self.section().cuckoo_filter = cuckoo_filter;
+            return self;
+        }
+
+        pub fn key_prefix(mut self, key_prefix: impl Into<String>) -> Self {
+            self.section().key_prefix = Some(key_prefix.into());
+            return self;
+        }
+
+        pub fn length_prefixed_keys(mut self, length_prefixed_keys: bool) -> Self {
+            self.section().length_prefixed_keys = length_prefixed_keys;
+            return self;
+        }
+
+        pub fn interleave_groups(mut self, interleave_groups: bool) -> Self {
+            self.section().interleave_groups = interleave_groups;
+            return self;
+        }
+
+        pub fn group_by_key(mut self, group_by_key: bool) -> Self {
+            self.section().group_by_key = group_by_key;
+            return self;
+        }
+
+        pub fn bulk_load(mut self, bulk_load: bool) -> Self {
+            self.section().bulk_load = bulk_load;
+            return self;
+        }
+
+        pub fn warmup_point_queries(mut self, amount: usize) -> Self {
+            self.section().warmup_point_queries = Some(amount);
+            return self;
+        }
+
+        /// Tags every key with its section, see [`WorkloadSpec::sections`].
+        pub fn partition_sections(mut self, partition_sections: bool) -> Self {
+            self.partition_sections = partition_sections;
+            return self;
+        }
+
+        /// Seeds the workload, see [`WorkloadSpecBuilder::section_seed`] to seed a single section.
+        pub fn seed(mut self, seed: u64) -> Self {
+            self.seed = Some(seed);
+            return self;
+        }
+
+        pub fn rng(mut self, rng: RngAlgorithm) -> Self {
+            self.rng = rng;
+            return self;
+        }
+
+        pub fn section_seed(mut self, seed: u64) -> Self {
+            self.section().seed = Some(seed);
+            return self;
+        }
+
+        pub fn max_tracked_keys(mut self, max_tracked_keys: usize) -> Self {
+            self.section().max_tracked_keys = Some(max_tracked_keys);
+            return self;
+        }
+
+        pub fn key_eviction(mut self, key_eviction: KeyEviction) -> Self {
+            self.section().key_eviction = key_eviction;
+            return self;
+        }
+
+        pub fn deterministic_keys(mut self, deterministic_keys: bool) -> Self {
+            self.section().deterministic_keys = deterministic_keys;
+            return self;
+        }
+
+        pub fn column_family(mut self, column_family: impl Into<String>) -> Self {
+            self.section().column_family = Some(column_family.into());
+            return self;
+        }
+
+        pub fn build(self) -> WorkloadSpec {
+            return WorkloadSpec {
+                sections: self.sections,
+                partition_sections: self.partition_sections,
+                seed: self.seed,
+                rng: self.rng,
+            };
+        }
+
+        fn section(&mut self) -> &mut WorkloadSpecSection {
+            if self.sections.is_empty() {
+                self.sections.push(WorkloadSpecSection::default());
+            }
+            return self.sections.last_mut().expect("a section was just added");
+        }
+    }
+
+    /// Example of the schema: a million inserts.
+    fn insert_only_example() -> serde_json::Value {
+        return serde_json::json!({
+            "sections": [{
+                "groups": [{
+                    "inserts": {"amount": 1_000_000, "key_len": 16, "val_len": 64}
+                }]
+            }]
+        });
+    }
+
+    /// Example of the schema: inserts to populate the keys, then a mix of operations on them.
+    fn mixed_example() -> serde_json::Value {
+        return serde_json::json!({
+            "sections": [{
+                "groups": [
+                    {"inserts": {"amount": 100_000, "key_len": 16, "val_len": 64}},
+                    {
+                        "inserts": {"amount": 10_000, "key_len": 16, "val_len": 64},
+                        "updates": {"amount": 10_000, "val_len": 64},
+                        "deletes": {"amount": 1_000},
+                        "point_queries": {"amount": 50_000},
+                        "empty_point_queries": {"amount": 10_000, "key_len": 16},
+                        "range_queries": {"amount": 1_000, "selectivity": 0.001}
+                    }
+                ]
+            }]
+        });
+    }
+
+    /// Serialization format of a workload spec.
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+    pub enum SpecFormat {
+        #[default]
+        Json,
+        Toml,
+        Yaml,
+    }
+
+    impl SpecFormat {
+        /// The format of a spec file by its extension, ignoring a trailing `.gz`. Files with any
+        /// other extension are json.
+        pub fn from_path(path: &Path) -> Self {
+            let path = match path.extension() {
+                Some(ext) if ext == "gz" => Path::new(path.file_stem().unwrap_or_default()),
+                _ => path,
+            };
+            return match path.extension().and_then(|ext| ext.to_str()) {
+                Some("toml") => SpecFormat::Toml,
+                Some("yaml" | "yml") => SpecFormat::Yaml,
+                _ => SpecFormat::Json,
+            };
+        }
+
+        /// Deserializes a spec written in this format.
+        pub fn parse(&self, workload_spec_string: &str) -> Result<WorkloadSpec> {
+            return Ok(match self {
+                SpecFormat::Json => from_json_str(workload_spec_string)?,
+                SpecFormat::Toml => toml::from_str(workload_spec_string)?,
+                // serde_yaml expects `!variant` tags for enums with fields, so yaml specs go
+                // through json to write enums as maps, like in json and toml.
+                SpecFormat::Yaml => {
+                    let spec = self.to_json(workload_spec_string)?;
+                    serde_json::from_value(spec)
+                        .map_err(<serde_yaml::Error as serde::de::Error>::custom)?
+                }
+            });
+        }
+
+        /// The spec as a json value, e.g. to validate it against the json schema.
+        pub fn to_json(&self, workload_spec_string: &str) -> Result<serde_json::Value> {
+            return Ok(match self {
+                SpecFormat::Json => from_json_str(workload_spec_string)?,
+                SpecFormat::Toml => toml::from_str(workload_spec_string)?,
+                SpecFormat::Yaml => serde_yaml::from_str(workload_spec_string)?,
+            });
+        }
+    }
+
+    /// Deserializes a json spec, failing with [`WorkloadError::ParseSpec`] at the offending value.
+    pub(crate) fn from_json_str<T: serde::de::DeserializeOwned>(json: &str) -> Result<T> {
+        let de = &mut serde_json::Deserializer::from_str(json);
+        let parse_error = |err: serde_json::Error, path: String| {
+            // serde_json ends its messages with the position, which is part of the variant.
+            let position = format!(" at line {} column {}", err.line(), err.column());
+            let message = err.to_string();
+            return WorkloadError::ParseSpec {
+                line: err.line(),
+                column: err.column(),
+                path,
+                message: message
+                    .strip_suffix(&position)
+                    .unwrap_or(&message)
+                    .to_string(),
+            };
+        };
+        let value = serde_path_to_error::deserialize(&mut *de).map_err(|err| {
+            let path = err.path().to_string();
+            return parse_error(err.into_inner(), path);
+        })?;
+        // Trailing characters after the spec.
+        de.end().map_err(|err| parse_error(err, ".".to_string()))?;
+        return Ok(value);
+    }
+}
+
+/// Json schema generation
+mod schema {
+    use crate::error::{Result, WorkloadError};
+    use crate::spec::WorkloadSpec;
+    use jsonschema::error::ValidationErrorKind;
+    use jsonschema::ValidationError;
+    use schemars::schema_for;
+
+    pub fn generate_workload_spec_schema() -> serde_json::Result<String> {
+        let schema = schema_for!(WorkloadSpec);
+        return serde_json::to_string_pretty(&schema);
+    }
+
+    /// Validates a json workload spec against the schema of [`WorkloadSpec`], listing every
+    /// error with the JSON pointer of the offending value.
+    pub fn validate_workload_spec(workload_spec_string: &str) -> Result<()> {
+        return validate_workload_spec_value(&crate::spec::from_json_str(workload_spec_string)?);
+    }
+
+    /// Same as [`validate_workload_spec`], for a spec that was already parsed, e.g. from toml.
+    pub fn validate_workload_spec_value(spec: &serde_json::Value) -> Result<()> {
+        let schema = serde_json::to_value(schema_for!(WorkloadSpec))?;
+        let validator = jsonschema::validator_for(&schema).map_err(|err| {
+            WorkloadError::Schema(vec![format!("Invalid workload spec schema: {err}")])
+        })?;
+
+        let mut errors = Vec::new();
+        for err in validator.iter_errors(spec) {
+            leaf_errors(&err, &mut errors);
+        }
+        if !errors.is_empty() {
+            return Err(WorkloadError::Schema(errors));
+        }
+        return Ok(());
+    }
+
+    /// Collects the messages of an error. Groups are either amounts or ratios, so a wrong group
+    /// fails both `anyOf` branches; only the branch closest to matching is reported, which points
+    /// at the offending field instead of the whole group.
+    fn leaf_errors(err: &ValidationError, errors: &mut Vec<String>) {
+        if let ValidationErrorKind::AnyOf { context } = err.kind() {
+            let closest = context.iter().min_by_key(|branch| {
+                let depth = branch
+                    .iter()
+                    .map(|err| err.instance_path().as_str().len())
+                    .max();
+                (branch.len(), std::cmp::Reverse(depth))
+            });
+            if let Some(branch) = closest.filter(|branch| !branch.is_empty()) {
+                for err in branch {
+                    leaf_errors(err, errors);
+                }
+                return;
+            }
+        }
+        let path = match err.instance_path().as_str() {
+            "" => "/",
+            path => path,
+        };
+        errors.push(format!("{path}: {err}"));
+    }
+}
+
+/// Specs of standard benchmark workloads, to generate as they are or to start a spec from.
+pub mod presets {
+    use crate::error::{Result, WorkloadError};
+    use serde_json::{json, Value};
+    use std::str::FromStr;
+
+    /// Key length of YCSB records after their `user` prefix, the digits of a 64-bit hash.
+    const YCSB_KEY_LEN: usize = 19;
+    /// Value length of YCSB records, 10 fields of 100 bytes.
+    const YCSB_VAL_LEN: usize = 1000;
+    /// Keys read by a YCSB scan, the mean of YCSB's uniform scan length up to 100.
+    const YCSB_SCAN_LEN: usize = 50;
+    /// Skew of YCSB's zipfian request distribution.
+    const YCSB_THETA: f64 = 0.99;
+
+    /// The core workloads of YCSB.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum Ycsb {
+        /// Update heavy, 50% reads and 50% updates.
+        A,
+        /// Read mostly, 95% reads and 5% updates.
+        B,
+        /// Read only.
+        C,
+        /// Read latest, 95% reads favoring the latest keys and 5% inserts.
+        D,
+        /// Short ranges, 95% scans and 5% inserts.
+        E,
+        /// Read-modify-write, 50% reads and 50% read-modify-writes.
+        F,
+    }
+
+    impl FromStr for Ycsb {
+        type Err = WorkloadError;
+
+        fn from_str(s: &str) -> Result<Self> {
+            return match s {
+                "ycsb-a" => Ok(Ycsb::A),
+                "ycsb-b" => Ok(Ycsb::B),
+                "ycsb-c" => Ok(Ycsb::C),
+                "ycsb-d" => Ok(Ycsb::D),
+                "ycsb-e" => Ok(Ycsb::E),
+                "ycsb-f" => Ok(Ycsb::F),
+                _ => Err(WorkloadError::UnknownPreset(s.to_string())),
+            };
+        }
+    }
+
+    impl Ycsb {
+        /// Spec of the workload: a load phase of `record_count` inserts, sorted by key, then
+        /// `operation_count` operations in the workload's mix, interleaved at random, on zipfian
+        /// keys. Inserts of the run phase insert new keys after the load phase, and a
+        /// read-modify-write counts as one of the operations.
+        pub fn spec(&self, record_count: usize, operation_count: usize) -> Value {
+            // Percent of the run phase for every operation other than reads.
+            let (updates, inserts, scans, read_modify_writes) = match self {
+                Ycsb::A => (50, 0, 0, 0),
+                Ycsb::B => (5, 0, 0, 0),
+                Ycsb::C => (0, 0, 0, 0),
+                Ycsb::D => (0, 5, 0, 0),
+                Ycsb::E => (0, 5, 95, 0),
+                Ycsb::F => (0, 0, 0, 50),
+            };
+            let amount = |percent: usize| operation_count * percent / 100;
+            let reads = operation_count
+                - amount(updates)
+                - amount(inserts)
+                - amount(scans)
+                - amount(read_modify_writes);
+
+            let mut groups = vec![json!({ "inserts": {
+                "amount": record_count,
+                "key_len": YCSB_KEY_LEN,
+                "val_len": YCSB_VAL_LEN,
+                "allow_duplicate_keys": false,
+            } })];
+            if reads > 0 {
+                groups.push(json!({ "point_queries": { "amount": reads } }));
+            }
+            if updates > 0 {
+                groups.push(json!({ "updates": {
+                    "amount": amount(updates),
+                    "val_len": YCSB_VAL_LEN,
+                } }));
+            }
+            if inserts > 0 {
+                groups.push(json!({ "inserts": {
+                    "amount": amount(inserts),
+                    "key_len": YCSB_KEY_LEN,
+                    "val_len": YCSB_VAL_LEN,
+                    "allow_duplicate_keys": false,
+                    "after_bulk_load": true,
+                } }));
+            }
+            if scans > 0 {
+                groups.push(json!({ "count_range_queries": {
+                    "amount": amount(scans),
+                    "count": YCSB_SCAN_LEN,
+                } }));
+            }
+            if read_modify_writes > 0 {
+                groups.push(json!({ "read_modify_writes": {
+                    "amount": amount(read_modify_writes),
+                    "val_len": YCSB_VAL_LEN,
+                } }));
+            }
+            let key_distribution = match self {
+                Ycsb::D => json!({ "latest": { "theta": YCSB_THETA } }),
+                _ => json!({ "zipfian": { "theta": YCSB_THETA } }),
+            };
+            return json!({ "sections": [{
+                "key_space": "numeric",
+                "key_prefix": "user",
+                "key_distribution": key_distribution,
+                "bulk_load": true,
+                "interleave_groups": true,
+                "groups": groups,
+            }] });
+        }
+    }
+}
+
+pub mod keyset {
+    use crate::rng::WorkloadRng;
+    use crate::spec::{KeySpace, WorkloadSpecSection};
+    use crate::Key;
+    use bloom::{CountingBloomFilter, ASMS};
+    use rand::Rng;
+    use roaring::RoaringTreemap;
+    use std::collections::HashMap;
+
+    /// The [`KeySet`] implementations a section can be generated with.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum KeySetKind {
+        Vec,
+        VecHashSet,
+        VecBloomFilter,
+        VecCuckooFilter,
+        Roaring,
+        OrderStat,
+    }
+
+    impl KeySetKind {
+        /// Picks the cheapest key set that supports the operations of a section.
+        ///
+        /// - Empty point queries need exact membership, which a roaring bitmap gives for small
+        ///   numeric keys and a hash set gives otherwise. Its [`KeySetKind::memory_bytes`]
+        ///   estimate is below a hash set's for any numeric key space, so it isn't compared.
+        ///   Sections with a `bloom_filter_fp_rate` or `cuckoo_filter` accept approximate
+        ///   membership from that filter instead.
+        /// - Range queries or sequential keys interleaved with deletes, sequential keys
+        ///   interleaved with new keys, and range deletes, need sorted keys with fast inserts and
+        ///   removal, which the order statistic tree gives without re-sorting after every change.
+        /// - Inserts that regenerate colliding keys need fast membership from a hash set.
+        /// - Everything else only needs random access, so a plain `Vec` is enough.
+        pub(crate) fn select(section: &WorkloadSpecSection) -> Self {
+            if section.has_empty_point_queries() {
+                if section.bloom_filter_fp_rate.is_some() {
+                    return KeySetKind::VecBloomFilter;
+                }
+                if section.cuckoo_filter {
+                    return KeySetKind::VecCuckooFilter;
+                }
+                let fits_roaring = matches!(
+                    section.key_space,
+                    KeySpace::Numeric | KeySpace::NumericRange { .. }
+                ) && section.key_prefix.is_none()
+                    && section.section_tag.is_none()
+                    && !section.length_prefixed_keys
+                    && section.max_key_len() <= RoaringKeySet::MAX_KEY_LEN;
+                if fits_roaring {
+                    return KeySetKind::Roaring;
+                }
+                return KeySetKind::VecHashSet;
+            }
+            let needs_sorted = section.has_range_queries() || section.has_sequential_keys();
+            if (needs_sorted && section.has_deletes())
+                || section.creates_keys_after_sequential_keys()
+                || section.has_range_deletes()
+            {
+                return KeySetKind::OrderStat;
+            }
+            if !section.allows_duplicate_keys() {
+                return KeySetKind::VecHashSet;
+            }
+            return KeySetKind::Vec;
+        }
+
+        /// Every key set, e.g. to compare their memory estimates.
+        pub const ALL: [KeySetKind; 6] = [
+            KeySetKind::Vec,
+            KeySetKind::VecHashSet,
+            KeySetKind::VecBloomFilter,
+            KeySetKind::VecCuckooFilter,
+            KeySetKind::Roaring,
+            KeySetKind::OrderStat,
+        ];
+
+        /// Estimate of [`KeySet::memory_bytes`] of this key set for a section, at its
+        /// [`WorkloadSpecSection::peak_live_keys`] keys of its longest key length.
+        pub(crate) fn memory_bytes(self, section: &WorkloadSpecSection) -> usize {
+            let keys = section.peak_live_keys();
+            // Sets are created with room for the inserts, and grow past it for blind updates.
+            let capacity = section.key_set_capacity().max(keys);
+            let keys_bytes = keys * (section.insert_key_len() + section.key_prefix_len());
+            let vec_bytes = capacity * size_of::<Key>() + keys_bytes;
+            let key_space = section
+                .key_space
+                .size(section.insert_key_len())
+                .map_or(f64::INFINITY, |size| size as f64);
+            return match self {
+                KeySetKind::Vec => vec_bytes,
+                KeySetKind::VecHashSet => {
+                    vec_bytes
+                        + map_capacity(capacity) * (size_of::<(Key, usize)>() + 1)
+                        + keys_bytes
+                }
+                KeySetKind::VecBloomFilter => {
+                    let fp_rate = section
+                        .bloom_filter_fp_rate
+                        .map_or(VecBloomFilterKeySet::DEFAULT_FP_RATE, |fp_rate| {
+                            fp_rate as f32
+                        });
+                    let bits = bloom::needed_bits(fp_rate, capacity as u32);
+                    vec_bytes + (bits * VecBloomFilterKeySet::BITS_PER_COUNTER).div_ceil(8)
+                }
+                KeySetKind::VecCuckooFilter => {
+                    let buckets = CuckooFilter::bucket_count(capacity).next_power_of_two();
+                    // Once keys repeat more often than their two buckets hold, every key of the
+                    // key space keeps its further copies in the saturated map.
+                    let saturated = match keys as f64 / key_space {
+                        copies if copies > (2 * CuckooFilter::BUCKET_LEN) as f64 => {
+                            key_space as usize
+                        }
+                        _ => 0,
+                    };
+                    vec_bytes
+                        + buckets * size_of::<[u16; CuckooFilter::BUCKET_LEN]>()
+                        + map_capacity(saturated) * (size_of::<(Key, usize)>() + 1)
+                        + saturated * section.insert_key_len()
+                }
+                KeySetKind::Roaring => vec_bytes + roaring_bytes(keys, key_space),
+                KeySetKind::OrderStat => capacity * size_of::<TreapNode>() + keys_bytes,
+            };
+        }
+    }
+
+    /// Capacity of a `HashMap` created with room for `capacity` entries. Its table has a power of
+    /// two buckets, of which it fills up to seven eighths.
+    fn map_capacity(capacity: usize) -> usize {
+        return match capacity {
+            0 => 0,
+            1..4 => 3,
+            4..8 => 7,
+            _ => (capacity * 8 / 7).next_power_of_two() / 8 * 7,
+        };
+    }
+
+    /// Serialized bytes of a roaring treemap of `keys` keys spread uniformly over `key_space`.
+    /// Every 2^32 keys have a bitmap, and every 2^16 keys of a bitmap a container, which holds
+    /// its keys in an array of 2 bytes per key up to 4096 keys and in an 8 KiB bitmap past that.
+    fn roaring_bytes(keys: usize, key_space: f64) -> usize {
+        if keys == 0 {
+            return 0;
+        }
+        let bitmaps = (key_space / 2f64.powi(32)).ceil().min(keys as f64) as usize;
+        let containers = (key_space / 2f64.powi(16)).ceil().min(keys as f64) as usize;
+        let container_bytes = match keys / containers {
+            ..=4096 => keys * 2,
+            _ => containers * 8192,
+        };
+        return 8 + bitmaps * 12 + containers * 8 + container_bytes;
+    }
+
+    /// The valid keys of a section. Pushing a key that is already in the set keeps both copies,
+    /// and the key stays contained until every copy is removed.
+    pub trait KeySet {
+        fn new(capacity: usize) -> Self;
+
+        fn len(&self) -> usize;
+
+        fn is_empty(&self) -> bool;
+
+        fn push(&mut self, key: Key);
+
+        fn remove(&mut self, idx: usize) -> Key;
+
+        fn get(&self, idx: usize) -> Option<&Key>;
+
+        fn get_random(&self, rng: &mut WorkloadRng) -> &Key;
+
+        fn contains(&self, key: &Key) -> bool;
+
+        fn sort(&mut self);
+
+        /// Number of keys the set can hold without reallocating.
+        fn capacity(&self) -> usize;
+
+        /// Estimate of the heap bytes held by the set, including its keys, e.g. to compare the
+        /// footprint of key sets in memory profiles.
+        fn memory_bytes(&self) -> usize;
+
+        /// Frees spare capacity, e.g. after many removes.
+        fn shrink_to_fit(&mut self);
+
+        /// Number of keys strictly between `lo` and `hi`. The keys must be sorted, i.e. `sort` was
+        /// called since the last push or remove, so this is a binary search over `get`.
+        fn count_in_range(&self, lo: &Key, hi: &Key) -> usize {
+            let first_above_lo = self.partition_point(|key| key <= lo);
+            let first_at_hi = self.partition_point(|key| key < hi);
+            return first_at_hi.saturating_sub(first_above_lo);
+        }
+
+        /// Index of the first sorted key for which `pred` is false, like [`slice::partition_point`].
+        fn partition_point(&self, pred: impl Fn(&Key) -> bool) -> usize {
+            let (mut lo, mut hi) = (0, self.len());
+            while lo < hi {
+                let mid = lo + (hi - lo) / 2;
+                if pred(self.get(mid).expect("index to be in range")) {
+                    lo = mid + 1;
+                } else {
+                    hi = mid;
+                }
+            }
+            return lo;
+        }
+
+        /// `n` random keys at distinct indices, or every key if there are fewer than `n`. Runs a
+        /// partial Fisher-Yates shuffle over the indices, tracking only the swapped indices, so it
+        /// takes `O(n)` time and memory regardless of the size of the set.
+        fn sample_random(&self, rng: &mut WorkloadRng, n: usize) -> Vec<&Key> {
+            let len = self.len();
+            let n = n.min(len);
+            let mut swapped: HashMap<usize, usize> = HashMap::with_capacity(n);
+            let mut sample = Vec::with_capacity(n);
+            for i in 0..n {
+                let j = rng.random_range(i..len);
+                let picked = *swapped.get(&j).unwrap_or(&j);
+                swapped.insert(j, *swapped.get(&i).unwrap_or(&i));
+                sample.push(self.get(picked).expect("index to be in range"));
+            }
+            return sample;
+        }
+    }
+
+    pub struct VecKeySet {
+        keys: Vec<Key>,
+        sorted: bool,
+    }
+
+    impl KeySet for VecKeySet {
+        fn new(capacity: usize) -> Self {
+            return Self {
+                keys: Vec::with_capacity(capacity),
+                sorted: true,
+            };
+        }
+
+        fn len(&self) -> usize {
+            return self.keys.len();
+        }
+
+        fn is_empty(&self) -> bool {
+            return self.keys.is_empty();
+        }
+
+        fn push(&mut self, key: Key) {
+            if self.sorted && self.keys.last().is_some_and(|last_key| last_key > &key) {
+                self.sorted = false;
+            }
+            self.keys.push(key);
+        }
+
+        fn remove(&mut self, idx: usize) -> Key {
+            let key = self.keys.swap_remove(idx);
+            if idx < self.keys.len() {
+                self.sorted = false;
+            }
+            return key;
+        }
+
+        fn get(&self, idx: usize) -> Option<&Key> {
+            return self.keys.get(idx);
+        }
+
+        fn get_random(&self, rng: &mut WorkloadRng) -> &Key {
+            return self
+                .keys
+                .get(rng.random_range(0..self.keys.len()))
+                .expect("KeySet to not be empty");
+        }
+
+        fn contains(&self, key: &Key) -> bool {
+            return self.keys.contains(key);
+        }
+
+        fn sort(&mut self) {
+            if !self.sorted {
+                self.keys.sort();
+                self.sorted = true;
+            }
+        }
+
+        fn capacity(&self) -> usize {
+            return self.keys.capacity();
+        }
+
+        fn memory_bytes(&self) -> usize {
+            return vec_bytes(&self.keys) + keys_bytes(&self.keys);
+        }
+
+        fn shrink_to_fit(&mut self) {
+            self.keys.shrink_to_fit();
+        }
+    }
     pub struct VecHashSetKeySet {
         keys: Vec<Key>,
-        key_set: HashSet<Key>,
+        /// Number of copies of every key in `keys`.
+        key_counts: HashMap<Key, usize>,
+        sorted: bool,
+    }
+
+    impl KeySet for VecHashSetKeySet {
+        fn new(capacity: usize) -> Self {
+            return Self {
+                keys: Vec::with_capacity(capacity),
+                key_counts: HashMap::with_capacity(capacity),
+                sorted: true,
+            };
+        }
+
+        fn len(&self) -> usize {
+            return self.keys.len();
+        }
+
+        fn is_empty(&self) -> bool {
+            return self.keys.is_empty();
+        }
+
+        fn push(&mut self, key: Key) {
+            if self.sorted && self.keys.last().is_some_and(|last_key| last_key > &key) {
+                self.sorted = false;
+            }
+            self.keys.push(key.clone());
+            *self.key_counts.entry(key).or_default() += 1;
+        }
+
+        fn remove(&mut self, idx: usize) -> Key {
+            let key = self.keys.swap_remove(idx);
+            if idx < self.keys.len() {
+                self.sorted = false;
+            }
+            remove_copy(&mut self.key_counts, &key);
+            return key;
+        }
+
+        fn get(&self, idx: usize) -> Option<&Key> {
+            return self.keys.get(idx);
+        }
+
+        fn get_random(&self, rng: &mut WorkloadRng) -> &Key {
+            return self
+                .keys
+                .get(rng.random_range(0..self.keys.len()))
+                .expect("KeySet to not be empty");
+        }
+
+        fn contains(&self, key: &Key) -> bool {
+            return self.key_counts.contains_key(key);
+        }
+
+        fn sort(&mut self) {
+            if !self.sorted {
+                self.keys.sort();
+                self.sorted = true;
+            }
+        }
+
+        fn capacity(&self) -> usize {
+            return self.keys.capacity().min(self.key_counts.capacity());
+        }
+
+        fn memory_bytes(&self) -> usize {
+            return vec_bytes(&self.keys)
+                + keys_bytes(&self.keys)
+                + map_bytes(&self.key_counts)
+                + keys_bytes(self.key_counts.keys());
+        }
+
+        fn shrink_to_fit(&mut self) {
+            self.keys.shrink_to_fit();
+            self.key_counts.shrink_to_fit();
+        }
+    }
+    /// Heap bytes of the buffer of `vec`, not counting what its elements own.
+    fn vec_bytes<T>(vec: &Vec<T>) -> usize {
+        return vec.capacity() * size_of::<T>();
+    }
+
+    /// Heap bytes of the table of `map`, not counting what its entries own. Every bucket has a
+    /// control byte besides its entry.
+    fn map_bytes<K, V>(map: &HashMap<K, V>) -> usize {
+        return map.capacity() * (size_of::<(K, V)>() + 1);
+    }
+
+    /// Heap bytes of the contents of `keys`.
+    fn keys_bytes<'a>(keys: impl IntoIterator<Item = &'a Key>) -> usize {
+        return keys.into_iter().map(|key| key.len()).sum();
+    }
+
+    /// Removes one copy of `key` from `counts`, removing the key once no copies are left.
+    fn remove_copy<T: std::hash::Hash + Eq>(counts: &mut HashMap<T, usize>, key: &T) {
+        if let Some(count) = counts.get_mut(key) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(key);
+            }
+        }
+    }
+
+    /// Key set with approximate membership. The counting bloom filter allows keys to be removed
+    /// without rebuilding the filter from the remaining keys.
+    pub struct VecBloomFilterKeySet {
+        keys: Vec<Key>,
+        bf: CountingBloomFilter,
+        /// Bytes of the counters of `bf`, which doesn't expose its size.
+        bf_bytes: usize,
+        sorted: bool,
+    }
+
+    impl VecBloomFilterKeySet {
+        /// Bits per counter in the bloom filter. Counters saturate at 15, which is plenty for keys
+        /// that are rarely generated twice.
+        const BITS_PER_COUNTER: usize = 4;
+        /// False-positive rate used by [`KeySet::new`].
+        pub const DEFAULT_FP_RATE: f32 = 0.01;
+
+        /// Creates a key set whose bloom filter has a false-positive rate of `fp_rate` once
+        /// `capacity` keys are inserted.
+        pub fn new_with_rate(capacity: usize, fp_rate: f32) -> Self {
+            return Self {
+                keys: Vec::with_capacity(capacity),
+                bf: CountingBloomFilter::with_rate(
+                    Self::BITS_PER_COUNTER,
+                    fp_rate,
+                    capacity as u32,
+                ),
+                bf_bytes: (bloom::needed_bits(fp_rate, capacity as u32) * Self::BITS_PER_COUNTER)
+                    .div_ceil(8),
+                sorted: true,
+            };
+        }
+    }
+
+    impl KeySet for VecBloomFilterKeySet {
+        fn new(capacity: usize) -> Self {
+            return Self::new_with_rate(capacity, Self::DEFAULT_FP_RATE);
+        }
+
+        fn len(&self) -> usize {
+            return self.keys.len();
+        }
+
+        fn is_empty(&self) -> bool {
+            return self.keys.is_empty();
+        }
+
+        fn push(&mut self, key: Key) {
+            if self.sorted && self.keys.last().is_some_and(|last_key| last_key > &key) {
+                self.sorted = false;
+            }
+            self.bf.insert(&key);
+            self.keys.push(key);
+        }
+
+        fn remove(&mut self, idx: usize) -> Key {
+            let key = self.keys.swap_remove(idx);
+            if idx < self.keys.len() {
+                self.sorted = false;
+            }
+            self.bf.remove(&key);
+            return key;
+        }
+
+        fn get(&self, idx: usize) -> Option<&Key> {
+            return self.keys.get(idx);
+        }
+
+        fn get_random(&self, rng: &mut WorkloadRng) -> &Key {
+            return self
+                .keys
+                .get(rng.random_range(0..self.keys.len()))
+                .expect("KeySet to not be empty");
+        }
+
+        fn contains(&self, key: &Key) -> bool {
+            return self.bf.contains(key);
+        }
+
+        fn sort(&mut self) {
+            if !self.sorted {
+                self.keys.sort();
+                self.sorted = true;
+            }
+        }
+
+        /// The bloom filter has a fixed size, only the keys grow.
+        fn capacity(&self) -> usize {
+            return self.keys.capacity();
+        }
+
+        fn memory_bytes(&self) -> usize {
+            return vec_bytes(&self.keys) + keys_bytes(&self.keys) + self.bf_bytes;
+        }
+
+        fn shrink_to_fit(&mut self) {
+            self.keys.shrink_to_fit();
+        }
+    }
+
+    /// Cuckoo filter of 16 bit fingerprints. Partial-key cuckoo hashing finds the other bucket of
+    /// a fingerprint from its bucket and the fingerprint alone, so full buckets make room by
+    /// moving fingerprints without their keys. Keys are hashed with FNV-1a and the fingerprints
+    /// to move come from a fixed sequence, so generating a workload stays reproducible.
+    struct CuckooFilter {
+        buckets: Vec<[u16; CuckooFilter::BUCKET_LEN]>,
+        /// State of the xorshift sequence picking the fingerprints to move.
+        kick_state: u64,
+    }
+
+    impl CuckooFilter {
+        const BUCKET_LEN: usize = 4;
+        /// Fingerprints moved before an insert gives up.
+        const MAX_KICKS: usize = 500;
+        /// Fingerprint of an empty slot.
+        const EMPTY: u16 = 0;
+
+        /// Creates a filter with a power of two number of buckets, so indices are masked.
+        fn with_buckets(buckets: usize) -> Self {
+            return Self {
+                buckets: vec![[Self::EMPTY; Self::BUCKET_LEN]; buckets.next_power_of_two()],
+                kick_state: 0x9E37_79B9_7F4A_7C15,
+            };
+        }
+
+        /// Creates a filter that is at most 80% full with `capacity` keys.
+        fn with_capacity(capacity: usize) -> Self {
+            return Self::with_buckets(Self::bucket_count(capacity));
+        }
+
+        /// Buckets for `capacity` fingerprints, before [`CuckooFilter::with_buckets`] rounds them
+        /// up to a power of two.
+        fn bucket_count(capacity: usize) -> usize {
+            return (capacity + capacity / 4).div_ceil(Self::BUCKET_LEN);
+        }
+
+        /// The fingerprint of `key` and its two buckets.
+        fn locate(&self, key: &Key) -> (u16, usize, usize) {
+            let hash = crate::fnv1a(key);
+            let fp = ((hash >> 48) as u16).max(1);
+            let idx = hash as usize & (self.buckets.len() - 1);
+            return (fp, idx, self.alt_index(idx, fp));
+        }
+
+        /// The other bucket of `fp` in bucket `idx`. Applying it twice gives back `idx`.
+        fn alt_index(&self, idx: usize, fp: u16) -> usize {
+            // MurmurHash2's multiplier spreads the fingerprint over the index bits.
+            return (idx ^ (fp as usize).wrapping_mul(0x5BD1_E995)) & (self.buckets.len() - 1);
+        }
+
+        fn contains(&self, key: &Key) -> bool {
+            let (fp, i1, i2) = self.locate(key);
+            return self.buckets[i1].contains(&fp) || self.buckets[i2].contains(&fp);
+        }
+
+        /// Puts `fp` into an empty slot of bucket `idx`, if there is one.
+        fn put(&mut self, idx: usize, fp: u16) -> bool {
+            let Some(slot) = self.buckets[idx].iter_mut().find(|fp| **fp == Self::EMPTY) else {
+                return false;
+            };
+            *slot = fp;
+            return true;
+        }
+
+        /// Adds a fingerprint of `key`. Once both buckets of a key hold only its fingerprints,
+        /// further copies aren't added and [`CuckooInsert::Saturated`] is returned, so the caller
+        /// has to track them itself.
+        fn insert(&mut self, key: &Key) -> CuckooInsert {
+            let (mut fp, i1, i2) = self.locate(key);
+            if self.put(i1, fp) || self.put(i2, fp) {
+                return CuckooInsert::Added;
+            }
+            if self.buckets[i1]
+                .iter()
+                .chain(&self.buckets[i2])
+                .all(|&f| f == fp)
+            {
+                return CuckooInsert::Saturated;
+            }
+            let mut idx = i1;
+            for _ in 0..Self::MAX_KICKS {
+                self.kick_state ^= self.kick_state << 13;
+                self.kick_state ^= self.kick_state >> 7;
+                self.kick_state ^= self.kick_state << 17;
+                let slot = self.kick_state as usize % Self::BUCKET_LEN;
+                std::mem::swap(&mut fp, &mut self.buckets[idx][slot]);
+                idx = self.alt_index(idx, fp);
+                if self.put(idx, fp) {
+                    return CuckooInsert::Added;
+                }
+            }
+            return CuckooInsert::Full;
+        }
+
+        /// Removes one fingerprint of `key`.
+        fn remove(&mut self, key: &Key) {
+            let (fp, i1, i2) = self.locate(key);
+            for idx in [i1, i2] {
+                if let Some(slot) = self.buckets[idx].iter_mut().find(|f| **f == fp) {
+                    *slot = Self::EMPTY;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Result of [`CuckooFilter::insert`].
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    enum CuckooInsert {
+        Added,
+        /// Both buckets of the key are full of its fingerprint, so the copy wasn't added.
+        Saturated,
+        /// No room was found after [`CuckooFilter::MAX_KICKS`] moves, which dropped some other
+        /// fingerprint, so the filter has to be rebuilt.
+        Full,
+    }
+
+    /// Key set with approximate membership from a cuckoo filter, which unlike a bloom filter
+    /// removes keys in constant time at about 2 bytes per key. The filter holds at most 8 copies
+    /// of a key, further copies are counted exactly, which is rare for generated keys.
+    pub struct VecCuckooFilterKeySet {
+        keys: Vec<Key>,
+        filter: CuckooFilter,
+        /// Copies of keys that the filter couldn't hold, removed before their fingerprints.
+        saturated: HashMap<Key, usize>,
+        sorted: bool,
+    }
+
+    impl VecCuckooFilterKeySet {
+        /// Rebuilds the filter from the keys with twice the buckets, until every key fits.
+        fn grow(&mut self) {
+            let mut buckets = self.filter.buckets.len();
+            'grow: loop {
+                buckets *= 2;
+                let mut filter = CuckooFilter::with_buckets(buckets);
+                let mut saturated: HashMap<Key, usize> = HashMap::new();
+                for key in &self.keys {
+                    match filter.insert(key) {
+                        CuckooInsert::Added => {}
+                        CuckooInsert::Saturated => *saturated.entry(key.clone()).or_default() += 1,
+                        CuckooInsert::Full => continue 'grow,
+                    }
+                }
+                self.filter = filter;
+                self.saturated = saturated;
+                return;
+            }
+        }
+    }
+
+    impl KeySet for VecCuckooFilterKeySet {
+        fn new(capacity: usize) -> Self {
+            return Self {
+                keys: Vec::with_capacity(capacity),
+                filter: CuckooFilter::with_capacity(capacity),
+                saturated: HashMap::new(),
+                sorted: true,
+            };
+        }
+
+        fn len(&self) -> usize {
+            return self.keys.len();
+        }
+
+        fn is_empty(&self) -> bool {
+            return self.keys.is_empty();
+        }
+
+        fn push(&mut self, key: Key) {
+            if self.sorted && self.keys.last().is_some_and(|last_key| last_key > &key) {
+                self.sorted = false;
+            }
+            match self.filter.insert(&key) {
+                CuckooInsert::Added => self.keys.push(key),
+                CuckooInsert::Saturated => {
+                    *self.saturated.entry(key.clone()).or_default() += 1;
+                    self.keys.push(key);
+                }
+                CuckooInsert::Full => {
+                    self.keys.push(key);
+                    self.grow();
+                }
+            }
+        }
+
+        fn remove(&mut self, idx: usize) -> Key {
+            let key = self.keys.swap_remove(idx);
+            if idx < self.keys.len() {
+                self.sorted = false;
+            }
+            match self.saturated.get_mut(&key) {
+                Some(copies) if *copies > 1 => *copies -= 1,
+                Some(_) => {
+                    self.saturated.remove(&key);
+                }
+                None => self.filter.remove(&key),
+            }
+            return key;
+        }
+
+        fn get(&self, idx: usize) -> Option<&Key> {
+            return self.keys.get(idx);
+        }
+
+        fn get_random(&self, rng: &mut WorkloadRng) -> &Key {
+            return self
+                .keys
+                .get(rng.random_range(0..self.keys.len()))
+                .expect("KeySet to not be empty");
+        }
+
+        fn contains(&self, key: &Key) -> bool {
+            return self.filter.contains(key);
+        }
+
+        fn sort(&mut self) {
+            if !self.sorted {
+                self.keys.sort();
+                self.sorted = true;
+            }
+        }
+
+        /// The filter grows when it is full, see [`VecCuckooFilterKeySet::grow`].
+        fn capacity(&self) -> usize {
+            return self.keys.capacity();
+        }
+
+        fn memory_bytes(&self) -> usize {
+            return vec_bytes(&self.keys)
+                + keys_bytes(&self.keys)
+                + vec_bytes(&self.filter.buckets)
+                + map_bytes(&self.saturated)
+                + keys_bytes(self.saturated.keys());
+        }
+
+        /// The filter keeps its buckets, only spare key capacity is freed.
+        fn shrink_to_fit(&mut self) {
+            self.keys.shrink_to_fit();
+        }
+    }
+
+    pub struct VecHashMapIndexKeySet {
+        keys: Vec<Key>,
+        /// Indices in `keys` of every copy of a key.
+        key_to_index: HashMap<Key, Vec<usize>>,
+        sorted: bool,
+    }
+
+    impl KeySet for VecHashMapIndexKeySet {
+        fn new(capacity: usize) -> Self {
+            return Self {
+                keys: Vec::with_capacity(capacity),
+                key_to_index: HashMap::with_capacity(capacity),
+                sorted: true,
+            };
+        }
+
+        fn len(&self) -> usize {
+            return self.keys.len();
+        }
+
+        fn is_empty(&self) -> bool {
+            return self.keys.is_empty();
+        }
+
+        fn push(&mut self, key: Key) {
+            if self.sorted && self.keys.last().is_some_and(|last_key| last_key > &key) {
+                self.sorted = false;
+            }
+            self.key_to_index
+                .entry(key.clone())
+                .or_default()
+                .push(self.keys.len());
+            self.keys.push(key);
+        }
+
+        fn remove(&mut self, idx: usize) -> Key {
+            assert!(idx < self.keys.len());
+
+            // Swap with last, pop, and update hashmap
+            let swap_idx = self.keys.len() - 1;
+            self.keys.swap(idx, swap_idx);
+            let removed = self.keys.pop().unwrap();
+            let indices = self.key_to_index.get_mut(&removed).unwrap();
+            indices.retain(|&i| i != idx);
+            if indices.is_empty() {
+                self.key_to_index.remove(&removed);
+            }
+
+            // Update index of swapped element if necessary
+            if idx < self.keys.len() {
+                self.sorted = false;
+                let swapped_key = &self.keys[idx];
+                for i in self.key_to_index.get_mut(swapped_key).unwrap() {
+                    if *i == swap_idx {
+                        *i = idx;
+                    }
+                }
+            }
+
+            return removed;
+        }
+
+        fn get(&self, idx: usize) -> Option<&Key> {
+            return self.keys.get(idx);
+        }
+
+        fn get_random(&self, rng: &mut WorkloadRng) -> &Key {
+            let idx = rng.random_range(0..self.keys.len());
+            return &self.keys[idx];
+        }
+
+        fn contains(&self, key: &Key) -> bool {
+            return self.key_to_index.contains_key(key);
+        }
+
+        fn sort(&mut self) {
+            if self.sorted {
+                return;
+            }
+            self.keys.sort();
+            self.key_to_index.clear();
+            for (i, key) in self.keys.iter().enumerate() {
+                self.key_to_index.entry(key.clone()).or_default().push(i);
+            }
+            self.sorted = true;
+        }
+
+        fn capacity(&self) -> usize {
+            return self.keys.capacity().min(self.key_to_index.capacity());
+        }
+
+        fn memory_bytes(&self) -> usize {
+            return vec_bytes(&self.keys)
+                + keys_bytes(&self.keys)
+                + map_bytes(&self.key_to_index)
+                + keys_bytes(self.key_to_index.keys())
+                + self.key_to_index.values().map(vec_bytes).sum::<usize>();
+        }
+
+        fn shrink_to_fit(&mut self) {
+            self.keys.shrink_to_fit();
+            self.key_to_index.shrink_to_fit();
+            for indices in self.key_to_index.values_mut() {
+                indices.shrink_to_fit();
+            }
+        }
+    }
+
+    /// Key set for keys from the numeric key space. Membership is tracked in a roaring bitmap,
+    /// and the keys are kept in a `Vec` for random selection.
+    pub struct RoaringKeySet {
+        keys: Vec<Key>,
+        bitmap: RoaringTreemap,
+        /// Number of extra copies of keys pushed more than once, which stay in the bitmap until
+        /// their last copy is removed.
+        duplicates: HashMap<u64, usize>,
         sorted: bool,
     }
 
-    impl KeySet for VecHashSetKeySet {
-        fn new(capacity: usize) -> Self {
-            return Self {
-                keys: Vec::with_capacity(capacity),
-                key_set: HashSet::with_capacity(capacity),
-                sorted: true,
+    impl RoaringKeySet {
+        /// Longest numeric key that can be mapped into the bitmap.
+        pub const MAX_KEY_LEN: usize = 18;
+
+        /// Maps a numeric key to a unique integer by prepending a `1` to its digits, so keys of
+        /// different lengths like `"12"` and `"0012"` don't collide.
+        fn key_to_int(key: &Key) -> Option<u64> {
+            if key.len() > Self::MAX_KEY_LEN {
+                return None;
+            }
+            let mut int = 1u64;
+            for byte in key.iter() {
+                if !byte.is_ascii_digit() {
+                    return None;
+                }
+                int = int * 10 + u64::from(byte - b'0');
+            }
+            return Some(int);
+        }
+    }
+
+    impl KeySet for RoaringKeySet {
+        fn new(capacity: usize) -> Self {
+            return Self {
+                keys: Vec::with_capacity(capacity),
+                bitmap: RoaringTreemap::new(),
+                duplicates: HashMap::new(),
+                sorted: true,
+            };
+        }
+
+        fn len(&self) -> usize {
+            return self.keys.len();
+        }
+
+        fn is_empty(&self) -> bool {
+            return self.keys.is_empty();
+        }
+
+        fn push(&mut self, key: Key) {
+            if self.sorted && self.keys.last().is_some_and(|last_key| last_key > &key) {
+                self.sorted = false;
+            }
+            let int = Self::key_to_int(&key).expect("RoaringKeySet to only hold numeric keys");
+            if !self.bitmap.insert(int) {
+                *self.duplicates.entry(int).or_default() += 1;
+            }
+            self.keys.push(key);
+        }
+
+        fn remove(&mut self, idx: usize) -> Key {
+            let key = self.keys.swap_remove(idx);
+            if idx < self.keys.len() {
+                self.sorted = false;
+            }
+            if let Some(int) = Self::key_to_int(&key) {
+                if self.duplicates.contains_key(&int) {
+                    remove_copy(&mut self.duplicates, &int);
+                } else {
+                    self.bitmap.remove(int);
+                }
+            }
+            return key;
+        }
+
+        fn get(&self, idx: usize) -> Option<&Key> {
+            return self.keys.get(idx);
+        }
+
+        fn get_random(&self, rng: &mut WorkloadRng) -> &Key {
+            return self
+                .keys
+                .get(rng.random_range(0..self.keys.len()))
+                .expect("KeySet to not be empty");
+        }
+
+        fn contains(&self, key: &Key) -> bool {
+            return Self::key_to_int(key).is_some_and(|int| self.bitmap.contains(int));
+        }
+
+        fn sort(&mut self) {
+            if !self.sorted {
+                self.keys.sort();
+                self.sorted = true;
+            }
+        }
+
+        fn capacity(&self) -> usize {
+            return self.keys.capacity();
+        }
+
+        /// The bitmap is estimated by its serialized size.
+        fn memory_bytes(&self) -> usize {
+            return vec_bytes(&self.keys)
+                + keys_bytes(&self.keys)
+                + self.bitmap.serialized_size()
+                + map_bytes(&self.duplicates);
+        }
+
+        fn shrink_to_fit(&mut self) {
+            self.keys.shrink_to_fit();
+            self.duplicates.shrink_to_fit();
+        }
+    }
+
+    /// Key set that always stays sorted, backed by a treap where every node knows the size of
+    /// its subtree. Pushing, removing, membership, and indexing are all O(log n), so range
+    /// queries interleaved with inserts and deletes don't need to re-sort or shift a `Vec`.
+    pub struct OrderStatKeySet {
+        nodes: Vec<TreapNode>,
+        /// Indices of removed nodes in `nodes` that can be reused.
+        free: Vec<usize>,
+        root: Option<usize>,
+        /// State of the splitmix64 generator used for node priorities.
+        priority_state: u64,
+    }
+
+    struct TreapNode {
+        key: Key,
+        priority: u64,
+        size: usize,
+        left: Option<usize>,
+        right: Option<usize>,
+    }
+
+    impl OrderStatKeySet {
+        fn next_priority(&mut self) -> u64 {
+            self.priority_state = self.priority_state.wrapping_add(0x9E3779B97F4A7C15);
+            let mut z = self.priority_state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            return z ^ (z >> 31);
+        }
+
+        fn size(&self, node: Option<usize>) -> usize {
+            return node.map_or(0, |n| self.nodes[n].size);
+        }
+
+        fn update_size(&mut self, node: usize) {
+            let (left, right) = (self.nodes[node].left, self.nodes[node].right);
+            self.nodes[node].size = 1 + self.size(left) + self.size(right);
+        }
+
+        /// Splits a subtree into nodes with keys less than the key of node `pivot` and the rest.
+        fn split_by_key(
+            &mut self,
+            node: Option<usize>,
+            pivot: usize,
+        ) -> (Option<usize>, Option<usize>) {
+            let Some(n) = node else {
+                return (None, None);
+            };
+            if self.nodes[n].key < self.nodes[pivot].key {
+                let (left, right) = self.split_by_key(self.nodes[n].right, pivot);
+                self.nodes[n].right = left;
+                self.update_size(n);
+                return (Some(n), right);
+            } else {
+                let (left, right) = self.split_by_key(self.nodes[n].left, pivot);
+                self.nodes[n].left = right;
+                self.update_size(n);
+                return (left, Some(n));
+            }
+        }
+
+        /// Splits a subtree into its first `rank` nodes and the rest.
+        fn split_by_rank(
+            &mut self,
+            node: Option<usize>,
+            rank: usize,
+        ) -> (Option<usize>, Option<usize>) {
+            let Some(n) = node else {
+                return (None, None);
+            };
+            let left_size = self.size(self.nodes[n].left);
+            if left_size < rank {
+                let (left, right) = self.split_by_rank(self.nodes[n].right, rank - left_size - 1);
+                self.nodes[n].right = left;
+                self.update_size(n);
+                return (Some(n), right);
+            } else {
+                let (left, right) = self.split_by_rank(self.nodes[n].left, rank);
+                self.nodes[n].left = right;
+                self.update_size(n);
+                return (left, Some(n));
+            }
+        }
+
+        /// Joins two subtrees where every key in `left` is at most every key in `right`.
+        fn merge(&mut self, left: Option<usize>, right: Option<usize>) -> Option<usize> {
+            let (l, r) = match (left, right) {
+                (None, node) | (node, None) => return node,
+                (Some(l), Some(r)) => (l, r),
+            };
+            if self.nodes[l].priority > self.nodes[r].priority {
+                self.nodes[l].right = self.merge(self.nodes[l].right, Some(r));
+                self.update_size(l);
+                return Some(l);
+            } else {
+                self.nodes[r].left = self.merge(Some(l), self.nodes[r].left);
+                self.update_size(r);
+                return Some(r);
+            }
+        }
+    }
+
+    impl KeySet for OrderStatKeySet {
+        fn new(capacity: usize) -> Self {
+            return Self {
+                nodes: Vec::with_capacity(capacity),
+                free: Vec::new(),
+                root: None,
+                priority_state: 0,
+            };
+        }
+
+        fn len(&self) -> usize {
+            return self.size(self.root);
+        }
+
+        fn is_empty(&self) -> bool {
+            return self.root.is_none();
+        }
+
+        fn push(&mut self, key: Key) {
+            let node = TreapNode {
+                priority: self.next_priority(),
+                key,
+                size: 1,
+                left: None,
+                right: None,
+            };
+            let idx = match self.free.pop() {
+                Some(idx) => {
+                    self.nodes[idx] = node;
+                    idx
+                }
+                None => {
+                    self.nodes.push(node);
+                    self.nodes.len() - 1
+                }
+            };
+            let (left, right) = self.split_by_key(self.root, idx);
+            let left = self.merge(left, Some(idx));
+            self.root = self.merge(left, right);
+        }
+
+        fn remove(&mut self, idx: usize) -> Key {
+            assert!(idx < self.len());
+            let (left, rest) = self.split_by_rank(self.root, idx);
+            let (removed, right) = self.split_by_rank(rest, 1);
+            self.root = self.merge(left, right);
+
+            let removed = removed.expect("index to be in range");
+            self.free.push(removed);
+            return std::mem::take(&mut self.nodes[removed].key);
+        }
+
+        fn get(&self, mut idx: usize) -> Option<&Key> {
+            let mut node = self.root;
+            while let Some(n) = node {
+                let left_size = self.size(self.nodes[n].left);
+                match idx.cmp(&left_size) {
+                    std::cmp::Ordering::Less => node = self.nodes[n].left,
+                    std::cmp::Ordering::Equal => return Some(&self.nodes[n].key),
+                    std::cmp::Ordering::Greater => {
+                        idx -= left_size + 1;
+                        node = self.nodes[n].right;
+                    }
+                }
+            }
+            return None;
+        }
+
+        fn get_random(&self, rng: &mut WorkloadRng) -> &Key {
+            return self
+                .get(rng.random_range(0..self.len()))
+                .expect("KeySet to not be empty");
+        }
+
+        fn contains(&self, key: &Key) -> bool {
+            let mut node = self.root;
+            while let Some(n) = node {
+                match key.cmp(&self.nodes[n].key) {
+                    std::cmp::Ordering::Less => node = self.nodes[n].left,
+                    std::cmp::Ordering::Equal => return true,
+                    std::cmp::Ordering::Greater => node = self.nodes[n].right,
+                }
+            }
+            return false;
+        }
+
+        /// Keys are always sorted.
+        fn sort(&mut self) {}
+
+        fn capacity(&self) -> usize {
+            return self.nodes.capacity();
+        }
+
+        fn memory_bytes(&self) -> usize {
+            let keys = self.nodes.iter().map(|node| &node.key);
+            return vec_bytes(&self.nodes) + keys_bytes(keys) + vec_bytes(&self.free);
+        }
+
+        /// Removed nodes are reused by later pushes instead of freed, so only spare capacity
+        /// past the last node is freed.
+        fn shrink_to_fit(&mut self) {
+            self.nodes.shrink_to_fit();
+            self.free.shrink_to_fit();
+        }
+    }
+}
+
+/// The rng that generates workloads, of the algorithm picked by the spec.
+pub mod rng {
+    use crate::spec::RngAlgorithm;
+    use rand::RngCore;
+    use rand_chacha::ChaCha8Rng;
+    use rand_pcg::Pcg64;
+    use rand_xoshiro::Xoshiro256Plus;
+
+    /// An rng of any [`RngAlgorithm`], dispatching every draw to it. Created by
+    /// [`RngAlgorithm::seed_from_u64`] and [`RngAlgorithm::from_os_rng`].
+    #[derive(Clone, Debug)]
+    pub enum WorkloadRng {
+        Xoshiro256Plus(Xoshiro256Plus),
+        /// Boxed, its state is ten times the size of the others.
+        ChaCha8(Box<ChaCha8Rng>),
+        Pcg64(Pcg64),
+    }
+
+    impl WorkloadRng {
+        pub fn algorithm(&self) -> RngAlgorithm {
+            return match self {
+                WorkloadRng::Xoshiro256Plus(_) => RngAlgorithm::Xoshiro256Plus,
+                WorkloadRng::ChaCha8(_) => RngAlgorithm::ChaCha8,
+                WorkloadRng::Pcg64(_) => RngAlgorithm::Pcg64,
+            };
+        }
+    }
+
+    impl RngCore for WorkloadRng {
+        #[inline]
+        fn next_u32(&mut self) -> u32 {
+            return match self {
+                WorkloadRng::Xoshiro256Plus(rng) => rng.next_u32(),
+                WorkloadRng::ChaCha8(rng) => rng.next_u32(),
+                WorkloadRng::Pcg64(rng) => rng.next_u32(),
+            };
+        }
+
+        #[inline]
+        fn next_u64(&mut self) -> u64 {
+            return match self {
+                WorkloadRng::Xoshiro256Plus(rng) => rng.next_u64(),
+                WorkloadRng::ChaCha8(rng) => rng.next_u64(),
+                WorkloadRng::Pcg64(rng) => rng.next_u64(),
+            };
+        }
+
+        #[inline]
+        fn fill_bytes(&mut self, dst: &mut [u8]) {
+            match self {
+                WorkloadRng::Xoshiro256Plus(rng) => rng.fill_bytes(dst),
+                WorkloadRng::ChaCha8(rng) => rng.fill_bytes(dst),
+                WorkloadRng::Pcg64(rng) => rng.fill_bytes(dst),
+            }
+        }
+    }
+}
+
+/// Key generators of the key spaces.
+pub mod keygen {
+    use crate::rng::WorkloadRng;
+    use crate::Key;
+    use rand::distr::Alphanumeric;
+    use rand::{Rng, RngCore};
+
+    /// The alphanumeric bytes in ascii order.
+    pub const ALPHANUMERIC: &[u8; 62] =
+        b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+    /// Generates the keys of a key space. A new key space implements this and returns it from
+    /// [`crate::spec::KeySpace::key_gen`].
+    pub trait KeyGen {
+        /// Generates a key of `len` bytes.
+        fn generate(&mut self, rng: &mut WorkloadRng, len: usize) -> Key;
+    }
+
+    pub struct AlphanumericKeyGen;
+
+    impl KeyGen for AlphanumericKeyGen {
+        fn generate(&mut self, rng: &mut WorkloadRng, len: usize) -> Key {
+            return rng.sample_iter(Alphanumeric).take(len).collect();
+        }
+    }
+
+    pub struct NumericKeyGen;
+
+    impl KeyGen for NumericKeyGen {
+        fn generate(&mut self, rng: &mut WorkloadRng, len: usize) -> Key {
+            return (0..len).map(|_| rng.random_range(b'0'..=b'9')).collect();
+        }
+    }
+
+    /// Integers drawn uniformly from `min..=max`, zero-padded to `len`.
+    pub struct NumericRangeKeyGen {
+        pub min: u64,
+        pub max: u64,
+    }
+
+    impl KeyGen for NumericRangeKeyGen {
+        fn generate(&mut self, rng: &mut WorkloadRng, len: usize) -> Key {
+            let n = rng.random_range(self.min..=self.max);
+            return format!("{:0len$}", n).into_bytes().into();
+        }
+    }
+
+    /// Characters drawn uniformly from a charset.
+    pub struct CharsetKeyGen {
+        pub(crate) charset: Vec<u8>,
+    }
+
+    impl CharsetKeyGen {
+        /// Draws from the distinct bytes of `charset`.
+        pub fn new(charset: &str) -> Self {
+            let mut charset = charset.as_bytes().to_vec();
+            charset.sort_unstable();
+            charset.dedup();
+            return Self { charset };
+        }
+    }
+
+    impl KeyGen for CharsetKeyGen {
+        fn generate(&mut self, rng: &mut WorkloadRng, len: usize) -> Key {
+            return (0..len)
+                .map(|_| self.charset[rng.random_range(0..self.charset.len())])
+                .collect();
+        }
+    }
+
+    /// Uniformly random bytes.
+    pub struct BinaryKeyGen;
+
+    impl KeyGen for BinaryKeyGen {
+        fn generate(&mut self, rng: &mut WorkloadRng, len: usize) -> Key {
+            let mut key = vec![0; len];
+            rng.fill_bytes(&mut key);
+            return key.into();
+        }
+    }
+
+    /// Draws the `i`-th key of `inner` from an rng seeded by `seed` and `i` alone, so the `i`-th key
+    /// is the same wherever the generator is used with the same seed, e.g. in another section.
+    pub struct DeterministicKeyGen {
+        pub inner: Box<dyn KeyGen>,
+        pub seed: u64,
+        /// Index of the next key.
+        pub next: u64,
+    }
+
+    impl DeterministicKeyGen {
+        pub fn new(inner: Box<dyn KeyGen>, seed: u64) -> Self {
+            return Self {
+                inner,
+                seed,
+                next: 0,
+            };
+        }
+    }
+
+    impl KeyGen for DeterministicKeyGen {
+        fn generate(&mut self, rng: &mut WorkloadRng, len: usize) -> Key {
+            // `seed_from_u64` mixes its input, so neighbouring indices get unrelated rngs.
+            let index_seed = self.seed ^ self.next.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            self.next += 1;
+            return self
+                .inner
+                .generate(&mut rng.algorithm().seed_from_u64(index_seed), len);
+        }
+    }
+
+    /// Random v4 UUIDs, hyphenated or as 32 hex characters. The length is fixed by the format.
+    pub struct UuidKeyGen {
+        pub hyphenated: bool,
+    }
+
+    impl KeyGen for UuidKeyGen {
+        fn generate(&mut self, rng: &mut WorkloadRng, _len: usize) -> Key {
+            let uuid = uuid::Builder::from_random_bytes(rng.random()).into_uuid();
+            let mut buf = [0; uuid::fmt::Hyphenated::LENGTH];
+            let key = if self.hyphenated {
+                uuid.hyphenated().encode_lower(&mut buf)
+            } else {
+                uuid.simple().encode_lower(&mut buf)
+            };
+            return key.as_bytes().into();
+        }
+    }
+}
+
+/// Output formats for generated operations.
+pub mod writer {
+    use crate::error::{Result, WorkloadError};
+    use crate::operations::Operation;
+    use crate::spec::WorkloadSpec;
+    use crate::{write_operations_with_op_writer, write_operations_with_options};
+    use crate::{GenerateOptions, Key, WorkloadStats};
+    use memmap2::MmapMut;
+    use serde::Deserialize;
+    use std::borrow::Cow;
+    use std::cell::Cell;
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::fs::File;
+    use std::io::{self, BufRead, Write};
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::OnceLock;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// Serializes operations into an output format.
+    pub trait OpWriter: Sync {
+        fn write_insert(&self, w: &mut impl Write, key: &Key, val: &Key) -> Result<()>;
+
+        fn write_update(&self, w: &mut impl Write, key: &Key, val: &Key) -> Result<()>;
+
+        fn write_delete(&self, w: &mut impl Write, key: &Key) -> Result<()>;
+
+        fn write_point_query(&self, w: &mut impl Write, key: &Key) -> Result<()>;
+
+        fn write_range_query(&self, w: &mut impl Write, key1: &Key, key2: &Key) -> Result<()>;
+
+        fn write_seek_query(&self, w: &mut impl Write, key: &Key) -> Result<()>;
+
+        fn write_count_range_query(
+            &self,
+            w: &mut impl Write,
+            key: &Key,
+            count: usize,
+        ) -> Result<()>;
+
+        fn write_range_delete(&self, w: &mut impl Write, start: &Key, end: &Key) -> Result<()>;
+
+        fn write_think_time(&self, w: &mut impl Write, micros: u64) -> Result<()>;
+
+        fn write_flush(&self, w: &mut impl Write) -> Result<()>;
+
+        /// Writes a comment that isn't an operation, e.g. the fence around warmup operations: a
+        /// `# text` line in ascii and a `comment` op in jsonl and msgpack, which consumers skip
+        /// like ascii comments. Writers that don't output a workload skip it.
+        fn write_comment(&self, _: &mut impl Write, _: &str) -> Result<()> {
+            return Ok(());
+        }
+
+        /// Writes a directive routing the operations after it to the column family `name`, see
+        /// `column_family` of a section. Writers that don't output a workload skip it.
+        fn write_column_family(&self, _: &mut impl Write, _: &str) -> Result<()> {
+            return Ok(());
+        }
+    }
+
+    /// Parses the operations of an output format back, the inverse of its [`OpWriter`].
+    pub trait OpReader {
+        /// Writes every operation and comment read from `r` with `op_writer`, returning the
+        /// number of operations.
+        fn read_into(
+            &self,
+            r: &mut impl BufRead,
+            op_writer: &impl OpWriter,
+            w: &mut impl Write,
+        ) -> Result<usize>;
+    }
+
+    /// Separators of the ascii format.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct AsciiFormat {
+        /// Separates the operation and its keys and values, a space by default.
+        pub field_sep: u8,
+        /// Ends every operation, a newline by default.
+        pub record_sep: u8,
+        /// What happens to keys and values that contain a separator.
+        pub escaping: FieldEscaping,
+    }
+
+    impl Default for AsciiFormat {
+        fn default() -> Self {
+            return Self {
+                field_sep: b' ',
+                record_sep: b'\n',
+                escaping: FieldEscaping::default(),
+            };
+        }
+    }
+
+    /// How the ascii format writes keys and values that contain a separator, e.g. from a custom
+    /// charset with a space.
+    #[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+    pub enum FieldEscaping {
+        /// Fail with [`WorkloadError::SeparatorInField`].
+        #[default]
+        Reject,
+        /// Put a backslash in front of every separator and backslash, e.g. `a\ b` for `a b`.
+        Backslash,
+    }
+
+    /// Separated operations, one per record, e.g. `I key val` with the default separators.
+    #[derive(Debug, Default, Copy, Clone)]
+    pub struct AsciiWriter {
+        pub format: AsciiFormat,
+    }
+
+    impl AsciiWriter {
+        pub fn new(format: AsciiFormat) -> Self {
+            return Self { format };
+        }
+
+        fn write_record(&self, w: &mut impl Write, op: &[u8], fields: &[&[u8]]) -> Result<()> {
+            w.write_all(op)?;
+            for field in fields {
+                w.write_all(&[self.format.field_sep])?;
+                self.write_field(w, field)?;
+            }
+            w.write_all(&[self.format.record_sep])?;
+
+            return Ok(());
+        }
+
+        fn write_field(&self, w: &mut impl Write, field: &[u8]) -> Result<()> {
+            let AsciiFormat {
+                field_sep,
+                record_sep,
+                escaping,
+            } = self.format;
+            let escape_backslash = escaping == FieldEscaping::Backslash;
+            // Searching for single bytes is fast, so clean fields are written as they are.
+            let dirty = field.contains(&field_sep)
+                || field.contains(&record_sep)
+                || (escape_backslash && field.contains(&b'\\'));
+            if !dirty {
+                w.write_all(field)?;
+                return Ok(());
+            }
+            if escaping == FieldEscaping::Reject {
+                let sep = match field.contains(&field_sep) {
+                    true => field_sep,
+                    false => record_sep,
+                };
+                return Err(WorkloadError::SeparatorInField {
+                    field: String::from_utf8_lossy(field).into_owned(),
+                    sep: char::from(sep),
+                });
+            }
+            let mut start = 0;
+            for (i, &b) in field.iter().enumerate() {
+                if b == field_sep || b == record_sep || b == b'\\' {
+                    w.write_all(&field[start..i])?;
+                    w.write_all(b"\\")?;
+                    start = i;
+                }
+            }
+            w.write_all(&field[start..])?;
+            return Ok(());
+        }
+    }
+
+    impl OpWriter for AsciiWriter {
+        fn write_insert(&self, w: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
+            return self.write_record(w, b"I", &[key, val]);
+        }
+
+        fn write_update(&self, w: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
+            return self.write_record(w, b"U", &[key, val]);
+        }
+
+        fn write_delete(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            return self.write_record(w, b"D", &[key]);
+        }
+
+        fn write_point_query(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            return self.write_record(w, b"P", &[key]);
+        }
+
+        fn write_range_query(&self, w: &mut impl Write, key1: &Key, key2: &Key) -> Result<()> {
+            return self.write_record(w, b"R", &[key1, key2]);
+        }
+
+        fn write_seek_query(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            return self.write_record(w, b"SK", &[key]);
+        }
+
+        fn write_count_range_query(
+            &self,
+            w: &mut impl Write,
+            key: &Key,
+            count: usize,
+        ) -> Result<()> {
+            let count = format!("+{count}");
+            return self.write_record(w, b"R", &[key, count.as_bytes()]);
+        }
+
+        fn write_range_delete(&self, w: &mut impl Write, start: &Key, end: &Key) -> Result<()> {
+            return self.write_record(w, b"DR", &[start, end]);
+        }
+
+        fn write_think_time(&self, w: &mut impl Write, micros: u64) -> Result<()> {
+            return self.write_record(w, b"T", &[micros.to_string().as_bytes()]);
+        }
+
+        fn write_flush(&self, w: &mut impl Write) -> Result<()> {
+            return self.write_record(w, b"F", &[]);
+        }
+
+        fn write_comment(&self, w: &mut impl Write, text: &str) -> Result<()> {
+            w.write_all(b"# ")?;
+            w.write_all(text.as_bytes())?;
+            w.write_all(&[self.format.record_sep])?;
+            return Ok(());
+        }
+
+        fn write_column_family(&self, w: &mut impl Write, name: &str) -> Result<()> {
+            return self.write_record(w, b"CF", &[name.as_bytes()]);
+        }
+    }
+
+    /// Parses the operations written by an [`AsciiWriter`] of the same format. Footers are
+    /// skipped, they check the ascii bytes rather than the operations.
+    #[derive(Debug, Default, Copy, Clone)]
+    pub struct AsciiReader {
+        pub format: AsciiFormat,
+    }
+
+    impl AsciiReader {
+        pub fn new(format: AsciiFormat) -> Self {
+            return Self { format };
+        }
+
+        /// Reads the next record into `record` without its separator, returning false at the end
+        /// of the input. An escaped record separator belongs to a field, so the record goes on.
+        pub fn read_record(&self, r: &mut impl BufRead, record: &mut Vec<u8>) -> Result<bool> {
+            let AsciiFormat {
+                record_sep,
+                escaping,
+                ..
+            } = self.format;
+            record.clear();
+            while r.read_until(record_sep, record)? > 0 {
+                // The last record may end without a separator.
+                if record.last() != Some(&record_sep) {
+                    break;
+                }
+                let backslashes = record[..record.len() - 1]
+                    .iter()
+                    .rev()
+                    .take_while(|&&b| b == b'\\')
+                    .count();
+                if escaping == FieldEscaping::Reject || backslashes % 2 == 0 {
+                    record.pop();
+                    return Ok(true);
+                }
+            }
+            return Ok(!record.is_empty());
+        }
+
+        /// Splits a record into its fields, dropping the escaping backslashes.
+        pub fn split_fields(&self, record: &[u8]) -> Vec<Vec<u8>> {
+            let field_sep = self.format.field_sep;
+            if self.format.escaping == FieldEscaping::Reject {
+                return record
+                    .split(|&b| b == field_sep)
+                    .map(<[u8]>::to_vec)
+                    .collect();
+            }
+            let mut fields = vec![Vec::new()];
+            let mut bytes = record.iter();
+            while let Some(&b) = bytes.next() {
+                let field = fields.last_mut().expect("at least one field");
+                match b {
+                    b'\\' => field.extend(bytes.next()),
+                    _ if b == field_sep => fields.push(Vec::new()),
+                    _ => field.push(b),
+                }
+            }
+            return fields;
+        }
+
+        /// The operation of a record, or `None` if it isn't one. Like `inspect`, a range query
+        /// whose end is `+` and a number is a count range query.
+        fn parse_operation(&self, record: &[u8]) -> Option<Operation> {
+            let fields = self.split_fields(record);
+            let key = |idx: usize| Key::from(fields[idx].as_slice());
+            let op = match (fields[0].as_slice(), fields.len()) {
+                (b"I", 3) => Operation::Insert {
+                    key: key(1),
+                    val: key(2),
+                },
+                (b"U", 3) => Operation::Update {
+                    key: key(1),
+                    val: key(2),
+                },
+                (b"D", 2) => Operation::Delete { key: key(1) },
+                (b"DR", 3) => Operation::RangeDelete {
+                    start: key(1),
+                    end: key(2),
+                },
+                (b"P", 2) => Operation::PointQuery { key: key(1) },
+                (b"SK", 2) => Operation::SeekQuery { key: key(1) },
+                (b"R", 3) => match fields[2].strip_prefix(b"+").and_then(parse_number) {
+                    Some(count) => Operation::CountRangeQuery {
+                        start: key(1),
+                        count,
+                    },
+                    None => Operation::RangeQuery {
+                        lo: key(1),
+                        hi: key(2),
+                    },
+                },
+                (b"T", 2) => Operation::ThinkTime {
+                    micros: parse_number(&fields[1])?,
+                },
+                (b"F", 1) => Operation::Flush,
+                _ => return None,
+            };
+            return Some(op);
+        }
+    }
+
+    /// A decimal number of an ascii field.
+    fn parse_number<T: FromStr>(field: &[u8]) -> Option<T> {
+        return std::str::from_utf8(field).ok()?.parse().ok();
+    }
+
+    impl OpReader for AsciiReader {
+        fn read_into(
+            &self,
+            r: &mut impl BufRead,
+            op_writer: &impl OpWriter,
+            w: &mut impl Write,
+        ) -> Result<usize> {
+            let mut ops = 0;
+            let mut records = 0;
+            let mut record = Vec::new();
+            while self.read_record(r, &mut record)? {
+                records += 1;
+                if record.starts_with(b"#") {
+                    if Footer::parse(&record).is_none() {
+                        let text = record.strip_prefix(b"# ").unwrap_or(&record[1..]);
+                        op_writer.write_comment(w, &String::from_utf8_lossy(text))?;
+                    }
+                    continue;
+                }
+                if record.starts_with(b"CF") && record.get(2) == Some(&self.format.field_sep) {
+                    let fields = self.split_fields(&record);
+                    if let [_, name] = fields.as_slice() {
+                        op_writer.write_column_family(w, &String::from_utf8_lossy(name))?;
+                        continue;
+                    }
+                }
+                let Some(op) = self.parse_operation(&record) else {
+                    return Err(WorkloadError::MalformedOperation {
+                        record: records,
+                        message: format!("`{}`", String::from_utf8_lossy(&record)),
+                    });
+                };
+                op.write(op_writer, w)?;
+                ops += 1;
+            }
+            return Ok(ops);
+        }
+    }
+
+    /// A JSON object per line, e.g. `{"op":"insert","key":"...","val":"..."}`.
+    ///
+    /// Keys and values are written as strings, which is fine for the ascii key spaces. Binary key
+    /// spaces would need to be base64 encoded.
+    #[derive(Debug, Default, Copy, Clone)]
+    pub struct JsonlWriter;
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(tag = "op", rename_all = "snake_case")]
+    enum JsonlOp<'a> {
+        Insert {
+            key: Cow<'a, str>,
+            val: Cow<'a, str>,
+        },
+        Update {
+            key: Cow<'a, str>,
+            val: Cow<'a, str>,
+        },
+        Delete {
+            key: Cow<'a, str>,
+        },
+        PointQuery {
+            key: Cow<'a, str>,
+        },
+        RangeQuery {
+            start: Cow<'a, str>,
+            end: Cow<'a, str>,
+        },
+        SeekQuery {
+            key: Cow<'a, str>,
+        },
+        CountRangeQuery {
+            start: Cow<'a, str>,
+            count: usize,
+        },
+        RangeDelete {
+            start: Cow<'a, str>,
+            end: Cow<'a, str>,
+        },
+        ThinkTime {
+            micros: u64,
+        },
+        Flush,
+        /// Not an operation, e.g. `{"op":"comment","text":"warmup-start"}`, see
+        /// [`OpWriter::write_comment`].
+        Comment {
+            text: Cow<'a, str>,
+        },
+        ColumnFamily {
+            name: Cow<'a, str>,
+        },
+    }
+
+    impl JsonlWriter {
+        fn as_str(bytes: &Key) -> Result<Cow<'_, str>> {
+            return Ok(Cow::from(std::str::from_utf8(bytes)?));
+        }
+
+        fn write_op(w: &mut impl Write, op: &JsonlOp) -> Result<()> {
+            serde_json::to_writer(&mut *w, op)?;
+            w.write_all("\n".as_bytes())?;
+
+            return Ok(());
+        }
+    }
+
+    impl OpWriter for JsonlWriter {
+        fn write_insert(&self, w: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
+            let (key, val) = (Self::as_str(key)?, Self::as_str(val)?);
+            return Self::write_op(w, &JsonlOp::Insert { key, val });
+        }
+
+        fn write_update(&self, w: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
+            let (key, val) = (Self::as_str(key)?, Self::as_str(val)?);
+            return Self::write_op(w, &JsonlOp::Update { key, val });
+        }
+
+        fn write_delete(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            let key = Self::as_str(key)?;
+            return Self::write_op(w, &JsonlOp::Delete { key });
+        }
+
+        fn write_point_query(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            let key = Self::as_str(key)?;
+            return Self::write_op(w, &JsonlOp::PointQuery { key });
+        }
+
+        fn write_range_query(&self, w: &mut impl Write, key1: &Key, key2: &Key) -> Result<()> {
+            let (start, end) = (Self::as_str(key1)?, Self::as_str(key2)?);
+            return Self::write_op(w, &JsonlOp::RangeQuery { start, end });
+        }
+
+        fn write_seek_query(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            let key = Self::as_str(key)?;
+            return Self::write_op(w, &JsonlOp::SeekQuery { key });
+        }
+
+        fn write_count_range_query(
+            &self,
+            w: &mut impl Write,
+            key: &Key,
+            count: usize,
+        ) -> Result<()> {
+            let start = Self::as_str(key)?;
+            return Self::write_op(w, &JsonlOp::CountRangeQuery { start, count });
+        }
+
+        fn write_range_delete(&self, w: &mut impl Write, start: &Key, end: &Key) -> Result<()> {
+            let (start, end) = (Self::as_str(start)?, Self::as_str(end)?);
+            return Self::write_op(w, &JsonlOp::RangeDelete { start, end });
+        }
+
+        fn write_think_time(&self, w: &mut impl Write, micros: u64) -> Result<()> {
+            return Self::write_op(w, &JsonlOp::ThinkTime { micros });
+        }
+
+        fn write_flush(&self, w: &mut impl Write) -> Result<()> {
+            return Self::write_op(w, &JsonlOp::Flush);
+        }
+
+        fn write_comment(&self, w: &mut impl Write, text: &str) -> Result<()> {
+            let text = Cow::from(text);
+            return Self::write_op(w, &JsonlOp::Comment { text });
+        }
+
+        fn write_column_family(&self, w: &mut impl Write, name: &str) -> Result<()> {
+            let name = Cow::from(name);
+            return Self::write_op(w, &JsonlOp::ColumnFamily { name });
+        }
+    }
+
+    /// Parses the operations written by a [`JsonlWriter`].
+    #[derive(Debug, Default, Copy, Clone)]
+    pub struct JsonlReader;
+
+    impl OpReader for JsonlReader {
+        fn read_into(
+            &self,
+            r: &mut impl BufRead,
+            op_writer: &impl OpWriter,
+            w: &mut impl Write,
+        ) -> Result<usize> {
+            let mut ops = 0;
+            for (idx, line) in r.lines().enumerate() {
+                let line = line?;
+                let op = serde_json::from_str::<JsonlOp>(&line).map_err(|err| {
+                    return WorkloadError::MalformedOperation {
+                        record: idx + 1,
+                        message: err.to_string(),
+                    };
+                })?;
+                let key = |s: &str| Key::from(s.as_bytes());
+                let op = match op {
+                    JsonlOp::Insert { key: k, val } => Operation::Insert {
+                        key: key(&k),
+                        val: key(&val),
+                    },
+                    JsonlOp::Update { key: k, val } => Operation::Update {
+                        key: key(&k),
+                        val: key(&val),
+                    },
+                    JsonlOp::Delete { key: k } => Operation::Delete { key: key(&k) },
+                    JsonlOp::PointQuery { key: k } => Operation::PointQuery { key: key(&k) },
+                    JsonlOp::RangeQuery { start, end } => Operation::RangeQuery {
+                        lo: key(&start),
+                        hi: key(&end),
+                    },
+                    JsonlOp::SeekQuery { key: k } => Operation::SeekQuery { key: key(&k) },
+                    JsonlOp::CountRangeQuery { start, count } => Operation::CountRangeQuery {
+                        start: key(&start),
+                        count,
+                    },
+                    JsonlOp::RangeDelete { start, end } => Operation::RangeDelete {
+                        start: key(&start),
+                        end: key(&end),
+                    },
+                    JsonlOp::ThinkTime { micros } => Operation::ThinkTime { micros },
+                    JsonlOp::Flush => Operation::Flush,
+                    JsonlOp::Comment { text } => {
+                        op_writer.write_comment(w, &text)?;
+                        continue;
+                    }
+                    JsonlOp::ColumnFamily { name } => {
+                        op_writer.write_column_family(w, &name)?;
+                        continue;
+                    }
+                };
+                op.write(op_writer, w)?;
+                ops += 1;
+            }
+            return Ok(ops);
+        }
+    }
+
+    /// A MessagePack map per operation, e.g. `{"op": "insert", "key": <bin>, "val": <bin>}`,
+    /// streamed one after another. Keys and values are binary, so unlike JSON Lines any key space
+    /// works.
+    #[derive(Debug, Default, Copy, Clone)]
+    pub struct MsgpackWriter;
+
+    /// An operation of a MessagePack workload, borrowing its keys and values when decoded from a
+    /// slice.
+    #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+    #[serde(tag = "op", rename_all = "snake_case")]
+    pub enum MsgpackOp<'a> {
+        Insert {
+            #[serde(borrow, with = "serde_bytes")]
+            key: Cow<'a, [u8]>,
+            #[serde(borrow, with = "serde_bytes")]
+            val: Cow<'a, [u8]>,
+        },
+        Update {
+            #[serde(borrow, with = "serde_bytes")]
+            key: Cow<'a, [u8]>,
+            #[serde(borrow, with = "serde_bytes")]
+            val: Cow<'a, [u8]>,
+        },
+        Delete {
+            #[serde(borrow, with = "serde_bytes")]
+            key: Cow<'a, [u8]>,
+        },
+        PointQuery {
+            #[serde(borrow, with = "serde_bytes")]
+            key: Cow<'a, [u8]>,
+        },
+        RangeQuery {
+            #[serde(borrow, with = "serde_bytes")]
+            start: Cow<'a, [u8]>,
+            #[serde(borrow, with = "serde_bytes")]
+            end: Cow<'a, [u8]>,
+        },
+        SeekQuery {
+            #[serde(borrow, with = "serde_bytes")]
+            key: Cow<'a, [u8]>,
+        },
+        CountRangeQuery {
+            #[serde(borrow, with = "serde_bytes")]
+            start: Cow<'a, [u8]>,
+            count: usize,
+        },
+        RangeDelete {
+            #[serde(borrow, with = "serde_bytes")]
+            start: Cow<'a, [u8]>,
+            #[serde(borrow, with = "serde_bytes")]
+            end: Cow<'a, [u8]>,
+        },
+        ThinkTime {
+            micros: u64,
+        },
+        Flush,
+        /// Not an operation, see [`OpWriter::write_comment`].
+        Comment {
+            #[serde(borrow)]
+            text: Cow<'a, str>,
+        },
+        ColumnFamily {
+            #[serde(borrow)]
+            name: Cow<'a, str>,
+        },
+    }
+
+    impl MsgpackWriter {
+        fn write_op(w: &mut impl Write, op: &MsgpackOp) -> Result<()> {
+            // Named fields make every operation a map instead of an array.
+            rmp_serde::encode::write_named(w, op)?;
+
+            return Ok(());
+        }
+    }
+
+    impl OpWriter for MsgpackWriter {
+        fn write_insert(&self, w: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
+            let (key, val) = (Cow::from(&**key), Cow::from(&**val));
+            return Self::write_op(w, &MsgpackOp::Insert { key, val });
+        }
+
+        fn write_update(&self, w: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
+            let (key, val) = (Cow::from(&**key), Cow::from(&**val));
+            return Self::write_op(w, &MsgpackOp::Update { key, val });
+        }
+
+        fn write_delete(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            let key = Cow::from(&**key);
+            return Self::write_op(w, &MsgpackOp::Delete { key });
+        }
+
+        fn write_point_query(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            let key = Cow::from(&**key);
+            return Self::write_op(w, &MsgpackOp::PointQuery { key });
+        }
+
+        fn write_range_query(&self, w: &mut impl Write, key1: &Key, key2: &Key) -> Result<()> {
+            let (start, end) = (Cow::from(&**key1), Cow::from(&**key2));
+            return Self::write_op(w, &MsgpackOp::RangeQuery { start, end });
+        }
+
+        fn write_seek_query(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            let key = Cow::from(&**key);
+            return Self::write_op(w, &MsgpackOp::SeekQuery { key });
+        }
+
+        fn write_count_range_query(
+            &self,
+            w: &mut impl Write,
+            key: &Key,
+            count: usize,
+        ) -> Result<()> {
+            let start = Cow::from(&**key);
+            return Self::write_op(w, &MsgpackOp::CountRangeQuery { start, count });
+        }
+
+        fn write_range_delete(&self, w: &mut impl Write, start: &Key, end: &Key) -> Result<()> {
+            let (start, end) = (Cow::from(&**start), Cow::from(&**end));
+            return Self::write_op(w, &MsgpackOp::RangeDelete { start, end });
+        }
+
+        fn write_think_time(&self, w: &mut impl Write, micros: u64) -> Result<()> {
+            return Self::write_op(w, &MsgpackOp::ThinkTime { micros });
+        }
+
+        fn write_flush(&self, w: &mut impl Write) -> Result<()> {
+            return Self::write_op(w, &MsgpackOp::Flush);
+        }
+
+        fn write_comment(&self, w: &mut impl Write, text: &str) -> Result<()> {
+            let text = Cow::from(text);
+            return Self::write_op(w, &MsgpackOp::Comment { text });
+        }
+
+        fn write_column_family(&self, w: &mut impl Write, name: &str) -> Result<()> {
+            let name = Cow::from(name);
+            return Self::write_op(w, &MsgpackOp::ColumnFamily { name });
+        }
+    }
+
+    /// Parses the operations written by a [`MsgpackWriter`].
+    #[derive(Debug, Default, Copy, Clone)]
+    pub struct MsgpackReader;
+
+    impl OpReader for MsgpackReader {
+        fn read_into(
+            &self,
+            r: &mut impl BufRead,
+            op_writer: &impl OpWriter,
+            w: &mut impl Write,
+        ) -> Result<usize> {
+            let mut ops = 0;
+            let mut records = 0;
+            while !r.fill_buf()?.is_empty() {
+                records += 1;
+                let mut de = rmp_serde::Deserializer::new(&mut *r);
+                let op = MsgpackOp::deserialize(&mut de).map_err(|err| {
+                    return WorkloadError::MalformedOperation {
+                        record: records,
+                        message: err.to_string(),
+                    };
+                })?;
+                let key = |bytes: &[u8]| Key::from(bytes);
+                let op = match op {
+                    MsgpackOp::Insert { key: k, val } => Operation::Insert {
+                        key: key(&k),
+                        val: key(&val),
+                    },
+                    MsgpackOp::Update { key: k, val } => Operation::Update {
+                        key: key(&k),
+                        val: key(&val),
+                    },
+                    MsgpackOp::Delete { key: k } => Operation::Delete { key: key(&k) },
+                    MsgpackOp::PointQuery { key: k } => Operation::PointQuery { key: key(&k) },
+                    MsgpackOp::RangeQuery { start, end } => Operation::RangeQuery {
+                        lo: key(&start),
+                        hi: key(&end),
+                    },
+                    MsgpackOp::SeekQuery { key: k } => Operation::SeekQuery { key: key(&k) },
+                    MsgpackOp::CountRangeQuery { start, count } => Operation::CountRangeQuery {
+                        start: key(&start),
+                        count,
+                    },
+                    MsgpackOp::RangeDelete { start, end } => Operation::RangeDelete {
+                        start: key(&start),
+                        end: key(&end),
+                    },
+                    MsgpackOp::ThinkTime { micros } => Operation::ThinkTime { micros },
+                    MsgpackOp::Flush => Operation::Flush,
+                    MsgpackOp::Comment { text } => {
+                        op_writer.write_comment(w, &text)?;
+                        continue;
+                    }
+                    MsgpackOp::ColumnFamily { name } => {
+                        op_writer.write_column_family(w, &name)?;
+                        continue;
+                    }
+                };
+                op.write(op_writer, w)?;
+                ops += 1;
+            }
+            return Ok(ops);
+        }
+    }
+
+    /// Reads a workload written in the format `from` and writes its operations and comments in
+    /// the format `to`, returning the number of operations. Ascii workloads on either side use
+    /// `ascii_format`.
+    pub fn convert(
+        input: &mut impl BufRead,
+        from: OutputFormat,
+        output: &mut impl Write,
+        to: OutputFormat,
+        ascii_format: AsciiFormat,
+    ) -> Result<usize> {
+        return match from {
+            OutputFormat::Ascii => {
+                let reader = AsciiReader::new(ascii_format);
+                convert_to(&reader, input, output, to, ascii_format)
+            }
+            OutputFormat::Jsonl => convert_to(&JsonlReader, input, output, to, ascii_format),
+            OutputFormat::Msgpack => convert_to(&MsgpackReader, input, output, to, ascii_format),
+        };
+    }
+
+    fn convert_to(
+        reader: &impl OpReader,
+        input: &mut impl BufRead,
+        output: &mut impl Write,
+        to: OutputFormat,
+        ascii_format: AsciiFormat,
+    ) -> Result<usize> {
+        return match to {
+            OutputFormat::Ascii => reader.read_into(input, &AsciiWriter::new(ascii_format), output),
+            OutputFormat::Jsonl => reader.read_into(input, &JsonlWriter, output),
+            OutputFormat::Msgpack => reader.read_into(input, &MsgpackWriter, output),
+        };
+    }
+
+    /// Output format of a generated workload.
+    #[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+    pub enum OutputFormat {
+        #[default]
+        Ascii,
+        Jsonl,
+        Msgpack,
+    }
+
+    impl OutputFormat {
+        /// Name of the format, as parsed by [`OutputFormat::from_str`].
+        pub fn name(&self) -> &'static str {
+            return match self {
+                OutputFormat::Ascii => "ascii",
+                OutputFormat::Jsonl => "jsonl",
+                OutputFormat::Msgpack => "msgpack",
+            };
+        }
+
+        /// Whether the format writes keys as text, so it can't write binary keys.
+        pub fn is_text(&self) -> bool {
+            return !matches!(self, OutputFormat::Msgpack);
+        }
+
+        /// File extension for workloads written in this format.
+        pub fn extension(&self) -> &'static str {
+            return match self {
+                OutputFormat::Ascii => "txt",
+                OutputFormat::Jsonl => "jsonl",
+                OutputFormat::Msgpack => "msgpack",
+            };
+        }
+    }
+
+    impl FromStr for OutputFormat {
+        type Err = WorkloadError;
+
+        fn from_str(s: &str) -> Result<Self> {
+            return match s {
+                "ascii" => Ok(OutputFormat::Ascii),
+                "jsonl" => Ok(OutputFormat::Jsonl),
+                "msgpack" => Ok(OutputFormat::Msgpack),
+                _ => Err(WorkloadError::UnknownFormat(s.to_string())),
+            };
+        }
+    }
+
+    /// Writes a whole workload in one format of a [`FormatRegistry`].
+    type WriteFormat = dyn Fn(
+            &mut dyn Write,
+            &WorkloadSpec,
+            GenerateOptions,
+            &mut dyn FnMut(usize, usize),
+        ) -> Result<WorkloadStats>
+        + Send
+        + Sync;
+
+    /// Output formats by name, the built-in ones and the [`OpWriter`]s registered next to them,
+    /// e.g. by a downstream crate with a format of its own.
+    pub struct FormatRegistry {
+        formats: BTreeMap<String, Box<WriteFormat>>,
+    }
+
+    impl FormatRegistry {
+        /// A registry of `ascii`, `jsonl`, and `msgpack`, written like their [`OutputFormat`].
+        pub fn new() -> Self {
+            let mut formats: BTreeMap<String, Box<WriteFormat>> = BTreeMap::new();
+            for name in ["ascii", "jsonl", "msgpack"] {
+                let format: OutputFormat = name.parse().expect("a built-in format");
+                let write =
+                    move |mut w: &mut dyn Write,
+                          workload: &WorkloadSpec,
+                          options: GenerateOptions,
+                          progress: &mut dyn FnMut(usize, usize)| {
+                        let options = GenerateOptions { format, ..options };
+                        return write_operations_with_options(&mut w, workload, options, progress);
+                    };
+                formats.insert(name.to_string(), Box::new(write));
+            }
+            return FormatRegistry { formats };
+        }
+
+        /// Registers `op_writer` as the format `name`, replacing a format of the same name.
+        pub fn register(&mut self, name: &str, op_writer: impl OpWriter + Send + 'static) {
+            let write = move |mut w: &mut dyn Write,
+                              workload: &WorkloadSpec,
+                              options: GenerateOptions,
+                              progress: &mut dyn FnMut(usize, usize)| {
+                return write_operations_with_op_writer(
+                    &mut w, &op_writer, workload, options, progress,
+                );
+            };
+            self.formats.insert(name.to_string(), Box::new(write));
+        }
+
+        /// Names of the registered formats, in alphabetical order.
+        pub fn names(&self) -> impl Iterator<Item = &str> {
+            return self.formats.keys().map(|name| name.as_str());
+        }
+
+        /// Writes the operations of `workload` in the format `name`, like
+        /// [`write_operations_with_options`]. `options.format` is ignored.
+        pub fn write(
+            &self,
+            name: &str,
+            mut writer: &mut impl Write,
+            workload: &WorkloadSpec,
+            options: GenerateOptions,
+            mut progress: impl FnMut(usize, usize),
+        ) -> Result<WorkloadStats> {
+            let Some(write) = self.formats.get(name) else {
+                return Err(WorkloadError::UnregisteredFormat {
+                    name: name.to_string(),
+                    registered: self.names().map(|name| format!("`{name}`")).collect(),
+                });
+            };
+            return write(&mut writer, workload, options, &mut progress);
+        }
+    }
+
+    impl Default for FormatRegistry {
+        fn default() -> Self {
+            return Self::new();
+        }
+    }
+
+    /// Last line of an ascii workload with its number of operations and the CRC32 of every byte
+    /// before it, e.g. `# ops=1000 crc=8587d865`, to check a copied workload for corruption.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct Footer {
+        pub ops: usize,
+        pub crc: u32,
+    }
+
+    impl Footer {
+        /// Parses a footer line without its record separator, or `None` if it isn't one.
+        pub fn parse(line: &[u8]) -> Option<Footer> {
+            let line = std::str::from_utf8(line).ok()?;
+            let (ops, crc) = line.strip_prefix("# ops=")?.split_once(" crc=")?;
+            return Some(Footer {
+                ops: ops.parse().ok()?,
+                crc: u32::from_str_radix(crc, 16).ok()?,
+            });
+        }
+    }
+
+    impl fmt::Display for Footer {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            return write!(f, "# ops={} crc={:08x}", self.ops, self.crc);
+        }
+    }
+
+    /// First line of an ascii workload with the longest key and value and the number of
+    /// operations, e.g. `# max_key=16 max_val=100 ops=1000`, for replay tools that pre-allocate
+    /// their buffers. Derived from the spec, so the lengths are upper bounds.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct Header {
+        pub max_key: usize,
+        pub max_val: usize,
+        pub ops: usize,
+    }
+
+    impl Header {
+        /// The header of `workload`, stopped after `max_ops` operations if set.
+        pub fn new(workload: &WorkloadSpec, max_ops: Option<usize>) -> Header {
+            let ops = workload.operation_count();
+            return Header {
+                max_key: workload.max_key_len(),
+                max_val: workload.max_val_len(),
+                ops: max_ops.map_or(ops, |max_ops| max_ops.min(ops)),
+            };
+        }
+
+        /// Parses a header line without its record separator, or `None` if it isn't one.
+        pub fn parse(line: &[u8]) -> Option<Header> {
+            let line = std::str::from_utf8(line).ok()?;
+            let (max_key, rest) = line.strip_prefix("# max_key=")?.split_once(" max_val=")?;
+            let (max_val, ops) = rest.split_once(" ops=")?;
+            return Some(Header {
+                max_key: max_key.parse().ok()?,
+                max_val: max_val.parse().ok()?,
+                ops: ops.parse().ok()?,
+            });
+        }
+    }
+
+    impl fmt::Display for Header {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            return write!(
+                f,
+                "# max_key={} max_val={} ops={}",
+                self.max_key, self.max_val, self.ops
+            );
+        }
+    }
+
+    /// Passes writes on to `inner`, hashing the written bytes for a [`Footer`].
+    pub(crate) struct CrcWriter<W: Write> {
+        pub(crate) inner: W,
+        pub(crate) hasher: crc32fast::Hasher,
+    }
+
+    impl<W: Write> Write for CrcWriter<W> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let written = self.inner.write(buf)?;
+            self.hasher.update(&buf[..written]);
+            return Ok(written);
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            return self.inner.flush();
+        }
+    }
+
+    /// Passes writes on to `inner`, counting the written bytes in `bytes`, which a progress
+    /// callback can read while the workload is written, e.g. to estimate the time left from
+    /// [`crate::spec::WorkloadSpec::bytes_count`].
+    pub struct CountingWriter<'a, W: Write> {
+        pub inner: W,
+        pub bytes: &'a Cell<usize>,
+    }
+
+    impl<W: Write> Write for CountingWriter<'_, W> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let written = self.inner.write(buf)?;
+            self.bytes.set(self.bytes.get() + written);
+            return Ok(written);
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            return self.inner.flush();
+        }
+    }
+
+    /// Writes into a file mapped into memory and pre-sized to the length of the workload, which
+    /// saves a write call for every flushed buffer. Writing past that length fails, so the length
+    /// has to be exact or an overestimate, see [`crate::GenerateOptions::mmap_len`].
+    pub struct MmapWriter {
+        file: File,
+        map: MmapMut,
+        len: usize,
+    }
+
+    impl MmapWriter {
+        /// Resizes `file`, which has to be open for reading and writing, to `len` bytes and maps
+        /// it.
+        pub fn create(file: File, len: usize) -> io::Result<Self> {
+            file.set_len(len as u64)?;
+            // SAFETY: The file was just created for the workload, nothing else maps or resizes
+            // it while it is written.
+            let map = unsafe { MmapMut::map_mut(&file)? };
+            return Ok(MmapWriter { file, map, len: 0 });
+        }
+
+        /// Flushes the mapped bytes and truncates the file to the bytes written, for workloads
+        /// that stopped early or wrote fewer bytes than expected.
+        pub fn finish(self) -> io::Result<()> {
+            self.map.flush()?;
+            drop(self.map);
+            return self.file.set_len(self.len as u64);
+        }
+    }
+
+    impl Write for MmapWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            // Writing nothing once the map is full makes `write_all` fail with `WriteZero`.
+            let written = buf.len().min(self.map.len() - self.len);
+            self.map[self.len..self.len + written].copy_from_slice(&buf[..written]);
+            self.len += written;
+            return Ok(written);
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            return Ok(());
+        }
+    }
+
+    /// Passes writes on to `load` while `loading` is set and to `run` otherwise. Generation sets
+    /// `loading` at the start of every section and clears it before the section's first operation
+    /// that isn't an insert, see [`crate::write_phases_with_options`].
+    pub struct PhaseWriter<'a, L: Write, R: Write> {
+        pub load: L,
+        pub run: R,
+        pub loading: &'a Cell<bool>,
+    }
+
+    impl<L: Write, R: Write> Write for PhaseWriter<'_, L, R> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            return match self.loading.get() {
+                true => self.load.write(buf),
+                false => self.run.write(buf),
+            };
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.load.flush()?;
+            return self.run.flush();
+        }
+    }
+
+    /// Selects every shard of a [`ShardWriter`].
+    pub const ALL_SHARDS: usize = usize::MAX;
+
+    /// Shard of `key` among `shards`, by its 64-bit FNV-1a hash, which unlike the std hasher is
+    /// the same in every run.
+    pub fn shard_of(key: &[u8], shards: usize) -> usize {
+        return (crate::fnv1a(key) % shards as u64) as usize;
+    }
+
+    /// Passes writes on to the shard that `shard` selects, or to every shard for [`ALL_SHARDS`].
+    /// A [`ShardOpWriter`] selects the shard of every operation before writing it.
+    pub struct ShardWriter<'a, W: Write> {
+        pub shards: &'a mut [W],
+        pub shard: &'a AtomicUsize,
+    }
+
+    impl<W: Write> Write for ShardWriter<'_, W> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            match self.shard.load(Ordering::Relaxed) {
+                ALL_SHARDS => {
+                    for shard in self.shards.iter_mut() {
+                        shard.write_all(buf)?;
+                    }
+                }
+                shard => self.shards[shard].write_all(buf)?,
+            }
+            return Ok(buf.len());
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            for shard in self.shards.iter_mut() {
+                shard.flush()?;
+            }
+            return Ok(());
+        }
+    }
+
+    /// Selects the shard of every operation for a [`ShardWriter`], by its key, and writes it with
+    /// `op_writer`. Range operations and seeks cover keys of every shard, so they go to every
+    /// shard.
+    pub struct ShardOpWriter<'a, O: OpWriter> {
+        pub op_writer: &'a O,
+        pub shards: usize,
+        /// Shard of think times, flushes, and comments, which have no key, or [`ALL_SHARDS`].
+        pub keyless_shard: usize,
+        pub shard: &'a AtomicUsize,
+    }
+
+    impl<O: OpWriter> ShardOpWriter<'_, O> {
+        fn select(&self, key: Option<&Key>) {
+            let shard = key.map_or(self.keyless_shard, |key| shard_of(key, self.shards));
+            self.shard.store(shard, Ordering::Relaxed);
+        }
+    }
+
+    impl<O: OpWriter> OpWriter for ShardOpWriter<'_, O> {
+        fn write_insert(&self, w: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
+            self.select(Some(key));
+            return self.op_writer.write_insert(w, key, val);
+        }
+
+        fn write_update(&self, w: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
+            self.select(Some(key));
+            return self.op_writer.write_update(w, key, val);
+        }
+
+        fn write_delete(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            self.select(Some(key));
+            return self.op_writer.write_delete(w, key);
+        }
+
+        fn write_point_query(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            self.select(Some(key));
+            return self.op_writer.write_point_query(w, key);
+        }
+
+        fn write_range_query(&self, w: &mut impl Write, key1: &Key, key2: &Key) -> Result<()> {
+            self.shard.store(ALL_SHARDS, Ordering::Relaxed);
+            return self.op_writer.write_range_query(w, key1, key2);
+        }
+
+        fn write_seek_query(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            // The first key at or after `key` can be in any shard.
+            self.shard.store(ALL_SHARDS, Ordering::Relaxed);
+            return self.op_writer.write_seek_query(w, key);
+        }
+
+        fn write_count_range_query(
+            &self,
+            w: &mut impl Write,
+            key: &Key,
+            count: usize,
+        ) -> Result<()> {
+            self.shard.store(ALL_SHARDS, Ordering::Relaxed);
+            return self.op_writer.write_count_range_query(w, key, count);
+        }
+
+        fn write_range_delete(&self, w: &mut impl Write, start: &Key, end: &Key) -> Result<()> {
+            // Deleting the range on every shard keeps each shard's state a slice of the unsharded
+            // one.
+            self.shard.store(ALL_SHARDS, Ordering::Relaxed);
+            return self.op_writer.write_range_delete(w, start, end);
+        }
+
+        fn write_think_time(&self, w: &mut impl Write, micros: u64) -> Result<()> {
+            self.select(None);
+            return self.op_writer.write_think_time(w, micros);
+        }
+
+        fn write_flush(&self, w: &mut impl Write) -> Result<()> {
+            self.select(None);
+            return self.op_writer.write_flush(w);
+        }
+
+        fn write_comment(&self, w: &mut impl Write, text: &str) -> Result<()> {
+            self.select(None);
+            return self.op_writer.write_comment(w, text);
+        }
+
+        fn write_column_family(&self, w: &mut impl Write, name: &str) -> Result<()> {
+            // Every shard routes its own operations of the section, keyless shard or not.
+            self.shard.store(ALL_SHARDS, Ordering::Relaxed);
+            return self.op_writer.write_column_family(w, name);
+        }
+    }
+
+    /// Sleeps shorter than this are put off until the operations are further ahead, so fast
+    /// rates sleep once per batch of operations instead of once per operation.
+    const MIN_THROTTLE_SLEEP: Duration = Duration::from_millis(1);
+
+    /// Writes every operation with `op_writer` once it is due at `ops_per_sec`, counted from the
+    /// first operation. Operations are due at fixed times rather than an interval after the one
+    /// before, so oversleeping catches up instead of drifting. Flushes before sleeping, so the
+    /// operations written so far go out on time.
+    pub struct ThrottledOpWriter<'a, O: OpWriter> {
+        pub op_writer: &'a O,
+        pub ops_per_sec: f64,
+        /// When the first operation was written.
+        start: OnceLock<Instant>,
+        /// Number of operations written.
+        ops: AtomicUsize,
+    }
+
+    impl<'a, O: OpWriter> ThrottledOpWriter<'a, O> {
+        pub fn new(op_writer: &'a O, ops_per_sec: f64) -> Self {
+            return Self {
+                op_writer,
+                ops_per_sec,
+                start: OnceLock::new(),
+                ops: AtomicUsize::new(0),
+            };
+        }
+
+        fn throttle(&self, w: &mut impl Write) -> Result<()> {
+            let start = *self.start.get_or_init(Instant::now);
+            let op = self.ops.fetch_add(1, Ordering::Relaxed);
+            let due = start + Duration::from_secs_f64(op as f64 / self.ops_per_sec);
+            let ahead = due.saturating_duration_since(Instant::now());
+            if ahead >= MIN_THROTTLE_SLEEP {
+                w.flush()?;
+                thread::sleep(ahead);
+            }
+            return Ok(());
+        }
+    }
+
+    impl<O: OpWriter> OpWriter for ThrottledOpWriter<'_, O> {
+        fn write_insert(&self, w: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
+            self.throttle(w)?;
+            return self.op_writer.write_insert(w, key, val);
+        }
+
+        fn write_update(&self, w: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
+            self.throttle(w)?;
+            return self.op_writer.write_update(w, key, val);
+        }
+
+        fn write_delete(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            self.throttle(w)?;
+            return self.op_writer.write_delete(w, key);
+        }
+
+        fn write_point_query(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            self.throttle(w)?;
+            return self.op_writer.write_point_query(w, key);
+        }
+
+        fn write_range_query(&self, w: &mut impl Write, key1: &Key, key2: &Key) -> Result<()> {
+            self.throttle(w)?;
+            return self.op_writer.write_range_query(w, key1, key2);
+        }
+
+        fn write_seek_query(&self, w: &mut impl Write, key: &Key) -> Result<()> {
+            self.throttle(w)?;
+            return self.op_writer.write_seek_query(w, key);
+        }
+
+        fn write_count_range_query(
+            &self,
+            w: &mut impl Write,
+            key: &Key,
+            count: usize,
+        ) -> Result<()> {
+            self.throttle(w)?;
+            return self.op_writer.write_count_range_query(w, key, count);
+        }
+
+        fn write_range_delete(&self, w: &mut impl Write, start: &Key, end: &Key) -> Result<()> {
+            self.throttle(w)?;
+            return self.op_writer.write_range_delete(w, start, end);
+        }
+
+        fn write_think_time(&self, w: &mut impl Write, micros: u64) -> Result<()> {
+            self.throttle(w)?;
+            return self.op_writer.write_think_time(w, micros);
+        }
+
+        fn write_flush(&self, w: &mut impl Write) -> Result<()> {
+            self.throttle(w)?;
+            return self.op_writer.write_flush(w);
+        }
+
+        /// Comments aren't operations, so they are never held back.
+        fn write_comment(&self, w: &mut impl Write, text: &str) -> Result<()> {
+            return self.op_writer.write_comment(w, text);
+        }
+
+        fn write_column_family(&self, w: &mut impl Write, name: &str) -> Result<()> {
+            return self.op_writer.write_column_family(w, name);
+        }
+    }
+}
+
+use crate::error::Result;
+pub use crate::error::WorkloadError;
+use crate::keygen::{AlphanumericKeyGen, DeterministicKeyGen, KeyGen, ALPHANUMERIC};
+use crate::keyset::{KeySet, KeySetKind};
+use crate::operations::OperationWriter;
+pub use crate::operations::{iter_operations, Operation};
+use crate::rng::WorkloadRng;
+pub use crate::schema::{
+    generate_workload_spec_schema, validate_workload_spec, validate_workload_spec_value,
+};
+use crate::spec::{
+    Inserts, KeyCursor, KeyEviction, RangeQueries, RngAlgorithm, SpecFormat, ValLen, ValPattern,
+    WorkloadSpec, WorkloadSpecGroup, WorkloadSpecSection,
+};
+use crate::writer::{
+    AsciiFormat, AsciiWriter, CrcWriter, FieldEscaping, Footer, Header, JsonlWriter, MmapWriter,
+    MsgpackWriter, OpWriter, OutputFormat, PhaseWriter, ShardOpWriter, ShardWriter,
+    ThrottledOpWriter, ALL_SHARDS,
+};
+
+pub type Key = Box<[u8]>;
+
+/// 64-bit FNV-1a hash of `key`, which unlike the std hasher is the same in every run and on every
+/// Rust version.
+pub(crate) fn fnv1a(key: &[u8]) -> u64 {
+    return key.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &b| {
+        return (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3);
+    });
+}
+
+#[derive(Debug, Copy, Clone, Eq, Ord, PartialOrd, PartialEq)]
+enum OpMarker {
+    Insert,
+    /// An insert of a valid key.
+    Overwrite,
+    /// An insert of a deleted key, or of a new key while none is deleted.
+    Reinsert,
+    Update,
+    Delete,
+    PointQuery,
+    /// A point query on a key that isn't valid, of point queries with a `hit_ratio` below 1.
+    PointQueryMiss,
+    EmptyPointQuery,
+    RangeQuery,
+    CountRangeQuery,
+    RangeDelete,
+    ThinkTime,
+    Flush,
+    /// A point query and an update of the same valid key, two operations.
+    ReadModifyWrite,
+    SeekQuery,
+    /// A seek to a key that isn't valid, of seeks with a `hit_ratio` below 1.
+    SeekQueryMiss,
+}
+
+impl OpMarker {
+    /// Whether the operation picks one of the valid keys, so it can't run while there are none.
+    fn needs_valid_key(self, group: &WorkloadSpecGroup) -> bool {
+        return match self {
+            OpMarker::Insert
+            | OpMarker::Reinsert
+            | OpMarker::PointQueryMiss
+            | OpMarker::SeekQueryMiss
+            | OpMarker::ThinkTime
+            | OpMarker::Flush => false,
+            OpMarker::Update => group.updates.is_some_and(|us| us.existing),
+            OpMarker::EmptyPointQuery => {
+                group.empty_point_queries.is_some_and(|epqs| epqs.near_miss)
+            }
+            _ => true,
+        };
+    }
+}
+
+/// Number of keys to try when generating a key absent from the valid keys before giving up, so a
+/// small or exhausted key space errors instead of looping forever. Empty point queries can
+/// override it with `max_attempts`.
+const MAX_ABSENT_KEY_ATTEMPTS: usize = 10_000;
+
+#[inline]
+fn gen_string(rng: &mut WorkloadRng, len: usize) -> Key {
+    return AlphanumericKeyGen.generate(rng, len);
+}
+
+/// Generates a value with a length sampled from `val_len` and bytes following `val_pattern`.
+#[inline]
+fn gen_value(rng: &mut WorkloadRng, val_len: ValLen, val_pattern: ValPattern) -> Key {
+    let len = val_len.sample(rng);
+    return match val_pattern {
+        ValPattern::Random => gen_string(rng, len),
+        ValPattern::Repeated(byte) => std::iter::repeat_n(byte, len).collect(),
+        ValPattern::LowEntropy { distinct_bytes } => (0..len)
+            .map(|_| ALPHANUMERIC[rng.random_range(0..usize::from(distinct_bytes))])
+            .collect(),
+    };
+}
+
+/// Generates a key of `len` bytes with `key_gen`, the generator of the section's key space, after
+/// the section's key prefix.
+#[inline]
+fn gen_section_key(
+    rng: &mut WorkloadRng,
+    key_gen: &mut dyn KeyGen,
+    section: &WorkloadSpecSection,
+    len: usize,
+) -> Key {
+    let mut key = key_gen.generate(rng, len);
+    if section.length_prefixed_keys {
+        let width = section.length_prefix_width();
+        key = [format!("{len:0width$}").as_bytes(), &key].concat().into();
+    }
+    for prefix in [&section.key_prefix, &section.section_tag]
+        .into_iter()
+        .flatten()
+    {
+        key = [prefix.as_bytes(), &key].concat().into();
+    }
+    return key;
+}
+
+/// Generates a key of `len` bytes that isn't in `keys`, trying up to `max_attempts` keys. `hint`
+/// tells how to fix the spec when the key space is too small.
+fn gen_absent_key(
+    rng: &mut WorkloadRng,
+    key_gen: &mut dyn KeyGen,
+    section: &WorkloadSpecSection,
+    keys: &impl KeySet,
+    len: usize,
+    max_attempts: usize,
+    hint: &str,
+) -> Result<Key> {
+    let key = (0..max_attempts)
+        .map(|_| gen_section_key(rng, key_gen, section, len))
+        .find(|key| !keys.contains(key));
+    let Some(key) = key else {
+        let key_space_size = section
+            .key_space
+            .size(len)
+            .map_or_else(|| "more than u128::MAX".to_string(), |n| n.to_string());
+        return Err(WorkloadError::KeySpaceExhausted(format!(
+            "Could not find a key of length {} absent from {} valid keys after {} attempts. \
+             The key space only has {} keys; {}.",
+            len,
+            keys.len(),
+            max_attempts,
+            key_space_size,
+            hint,
+        )));
+    };
+    return Ok(key);
+}
+
+/// Changes one byte of a random valid key, after the `prefix_len` bytes of section prefixes, to the
+/// byte at the same position of a key from `key_gen`, so the result stays in the key space. Tries
+/// again, up to `max_attempts` times, until the result isn't in `keys`. Keys without bytes after
+/// the prefixes, of a `key_len` of 0, can't be changed and count as failed attempts.
+fn gen_near_miss_key(
+    rng: &mut WorkloadRng,
+    key_gen: &mut dyn KeyGen,
+    keys: &impl KeySet,
+    prefix_len: usize,
+    max_attempts: usize,
+) -> Result<Key> {
+    for _ in 0..max_attempts {
+        let mut key = keys.get_random(rng).clone();
+        if key.len() <= prefix_len {
+            continue;
+        }
+        let pos = rng.random_range(prefix_len..key.len());
+        let byte = key_gen.generate(rng, key.len() - prefix_len)[pos - prefix_len];
+        if byte != key[pos] {
+            key[pos] = byte;
+            if !keys.contains(&key) {
+                return Ok(key);
+            }
+        }
+    }
+    return Err(WorkloadError::KeySpaceExhausted(format!(
+        "Could not find a near miss absent from {} valid keys after {} attempts.",
+        keys.len(),
+        max_attempts,
+    )));
+}
+
+/// Generates the key of an insert, regenerating keys that are already valid unless the inserts
+/// allow duplicate keys.
+#[inline]
+fn gen_insert_key(
+    rng: &mut WorkloadRng,
+    key_gen: &mut dyn KeyGen,
+    section: &WorkloadSpecSection,
+    keys: &impl KeySet,
+    inserts: Inserts,
+) -> Result<Key> {
+    let key_len = inserts.sample_key_len(rng);
+    if inserts.allow_duplicate_keys {
+        return Ok(gen_section_key(rng, key_gen, section, key_len));
+    }
+    return gen_absent_key(
+        rng,
+        key_gen,
+        section,
+        keys,
+        key_len,
+        MAX_ABSENT_KEY_ATTEMPTS,
+        "use a longer key_len or allow_duplicate_keys for inserts",
+    );
+}
+
+/// The new keys of a group with sorted inserts, in descending order so they can be popped in
+/// ascending order. Without duplicates, the keys are distinct and absent from `keys`.
+fn gen_sorted_keys(
+    rng: &mut WorkloadRng,
+    key_gen: &mut dyn KeyGen,
+    section: &WorkloadSpecSection,
+    keys: &impl KeySet,
+    inserts: Inserts,
+) -> Result<Vec<Key>> {
+    let count = inserts.new_key_count();
+    let mut sorted_keys: Vec<Key> = Vec::with_capacity(count);
+    // Rounds in a row that only drew keys already drawn.
+    let mut stalled_rounds = 0;
+    while sorted_keys.len() < count {
+        let before = sorted_keys.len();
+        for _ in before..count {
+            sorted_keys.push(gen_insert_key(rng, key_gen, section, keys, inserts)?);
+        }
+        sorted_keys.sort_unstable_by(|a, b| b.cmp(a));
+        if !inserts.allow_duplicate_keys {
+            sorted_keys.dedup();
+            stalled_rounds = match sorted_keys.len() == before {
+                true => stalled_rounds + 1,
+                false => 0,
+            };
+            if stalled_rounds == MAX_ABSENT_KEY_ATTEMPTS {
+                return Err(WorkloadError::KeySpaceExhausted(
+                    "Not enough distinct keys for sorted inserts, use a longer key_len or allow_duplicate_keys for inserts".to_string(),
+                ));
+            }
+        }
+    }
+    return Ok(sorted_keys);
+}
+
+pub fn write_operations(writer: &mut impl Write, workload: &WorkloadSpec) -> Result<()> {
+    return write_operations_with_format(writer, workload, OutputFormat::Ascii);
+}
+
+/// Same as [`write_operations`], writing `ops_per_sec` operations per second, see
+/// [`GenerateOptions::ops_per_sec`].
+pub fn write_operations_throttled(
+    writer: &mut impl Write,
+    workload: &WorkloadSpec,
+    ops_per_sec: f64,
+) -> Result<()> {
+    let options = GenerateOptions {
+        ops_per_sec: Some(ops_per_sec),
+        ..GenerateOptions::default()
+    };
+    write_operations_with_options(writer, workload, options, |_, _| {})?;
+    return Ok(());
+}
+
+/// Generates the ascii operations of a workload in memory, pre-sized with
+/// [`WorkloadSpec::bytes_count`].
+pub fn generate_to_vec(workload: &WorkloadSpec) -> Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(workload.bytes_count());
+    write_operations(&mut buf, workload)?;
+    return Ok(buf);
+}
+
+pub fn write_operations_with_format(
+    writer: &mut impl Write,
+    workload: &WorkloadSpec,
+    format: OutputFormat,
+) -> Result<()> {
+    return write_operations_with_progress(writer, workload, format, |_, _| {});
+}
+
+/// Writes the operations of a workload, calling `progress` with the number of operations done
+/// and the total number of operations every [`PROGRESS_INTERVAL`] operations and once at the end.
+pub fn write_operations_with_progress(
+    writer: &mut impl Write,
+    workload: &WorkloadSpec,
+    format: OutputFormat,
+    progress: impl FnMut(usize, usize),
+) -> Result<()> {
+    let options = GenerateOptions {
+        format,
+        ..GenerateOptions::default()
+    };
+    write_operations_with_options(writer, workload, options, progress)?;
+    return Ok(());
+}
+
+/// How a workload is generated, beyond what its spec describes.
+#[derive(Debug, Copy, Clone)]
+pub struct GenerateOptions {
+    pub format: OutputFormat,
+    /// Separators of the ascii format.
+    pub ascii_format: AsciiFormat,
+    /// Number of threads used to generate large insert-only groups.
+    pub threads: usize,
+    /// Stop after this many operations across all sections.
+    pub max_ops: Option<usize>,
+    /// Validate a json spec against its schema before parsing it, see [`validate_workload_spec`].
+    pub validate: bool,
+    /// Collect the keys inserted in every section into [`WorkloadStats::keys`].
+    pub collect_keys: bool,
+    /// Record how many keys every range query spans into [`WorkloadStats::range_widths`].
+    pub range_width_histogram: bool,
+    /// Format of the spec string passed to [`generate_workload_into`] and friends.
+    pub spec_format: SpecFormat,
+    /// Capacity in bytes of the buffered writer of [`generate_workload_with_progress`].
+    pub buffer_size: usize,
+    /// End ascii workloads written by [`generate_workload_into`] and friends with a [`Footer`].
+    /// The other formats have no comment lines, so they never get one.
+    pub footer: bool,
+    /// Write ascii workloads of [`generate_workload_with_progress`] into a memory-mapped file
+    /// when their size is known up front, see [`GenerateOptions::mmap_len`]. Otherwise they are
+    /// buffered as usual.
+    pub mmap: bool,
+    /// Write the operations of every section in reverse order, as far as the operations stay
+    /// valid, see [`reverse_operations`]. Buffers the operations of the whole section in memory.
+    pub reverse: bool,
+    /// Start ascii workloads written by [`generate_workload_into`] and friends with a
+    /// [`Header`]. The other formats have no comment lines, so they never get one.
+    pub header: bool,
+    /// Throttle the written operations to this many per second, see [`ThrottledOpWriter`], e.g.
+    /// to pipe a workload live into a system under test. Throttled workloads are generated on
+    /// one thread, since parallel inserts skip the op writer.
+    pub ops_per_sec: Option<f64>,
+    /// Scale the spec string passed to [`generate_workload_into`] and friends to this many
+    /// operations, see [`WorkloadSpec::scaled_to`].
+    pub total_operations: Option<usize>,
+}
+
+impl GenerateOptions {
+    /// Parses a spec string in `spec_format`, scaled to `total_operations` when set.
+    pub fn parse_spec(&self, workload_spec_string: &str) -> Result<WorkloadSpec> {
+        let workload_spec = self.spec_format.parse(workload_spec_string)?;
+        return Ok(match self.total_operations {
+            Some(total) => workload_spec.scaled_to(total),
+            None => workload_spec,
+        });
+    }
+
+    /// Length of the memory-mapped output file of `workload`, when `mmap` is set and the bytes
+    /// of the workload are known up front: exact, ascii, and without a [`Footer`] or [`Header`].
+    pub fn mmap_len(&self, workload: &WorkloadSpec) -> Option<usize> {
+        // Escaped separators would make the workload longer.
+        let known = self.format == OutputFormat::Ascii
+            && !self.footer
+            && !self.header
+            && self.ascii_format.escaping == FieldEscaping::Reject
+            && workload.bytes_count_is_exact();
+        return (self.mmap && known).then(|| workload.bytes_count());
+    }
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        return Self {
+            format: OutputFormat::Ascii,
+            ascii_format: AsciiFormat::default(),
+            threads: 1,
+            max_ops: None,
+            validate: false,
+            collect_keys: false,
+            range_width_histogram: false,
+            spec_format: SpecFormat::Json,
+            buffer_size: 1024 * 1024,
+            footer: false,
+            mmap: false,
+            reverse: false,
+            header: false,
+            ops_per_sec: None,
+            total_operations: None,
+        };
+    }
+}
+
+/// Same as [`write_operations_with_progress`], with every [`GenerateOptions`]. Returns a summary
+/// of the written workload.
+pub fn write_operations_with_options(
+    writer: &mut impl Write,
+    workload: &WorkloadSpec,
+    options: GenerateOptions,
+    progress: impl FnMut(usize, usize),
+) -> Result<WorkloadStats> {
+    return write_operations_phased(writer, workload, options, None, progress);
+}
+
+/// Same as [`write_operations_with_options`], split into a load and a run workload. The load
+/// phase of every section, its inserts before its first operation that isn't an insert, goes to
+/// `load` and everything else to `run`.
+pub fn write_phases_with_options(
+    load: &mut impl Write,
+    run: &mut impl Write,
+    workload: &WorkloadSpec,
+    options: GenerateOptions,
+    progress: impl FnMut(usize, usize),
+) -> Result<WorkloadStats> {
+    let loading = Cell::new(true);
+    let mut writer = PhaseWriter {
+        load,
+        run,
+        loading: &loading,
+    };
+    return write_operations_phased(&mut writer, workload, options, Some(&loading), progress);
+}
+
+/// Same as [`write_operations_with_options`], split into `shards` by key: every operation goes
+/// to the shard of its key, see [`writer::shard_of`], so all the operations on a key land in the
+/// same shard. Range queries, seeks, and range deletes go to every shard, since their keys are
+/// spread over all of them. Operations without a key go to every shard, or only to
+/// `keyless_shard`. Generated on one thread, since parallel inserts skip the op writer.
+pub fn write_shards_with_options(
+    shards: &mut [impl Write],
+    workload: &WorkloadSpec,
+    options: GenerateOptions,
+    keyless_shard: Option<usize>,
+    progress: impl FnMut(usize, usize),
+) -> Result<WorkloadStats> {
+    if shards.is_empty() || keyless_shard.is_some_and(|shard| shard >= shards.len()) {
+        return Err(WorkloadError::InvalidShards {
+            shards: shards.len(),
+            keyless_shard,
+        });
+    }
+    check_options(workload, &options)?;
+    let options = GenerateOptions {
+        threads: 1,
+        ..options
+    };
+    return match options.format {
+        OutputFormat::Ascii => {
+            let op_writer = AsciiWriter::new(options.ascii_format);
+            write_shards_to(
+                shards,
+                &op_writer,
+                workload,
+                options,
+                keyless_shard,
+                progress,
+            )
+        }
+        OutputFormat::Jsonl => write_shards_to(
+            shards,
+            &JsonlWriter,
+            workload,
+            options,
+            keyless_shard,
+            progress,
+        ),
+        OutputFormat::Msgpack => write_shards_to(
+            shards,
+            &MsgpackWriter,
+            workload,
+            options,
+            keyless_shard,
+            progress,
+        ),
+    };
+}
+
+fn write_shards_to(
+    shards: &mut [impl Write],
+    op_writer: &impl OpWriter,
+    workload: &WorkloadSpec,
+    options: GenerateOptions,
+    keyless_shard: Option<usize>,
+    progress: impl FnMut(usize, usize),
+) -> Result<WorkloadStats> {
+    let shard = AtomicUsize::new(ALL_SHARDS);
+    let op_writer = ShardOpWriter {
+        op_writer,
+        shards: shards.len(),
+        keyless_shard: keyless_shard.unwrap_or(ALL_SHARDS),
+        shard: &shard,
+    };
+    let mut writer = ShardWriter {
+        shards,
+        shard: &shard,
+    };
+    return write_operations_to(&mut writer, &op_writer, workload, options, None, progress);
+}
+
+/// Fails if `workload` can't be generated with `options`, e.g. if the output format can't write its
+/// keys, before generating anything.
+fn check_options(workload: &WorkloadSpec, options: &GenerateOptions) -> Result<()> {
+    if options.reverse
+        && workload
+            .sections
+            .iter()
+            .any(|s| s.warmup_point_queries.is_some_and(|amount| amount > 0))
+    {
+        return Err(WorkloadError::InvalidSpec(
+            "Warmup point queries can't be reversed, it moves them out of their fence.".to_string(),
+        ));
+    }
+    let format = options.format;
+    if format.is_text() && workload.has_binary_keys() {
+        return Err(WorkloadError::BinaryKeys {
+            format: format.name(),
+        });
+    }
+    if format == OutputFormat::Ascii {
+        workload.validate_separators(&options.ascii_format)?;
+    }
+    return Ok(());
+}
+
+fn write_operations_phased(
+    writer: &mut impl Write,
+    workload: &WorkloadSpec,
+    options: GenerateOptions,
+    loading: Option<&Cell<bool>>,
+    progress: impl FnMut(usize, usize),
+) -> Result<WorkloadStats> {
+    check_options(workload, &options)?;
+    return match options.format {
+        OutputFormat::Ascii => {
+            let op_writer = AsciiWriter::new(options.ascii_format);
+            write_operations_to(writer, &op_writer, workload, options, loading, progress)
+        }
+        OutputFormat::Jsonl => {
+            write_operations_to(writer, &JsonlWriter, workload, options, loading, progress)
+        }
+        OutputFormat::Msgpack => {
+            write_operations_to(writer, &MsgpackWriter, workload, options, loading, progress)
+        }
+    };
+}
+
+/// Same as [`write_operations_with_options`], serializing the operations with `op_writer` instead
+/// of the writer of `options.format`, e.g. to write a format of its own. `options.ascii_format`
+/// is only used by [`writer::AsciiWriter`], so it is ignored too.
+pub fn write_operations_with_op_writer(
+    writer: &mut impl Write,
+    op_writer: &impl OpWriter,
+    workload: &WorkloadSpec,
+    options: GenerateOptions,
+    progress: impl FnMut(usize, usize),
+) -> Result<WorkloadStats> {
+    return write_operations_to(writer, op_writer, workload, options, None, progress);
+}
+
+fn write_operations_to(
+    writer: &mut impl Write,
+    op_writer: &impl OpWriter,
+    workload: &WorkloadSpec,
+    options: GenerateOptions,
+    loading: Option<&Cell<bool>>,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<WorkloadStats> {
+    let operation_count = workload.operation_count();
+    let mut progress = Progress {
+        done: 0,
+        total: options
+            .max_ops
+            .map_or(operation_count, |max_ops| max_ops.min(operation_count)),
+        callback: &mut progress,
+        stats: WorkloadStats::default(),
+        collect_keys: options.collect_keys,
+        range_width_histogram: options.range_width_histogram,
+        reverse: options.reverse,
+        loading,
+    };
+    match options.ops_per_sec {
+        Some(ops_per_sec) => {
+            if !(ops_per_sec.is_finite() && ops_per_sec > 0.0) {
+                return Err(WorkloadError::InvalidOpsPerSec(ops_per_sec));
+            }
+            let op_writer = ThrottledOpWriter::new(op_writer, ops_per_sec);
+            write_workload(writer, &op_writer, workload, 1, &mut progress)?;
+        }
+        None => write_workload(writer, op_writer, workload, options.threads, &mut progress)?,
+    }
+    return Ok(progress.stats);
+}
+
+/// Summary of a generated workload.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WorkloadStats {
+    pub inserts: usize,
+    pub updates: usize,
+    pub deletes: usize,
+    pub point_queries: usize,
+    pub empty_point_queries: usize,
+    /// Range queries of either form, with an end key or with a count.
+    pub range_queries: usize,
+    pub range_deletes: usize,
+    pub think_times: usize,
+    pub flushes: usize,
+    /// Iterator seeks, on valid keys or not.
+    pub seek_queries: usize,
+    /// Read-modify-writes, whose point queries and updates are also counted in `point_queries`
+    /// and `updates`.
+    pub read_modify_writes: usize,
+    /// Keys that were valid at some point. Assumes generated keys don't collide, which inserts
+    /// that don't allow duplicate keys guarantee.
+    pub distinct_keys: usize,
+    /// Keys still valid at the end of the workload, i.e. inserted keys minus deleted keys, including
+    /// the keys of range deletes.
+    pub live_keys: usize,
+    /// Number of inserts of every insert key length, which has more than one entry when sections
+    /// or groups insert keys of different lengths.
+    pub key_len_histogram: BTreeMap<usize, usize>,
+    /// Distinct keys that were valid at some point, sorted within every section and in section
+    /// order. Only collected with [`GenerateOptions::collect_keys`].
+    pub keys: Vec<Key>,
+    /// Number of range queries by the number of valid keys strictly between their endpoints,
+    /// which is what `selectivity` targets. Only recorded with
+    /// [`GenerateOptions::range_width_histogram`].
+    pub range_widths: BTreeMap<usize, usize>,
+}
+
+impl WorkloadStats {
+    pub fn operation_count(&self) -> usize {
+        return self.inserts
+            + self.updates
+            + self.deletes
+            + self.point_queries
+            + self.empty_point_queries
+            + self.range_queries
+            + self.range_deletes
+            + self.think_times
+            + self.flushes
+            + self.seek_queries;
+    }
+
+    #[inline]
+    fn record(&mut self, op: OpMarker) {
+        let count = match op {
+            OpMarker::Insert | OpMarker::Overwrite | OpMarker::Reinsert => &mut self.inserts,
+            OpMarker::Update | OpMarker::ReadModifyWrite => &mut self.updates,
+            OpMarker::Delete => &mut self.deletes,
+            OpMarker::PointQuery => &mut self.point_queries,
+            OpMarker::PointQueryMiss | OpMarker::EmptyPointQuery => &mut self.empty_point_queries,
+            OpMarker::RangeQuery | OpMarker::CountRangeQuery => &mut self.range_queries,
+            OpMarker::RangeDelete => &mut self.range_deletes,
+            OpMarker::ThinkTime => &mut self.think_times,
+            OpMarker::Flush => &mut self.flushes,
+            OpMarker::SeekQuery | OpMarker::SeekQueryMiss => &mut self.seek_queries,
+        };
+        *count += 1;
+    }
+
+    /// Records the key length of `n` inserts. Their operations are recorded separately.
+    #[inline]
+    fn record_inserts(&mut self, key_len: usize, n: usize) {
+        *self.key_len_histogram.entry(key_len).or_default() += n;
+    }
+}
+
+/// Number of operations between progress callbacks.
+pub const PROGRESS_INTERVAL: usize = 10_000;
+
+/// Operations written so far. Generation stops once `total` operations are written, which is
+/// less than the operations of the workload when it is truncated.
+struct Progress<'a> {
+    done: usize,
+    total: usize,
+    callback: &'a mut dyn FnMut(usize, usize),
+    stats: WorkloadStats,
+    /// Whether to collect the keys of every section into `stats.keys`.
+    collect_keys: bool,
+    /// Whether to record the width of every range query into `stats.range_widths`.
+    range_width_histogram: bool,
+    /// Whether to reverse the operations of every section, see [`reverse_operations`].
+    reverse: bool,
+    /// Set while a section writes its load phase, when the workload is split into phases.
+    loading: Option<&'a Cell<bool>>,
+}
+
+impl Progress<'_> {
+    #[inline]
+    fn tick(&mut self) {
+        self.done += 1;
+        if self.done.is_multiple_of(PROGRESS_INTERVAL) || self.done == self.total {
+            (self.callback)(self.done, self.total);
+        }
+    }
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        return self.done >= self.total;
+    }
+
+    fn remaining(&self) -> usize {
+        return self.total - self.done;
+    }
+
+    fn set_loading(&self, loading: bool) {
+        if let Some(cell) = self.loading {
+            cell.set(loading);
+        }
+    }
+
+    /// Same as calling [`Progress::tick`] `n` times, with at most one callback.
+    fn advance(&mut self, n: usize) {
+        let before = self.done / PROGRESS_INTERVAL;
+        self.done += n;
+        if self.done / PROGRESS_INTERVAL != before || self.done == self.total {
+            (self.callback)(self.done, self.total);
+        }
+    }
+}
+
+fn write_workload<W: OpWriter>(
+    writer: &mut impl Write,
+    op_writer: &W,
+    workload: &WorkloadSpec,
+    threads: usize,
+    progress: &mut Progress,
+) -> Result<()> {
+    workload.validate()?;
+    let mut rng = match workload.seed {
+        Some(seed) => workload.rng.seed_from_u64(seed),
+        None => workload.rng.from_os_rng(),
+    };
+
+    for section in workload.sections() {
+        if progress.is_done() {
+            break;
+        }
+        let section = section.as_ref();
+        // Both phases of a split workload route their operations of the section.
+        let column_family = section.column_family.as_deref();
+        if let Some(column_family) = column_family.filter(|_| progress.loading.is_some()) {
+            progress.set_loading(false);
+            op_writer.write_column_family(writer, column_family)?;
+        }
+        progress.set_loading(true);
+        if let Some(column_family) = column_family {
+            op_writer.write_column_family(writer, column_family)?;
+        }
+        // Every section gets its own rng, so seeding one section doesn't change the others.
+        let derived_seed: u64 = rng.random();
+        let mut rng = workload
+            .rng
+            .seed_from_u64(section.seed.unwrap_or(derived_seed));
+        let capacity = match section.needs_valid_keys() || progress.collect_keys {
+            true => section.key_set_capacity(),
+            false => 0,
+        };
+        match KeySetKind::select(section) {
+            KeySetKind::Vec => {
+                let keys = keyset::VecKeySet::new(capacity);
+                write_section(
+                    writer, op_writer, section, keys, &mut rng, threads, progress,
+                )?
+            }
+            KeySetKind::VecHashSet => {
+                let keys = keyset::VecHashSetKeySet::new(capacity);
+                write_section(
+                    writer, op_writer, section, keys, &mut rng, threads, progress,
+                )?
+            }
+            KeySetKind::VecBloomFilter => {
+                let fp_rate = section
+                    .bloom_filter_fp_rate
+                    .map_or(keyset::VecBloomFilterKeySet::DEFAULT_FP_RATE, |fp_rate| {
+                        fp_rate as f32
+                    });
+                let keys = keyset::VecBloomFilterKeySet::new_with_rate(capacity, fp_rate);
+                write_section(
+                    writer, op_writer, section, keys, &mut rng, threads, progress,
+                )?
+            }
+            KeySetKind::VecCuckooFilter => {
+                let keys = keyset::VecCuckooFilterKeySet::new(capacity);
+                write_section(
+                    writer, op_writer, section, keys, &mut rng, threads, progress,
+                )?
+            }
+            KeySetKind::Roaring => {
+                let keys = keyset::RoaringKeySet::new(capacity);
+                write_section(
+                    writer, op_writer, section, keys, &mut rng, threads, progress,
+                )?
+            }
+            KeySetKind::OrderStat => {
+                let keys = keyset::OrderStatKeySet::new(capacity);
+                write_section(
+                    writer, op_writer, section, keys, &mut rng, threads, progress,
+                )?
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+fn write_section<K: KeySet, W: OpWriter>(
+    writer: &mut impl Write,
+    op_writer: &W,
+    section: &WorkloadSpecSection,
+    keys_valid: K,
+    rng: &mut WorkloadRng,
+    threads: usize,
+    progress: &mut Progress,
+) -> Result<()> {
+    let deletes_before = progress.stats.deletes;
+    let keys_before = progress.stats.keys.len();
+
+    let buffered = section.group_by_key || progress.reverse;
+    let (keys_valid, range_deleted_keys, untracked_keys, reinserted_keys) = if buffered {
+        let ops = Mutex::new(Vec::new());
+        let buffer = OperationWriter {
+            emit: |op| {
+                ops.lock()
+                    .expect("no panic while holding the lock")
+                    .push(op);
+                return Ok(());
+            },
+        };
+        // Parallel inserts bypass the op writer, so the section is generated on one thread.
+        let sink = &mut io::sink();
+        let result = write_section_ops(sink, &buffer, section, keys_valid, rng, 1, progress)?;
+        let mut ops = ops.into_inner().expect("no panic while holding the lock");
+        if section.group_by_key {
+            ops = group_by_key(ops);
+        }
+        if progress.reverse {
+            ops = reverse_operations(ops);
+        }
+        // The buffered operations ended the load phase, it restarts for their reordered writes.
+        progress.set_loading(true);
+        for op in ops {
+            if !matches!(op, Operation::Insert { .. }) {
+                progress.set_loading(false);
+            }
+            op.write(op_writer, writer)?;
+        }
+        result
+    } else {
+        write_section_ops(
+            writer, op_writer, section, keys_valid, rng, threads, progress,
+        )?
+    };
+
+    // Every key deleted in this section, by deletes or range deletes, was valid at some point, so
+    // it is distinct from the keys that are still valid, as long as generated keys don't collide.
+    // Untracked keys are still live, just not in the key set. Reinserted keys were counted when
+    // they were deleted.
+    let section_deletes = progress.stats.deletes - deletes_before;
+    progress.stats.live_keys += keys_valid.len() + untracked_keys;
+    progress.stats.distinct_keys +=
+        keys_valid.len() + section_deletes + range_deleted_keys + untracked_keys - reinserted_keys;
+
+    if progress.collect_keys {
+        // The deleted and evicted keys were collected as they were removed, the valid keys are
+        // added here.
+        let mut section_keys = progress.stats.keys.split_off(keys_before);
+        section_keys.extend((0..keys_valid.len()).filter_map(|idx| keys_valid.get(idx).cloned()));
+        section_keys.sort();
+        section_keys.dedup();
+        progress.stats.keys.append(&mut section_keys);
+    }
+
+    return Ok(());
+}
+
+/// Reorders operations so the operations on every key are contiguous, keys in the order they are
+/// first used and the operations on a key in their original order. Keyless think times stay after
+/// the operation they follow.
+fn group_by_key(ops: Vec<Operation>) -> Vec<Operation> {
+    let mut first_used: HashMap<Key, usize> = HashMap::new();
+    let mut groups: Vec<usize> = Vec::with_capacity(ops.len());
+    for op in &ops {
+        let group = match op.key() {
+            Some(key) => {
+                let next = first_used.len();
+                *first_used.entry(key.clone()).or_insert(next)
+            }
+            None => groups.last().copied().unwrap_or(0),
+        };
+        groups.push(group);
+    }
+    let mut ops: Vec<(usize, Operation)> = groups.into_iter().zip(ops).collect();
+    // The sort is stable, so the operations on a key keep their order.
+    ops.sort_by_key(|(group, _)| *group);
+    return ops.into_iter().map(|(_, op)| op).collect();
+}
+
+/// Reverses the operations in runs that use every key at most once, so the operations on a key
+/// keep their order, e.g. no operation moves before the insert of its key or after its delete.
+/// Range deletes remove the keys between their endpoints, so they keep their place.
+fn reverse_operations(ops: Vec<Operation>) -> Vec<Operation> {
+    let mut reversed: Vec<Operation> = Vec::with_capacity(ops.len());
+    let mut run: Vec<Operation> = Vec::new();
+    let mut run_keys: HashSet<Key> = HashSet::new();
+    for op in ops {
+        let keys: Vec<Key> = match &op {
+            Operation::RangeQuery { lo, hi } => vec![lo.clone(), hi.clone()],
+            Operation::RangeDelete { .. } => {
+                reversed.extend(run.drain(..).rev());
+                run_keys.clear();
+                reversed.push(op);
+                continue;
+            }
+            op => op.key().into_iter().cloned().collect(),
+        };
+        if keys.iter().any(|key| run_keys.contains(key)) {
+            reversed.extend(run.drain(..).rev());
+            run_keys.clear();
+        }
+        run_keys.extend(keys);
+        run.push(op);
+    }
+    reversed.extend(run.into_iter().rev());
+    return reversed;
+}
+
+/// Generates the operations of a section, returning its valid keys, the number of keys removed by
+/// range deletes, the number of untracked keys, and the number of reinserted deleted keys.
+fn write_section_ops<K: KeySet, W: OpWriter>(
+    writer: &mut impl Write,
+    op_writer: &W,
+    section: &WorkloadSpecSection,
+    keys_valid: K,
+    rng: &mut WorkloadRng,
+    threads: usize,
+    progress: &mut Progress,
+) -> Result<(K, usize, usize, usize)> {
+    let mut section_writer = SectionWriter::new(op_writer, section, keys_valid, progress);
+    section_writer.write(writer, rng, threads, progress)?;
+    if progress.collect_keys {
+        // The deleted keys that weren't reinserted are still held back.
+        if let Some(deleted_keys) = section_writer.deleted_keys.take() {
+            progress.stats.keys.extend(deleted_keys);
+        }
+    }
+    let SectionWriter {
+        keys_valid,
+        range_deleted_keys,
+        untracked_keys,
+        reinserted_keys,
+        ..
+    } = section_writer;
+    return Ok((
+        keys_valid,
+        range_deleted_keys,
+        untracked_keys,
+        reinserted_keys,
+    ));
+}
+
+/// The markers of every operation of a group, in the order they are written when the group runs
+/// on its own. The inserts of `bulk_load` sections were already written by the load phase.
+fn group_markers(section: &WorkloadSpecSection, group: &WorkloadSpecGroup) -> Vec<OpMarker> {
+    let mut markers: Vec<OpMarker> = Vec::with_capacity(group.operation_count());
+    if let Some(is) = group.inserts.filter(|is| !section.bulk_loads(is)) {
+        markers.extend((0..is.amount).map(|i| {
+            if is.is_overwrite(i) {
+                OpMarker::Overwrite
+            } else if is.is_reinsert(i) {
+                OpMarker::Reinsert
+            } else {
+                OpMarker::Insert
+            }
+        }));
+    }
+    if let Some(us) = group.updates {
+        markers.append(&mut vec![OpMarker::Update; us.amount]);
+    }
+    if let Some(ds) = group.deletes {
+        markers.append(&mut vec![OpMarker::Delete; ds.amount]);
+    }
+    if let Some(rds) = group.range_deletes {
+        markers.append(&mut vec![OpMarker::RangeDelete; rds.amount]);
+    }
+    if let Some(pqs) = group.point_queries {
+        markers.extend((0..pqs.amount).map(|i| match pqs.is_miss(i) {
+            true => OpMarker::PointQueryMiss,
+            false => OpMarker::PointQuery,
+        }));
+    }
+    if let Some(epqs) = group.empty_point_queries {
+        markers.append(&mut vec![OpMarker::EmptyPointQuery; epqs.amount]);
+    }
+    if let Some(rqs) = group.range_queries {
+        markers.append(&mut vec![OpMarker::RangeQuery; rqs.amount]);
+    }
+    if let Some(crqs) = group.count_range_queries {
+        markers.append(&mut vec![OpMarker::CountRangeQuery; crqs.amount]);
+    }
+    if let Some(ts) = group.think_times {
+        markers.append(&mut vec![OpMarker::ThinkTime; ts.amount]);
+    }
+    if let Some(rmws) = group.read_modify_writes {
+        markers.append(&mut vec![OpMarker::ReadModifyWrite; rmws.amount]);
+    }
+    if let Some(sks) = group.seek_queries {
+        markers.extend((0..sks.amount).map(|i| match sks.is_miss(i) {
+            true => OpMarker::SeekQueryMiss,
+            false => OpMarker::SeekQuery,
+        }));
+    }
+    if let Some(fs) = group.flushes.filter(|fs| fs.amount > 0) {
+        // The i-th flush follows the first i/amount of the other operations.
+        let ops = markers.len();
+        let mut spread = Vec::with_capacity(ops + fs.amount);
+        let mut start = 0;
+        for i in 1..=fs.amount {
+            let end = i * ops / fs.amount;
+            spread.extend_from_slice(&markers[start..end]);
+            spread.push(OpMarker::Flush);
+            start = end;
+        }
+        markers = spread;
+    }
+    return markers;
+}
+
+/// Writes the operations of a section, tracking its valid keys.
+struct SectionWriter<'a, K: KeySet, W: OpWriter> {
+    op_writer: &'a W,
+    section: &'a WorkloadSpecSection,
+    key_gen: Box<dyn KeyGen>,
+    /// Generates the keys of new inserts with `deterministic_keys`.
+    deterministic_key_gen: Option<DeterministicKeyGen>,
+    insert_key_len: usize,
+    /// Bytes in front of every generated key, see [`WorkloadSpecSection::key_prefix_len`].
+    key_prefix_len: usize,
+    keys_valid: K,
+    /// Number of keys removed by range deletes.
+    range_deleted_keys: usize,
+    /// Number of live keys that aren't tracked, evicted past `max_tracked_keys` or never tracked
+    /// because the section doesn't need them.
+    untracked_keys: usize,
+    /// Whether new keys are tracked, see [`WorkloadSpecSection::needs_valid_keys`].
+    track_keys: bool,
+    /// The tracked keys in the order they became valid, for evicting the oldest keys. Deleted
+    /// keys are skipped once they come up, or dropped once they fill half of it.
+    insertion_order: VecDeque<Key>,
+    /// The remaining new keys of every group with sorted inserts, in descending order, generated
+    /// at its first insert.
+    sorted_inserts: Vec<Option<Vec<Key>>>,
+    /// Position of the operations that pick keys sequentially.
+    key_cursor: KeyCursor,
+    /// The deleted keys that weren't reinserted yet, only tracked when inserts reinsert them, and
+    /// at most `max_tracked_keys` of them.
+    deleted_keys: Option<Vec<Key>>,
+    /// Number of deleted keys that were reinserted.
+    reinserted_keys: usize,
+    /// Whether the warmup point queries are still to be written when the load phase ends.
+    warmup_pending: bool,
+}
+
+impl<'a, K: KeySet, W: OpWriter> SectionWriter<'a, K, W> {
+    fn new(
+        op_writer: &'a W,
+        section: &'a WorkloadSpecSection,
+        keys_valid: K,
+        progress: &Progress,
+    ) -> Self {
+        return Self {
+            op_writer,
+            section,
+            key_gen: section.key_space.key_gen(),
+            deterministic_key_gen: section.deterministic_keys.then(|| {
+                DeterministicKeyGen::new(section.key_space.key_gen(), section.seed.unwrap_or(0))
+            }),
+            insert_key_len: section.insert_key_len(),
+            key_prefix_len: section.key_prefix_len(),
+            keys_valid,
+            range_deleted_keys: 0,
+            untracked_keys: 0,
+            track_keys: section.needs_valid_keys() || progress.collect_keys,
+            insertion_order: VecDeque::new(),
+            sorted_inserts: vec![None; section.groups.len()],
+            key_cursor: KeyCursor::default(),
+            deleted_keys: section
+                .groups
+                .iter()
+                .any(|group| group.inserts.is_some_and(|is| is.reinsert_ratio > 0.0))
+                .then(Vec::new),
+            reinserted_keys: 0,
+            warmup_pending: section
+                .warmup_point_queries
+                .is_some_and(|amount| amount > 0),
+        };
+    }
+
+    /// Writes the operations of every group, one group after another or interleaved.
+    fn write(
+        &mut self,
+        writer: &mut impl Write,
+        rng: &mut WorkloadRng,
+        threads: usize,
+        progress: &mut Progress,
+    ) -> Result<()> {
+        let section = self.section;
+        if section.bulk_load {
+            self.write_bulk_load(writer, rng, progress)?;
+            // The bulk load is the whole load phase, inserts left to the groups are in the run.
+            progress.set_loading(false);
+            if self.warmup_pending && !self.keys_valid.is_empty() {
+                self.write_warmup(writer, rng, progress)?;
+            }
+        }
+        if section.interleave_groups {
+            self.write_interleaved_groups(writer, rng, progress)?;
+        } else {
+            self.write_groups(writer, rng, threads, progress)?;
+        }
+        // A section of only inserts is all load, so its warmup comes last.
+        if self.warmup_pending {
+            progress.set_loading(false);
+            self.write_warmup(writer, rng, progress)?;
+        }
+        return Ok(());
+    }
+
+    /// Writes the inserts of all groups sorted by key, tracking their keys before the groups run.
+    /// Inserts `after_bulk_load` are left to their groups.
+    fn write_bulk_load(
+        &mut self,
+        writer: &mut impl Write,
+        rng: &mut WorkloadRng,
+        progress: &mut Progress,
+    ) -> Result<()> {
+        let section = self.section;
+        let mut loaded: Vec<(Key, Key)> = Vec::with_capacity(section.insert_count());
+        'groups: for (group_idx, group) in section.groups.iter().enumerate() {
+            let Some(is) = group.inserts.filter(|is| section.bulk_loads(is)) else {
+                continue;
+            };
+            for i in 0..is.amount {
+                if progress.is_done() {
+                    break 'groups;
+                }
+                let (key, marker) = if is.is_overwrite(i) {
+                    let idx = section.key_distribution.sample_index(
+                        rng,
+                        &mut self.keys_valid,
+                        &mut self.key_cursor,
+                    );
+                    let key = self.keys_valid.get(idx).expect("index to be in range");
+                    (key.clone(), OpMarker::Overwrite)
+                } else {
+                    let key = self.gen_insert_key(group_idx, rng)?;
+                    self.track_key(key.clone(), rng, progress);
+                    (key, OpMarker::Insert)
+                };
+                progress
+                    .stats
+                    .record_inserts(key.len() - self.key_prefix_len, 1);
+                loaded.push((key, gen_value(rng, is.val_len, is.val_pattern)));
+                progress.stats.record(marker);
+                progress.tick();
+            }
+        }
+        // The sort is stable, so overwrites stay after the insert of their key.
+        loaded.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (key, val) in &loaded {
+            self.op_writer.write_insert(writer, key, val)?;
+        }
+        return Ok(());
+    }
+
+    /// Generates the key of a new insert of a group, popping the next key of sorted inserts.
+    fn gen_insert_key(&mut self, group_idx: usize, rng: &mut WorkloadRng) -> Result<Key> {
+        let section = self.section;
+        let is = section.groups[group_idx]
+            .inserts
+            .expect("Insert marker can only appear when inserts is not None");
+        let key_gen: &mut dyn KeyGen = match &mut self.deterministic_key_gen {
+            Some(key_gen) => key_gen,
+            None => &mut *self.key_gen,
+        };
+        if !is.sorted {
+            return gen_insert_key(rng, key_gen, section, &self.keys_valid, is);
+        }
+        let sorted_keys = match &mut self.sorted_inserts[group_idx] {
+            Some(sorted_keys) => sorted_keys,
+            empty => empty.insert(gen_sorted_keys(
+                rng,
+                key_gen,
+                section,
+                &self.keys_valid,
+                is,
+            )?),
+        };
+        let mut key = sorted_keys.pop().expect("a sorted key for every new key");
+        // Interleaved groups may have inserted the key since it was generated. A replacement takes
+        // its place among the remaining keys, so they stay in order unless it is smaller than
+        // every one of them.
+        while !is.allow_duplicate_keys && self.keys_valid.contains(&key) {
+            let replacement = gen_insert_key(rng, key_gen, section, &self.keys_valid, is)?;
+            if let Err(idx) = sorted_keys.binary_search_by(|k| replacement.cmp(k)) {
+                sorted_keys.insert(idx, replacement);
+                key = sorted_keys.pop().expect("the replacement key");
+            }
+        }
+        return Ok(key);
+    }
+
+    /// Writes the warmup point queries of the section between their fence comments, picking keys
+    /// like the section's point queries.
+    fn write_warmup(
+        &mut self,
+        writer: &mut impl Write,
+        rng: &mut WorkloadRng,
+        progress: &mut Progress,
+    ) -> Result<()> {
+        self.warmup_pending = false;
+        let amount = self.section.warmup_point_queries.unwrap_or(0);
+        if progress.is_done() {
+            return Ok(());
+        }
+        if self.keys_valid.is_empty() {
+            return Err(WorkloadError::NoValidKeys(
+                "Warmup point queries need keys inserted before them.".to_string(),
+            ));
+        }
+        self.op_writer.write_comment(writer, "warmup-start")?;
+        for _ in 0..amount {
+            if progress.is_done() {
+                break;
+            }
+            let idx = self.section.key_distribution.sample_index(
+                rng,
+                &mut self.keys_valid,
+                &mut self.key_cursor,
+            );
+            let key = self.keys_valid.get(idx).expect("index to be in range");
+            self.op_writer.write_point_query(writer, key)?;
+            progress.stats.record(OpMarker::PointQuery);
+            progress.tick();
+        }
+        self.op_writer.write_comment(writer, "warmup-end")?;
+        return Ok(());
+    }
+
+    /// Writes the groups one after another.
+    fn write_groups(
+        &mut self,
+        writer: &mut impl Write,
+        rng: &mut WorkloadRng,
+        threads: usize,
+        progress: &mut Progress,
+    ) -> Result<()> {
+        let section = self.section;
+        'groups: for (group_idx, group) in section.groups.iter().enumerate() {
+            if progress.is_done() {
+                break;
+            }
+            if let Some(is) = group.inserts.filter(|is| {
+                threads > 1
+                    && !section.bulk_loads(is)
+                    && group.is_insert_only()
+                    && is.allow_duplicate_keys
+                    && is.overwrite_ratio == 0.0
+                    && is.reinsert_ratio == 0.0
+                    && !is.sorted
+                    && !section.deterministic_keys
+                    && is.amount >= MIN_PARALLEL_INSERTS
+            }) {
+                let seeds: Vec<u64> = (0..threads).map(|_| rng.random()).collect();
+                let is = Inserts {
+                    amount: is.amount.min(progress.remaining()),
+                    ..is
+                };
+                for (buf, keys) in
+                    gen_insert_chunks(self.op_writer, section, is, rng.algorithm(), &seeds)?
+                {
+                    writer.write_all(&buf)?;
+                    progress.stats.inserts += keys.len();
+                    progress.advance(keys.len());
+                    for key in keys {
+                        progress
+                            .stats
+                            .record_inserts(key.len() - self.key_prefix_len, 1);
+                        self.track_key(key, rng, progress);
+                    }
+                }
+                continue;
+            }
+
+            // A group must have at least 1 valid key before any other operation can occur. Inserts
+            // come first, so the first insert provides it.
+            // TODO: handle empty point queries
+            if (group.updates.is_some_and(|us| us.existing)
+                || group.deletes.is_some()
+                || group.point_queries.is_some()
+                || group.range_queries.is_some()
+                || group.count_range_queries.is_some()
+                || group.range_deletes.is_some()
+                || group.read_modify_writes.is_some()
+                || group.seek_queries.is_some_and(|sks| sks.hit_ratio > 0.0)
+                || group.empty_point_queries.is_some_and(|epqs| epqs.near_miss))
+                && group.inserts.is_none()
+                && self.keys_valid.is_empty()
+            {
+                return Err(WorkloadError::NoValidKeys(
+                    "Group must have existing valid keys or have insert operations.".to_string(),
+                ));
+            }
+
+            for marker in group_markers(section, group) {
+                if progress.is_done() {
+                    break 'groups;
+                }
+                self.write_op(writer, group_idx, marker, rng, progress)?;
+            }
+        }
+        return Ok(());
+    }
+
+    /// Writes the operations of all groups randomly interleaved, picking the group of every
+    /// operation weighted by its remaining operations. Each group keeps the order of its own
+    /// operations. An operation that needs a valid key while there is none yields to a group whose
+    /// next operation doesn't, so the first such operation still follows an insert. If there is no
+    /// such group, the closest operation that doesn't need a valid key comes first in its group.
+    fn write_interleaved_groups(
+        &mut self,
+        writer: &mut impl Write,
+        rng: &mut WorkloadRng,
+        progress: &mut Progress,
+    ) -> Result<()> {
+        let section = self.section;
+        // Every group with its markers and the index of its next marker.
+        let mut queues: Vec<(&WorkloadSpecGroup, Vec<OpMarker>, usize)> = section
+            .groups
+            .iter()
+            .map(|group| (group, group_markers(section, group), 0))
+            .collect();
+        // Read-modify-writes are one marker, but two operations.
+        let mut remaining: usize = queues.iter().map(|(_, markers, _)| markers.len()).sum();
+
+        while remaining > 0 && !progress.is_done() {
+            let mut pick = rng.random_range(0..remaining);
+            let mut idx = queues
+                .iter()
+                .position(|(_, markers, next)| {
+                    let left = markers.len() - next;
+                    if pick < left {
+                        return true;
+                    }
+                    pick -= left;
+                    return false;
+                })
+                .expect("remaining operations to be in a group");
+            let (group, markers, next) = &queues[idx];
+            if self.keys_valid.is_empty() && markers[*next].needs_valid_key(group) {
+                let yields_to =
+                    |(group, markers, next): &(&WorkloadSpecGroup, Vec<OpMarker>, usize)| {
+                        return markers[*next..]
+                            .iter()
+                            .position(|marker| !marker.needs_valid_key(group));
+                    };
+                // Prefer a group whose next operation doesn't need a valid key. Otherwise a group
+                // brings its next such operation forward, e.g. an insert behind an overwrite of a
+                // key that another group deleted.
+                let (yield_idx, offset) = queues
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, queue)| Some((i, yields_to(queue)?)))
+                    .min_by_key(|&(_, offset)| offset)
+                    .ok_or_else(|| {
+                        WorkloadError::NoValidKeys(
+                            "Interleaved groups ran out of valid keys.".to_string(),
+                        )
+                    })?;
+                idx = yield_idx;
+                let (_, markers, next) = &mut queues[idx];
+                markers[*next..=*next + offset].rotate_right(1);
+            }
+
+            let (_, markers, next) = &mut queues[idx];
+            let marker = markers[*next];
+            *next += 1;
+            remaining -= 1;
+            self.write_op(writer, idx, marker, rng, progress)?;
+        }
+        return Ok(());
+    }
+
+    fn write_op(
+        &mut self,
+        writer: &mut impl Write,
+        group_idx: usize,
+        marker: OpMarker,
+        rng: &mut WorkloadRng,
+        progress: &mut Progress,
+    ) -> Result<()> {
+        if !matches!(
+            marker,
+            OpMarker::Insert | OpMarker::Overwrite | OpMarker::Reinsert
+        ) {
+            progress.set_loading(false);
+            if self.warmup_pending {
+                self.write_warmup(writer, rng, progress)?;
+            }
+        }
+        let section = self.section;
+        let group = &section.groups[group_idx];
+        let op_writer = self.op_writer;
+        let keys_valid = &mut self.keys_valid;
+        let key_gen = &mut *self.key_gen;
+        let key_cursor = &mut self.key_cursor;
+        match marker {
+            OpMarker::Insert => {
+                let is = group
+                    .inserts
+                    .expect("Insert marker can only appear when inserts is not None");
+                let key = self.gen_insert_key(group_idx, rng)?;
+                let val = gen_value(rng, is.val_len, is.val_pattern);
+                op_writer.write_insert(writer, &key, &val)?;
+                progress
+                    .stats
+                    .record_inserts(key.len() - self.key_prefix_len, 1);
+                self.track_key(key, rng, progress);
+            }
+            OpMarker::Reinsert => {
+                let is = group
+                    .inserts
+                    .expect("Reinsert marker can only appear when inserts is not None");
+                let deleted_keys = self.deleted_keys.as_mut().expect("deleted keys tracked");
+                let key = if deleted_keys.is_empty() {
+                    self.gen_insert_key(group_idx, rng)?
+                } else {
+                    self.reinserted_keys += 1;
+                    deleted_keys.swap_remove(rng.random_range(0..deleted_keys.len()))
+                };
+                let val = gen_value(rng, is.val_len, is.val_pattern);
+                op_writer.write_insert(writer, &key, &val)?;
+                progress
+                    .stats
+                    .record_inserts(key.len() - self.key_prefix_len, 1);
+                self.track_key(key, rng, progress);
+            }
+            OpMarker::Overwrite => {
+                let is = group
+                    .inserts
+                    .expect("Overwrite marker can only appear when inserts is not None");
+                let idx = section
+                    .key_distribution
+                    .sample_index(rng, keys_valid, key_cursor);
+                let val = gen_value(rng, is.val_len, is.val_pattern);
+                let key = keys_valid.get(idx).expect("index to be in range");
+                op_writer.write_insert(writer, key, &val)?;
+                progress
+                    .stats
+                    .record_inserts(key.len() - self.key_prefix_len, 1);
+            }
+            OpMarker::Update => {
+                let us = group
+                    .updates
+                    .expect("Update marker can only appear when updates is not None");
+                let val = gen_value(rng, us.val_len, us.val_pattern);
+                if us.existing {
+                    let idx = us
+                        .key_distribution
+                        .unwrap_or(section.key_distribution)
+                        .sample_index(rng, keys_valid, key_cursor);
+                    let key = keys_valid.get(idx).expect("index to be in range");
+                    op_writer.write_update(writer, key, &val)?;
+                } else {
+                    // A blind update creates the key if it doesn't exist.
+                    let key = gen_section_key(rng, key_gen, section, self.insert_key_len);
+                    op_writer.write_update(writer, &key, &val)?;
+                    self.track_key(key, rng, progress);
+                }
+            }
+            OpMarker::Delete => {
+                let idx = section
+                    .key_distribution
+                    .sample_index(rng, keys_valid, key_cursor);
+                let key = keys_valid.remove(idx);
+
+                op_writer.write_delete(writer, &key)?;
+                self.record_deleted_key(key, progress);
+            }
+            OpMarker::RangeDelete => {
+                let rds = group
+                    .range_deletes
+                    .expect("RangeDelete marker can only appear when range_deletes is not None");
+
+                keys_valid.sort();
+                let num_items = spec::range_delete_len(rds.selectivity, keys_valid.len());
+                let start_idx = rng.random_range(0..keys_valid.len() - num_items);
+                let start = keys_valid.get(start_idx).expect("index to be in range");
+                let end = keys_valid
+                    .get(start_idx + num_items)
+                    .expect("index to be in range");
+                op_writer.write_range_delete(writer, start, end)?;
+
+                // Removing from the back keeps the indices of the rest of the range valid,
+                // even for key sets that swap the last key into the removed slot.
+                let removed: Vec<Key> = (start_idx..start_idx + num_items)
+                    .rev()
+                    .map(|idx| keys_valid.remove(idx))
+                    .collect();
+                for key in removed {
+                    self.record_deleted_key(key, progress);
+                }
+                self.range_deleted_keys += num_items;
+            }
+            OpMarker::PointQuery => {
+                let pqs = group
+                    .point_queries
+                    .expect("PointQuery marker can only appear when point_queries is not None");
+                let idx = pqs
+                    .key_distribution
+                    .unwrap_or(section.key_distribution)
+                    .sample_index(rng, keys_valid, key_cursor);
+                let key = keys_valid.get(idx).unwrap();
+                op_writer.write_point_query(writer, key)?
+            }
+            OpMarker::PointQueryMiss => {
+                let key = gen_absent_key(
+                    rng,
+                    key_gen,
+                    section,
+                    &*keys_valid,
+                    self.insert_key_len,
+                    MAX_ABSENT_KEY_ATTEMPTS,
+                    "use a longer key_len for inserts or a higher hit_ratio for point queries",
+                )?;
+                op_writer.write_point_query(writer, &key)?
+            }
+            OpMarker::EmptyPointQuery => {
+                let epq = group.empty_point_queries.expect(
+                    "EmptyPointQuery marker can only appear when point_queries is not None",
+                );
+                let max_attempts = epq.max_attempts.unwrap_or(MAX_ABSENT_KEY_ATTEMPTS);
+                let key = if epq.near_miss {
+                    gen_near_miss_key(
+                        rng,
+                        key_gen,
+                        &*keys_valid,
+                        self.key_prefix_len,
+                        max_attempts,
+                    )?
+                } else {
+                    gen_absent_key(
+                        rng,
+                        key_gen,
+                        section,
+                        &*keys_valid,
+                        epq.key_len,
+                        max_attempts,
+                        "use a longer key_len for empty point queries",
+                    )?
+                };
+                op_writer.write_point_query(writer, &key)?
+            }
+            OpMarker::RangeQuery => {
+                let rs = group
+                    .range_queries
+                    .expect("RangeQuery marker can only appear when range_queries is not None");
+
+                keys_valid.sort();
+                let (start_idx, end_idx) = range_query_range(rng, &*keys_valid, &rs)?;
+                if progress.range_width_histogram {
+                    let width = (end_idx - start_idx).saturating_sub(1);
+                    *progress.stats.range_widths.entry(width).or_default() += 1;
+                }
+                let key1 = &keys_valid.get(start_idx).expect("index to be in range");
+                let key2 = &keys_valid.get(end_idx).expect("index to be in range");
+
+                if rs.reverse {
+                    op_writer.write_range_query(writer, key2, key1)?
+                } else {
+                    op_writer.write_range_query(writer, key1, key2)?
+                }
+            }
+            OpMarker::CountRangeQuery => {
+                let crs = group.count_range_queries.expect(
+                    "CountRangeQuery marker can only appear when count_range_queries is not None",
+                );
+
+                keys_valid.sort();
+                // Clamp the start near the tail so `count` keys exist from the start key on.
+                let count = crs.count.min(keys_valid.len());
+                let start_idx = rng.random_range(0..=keys_valid.len() - count);
+                let key = keys_valid.get(start_idx).expect("index to be in range");
+                op_writer.write_count_range_query(writer, key, count)?
+            }
+            OpMarker::ThinkTime => {
+                let ts = group
+                    .think_times
+                    .expect("ThinkTime marker can only appear when think_times is not None");
+                op_writer.write_think_time(writer, ts.micros)?
+            }
+            OpMarker::Flush => op_writer.write_flush(writer)?,
+            OpMarker::ReadModifyWrite => {
+                let rmws = group.read_modify_writes.expect(
+                    "ReadModifyWrite marker can only appear when read_modify_writes is not None",
+                );
+                let idx = section
+                    .key_distribution
+                    .sample_index(rng, keys_valid, key_cursor);
+                let key = keys_valid.get(idx).expect("index to be in range");
+                op_writer.write_point_query(writer, key)?;
+                progress.stats.record(OpMarker::PointQuery);
+                progress.tick();
+                // `max_ops` may stop the workload between the read and the write.
+                if progress.is_done() {
+                    return Ok(());
+                }
+                let val = gen_value(rng, rmws.val_len, ValPattern::Random);
+                op_writer.write_update(writer, key, &val)?;
+                progress.stats.read_modify_writes += 1;
+            }
+            OpMarker::SeekQuery => {
+                let sks = group
+                    .seek_queries
+                    .expect("SeekQuery marker can only appear when seek_queries is not None");
+                let idx = sks
+                    .key_distribution
+                    .unwrap_or(section.key_distribution)
+                    .sample_index(rng, keys_valid, key_cursor);
+                let key = keys_valid.get(idx).expect("index to be in range");
+                op_writer.write_seek_query(writer, key)?
+            }
+            OpMarker::SeekQueryMiss => {
+                let key = gen_absent_key(
+                    rng,
+                    key_gen,
+                    section,
+                    &*keys_valid,
+                    self.insert_key_len,
+                    MAX_ABSENT_KEY_ATTEMPTS,
+                    "use a longer key_len for inserts or a higher hit_ratio for seek queries",
+                )?;
+                op_writer.write_seek_query(writer, &key)?
+            }
+        }
+        progress.stats.record(marker);
+        progress.tick();
+        return Ok(());
+    }
+
+    /// Adds a valid key, first evicting keys if the section would track more than
+    /// `max_tracked_keys`.
+    fn track_key(&mut self, key: Key, rng: &mut WorkloadRng, progress: &mut Progress) {
+        if !self.track_keys {
+            self.untracked_keys += 1;
+            return;
+        }
+        if let Some(max_tracked_keys) = self.section.max_tracked_keys {
+            if self.keys_valid.len() >= max_tracked_keys {
+                self.evict_keys(rng, progress);
+            }
+            if self.section.key_eviction == KeyEviction::Oldest {
+                self.insertion_order.push_back(key.clone());
+                if self.insertion_order.len() > 2 * max_tracked_keys {
+                    self.compact_insertion_order();
+                }
+            }
+        }
+        self.keys_valid.push(key);
+    }
+
+    /// Records a key removed by a delete or range delete. While inserts may reinsert deleted keys,
+    /// up to `max_tracked_keys` of them are held back for it and only collected for
+    /// [`GenerateOptions::collect_keys`] once the section ends, so a reinserted key isn't
+    /// collected twice. Keys deleted while the held keys are full aren't reinserted.
+    fn record_deleted_key(&mut self, key: Key, progress: &mut Progress) {
+        if let Some(deleted_keys) = &mut self.deleted_keys {
+            let max_tracked_keys = self.section.max_tracked_keys.unwrap_or(usize::MAX);
+            if deleted_keys.len() < max_tracked_keys {
+                deleted_keys.push(key);
+                return;
+            }
+        }
+        if progress.collect_keys {
+            progress.stats.keys.push(key);
+        }
+    }
+
+    /// Drops the deleted keys from the insertion order, which otherwise only skips them when they
+    /// come up for eviction. Copies of a duplicated key are interchangeable, so the newest ones
+    /// are kept.
+    fn compact_insertion_order(&mut self) {
+        let mut valid: HashMap<&Key, usize> = HashMap::with_capacity(self.keys_valid.len());
+        for idx in 0..self.keys_valid.len() {
+            let key = self.keys_valid.get(idx).expect("index to be in range");
+            *valid.entry(key).or_default() += 1;
+        }
+        let mut kept = VecDeque::with_capacity(self.keys_valid.len());
+        for key in self.insertion_order.drain(..).rev() {
+            if let Some(count) = valid.get_mut(&key).filter(|count| **count > 0) {
+                *count -= 1;
+                kept.push_front(key);
+            }
+        }
+        self.insertion_order = kept;
+    }
+
+    fn evict_keys(&mut self, rng: &mut WorkloadRng, progress: &mut Progress) {
+        let max_tracked_keys = self.section.max_tracked_keys.unwrap_or_default();
+        let evicted = match self.section.key_eviction {
+            KeyEviction::Random => {
+                let idx = rng.random_range(0..self.keys_valid.len());
+                vec![self.keys_valid.remove(idx)]
+            }
+            KeyEviction::Oldest => {
+                let batch_len = KeyEviction::Oldest
+                    .batch_len(max_tracked_keys)
+                    .min(self.keys_valid.len());
+                self.keys_valid.sort();
+                let mut indices: HashSet<usize> = HashSet::with_capacity(batch_len);
+                while indices.len() < batch_len {
+                    let key = self
+                        .insertion_order
+                        .pop_front()
+                        .expect("every valid key to be in the insertion order");
+                    // Copies of a duplicated key are adjacent, so skip the ones already evicted.
+                    let mut idx = self.keys_valid.partition_point(|k| k < &key);
+                    while indices.contains(&idx) {
+                        idx += 1;
+                    }
+                    if self.keys_valid.get(idx) == Some(&key) {
+                        indices.insert(idx);
+                    }
+                }
+                let mut indices: Vec<usize> = indices.into_iter().collect();
+                indices.sort_unstable();
+                // Removing from the back keeps the indices of the rest valid, like range deletes.
+                indices
+                    .into_iter()
+                    .rev()
+                    .map(|idx| self.keys_valid.remove(idx))
+                    .collect()
+            }
+        };
+        self.untracked_keys += evicted.len();
+        if progress.collect_keys {
+            progress.stats.keys.extend(evicted);
+        }
+    }
+}
+
+/// Endpoint indices of a range query over the sorted valid keys. Endpoints closer than `min_gap`
+/// are picked again.
+fn range_query_range(
+    rng: &mut WorkloadRng,
+    keys: &impl KeySet,
+    rs: &RangeQueries,
+) -> Result<(usize, usize)> {
+    let mut pick_range = || {
+        if rs.domain_based {
+            domain_range(rng, keys, rs.selectivity)
+        } else {
+            index_range(rng, keys.len(), rs.selectivity)
+        }
+    };
+    if rs.min_gap <= 0.0 {
+        return Ok(pick_range());
+    }
+    let domain = KeyDomain::new(keys);
+    return std::iter::repeat_with(pick_range)
+        .take(MAX_RANGE_TRIES)
+        .find(|&(start, end)| domain.gap(keys, start, end) >= rs.min_gap)
+        .ok_or_else(|| {
+            WorkloadError::NoValidKeys(format!(
+                "No range query endpoints at least {} of the key domain apart after {} tries, \
+                 lower min_gap or raise the selectivity.",
+                rs.min_gap, MAX_RANGE_TRIES
+            ))
+        });
+}
+
+/// Number of keys strictly between the endpoints of a range query with `selectivity` of `len`
+/// valid keys. At least one, so a tiny selectivity doesn't degenerate into a range of adjacent
+/// keys, before clamping to the keys there are.
+fn range_query_len(selectivity: f32, len: usize) -> usize {
+    // It would be better to use `from` and `try_from` instead of `as` here.
+    // Maybe the `num_traits` crate could help.
+    // https://doc.rust-lang.org/reference/expressions/operator-expr.html#r-expr.as.numeric.float-as-int
+    let num_items = (selectivity * len as f32).round() as usize;
+    return num_items.max(1);
+}
+
+/// Endpoint indices of a range query over `len` sorted valid keys, with `selectivity` of the keys
+/// strictly between the endpoints.
+fn index_range(rng: &mut WorkloadRng, len: usize, selectivity: f32) -> (usize, usize) {
+    // `num_items` keys lie strictly between the endpoints, so with few valid keys the range is
+    // clamped to the keys there are.
+    let num_items = range_query_len(selectivity, len).min(len.saturating_sub(2));
+    let end_offset = (num_items + 1).min(len - 1);
+    let start_idx = rng.random_range(0..len - end_offset);
+    return (start_idx, start_idx + end_offset);
+}
+
+/// Endpoint indices of a range query over the sorted valid keys that spans `selectivity` of the
+/// key domain between the smallest and the largest valid key. The start is picked uniformly in the
+/// domain, and both endpoints snap inwards to valid keys.
+fn domain_range(rng: &mut WorkloadRng, keys: &impl KeySet, selectivity: f32) -> (usize, usize) {
+    let KeyDomain { prefix_len, lo, hi } = KeyDomain::new(keys);
+    let width = (hi - lo) * f64::from(selectivity);
+    // Rounding can put `hi - width` a hair below `lo` for a selectivity of 1.
+    let start = rng.random_range(lo..=(hi - width).max(lo));
+    let end = start + width;
+    let start_idx = keys.partition_point(|key| key_position(key, prefix_len) < start);
+    let end_idx = keys
+        .partition_point(|key| key_position(key, prefix_len) <= end)
+        .saturating_sub(1)
+        .max(start_idx);
+    return (start_idx, end_idx);
+}
+
+/// Times the endpoints of a range query are picked before giving up on `min_gap`.
+const MAX_RANGE_TRIES: usize = 1000;
+
+/// The key domain between the smallest and the largest of the sorted valid keys.
+struct KeyDomain {
+    /// Length of the prefix every valid key shares.
+    prefix_len: usize,
+    lo: f64,
+    hi: f64,
+}
+
+impl KeyDomain {
+    fn new(keys: &impl KeySet) -> Self {
+        let min = keys.get(0).expect("valid keys to not be empty");
+        let max = keys.get(keys.len() - 1).expect("index to be in range");
+        // Every valid key shares the common prefix of the smallest and the largest key.
+        let prefix_len = min
+            .iter()
+            .zip(max.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let (lo, hi) = (key_position(min, prefix_len), key_position(max, prefix_len));
+        return KeyDomain { prefix_len, lo, hi };
+    }
+
+    /// Distance between the keys at `start` and `end`, as a fraction of the domain. 0 if every
+    /// valid key has the same position.
+    fn gap(&self, keys: &impl KeySet, start: usize, end: usize) -> f64 {
+        if self.hi <= self.lo {
+            return 0.0;
+        }
+        let position = |idx| {
+            key_position(
+                keys.get(idx).expect("index to be in range"),
+                self.prefix_len,
+            )
+        };
+        return (position(end) - position(start)) / (self.hi - self.lo);
+    }
+}
+
+/// Position of a key in the key domain, from the 8 bytes after its first `prefix_len` bytes. Keys
+/// that only differ after those bytes share a position.
+fn key_position(key: &Key, prefix_len: usize) -> f64 {
+    let tail = key.get(prefix_len..).unwrap_or_default();
+    let mut bytes = [0u8; 8];
+    let len = tail.len().min(bytes.len());
+    bytes[..len].copy_from_slice(&tail[..len]);
+    return u64::from_be_bytes(bytes) as f64;
+}
+
+/// Insert-only groups with fewer inserts than this are generated serially, since spawning threads
+/// costs more than it saves.
+const MIN_PARALLEL_INSERTS: usize = 100_000;
+
+/// Generates `inserts` in one chunk per seed, each on its own thread with its own rng of
+/// `algorithm`. Returns the written operations and the inserted keys of every chunk, in seed order.
+fn gen_insert_chunks<W: OpWriter>(
+    op_writer: &W,
+    section: &WorkloadSpecSection,
+    inserts: Inserts,
+    algorithm: RngAlgorithm,
+    seeds: &[u64],
+) -> Result<Vec<(Vec<u8>, Vec<Key>)>> {
+    return std::thread::scope(|scope| {
+        let handles: Vec<_> = seeds
+            .iter()
+            .enumerate()
+            .map(|(i, &seed)| {
+                let amount = chunk_amount(inserts.amount, seeds.len(), i);
+                scope.spawn(move || {
+                    gen_insert_chunk(op_writer, section, inserts, algorithm, seed, amount)
+                })
+            })
+            .collect();
+        return handles
+            .into_iter()
+            .map(|handle| handle.join().expect("insert chunk thread to not panic"))
+            .collect();
+    });
+}
+
+/// Number of operations in chunk `i` when splitting `amount` operations in `chunks` chunks.
+#[inline]
+fn chunk_amount(amount: usize, chunks: usize, i: usize) -> usize {
+    return amount / chunks + usize::from(i < amount % chunks);
+}
+
+fn gen_insert_chunk<W: OpWriter>(
+    op_writer: &W,
+    section: &WorkloadSpecSection,
+    inserts: Inserts,
+    algorithm: RngAlgorithm,
+    seed: u64,
+    amount: usize,
+) -> Result<(Vec<u8>, Vec<Key>)> {
+    let mut rng = algorithm.seed_from_u64(seed);
+    let mut key_gen = section.key_space.key_gen();
+    let prefix_len = section.key_prefix_len();
+    let key_len = inserts.mean_key_len();
+    let mut buf = Vec::with_capacity(amount * (prefix_len + key_len + inserts.val_len.mean() + 4));
+    let mut keys = Vec::with_capacity(amount);
+    for _ in 0..amount {
+        let key_len = inserts.sample_key_len(&mut rng);
+        let key = gen_section_key(&mut rng, &mut *key_gen, section, key_len);
+        let val = gen_value(&mut rng, inserts.val_len, inserts.val_pattern);
+        op_writer.write_insert(&mut buf, &key, &val)?;
+        keys.push(key);
+    }
+    return Ok((buf, keys));
+}
+
+/// Takes in a JSON representation of a workload specification and writes the workload to a file.
+pub fn generate_workload(
+    workload_spec_string: &str,
+    output_file: PathBuf,
+    format: OutputFormat,
+) -> Result<()> {
+    let options = GenerateOptions {
+        format,
+        ..GenerateOptions::default()
+    };
+    generate_workload_with_progress(workload_spec_string, output_file, options, |_, _| {})?;
+    return Ok(());
+}
+
+/// Same as [`generate_workload`], with every [`GenerateOptions`] and reporting progress like
+/// [`write_operations_with_progress`]. Returns a summary of the written workload.
+pub fn generate_workload_with_progress(
+    workload_spec_string: &str,
+    output_file: PathBuf,
+    options: GenerateOptions,
+    progress: impl FnMut(usize, usize),
+) -> Result<WorkloadStats> {
+    if options.mmap {
+        // The file is sized from the spec, so an invalid spec has to fail before it is created.
+        if options.validate {
+            validate_workload_spec_value(&options.spec_format.to_json(workload_spec_string)?)?;
+        }
+        let workload_spec = options.parse_spec(workload_spec_string)?;
+        workload_spec.validate()?;
+        if let Some(len) = options.mmap_len(&workload_spec) {
+            // The spec was just validated.
+            let options = GenerateOptions {
+                validate: false,
+                ..options
+            };
+            let result =
+                generate_workload_mmap(workload_spec_string, &output_file, len, options, progress);
+            // A failed workload leaves the file at its full length, mostly zeroes.
+            if result.is_err() {
+                let _ = std::fs::remove_file(&output_file);
+            }
+            return result;
+        }
+    }
+    let mut buf_writer = BufWriter::with_capacity(options.buffer_size, File::create(output_file)?);
+    let stats = generate_workload_into(workload_spec_string, &mut buf_writer, options, progress)?;
+    buf_writer.flush()?;
+
+    return Ok(stats);
+}
+
+/// Writes the workload into `output_file` memory-mapped at `len` bytes, see [`MmapWriter`].
+fn generate_workload_mmap(
+    workload_spec_string: &str,
+    output_file: &Path,
+    len: usize,
+    options: GenerateOptions,
+    progress: impl FnMut(usize, usize),
+) -> Result<WorkloadStats> {
+    let file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(output_file)?;
+    let mut mmap_writer = MmapWriter::create(file, len)?;
+    let stats = generate_workload_into(workload_spec_string, &mut mmap_writer, options, progress)?;
+    mmap_writer.finish()?;
+    return Ok(stats);
+}
+
+/// Same as [`generate_workload_with_progress`], writing to `writer` instead of a file, e.g. to
+/// append several workloads to one file.
+pub fn generate_workload_into(
+    workload_spec_string: &str,
+    writer: &mut impl Write,
+    options: GenerateOptions,
+    progress: impl FnMut(usize, usize),
+) -> Result<WorkloadStats> {
+    if options.validate {
+        validate_workload_spec_value(&options.spec_format.to_json(workload_spec_string)?)?;
+    }
+    let workload_spec = options.parse_spec(workload_spec_string)?;
+    // The header is written before the operations check the format.
+    check_options(&workload_spec, &options)?;
+    let ascii = options.format == OutputFormat::Ascii;
+    let header = (options.header && ascii).then(|| Header::new(&workload_spec, options.max_ops));
+    if options.footer && ascii {
+        let mut crc_writer = CrcWriter {
+            inner: &mut *writer,
+            hasher: crc32fast::Hasher::new(),
+        };
+        // The footer covers the header like any other line before it.
+        if let Some(header) = header {
+            write!(crc_writer, "{header}")?;
+            crc_writer.write_all(&[options.ascii_format.record_sep])?;
+        }
+        let stats =
+            write_operations_with_options(&mut crc_writer, &workload_spec, options, progress)?;
+        let footer = Footer {
+            ops: stats.operation_count(),
+            crc: crc_writer.hasher.finalize(),
+        };
+        write!(writer, "{footer}")?;
+        writer.write_all(&[options.ascii_format.record_sep])?;
+        return Ok(stats);
+    }
+    if let Some(header) = header {
+        write!(writer, "{header}")?;
+        writer.write_all(&[options.ascii_format.record_sep])?;
+    }
+    return write_operations_with_options(writer, &workload_spec, options, progress);
+}
+
+/// Same as [`generate_workload_into`], split into a load and a run workload like
+/// [`write_phases_with_options`]. Never writes a [`Footer`] or [`Header`].
+pub fn generate_phases_into(
+    workload_spec_string: &str,
+    load: &mut impl Write,
+    run: &mut impl Write,
+    options: GenerateOptions,
+    progress: impl FnMut(usize, usize),
+) -> Result<WorkloadStats> {
+    if options.validate {
+        validate_workload_spec_value(&options.spec_format.to_json(workload_spec_string)?)?;
+    }
+    let workload_spec = options.parse_spec(workload_spec_string)?;
+    return write_phases_with_options(load, run, &workload_spec, options, progress);
+}
+
+/// Same as [`generate_workload_into`], split into shards by key like
+/// [`write_shards_with_options`]. Never writes a [`Footer`] or [`Header`].
+pub fn generate_shards_into(
+    workload_spec_string: &str,
+    shards: &mut [impl Write],
+    options: GenerateOptions,
+    keyless_shard: Option<usize>,
+    progress: impl FnMut(usize, usize),
+) -> Result<WorkloadStats> {
+    if options.validate {
+        validate_workload_spec_value(&options.spec_format.to_json(workload_spec_string)?)?;
+    }
+    let workload_spec = options.parse_spec(workload_spec_string)?;
+    return write_shards_with_options(shards, &workload_spec, options, keyless_shard, progress);
+}
+
+/// RocksDB SST output of the inserts of a workload, for pre-populating a database.
+#[cfg(feature = "sst")]
+pub mod sst {
+    use crate::error::Result;
+    use crate::spec::WorkloadSpec;
+    use crate::writer::OpWriter;
+    use crate::{write_workload, Key, Progress};
+    use rocksdb::{Options, SstFileWriter};
+    use std::io::Write;
+    use std::path::Path;
+
+    /// Buffers the inserts of a workload as length prefixed key-value records, skipping every
+    /// other operation.
+    struct InsertCollector;
+
+    impl OpWriter for InsertCollector {
+        fn write_insert(&self, w: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
+            for bytes in [key, val] {
+                w.write_all(&(bytes.len() as u64).to_le_bytes())?;
+                w.write_all(bytes)?;
+            }
+
+            return Ok(());
+        }
+
+        fn write_update(&self, _: &mut impl Write, _: &Key, _: &Key) -> Result<()> {
+            return Ok(());
+        }
+
+        fn write_delete(&self, _: &mut impl Write, _: &Key) -> Result<()> {
+            return Ok(());
+        }
+
+        fn write_point_query(&self, _: &mut impl Write, _: &Key) -> Result<()> {
+            return Ok(());
+        }
+
+        fn write_range_query(&self, _: &mut impl Write, _: &Key, _: &Key) -> Result<()> {
+            return Ok(());
+        }
+
+        fn write_seek_query(&self, _: &mut impl Write, _: &Key) -> Result<()> {
+            return Ok(());
+        }
+
+        fn write_count_range_query(&self, _: &mut impl Write, _: &Key, _: usize) -> Result<()> {
+            return Ok(());
+        }
+
+        fn write_range_delete(&self, _: &mut impl Write, _: &Key, _: &Key) -> Result<()> {
+            return Ok(());
+        }
+
+        fn write_think_time(&self, _: &mut impl Write, _: u64) -> Result<()> {
+            return Ok(());
+        }
+
+        fn write_flush(&self, _: &mut impl Write) -> Result<()> {
+            return Ok(());
+        }
+    }
+
+    /// Splits off the next length prefixed field of the buffered records.
+    fn next_field<'a>(records: &mut &'a [u8]) -> &'a [u8] {
+        let (len, rest) = records.split_at(size_of::<u64>());
+        let len = u64::from_le_bytes(len.try_into().expect("8 byte length prefix")) as usize;
+        let (field, rest) = rest.split_at(len);
+        *records = rest;
+        return field;
+    }
+
+    /// Generates the inserts of a workload and writes them, sorted by key, to an SST file at
+    /// `output_file`. When a key is inserted more than once, the last value wins. Returns the
+    /// number of keys written.
+    pub fn write_sst(workload: &WorkloadSpec, output_file: &Path) -> Result<usize> {
+        let mut records = Vec::new();
+        let mut progress = Progress {
+            done: 0,
+            total: workload.operation_count(),
+            callback: &mut |_, _| {},
+            stats: Default::default(),
+            collect_keys: false,
+            range_width_histogram: false,
+            reverse: false,
+            loading: None,
+        };
+        write_workload(&mut records, &InsertCollector, workload, 1, &mut progress)?;
+
+        let mut inserts = Vec::with_capacity(workload.operation_count());
+        let mut remaining = records.as_slice();
+        while !remaining.is_empty() {
+            let key = next_field(&mut remaining);
+            let val = next_field(&mut remaining);
+            inserts.push((key, val));
+        }
+        // SST keys must be strictly increasing. The sort is stable, so the last of each run of
+        // equal keys is the latest insert.
+        inserts.sort_by(|(a, _), (b, _)| a.cmp(b));
+        inserts.reverse();
+        inserts.dedup_by(|(a, _), (b, _)| a == b);
+        inserts.reverse();
+
+        let opts = Options::default();
+        let mut writer = SstFileWriter::create(&opts);
+        writer.open(output_file)?;
+        for (key, val) in &inserts {
+            writer.put(key, val)?;
+        }
+        writer.finish()?;
+
+        return Ok(inserts.len());
+    }
+}
+
+/// Structured operations for in-process consumers, without writing and parsing a text format.
+pub mod operations {
+    use crate::error::Result;
+    use crate::spec::WorkloadSpec;
+    use crate::writer::OpWriter;
+    use crate::{write_workload, Key, Progress};
+    use std::io::{self, Write};
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread::{self, JoinHandle};
+
+    /// Operations generated ahead of the consumer before generation blocks.
+    const BUFFERED_OPERATIONS: usize = 1024;
+
+    /// A generated operation.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Operation {
+        Insert {
+            key: Key,
+            val: Key,
+        },
+        Update {
+            key: Key,
+            val: Key,
+        },
+        Delete {
+            key: Key,
+        },
+        PointQuery {
+            key: Key,
+        },
+        RangeQuery {
+            lo: Key,
+            hi: Key,
+        },
+        /// Positions an iterator at the first key at or after `key`.
+        SeekQuery {
+            key: Key,
+        },
+        /// A range query of `count` keys from `start`.
+        CountRangeQuery {
+            start: Key,
+            count: usize,
+        },
+        /// Deletes the keys from `start`, inclusive, to `end`, exclusive.
+        RangeDelete {
+            start: Key,
+            end: Key,
+        },
+        /// A pause of `micros` microseconds.
+        ThinkTime {
+            micros: u64,
+        },
+        /// A hint to flush the memtable.
+        Flush,
+    }
+
+    impl Operation {
+        /// The key the operation is on, the start key of a range. `None` for think times and
+        /// flushes.
+        pub fn key(&self) -> Option<&Key> {
+            return match self {
+                Operation::Insert { key, .. }
+                | Operation::Update { key, .. }
+                | Operation::Delete { key }
+                | Operation::PointQuery { key }
+                | Operation::SeekQuery { key } => Some(key),
+                Operation::RangeQuery { lo, .. } => Some(lo),
+                Operation::CountRangeQuery { start, .. } | Operation::RangeDelete { start, .. } => {
+                    Some(start)
+                }
+                Operation::ThinkTime { .. } | Operation::Flush => None,
+            };
+        }
+
+        /// Writes the operation with `op_writer`.
+        pub(crate) fn write(&self, op_writer: &impl OpWriter, w: &mut impl Write) -> Result<()> {
+            return match self {
+                Operation::Insert { key, val } => op_writer.write_insert(w, key, val),
+                Operation::Update { key, val } => op_writer.write_update(w, key, val),
+                Operation::Delete { key } => op_writer.write_delete(w, key),
+                Operation::PointQuery { key } => op_writer.write_point_query(w, key),
+                Operation::RangeQuery { lo, hi } => op_writer.write_range_query(w, lo, hi),
+                Operation::SeekQuery { key } => op_writer.write_seek_query(w, key),
+                Operation::CountRangeQuery { start, count } => {
+                    op_writer.write_count_range_query(w, start, *count)
+                }
+                Operation::RangeDelete { start, end } => {
+                    op_writer.write_range_delete(w, start, end)
+                }
+                Operation::ThinkTime { micros } => op_writer.write_think_time(w, *micros),
+                Operation::Flush => op_writer.write_flush(w),
+            };
+        }
+    }
+
+    /// Hands every operation to `emit` instead of writing it.
+    pub(crate) struct OperationWriter<F: Fn(Operation) -> Result<()> + Sync> {
+        pub(crate) emit: F,
+    }
+
+    impl<F: Fn(Operation) -> Result<()> + Sync> OpWriter for OperationWriter<F> {
+        fn write_insert(&self, _: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
+            let (key, val) = (key.clone(), val.clone());
+            return (self.emit)(Operation::Insert { key, val });
+        }
+
+        fn write_update(&self, _: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
+            let (key, val) = (key.clone(), val.clone());
+            return (self.emit)(Operation::Update { key, val });
+        }
+
+        fn write_delete(&self, _: &mut impl Write, key: &Key) -> Result<()> {
+            return (self.emit)(Operation::Delete { key: key.clone() });
+        }
+
+        fn write_point_query(&self, _: &mut impl Write, key: &Key) -> Result<()> {
+            return (self.emit)(Operation::PointQuery { key: key.clone() });
+        }
+
+        fn write_range_query(&self, _: &mut impl Write, key1: &Key, key2: &Key) -> Result<()> {
+            let (lo, hi) = (key1.clone(), key2.clone());
+            return (self.emit)(Operation::RangeQuery { lo, hi });
+        }
+
+        fn write_seek_query(&self, _: &mut impl Write, key: &Key) -> Result<()> {
+            return (self.emit)(Operation::SeekQuery { key: key.clone() });
+        }
+
+        fn write_count_range_query(
+            &self,
+            _: &mut impl Write,
+            key: &Key,
+            count: usize,
+        ) -> Result<()> {
+            let start = key.clone();
+            return (self.emit)(Operation::CountRangeQuery { start, count });
+        }
+
+        fn write_range_delete(&self, _: &mut impl Write, start: &Key, end: &Key) -> Result<()> {
+            let (start, end) = (start.clone(), end.clone());
+            return (self.emit)(Operation::RangeDelete { start, end });
+        }
+
+        fn write_think_time(&self, _: &mut impl Write, micros: u64) -> Result<()> {
+            return (self.emit)(Operation::ThinkTime { micros });
+        }
+
+        fn write_flush(&self, _: &mut impl Write) -> Result<()> {
+            return (self.emit)(Operation::Flush);
+        }
+    }
+
+    /// Iterator over the operations of a workload, generated on a background thread. Dropping it
+    /// stops the generation.
+    pub struct Operations {
+        receiver: Receiver<Result<Operation>>,
+        generator: Option<JoinHandle<()>>,
+    }
+
+    impl Iterator for Operations {
+        type Item = Result<Operation>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            if let Ok(op) = self.receiver.recv() {
+                return Some(op);
+            }
+            // The generator is done, so surface a panic instead of ending early.
+            if let Some(generator) = self.generator.take() {
+                if let Err(panic) = generator.join() {
+                    std::panic::resume_unwind(panic);
+                }
+            }
+            return None;
+        }
+    }
+
+    /// Generates the operations of a workload in order, like [`crate::write_operations`], but as
+    /// structured operations. A failure, e.g. an invalid spec, is the last item.
+    pub fn iter_operations(workload: &WorkloadSpec) -> Operations {
+        let (sender, receiver) = mpsc::sync_channel(BUFFERED_OPERATIONS);
+        let workload = workload.clone();
+        let generator = thread::spawn(move || {
+            // Sends every operation to the consumer.
+            let op_writer = OperationWriter {
+                emit: |op| {
+                    // The consumer dropped the iterator, so stop generating.
+                    return sender
+                        .send(Ok(op))
+                        .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe).into());
+                },
+            };
+            let mut progress = Progress {
+                done: 0,
+                total: workload.operation_count(),
+                callback: &mut |_, _| {},
+                stats: Default::default(),
+                collect_keys: false,
+                range_width_histogram: false,
+                reverse: false,
+                loading: None,
+            };
+            let result = write_workload(&mut io::sink(), &op_writer, &workload, 1, &mut progress);
+            if let Err(err) = result {
+                // Nobody is listening when the consumer stopped early.
+                let _ = sender.send(Err(err));
+            }
+        });
+        return Operations {
+            receiver,
+            generator: Some(generator),
+        };
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::needless_borrow)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::io::{sink, BufRead};
+
+    #[test]
+    fn workload_1m_i() {
+        let spec_str = include_str!("../test_specs/1m_i.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(&spec_str).unwrap();
+        let bytes_count = spec.bytes_count();
+        let mut buf = Vec::with_capacity(bytes_count);
+        write_operations(&mut buf, &spec).unwrap();
+        assert_eq!(buf.lines().count(), 1_000_000);
+        assert_eq!(buf.len(), bytes_count);
+    }
+
+    #[test]
+    fn workload_1m_i_1m_rq() {
+        let spec_str = include_str!("../test_specs/1m_i-1m_rq.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(&spec_str).unwrap();
+        let bytes_count = spec.bytes_count();
+        let mut buf = Vec::with_capacity(bytes_count);
+        write_operations(&mut buf, &spec).unwrap();
+
+        assert_eq!(buf.lines().count(), 2_000_000);
+        assert_eq!(buf.len(), bytes_count);
+    }
+
+    #[test]
+    fn generate_to_vec_len() {
+        let spec_str = include_str!("../test_specs/all_operations.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let buf = generate_to_vec(&spec).unwrap();
+        assert_eq!(buf.len(), spec.bytes_count());
+    }
+
+    #[test]
+    fn deletes() {
+        let spec_str = include_str!("../test_specs/deletes.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(&spec_str).unwrap();
+        let bytes_count = spec.bytes_count();
+        let mut buf = Vec::with_capacity(bytes_count);
+        write_operations(&mut buf, &spec).unwrap();
+        assert_eq!(buf.lines().count(), 1_100_000);
+        assert_eq!(buf.len(), bytes_count);
+    }
+
+    #[test]
+    fn deletes_after_inserts() {
+        // The second group starts with 10 valid keys, but inserts 100 more before deleting.
+        let spec_str = include_str!("../test_specs/deletes_after_inserts.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let bytes_count = spec.bytes_count();
+        let mut buf = Vec::with_capacity(bytes_count);
+        let stats =
+            write_operations_with_options(&mut buf, &spec, GenerateOptions::default(), |_, _| {})
+                .unwrap();
+        assert_eq!(buf.lines().count(), 210);
+        assert_eq!(buf.len(), bytes_count);
+        assert_eq!(stats.live_keys, 10);
+
+        // One more delete than keys inserted fails before anything is written.
+        let spec_str = spec_str.replace("\"amount\": 100\n", "\"amount\": 111\n");
+        let spec = serde_json::from_str::<WorkloadSpec>(&spec_str).unwrap();
+        let mut buf = Vec::new();
+        let err = write_operations(&mut buf, &spec).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Group 1 deletes 111 keys, but only 110 valid keys exist"));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn ratio_group() {
+        let spec_str = include_str!("../test_specs/ratio.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let group = spec.sections[0].groups[1];
+        assert_eq!(group.point_queries.unwrap().amount, 700_000);
+        assert_eq!(group.inserts.unwrap().amount, 300_000);
+        assert!(group.updates.is_none());
+        assert_eq!(spec.operation_count(), 1_001_000);
+    }
+
+    #[test]
+    fn ratio_group_remainder() {
+        let ratio = serde_json::from_str::<spec::WorkloadSpecGroupRatio>(
+            r#"{ "total": 10, "insert_weight": 1, "update_weight": 1, "delete_weight": 1, "key_len": 8, "val_len": 8 }"#,
+        )
+        .unwrap();
+        assert_eq!(ratio.amounts(), [4, 3, 3, 0, 0]);
+    }
+
+    #[test]
+    fn ratio_group_invalid_weights() {
+        let parse = |weights: &str| {
+            let spec_str = format!(
+                r#"{{ "sections": [{{ "groups": [{{ "total": 10, {weights}, "key_len": 8, "val_len": 8 }}] }}] }}"#
+            );
+            return serde_json::from_str::<WorkloadSpec>(&spec_str)
+                .unwrap_err()
+                .to_string();
+        };
+        let err = parse(r#""insert_weight": 1, "update_weight": -0.5"#);
+        assert!(
+            err.contains("update_weight must be finite and not negative"),
+            "{err}"
+        );
+        let err = parse(r#""insert_weight": 0, "update_weight": 0"#);
+        assert!(err.contains("at least one positive weight"), "{err}");
+    }
+
+    fn update_keys(spec_str: &str) -> (HashSet<String>, Vec<String>) {
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let mut buf = Vec::new();
+        write_operations(&mut buf, &spec).unwrap();
+        let mut inserted = HashSet::new();
+        let mut updated = Vec::new();
+        for line in buf.lines() {
+            let line = line.unwrap();
+            let mut parts = line.split(' ');
+            match parts.next().unwrap() {
+                "I" => inserted.insert(parts.next().unwrap().to_string()),
+                "U" => {
+                    updated.push(parts.next().unwrap().to_string());
+                    true
+                }
+                _ => false,
+            };
+        }
+        return (inserted, updated);
+    }
+
+    #[test]
+    fn updates_existing() {
+        let (inserted, updated) = update_keys(include_str!("../test_specs/updates_existing.json"));
+        assert_eq!(updated.len(), 1000);
+        assert!(updated.iter().all(|key| inserted.contains(key)));
+    }
+
+    #[test]
+    fn updates_blind() {
+        let (inserted, updated) = update_keys(include_str!("../test_specs/updates_blind.json"));
+        assert_eq!(updated.len(), 1000);
+        assert!(updated.iter().all(|key| key.len() == 16));
+        assert!(updated.iter().all(|key| !inserted.contains(key)));
+    }
+
+    #[test]
+    fn progress() {
+        let spec_str = r#"{ "sections": [{ "groups": [{ "inserts": { "amount": 25000, "key_len": 8, "val_len": 8 } }] }] }"#;
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let mut calls = Vec::new();
+        write_operations_with_progress(&mut sink(), &spec, OutputFormat::Ascii, |done, total| {
+            calls.push((done, total))
+        })
+        .unwrap();
+        assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(
+            calls,
+            vec![(10_000, 25_000), (20_000, 25_000), (25_000, 25_000)]
+        );
+    }
+
+    #[test]
+    fn byte_progress() {
+        use std::cell::Cell;
+        use writer::CountingWriter;
+
+        let spec_str = r#"{ "sections": [{ "groups": [
+            { "inserts": { "amount": 20000, "key_len": 8, "val_len": 8 } },
+            { "updates": { "amount": 5000, "val_len": 16 }, "point_queries": { "amount": 5000 } },
+            { "range_queries": { "amount": 1000, "selectivity": 0.01 } }
+        ] }] }"#;
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let bytes = Cell::new(0);
+        let mut writer = CountingWriter {
+            inner: sink(),
+            bytes: &bytes,
+        };
+        let mut seen = Vec::new();
+        write_operations_with_progress(&mut writer, &spec, OutputFormat::Ascii, |_, _| {
+            seen.push(bytes.get())
+        })
+        .unwrap();
+        assert_eq!(seen.len(), 4);
+        assert!(seen.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(bytes.get(), spec.bytes_count());
+        assert_eq!(seen.last(), Some(&spec.bytes_count()));
+    }
+
+    #[test]
+    fn parallel_inserts() {
+        let spec_str = r#"{ "seed": 1, "sections": [{ "groups": [
+            { "inserts": { "amount": 200000, "key_len": 8, "val_len": 8 } },
+            { "point_queries": { "amount": 10000 } }
+        ] }] }"#;
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let generate = || {
+            let options = GenerateOptions {
+                threads: 4,
+                ..GenerateOptions::default()
+            };
+            let mut buf = Vec::new();
+            write_operations_with_options(&mut buf, &spec, options, |_, _| {}).unwrap();
+            return buf;
+        };
+        let buf = generate();
+        assert_eq!(buf.len(), spec.bytes_count());
+        // Chunks are seeded from the workload's rng, so the output is reproducible.
+        assert_eq!(buf, generate());
+
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        let (inserts, queries) = lines.split_at(200_000);
+        let inserted: HashSet<&str> = inserts
+            .iter()
+            .map(|line| line.strip_prefix("I ").unwrap().split(' ').next().unwrap())
+            .collect();
+        assert!(inserted.len() > 199_000, "{} distinct keys", inserted.len());
+        // Keys inserted on other threads are valid keys for the groups that follow.
+        assert_eq!(queries.len(), 10_000);
+        assert!(queries
+            .iter()
+            .all(|line| inserted.contains(line.strip_prefix("P ").unwrap())));
+    }
+
+    #[test]
+    fn max_ops() {
+        let spec_str = include_str!("../test_specs/1m_i.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        for threads in [1, 4] {
+            let options = GenerateOptions {
+                threads,
+                max_ops: Some(500),
+                ..GenerateOptions::default()
+            };
+            let mut buf = Vec::new();
+            let mut calls = Vec::new();
+            write_operations_with_options(&mut buf, &spec, options, |done, total| {
+                calls.push((done, total))
+            })
+            .unwrap();
+            assert_eq!(buf.lines().count(), 500);
+            assert_eq!(calls, vec![(500, 500)]);
+        }
+
+        // Truncating mid-section skips the remaining groups and sections.
+        let spec_str = include_str!("../test_specs/all_operations.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let options = GenerateOptions {
+            max_ops: Some(1_050),
+            ..GenerateOptions::default()
+        };
+        let mut buf = Vec::new();
+        write_operations_with_options(&mut buf, &spec, options, |_, _| {}).unwrap();
+        assert_eq!(buf.lines().count(), 1_050);
+    }
+
+    #[test]
+    fn error_variants() {
+        use spec::{Deletes, Inserts, Updates, WorkloadSpecGroup};
+
+        let err = serde_json::from_str::<WorkloadSpec>("{").unwrap_err();
+        assert!(matches!(WorkloadError::from(err), WorkloadError::Parse(_)));
+
+        let spec_str = include_str!("../test_specs/wrong_types.json");
+        let err = validate_workload_spec(spec_str).unwrap_err();
+        assert!(matches!(err, WorkloadError::Schema(ref errors) if !errors.is_empty()));
+
+        let spec = serde_json::from_str::<WorkloadSpec>(r#"{"sections": [{"groups": []}]}"#);
+        let err = write_operations(&mut sink(), &spec.unwrap()).unwrap_err();
+        assert!(matches!(err, WorkloadError::EmptySection { section: 0 }));
+
+        let spec = serde_json::from_str::<WorkloadSpec>(r#"{"sections": []}"#);
+        let err = write_operations(&mut sink(), &spec.unwrap()).unwrap_err();
+        assert!(matches!(err, WorkloadError::EmptyWorkload));
+
+        // Groups without operations neither panic when sized nor generate silently.
+        let spec = serde_json::from_str::<WorkloadSpec>(r#"{"sections": [{"groups": [{}]}]}"#);
+        let spec = spec.unwrap();
+        assert_eq!(spec.bytes_count(), 0);
+        let err = write_operations(&mut sink(), &spec).unwrap_err();
+        assert!(matches!(
+            err,
+            WorkloadError::EmptyGroup {
+                section: 0,
+                group: 0
+            }
+        ));
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8)))
+            .add_section()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8)))
+            .add_group(WorkloadSpecGroup::new())
+            .build();
+        let err = write_operations(&mut sink(), &spec).unwrap_err();
+        assert!(matches!(
+            err,
+            WorkloadError::EmptyGroup {
+                section: 1,
+                group: 1
+            }
+        ));
+
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().updates(Updates::new(10, 8).existing(false)))
+            .build();
+        let err = write_operations(&mut sink(), &spec).unwrap_err();
+        assert!(matches!(err, WorkloadError::InvalidSpec(_)), "{err}");
+
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8)))
+            .add_group(WorkloadSpecGroup::new().deletes(Deletes::new(11)))
+            .build();
+        let err = write_operations(&mut sink(), &spec).unwrap_err();
+        assert!(matches!(
+            err,
+            WorkloadError::TooManyDeletes {
+                group: 1,
+                deletes: 11,
+                live_keys: 10
+            }
+        ));
+
+        let spec = WorkloadSpec::builder()
+            .interleave_groups(true)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8)))
+            .add_group(WorkloadSpecGroup::new().deletes(Deletes::new(11)))
+            .build();
+        let err = write_operations(&mut sink(), &spec).unwrap_err();
+        assert!(matches!(
+            err,
+            WorkloadError::TooManyInterleavedDeletes {
+                deletes: 11,
+                inserted: 10
+            }
+        ));
+
+        let spec_str = include_str!("../test_specs/empty_point_queries_exhausted.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let err = write_operations(&mut sink(), &spec).unwrap_err();
+        assert!(matches!(err, WorkloadError::KeySpaceExhausted(_)));
+
+        /// A writer whose disk is always full.
+        struct FullWriter;
+
+        impl Write for FullWriter {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                return Err(std::io::ErrorKind::StorageFull.into());
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                return Ok(());
+            }
+        }
+
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8)))
+            .build();
+        let err = write_operations(&mut FullWriter, &spec).unwrap_err();
+        assert!(matches!(err, WorkloadError::Io(_)));
+
+        let err = "csv".parse::<OutputFormat>().unwrap_err();
+        assert!(matches!(err, WorkloadError::UnknownFormat(format) if format == "csv"));
+    }
+
+    #[test]
+    fn parse_error_position() {
+        let malformed = r#"{
+            "sections": [{
+                "bulk_load": "yes",
+                "groups": [{ "inserts": { "amount": 10, "key_len": 8, "val_len": 8 } }]
+            }]
+        }"#;
+        let err = generate_workload_into(
+            malformed,
+            &mut Vec::new(),
+            GenerateOptions::default(),
+            |_, _| {},
+        )
+        .unwrap_err();
+        let WorkloadError::ParseSpec { line, path, .. } = &err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(*line, 3, "{err}");
+        assert_eq!(path, "sections[0].bulk_load");
+        let message = err.to_string();
+        assert!(
+            message.starts_with("parsing json spec at line 3, column 34"),
+            "{message}"
+        );
+        assert!(message.ends_with("expected a boolean"), "{message}");
+
+        // Groups are either amounts or ratios, so an invalid group points at the whole group.
+        let malformed =
+            r#"{ "sections": [{ "groups": [{ "point_queries": { "amount": "ten" } }] }] }"#;
+        let err = SpecFormat::Json.parse(malformed).unwrap_err();
+        assert!(
+            matches!(&err, WorkloadError::ParseSpec { path, .. } if path == "sections[0].groups[0]"),
+            "{err}"
+        );
+
+        // Syntax errors, also when validating against the schema, point at the broken token.
+        let options = GenerateOptions {
+            validate: true,
+            ..GenerateOptions::default()
+        };
+        let broken = "{\n  \"sections\": [\n    { \"groups\": [] },,\n  ]\n}";
+        let err = generate_workload_into(broken, &mut Vec::new(), options, |_, _| {}).unwrap_err();
+        assert!(
+            matches!(err, WorkloadError::ParseSpec { line: 3, .. }),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn validate_wrong_type() {
+        let spec_str = include_str!("../test_specs/all_operations.json");
+        validate_workload_spec(spec_str).unwrap();
+
+        let spec_str = spec_str.replace("\"selectivity\": 0.2", "\"selectivity\": \"0.2\"");
+        let err = validate_workload_spec(&spec_str).unwrap_err().to_string();
+        assert!(
+            err.contains("/sections/0/groups/2/range_queries/selectivity"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn schema_examples() {
+        let schema: serde_json::Value =
+            serde_json::from_str(&generate_workload_spec_schema().unwrap()).unwrap();
+        let examples = schema["examples"].as_array().unwrap();
+        assert_eq!(examples.len(), 2);
+        // The examples are valid specs.
+        for example in examples {
+            validate_workload_spec_value(example).unwrap();
+            let spec = serde_json::from_value::<WorkloadSpec>(example.clone()).unwrap();
+            spec.validate().unwrap();
+        }
+    }
+
+    #[test]
+    fn hotspot_key_distribution() {
+        let spec_str = include_str!("../test_specs/hotspot.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let mut buf = Vec::new();
+        write_operations(&mut buf, &spec).unwrap();
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        let key = |line: &String| line.split(' ').nth(1).unwrap().to_string();
+        // Only inserts happen before the point queries, so the valid keys are in insert order.
+        let hot_keys: HashSet<String> = lines[..200].iter().map(key).collect();
+        let hot_queries = lines[1000..]
+            .iter()
+            .filter(|line| hot_keys.contains(&key(line)))
+            .count();
+        let hot_rate = hot_queries as f64 / 100_000.0;
+        assert!((hot_rate - 0.8).abs() < 0.01, "{hot_rate}");
+    }
+
+    #[test]
+    fn zipfian_key_distributions() {
+        use spec::{Inserts, KeyDistribution, PointQueries, WorkloadSpecGroup};
+
+        let theta = 0.99;
+        for (key_distribution, hot_range) in [
+            (KeyDistribution::Zipfian { theta }, 0..100),
+            (KeyDistribution::Latest { theta }, 900..1000),
+        ] {
+            let spec = WorkloadSpec::builder()
+                .key_distribution(key_distribution)
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .inserts(Inserts::new(1000, 16, 8).allow_duplicate_keys(false)),
+                )
+                .add_group(WorkloadSpecGroup::new().point_queries(PointQueries::new(20_000)))
+                .build();
+            let buf = generate_to_vec(&spec).unwrap();
+            let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+            let key = |line: &String| line.split(' ').nth(1).unwrap().to_string();
+            // Only inserts happen before the point queries, so the valid keys are in insert order.
+            let hot_keys: HashSet<String> = lines[hot_range].iter().map(key).collect();
+            let hot_queries = lines[1000..]
+                .iter()
+                .filter(|line| hot_keys.contains(&key(line)))
+                .count();
+            // A tenth of the keys gets about 69% of the queries.
+            let hot_rate = hot_queries as f64 / 20_000.0;
+            assert!(
+                (hot_rate - 0.69).abs() < 0.03,
+                "{key_distribution:?}: {hot_rate}"
+            );
+        }
+
+        let spec = WorkloadSpec::builder()
+            .key_distribution(KeyDistribution::Zipfian { theta: -1.0 })
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8)))
+            .build();
+        let err = generate_to_vec(&spec).unwrap_err();
+        assert!(matches!(err, WorkloadError::InvalidSpec(_)), "{err}");
+    }
+
+    #[test]
+    fn ycsb_presets() {
+        use presets::Ycsb;
+
+        for (name, inserts, reads, updates, scans, read_modify_writes) in [
+            ("ycsb-a", 0, 500, 500, 0, 0),
+            ("ycsb-b", 0, 950, 50, 0, 0),
+            ("ycsb-c", 0, 1000, 0, 0, 0),
+            ("ycsb-d", 50, 950, 0, 0, 0),
+            ("ycsb-e", 50, 0, 0, 950, 0),
+            // Read-modify-writes also count as a read and an update.
+            ("ycsb-f", 0, 500 + 500, 500, 0, 500),
+        ] {
+            let preset: Ycsb = name.parse().unwrap();
+            let spec: WorkloadSpec = serde_json::from_value(preset.spec(200, 1000)).unwrap();
+            let mut buf = Vec::new();
+            let stats = write_operations_with_options(
+                &mut buf,
+                &spec,
+                GenerateOptions::default(),
+                |_, _| {},
+            )
+            .unwrap();
+            assert_eq!(stats.inserts, 200 + inserts, "{name}");
+            assert_eq!(
+                (stats.point_queries, stats.updates, stats.range_queries),
+                (reads, updates, scans),
+                "{name}"
+            );
+            assert_eq!(stats.read_modify_writes, read_modify_writes, "{name}");
+
+            // The inserts of the run phase insert new keys after the load phase.
+            let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+            let (load, run) = lines.split_at(200);
+            assert!(load.iter().all(|line| line.starts_with("I ")), "{name}");
+            let key = |line: &String| line.split(' ').nth(1).unwrap().to_string();
+            let loaded: HashSet<String> = load.iter().map(key).collect();
+            let run_inserts: HashSet<String> = run
+                .iter()
+                .filter(|line| line.starts_with("I "))
+                .map(key)
+                .collect();
+            assert_eq!(run_inserts.len(), inserts, "{name}");
+            assert!(run_inserts.is_disjoint(&loaded), "{name}");
+        }
+        assert!(matches!(
+            "ycsb-g".parse::<Ycsb>(),
+            Err(WorkloadError::UnknownPreset(_))
+        ));
+    }
+
+    #[test]
+    fn sequential_key_distribution() {
+        use spec::{Deletes, Inserts, KeyDistribution, PointQueries, WorkloadSpecGroup};
+
+        let sequential = PointQueries::new(2500).key_distribution(KeyDistribution::Sequential);
+        let spec = WorkloadSpec::builder()
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(Inserts::new(1000, 16, 8).allow_duplicate_keys(false)),
+            )
+            .add_group(WorkloadSpecGroup::new().point_queries(sequential))
+            .build();
+        let mut buf = Vec::new();
+        write_operations(&mut buf, &spec).unwrap();
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        let key = |line: &String| line.split(' ').nth(1).unwrap().to_string();
+        let mut inserted: Vec<String> = lines[..1000].iter().map(key).collect();
+        inserted.sort();
+
+        // Every pass scans the keys in order, then wraps around to the smallest key.
+        let queried: Vec<String> = lines[1000..].iter().map(key).collect();
+        for pass in queried.chunks(1000) {
+            assert!(pass.is_sorted());
+            assert_eq!(pass, &inserted[..pass.len()]);
+        }
+
+        // Deletes between the reads move the scan past the deleted keys.
+        let spec = WorkloadSpec::builder()
+            .key_distribution(KeyDistribution::Sequential)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 16, 8)))
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .deletes(Deletes::new(500))
+                    .point_queries(PointQueries::new(500)),
+            )
+            .build();
+        assert_eq!(KeySetKind::select(&spec.sections[0]), KeySetKind::OrderStat);
+        let mut buf = Vec::new();
+        write_operations(&mut buf, &spec).unwrap();
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        let deleted: Vec<String> = lines[1000..1500].iter().map(key).collect();
+        let queried: Vec<String> = lines[1500..].iter().map(key).collect();
+        assert!(deleted.is_sorted());
+        assert!(queried.is_sorted());
+    }
+
+    #[test]
+    fn per_operation_key_distribution() {
+        use spec::{Inserts, KeyDistribution, PointQueries, Updates, WorkloadSpecGroup};
+
+        let hot = KeyDistribution::Hotspot {
+            hot_fraction: 0.1,
+            hot_probability: 1.0,
+        };
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 16, 8)))
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .updates(Updates::new(10_000, 8).key_distribution(hot))
+                    .point_queries(PointQueries::new(10_000)),
+            )
+            .build();
+        let mut buf = Vec::new();
+        write_operations(&mut buf, &spec).unwrap();
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        let key = |line: &String| line.split(' ').nth(1).unwrap().to_string();
+        // Only inserts happen before the updates, so the valid keys are in insert order.
+        let hot_keys: HashSet<String> = lines[..100].iter().map(key).collect();
+        let is_hot = |line: &&String| hot_keys.contains(&key(line));
+        assert!(lines[1000..11_000].iter().all(|line| is_hot(&line)));
+        let hot_queries = lines[11_000..].iter().filter(is_hot).count();
+        let hot_rate = hot_queries as f64 / 10_000.0;
+        assert!((hot_rate - 0.1).abs() < 0.02, "{hot_rate}");
+    }
+
+    #[test]
+    fn ascii_separators() {
+        let spec_str = include_str!("../test_specs/all_operations.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let options = GenerateOptions {
+            ascii_format: AsciiFormat {
+                field_sep: b'\t',
+                record_sep: b'\0',
+                ..AsciiFormat::default()
+            },
+            ..GenerateOptions::default()
+        };
+        let mut buf = Vec::new();
+        write_operations_with_options(&mut buf, &spec, options, |_, _| {}).unwrap();
+        assert_eq!(buf.len(), spec.bytes_count());
+        assert_eq!(buf.last(), Some(&b'\0'));
+
+        let records: Vec<&[u8]> = buf[..buf.len() - 1].split(|&b| b == b'\0').collect();
+        assert_eq!(records.len(), spec.operation_count());
+        for record in records {
+            assert!(!record.contains(&b' ') && !record.contains(&b'\n'));
+            let fields: Vec<&[u8]> = record.split(|&b| b == b'\t').collect();
+            let expected_fields = match fields[0] {
+                b"I" | b"U" | b"R" => 3,
+                b"D" | b"P" => 2,
+                op => panic!("unexpected op {op:?}"),
+            };
+            assert_eq!(fields.len(), expected_fields);
+        }
+    }
+
+    #[test]
+    fn range_deletes() {
+        use spec::{Inserts, PointQueries, RangeDeletes, WorkloadSpecGroup};
+
+        let spec = WorkloadSpec::builder()
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(Inserts::new(1000, 16, 8).allow_duplicate_keys(false)),
+            )
+            .add_group(WorkloadSpecGroup::new().range_deletes(RangeDeletes::new(1, 0.2)))
+            .add_group(WorkloadSpecGroup::new().point_queries(PointQueries::new(10_000)))
+            .build();
+        let bytes_count = spec.bytes_count();
+        let mut buf = Vec::with_capacity(bytes_count);
+        let stats =
+            write_operations_with_options(&mut buf, &spec, GenerateOptions::default(), |_, _| {})
+                .unwrap();
+        assert_eq!(buf.len(), bytes_count);
+        assert_eq!(stats.range_deletes, 1);
+        assert_eq!(stats.live_keys, 800);
+        assert_eq!(stats.distinct_keys, 1000);
+
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        let mut parts = lines[1000].split(' ');
+        assert_eq!(parts.next(), Some("DR"));
+        let (start, end) = (parts.next().unwrap(), parts.next().unwrap());
+        let key = |line: &String| line.split(' ').nth(1).unwrap().to_string();
+        let in_range = |key: &String| start <= key.as_str() && key.as_str() < end;
+        let deleted = lines[..1000].iter().map(key).filter(in_range).count();
+        assert_eq!(deleted, 200);
+        // Deleted keys are gone from the valid keys, so no point query can pick them.
+        assert!(!lines[1001..].iter().map(key).any(|key| in_range(&key)));
+
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().range_deletes(RangeDeletes::new(1, 0.2)))
+            .build();
+        let err = write_operations(&mut sink(), &spec).unwrap_err();
+        assert!(err.to_string().contains("no valid keys exist"));
+    }
+
+    #[test]
+    fn max_tracked_keys() {
+        use keyset::VecKeySet;
+        use spec::{Deletes, Inserts, KeyEviction, PointQueries, WorkloadSpecGroup};
+
+        const MAX_TRACKED_KEYS: usize = 100;
+
+        fn new_progress<'a>(
+            spec: &WorkloadSpec,
+            callback: &'a mut dyn FnMut(usize, usize),
+        ) -> Progress<'a> {
+            return Progress {
+                done: 0,
+                total: spec.operation_count(),
+                callback,
+                stats: Default::default(),
+                collect_keys: false,
+                range_width_histogram: false,
+                reverse: false,
+                loading: None,
+            };
+        }
+
+        for key_eviction in [KeyEviction::Oldest, KeyEviction::Random] {
+            let spec = WorkloadSpec::builder()
+                .max_tracked_keys(MAX_TRACKED_KEYS)
+                .key_eviction(key_eviction)
+                .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 16, 8)))
+                .add_group(WorkloadSpecGroup::new().point_queries(PointQueries::new(200)))
+                .build();
+            spec.validate().unwrap();
+            let section = &spec.sections[0];
+            assert_eq!(KeySetKind::select(section), KeySetKind::Vec);
+
+            let mut buf = Vec::new();
+            let mut callback = |_, _| {};
+            let mut progress = new_progress(&spec, &mut callback);
+            let mut rng = RngAlgorithm::default().seed_from_u64(0);
+            let op_writer = AsciiWriter::default();
+            let keys = VecKeySet::new(section.key_set_capacity());
+            let mut section_writer = SectionWriter::new(&op_writer, section, keys, &progress);
+            section_writer
+                .write(&mut buf, &mut rng, 1, &mut progress)
+                .unwrap();
+            assert!(section_writer.keys_valid.len() <= MAX_TRACKED_KEYS);
+            // Evicted keys are still live.
+            assert_eq!(
+                section_writer.keys_valid.len() + section_writer.untracked_keys,
+                1000
+            );
+
+            let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+            let key = |line: &String| line.split(' ').nth(1).unwrap().to_string();
+            let inserted: Vec<String> = lines[..1000].iter().map(key).collect();
+            let queried: HashSet<String> = lines[1000..].iter().map(key).collect();
+            if key_eviction == KeyEviction::Oldest {
+                // Only the most recent inserts are left to query.
+                let recent: HashSet<String> = inserted[900..].iter().cloned().collect();
+                assert!(queried.is_subset(&recent));
+            }
+        }
+
+        // Deleted keys don't pile up in the insertion order of the oldest keys.
+        let mut builder = WorkloadSpec::builder().max_tracked_keys(MAX_TRACKED_KEYS);
+        for _ in 0..50 {
+            builder = builder.add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(Inserts::new(50, 16, 8))
+                    .deletes(Deletes::new(50)),
+            );
+        }
+        let spec = builder.build();
+        spec.validate().unwrap();
+        let section = &spec.sections[0];
+        let mut callback = |_, _| {};
+        let mut progress = new_progress(&spec, &mut callback);
+        let mut rng = RngAlgorithm::default().seed_from_u64(0);
+        let op_writer = AsciiWriter::default();
+        let keys = VecKeySet::new(section.key_set_capacity());
+        let mut section_writer = SectionWriter::new(&op_writer, section, keys, &progress);
+        section_writer
+            .write(&mut sink(), &mut rng, 1, &mut progress)
+            .unwrap();
+        assert!(section_writer.insertion_order.len() <= 2 * MAX_TRACKED_KEYS);
+    }
+
+    #[test]
+    fn spec_formats() {
+        use spec::SpecFormat;
+        use std::path::Path;
+
+        let generate = |spec_str: &str, spec_format: SpecFormat| {
+            let options = GenerateOptions {
+                validate: true,
+                spec_format,
+                ..GenerateOptions::default()
+            };
+            let mut buf = Vec::new();
+            generate_workload_into(spec_str, &mut buf, options, |_, _| {}).unwrap();
+            buf
+        };
+        let json = generate(
+            include_str!("../test_specs/spec_formats.json"),
+            SpecFormat::Json,
+        );
+        assert_eq!(json.lines().count(), 2500);
+        let toml = include_str!("../test_specs/spec_formats.toml");
+        assert_eq!(json, generate(toml, SpecFormat::Toml));
+        let yaml = include_str!("../test_specs/spec_formats.yaml");
+        assert_eq!(json, generate(yaml, SpecFormat::Yaml));
+
+        for (path, format) in [
+            ("a.spec.json", SpecFormat::Json),
+            ("a.spec.toml", SpecFormat::Toml),
+            ("a.yaml", SpecFormat::Yaml),
+            ("a.spec.yml.gz", SpecFormat::Yaml),
+            ("a.spec", SpecFormat::Json),
+        ] {
+            assert_eq!(SpecFormat::from_path(Path::new(path)), format, "{path}");
+        }
+
+        // Schema errors of toml specs are reported like those of json specs.
+        let toml = toml.replace("selectivity = 0.1", "selectivity = \"0.1\"");
+        let err = SpecFormat::Toml.to_json(&toml).unwrap();
+        let err = validate_workload_spec_value(&err).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("/sections/0/groups/1/range_queries/selectivity"));
+        let err = SpecFormat::Yaml.parse("sections: 1").unwrap_err();
+        assert!(matches!(err, WorkloadError::ParseYaml(_)), "{err}");
+    }
+
+    #[test]
+    fn footer() {
+        use writer::Footer;
+
+        let options = GenerateOptions {
+            footer: true,
+            ..GenerateOptions::default()
+        };
+        let mut buf = Vec::new();
+        let spec_str = include_str!("../test_specs/spec_formats.json");
+        let stats = generate_workload_into(spec_str, &mut buf, options, |_, _| {}).unwrap();
+        let body_len = buf[..buf.len() - 1]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .unwrap()
+            + 1;
+        let footer = Footer::parse(buf[body_len..].trim_ascii_end()).unwrap();
+        assert_eq!(footer.ops, 2500);
+        assert_eq!(footer.ops, stats.operation_count());
+        assert_eq!(footer.ops, buf[..body_len].lines().count());
+        assert_eq!(footer.crc, crc32fast::hash(&buf[..body_len]));
+    }
+
+    #[test]
+    fn header() {
+        use writer::{Footer, Header};
+
+        let spec_str = r#"{ "sections": [
+            { "key_prefix": "tenant-", "groups": [
+                { "inserts": { "amount": 1000, "key_len": 12, "val_len": 32 } },
+                { "updates": { "amount": 200, "val_len": { "exponential": { "mean": 50, "max": 400 } } } }
+            ] },
+            { "groups": [
+                { "inserts": { "amount": 500, "key_len": 16, "val_len": 8 } },
+                { "empty_point_queries": { "amount": 100, "key_len": 24 } }
+            ] }
+        ] }"#;
+        let spec = SpecFormat::Json.parse(spec_str).unwrap();
+        assert_eq!(spec.max_key_len(), 24);
+        assert_eq!(spec.max_val_len(), 400);
+
+        let options = GenerateOptions {
+            header: true,
+            footer: true,
+            ..GenerateOptions::default()
+        };
+        let mut buf = Vec::new();
+        generate_workload_into(spec_str, &mut buf, options, |_, _| {}).unwrap();
+        let lines: Vec<&[u8]> = buf.trim_ascii_end().split(|&b| b == b'\n').collect();
+        let header = Header::parse(lines[0]).unwrap();
+        assert_eq!(
+            header,
+            Header {
+                max_key: 24,
+                max_val: 400,
+                ops: 1800,
+            }
+        );
+        let ops = &lines[1..lines.len() - 1];
+        assert_eq!(ops.len(), header.ops);
+        for op in ops {
+            let fields: Vec<&[u8]> = op.split(|&b| b == b' ').collect();
+            assert!(fields[1].len() <= header.max_key);
+            assert!(fields.get(2).is_none_or(|val| val.len() <= header.max_val));
+        }
+        // The footer covers the header.
+        let footer = Footer::parse(lines[lines.len() - 1]).unwrap();
+        let body_len = buf.len() - lines[lines.len() - 1].len() - 1;
+        assert_eq!(footer.crc, crc32fast::hash(&buf[..body_len]));
+
+        let options = GenerateOptions {
+            header: true,
+            max_ops: Some(100),
+            ..GenerateOptions::default()
+        };
+        let mut buf = Vec::new();
+        generate_workload_into(spec_str, &mut buf, options, |_, _| {}).unwrap();
+        let header = Header::parse(buf.lines().next().unwrap().unwrap().as_bytes()).unwrap();
+        assert_eq!(header.ops, 100);
+    }
+
+    #[test]
+    fn seeded_sections() {
+        use spec::{Inserts, WorkloadSpecGroup};
+
+        let spec_str = include_str!("../test_specs/seeded_sections.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let generate = |spec: &WorkloadSpec| generate_to_vec(spec).unwrap();
+        let first = generate(&spec);
+        assert_eq!(first.lines().count(), 2500);
+        assert_eq!(first, generate(&spec));
+
+        // Without its seed, the first section is derived from a random workload seed, but the
+        // second section, its last 1100 operations, stays the same.
+        let spec_str = spec_str.replace(r#""seed": 1,"#, "");
+        let spec = serde_json::from_str::<WorkloadSpec>(&spec_str).unwrap();
+        let unseeded = generate(&spec);
+        let second_section = |buf: &[u8]| -> Vec<String> {
+            let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+            lines[lines.len() - 1100..].to_vec()
+        };
+        assert_ne!(first, unseeded);
+        assert_eq!(second_section(&first), second_section(&unseeded));
+
+        // A workload seed derives the same section seeds in every run.
+        let spec = WorkloadSpec::builder()
+            .seed(7)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 8, 8)))
+            .add_section()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 8, 8)))
+            .build();
+        assert_eq!(generate(&spec), generate(&spec));
+    }
+
+    #[test]
+    fn rng_algorithms() {
+        use spec::{Inserts, PointQueries, Updates, WorkloadSpecGroup};
+
+        let generate = |rng: RngAlgorithm| {
+            let spec = WorkloadSpec::builder()
+                .seed(7)
+                .rng(rng)
+                .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(100_000, 8, 8)))
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .updates(Updates::new(100, 8))
+                        .point_queries(PointQueries::new(100)),
+                )
+                .build();
+            let options = GenerateOptions {
+                threads: 2,
+                ..GenerateOptions::default()
+            };
+            let mut buf = Vec::new();
+            write_operations_with_options(&mut buf, &spec, options, |_, _| {}).unwrap();
+            return buf;
+        };
+
+        // Every algorithm generates the same workload from the same seed, with parallel inserts
+        // too, but a different one than the others.
+        let algorithms = [
+            RngAlgorithm::Xoshiro256Plus,
+            RngAlgorithm::ChaCha8,
+            RngAlgorithm::Pcg64,
+        ];
+        let workloads: Vec<Vec<u8>> = algorithms.iter().map(|&rng| generate(rng)).collect();
+        for (&rng, workload) in algorithms.iter().zip(&workloads) {
+            assert_eq!(workload, &generate(rng), "{rng:?}");
+        }
+        assert_ne!(workloads[0], workloads[1]);
+        assert_ne!(workloads[0], workloads[2]);
+        assert_ne!(workloads[1], workloads[2]);
+
+        let spec = SpecFormat::Json
+            .parse(r#"{ "rng": "chacha8", "sections": [{ "groups": [{ "inserts": { "amount": 1, "key_len": 8, "val_len": 8 } }] }] }"#)
+            .unwrap();
+        assert_eq!(spec.rng, RngAlgorithm::ChaCha8);
+    }
+
+    #[test]
+    fn overwrite_ratio() {
+        use spec::{Inserts, WorkloadSpecGroup};
+
+        let spec = WorkloadSpec::builder()
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(Inserts::new(1000, 16, 8).allow_duplicate_keys(false)),
+            )
+            .add_group(
+                WorkloadSpecGroup::new().inserts(
+                    Inserts::new(1000, 16, 8)
+                        .allow_duplicate_keys(false)
+                        .overwrite_ratio(0.3),
+                ),
+            )
+            .build();
+        let mut buf = Vec::new();
+        let stats =
+            write_operations_with_options(&mut buf, &spec, GenerateOptions::default(), |_, _| {})
+                .unwrap();
+        assert_eq!(stats.inserts, 2000);
+        assert_eq!(stats.live_keys, 1700);
+
+        let mut existing = HashSet::new();
+        let mut overwrites = 0;
+        for line in buf.lines().map(|line| line.unwrap()) {
+            let key = line.split(' ').nth(1).unwrap().to_string();
+            if !existing.insert(key) {
+                overwrites += 1;
+            }
+        }
+        assert_eq!(overwrites, 300);
+
+        // Without valid keys, the first insert still creates one.
+        let spec = WorkloadSpec::builder()
+            .add_group(
+                WorkloadSpecGroup::new().inserts(Inserts::new(10, 16, 8).overwrite_ratio(1.0)),
+            )
+            .build();
+        let stats = write_operations_with_options(
+            &mut sink(),
+            &spec,
+            GenerateOptions::default(),
+            |_, _| {},
+        )
+        .unwrap();
+        assert_eq!(stats.live_keys, 1);
+    }
+
+    #[test]
+    fn sorted_inserts() {
+        use spec::{Inserts, PointQueries, WorkloadSpecGroup};
+
+        let spec = WorkloadSpec::builder()
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(
+                        Inserts::new(1000, 4, 8)
+                            .allow_duplicate_keys(false)
+                            .sorted(true),
+                    )
+                    .point_queries(PointQueries::new(100)),
+            )
+            .build();
+        let mut buf = Vec::new();
+        let stats =
+            write_operations_with_options(&mut buf, &spec, GenerateOptions::default(), |_, _| {})
+                .unwrap();
+        assert_eq!(stats.live_keys, 1000);
+
+        let keys: Vec<String> = buf
+            .lines()
+            .map(|line| line.unwrap())
+            .filter(|line| line.starts_with("I "))
+            .map(|line| line.split(' ').nth(1).unwrap().to_string())
+            .collect();
+        assert_eq!(keys.len(), 1000);
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // Interleaved inserts of the other group land on many of the pre-generated keys of 2
+        // bytes, which are replaced.
+        let spec = WorkloadSpec::builder()
+            .interleave_groups(true)
+            .add_group(
+                WorkloadSpecGroup::new().inserts(
+                    Inserts::new(500, 2, 8)
+                        .allow_duplicate_keys(false)
+                        .sorted(true),
+                ),
+            )
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(Inserts::new(500, 2, 8).allow_duplicate_keys(false)),
+            )
+            .build();
+        let buf = generate_to_vec(&spec).unwrap();
+        let keys: HashSet<String> = buf
+            .lines()
+            .map(|line| line.unwrap().split(' ').nth(1).unwrap().to_string())
+            .collect();
+        assert_eq!(keys.len(), 1000);
+    }
+
+    #[test]
+    fn key_len_classes() {
+        use spec::{Inserts, WorkloadSpecGroup};
+
+        let spec = WorkloadSpec::builder()
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(Inserts::new(10_000, 0, 8).key_lens(&[(8, 3.0), (16, 1.0)])),
+            )
+            .build();
+        let mut buf = Vec::new();
+        let stats =
+            write_operations_with_options(&mut buf, &spec, GenerateOptions::default(), |_, _| {})
+                .unwrap();
+
+        let mut lens: BTreeMap<usize, usize> = BTreeMap::new();
+        for line in buf.lines().map(|line| line.unwrap()) {
+            *lens
+                .entry(line.split(' ').nth(1).unwrap().len())
+                .or_default() += 1;
+        }
+        assert_eq!(lens, stats.key_len_histogram);
+        assert_eq!(lens.values().sum::<usize>(), 10_000);
+        // 3:1 within a few standard deviations.
+        assert!((7_300..=7_700).contains(&lens[&8]), "{lens:?}");
+        assert!((2_300..=2_700).contains(&lens[&16]), "{lens:?}");
+
+        // The byte estimate uses the weighted mean key length.
+        let estimate = spec.bytes_count() as f64;
+        assert!((buf.len() as f64 - estimate).abs() / estimate < 0.02);
+
+        let invalid = r#"{"sections": [{"groups": [{"inserts": {"amount": 1, "val_len": 8, "key_lens": []}}]}]}"#;
+        assert!(serde_json::from_str::<WorkloadSpec>(invalid).is_err());
+    }
+
+    #[test]
+    fn custom_key_gen() {
+        use keyset::VecKeySet;
+        use spec::{Inserts, WorkloadSpecGroup};
+
+        /// Zero-padded sequence numbers, `0000`, `0001`, and so on.
+        struct SequenceKeyGen {
+            next: usize,
+        }
+
+        impl KeyGen for SequenceKeyGen {
+            fn generate(&mut self, _: &mut WorkloadRng, len: usize) -> Key {
+                let key = format!("{:0len$}", self.next);
+                self.next += 1;
+                return key.as_bytes().into();
+            }
+        }
+
+        let spec = WorkloadSpec::builder()
+            .key_prefix("tenant:")
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 4, 8)))
+            .build();
+        let section = &spec.sections[0];
+        let mut rng = RngAlgorithm::default().seed_from_u64(0);
+        let mut key_gen = SequenceKeyGen { next: 0 };
+        let key = gen_section_key(&mut rng, &mut key_gen, section, 4);
+        assert_eq!(&*key, b"tenant:0000");
+
+        // Keys that are already valid are skipped.
+        let mut keys = VecKeySet::new(1);
+        keys.push(b"tenant:0001".as_slice().into());
+        let key = gen_absent_key(&mut rng, &mut key_gen, section, &keys, 4, 10, "").unwrap();
+        assert_eq!(&*key, b"tenant:0002");
+    }
+
+    #[test]
+    fn think_times() {
+        use spec::{Inserts, PointQueries, ThinkTimes, WorkloadSpecGroup};
+
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(100, 8, 8)))
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .point_queries(PointQueries::new(100))
+                    .think_times(ThinkTimes::new(25, 1500)),
+            )
+            .build();
+        let bytes_count = spec.bytes_count();
+        let mut buf = Vec::with_capacity(bytes_count);
+        let stats =
+            write_operations_with_options(&mut buf, &spec, GenerateOptions::default(), |_, _| {})
+                .unwrap();
+        assert_eq!(buf.len(), bytes_count);
+        assert_eq!(stats.think_times, 25);
+        assert_eq!(stats.operation_count(), 225);
+
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        assert_eq!(lines.iter().filter(|line| *line == "T 1500").count(), 25);
+        assert!(lines
+            .iter()
+            .all(|line| line == "T 1500" || !line.starts_with('T')));
+    }
+
+    #[test]
+    fn flushes() {
+        use spec::{Flushes, Inserts, PointQueries, WorkloadSpecGroup};
+
+        for interleave_groups in [false, true] {
+            let spec = WorkloadSpec::builder()
+                .interleave_groups(interleave_groups)
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .inserts(Inserts::new(100, 8, 8))
+                        .flushes(Flushes::new(4)),
+                )
+                .add_group(WorkloadSpecGroup::new().point_queries(PointQueries::new(100)))
+                .build();
+            let bytes_count = spec.bytes_count();
+            let mut buf = Vec::with_capacity(bytes_count);
+            let stats = write_operations_with_options(
+                &mut buf,
+                &spec,
+                GenerateOptions::default(),
+                |_, _| {},
+            )
+            .unwrap();
+            assert_eq!(buf.len(), bytes_count);
+            assert_eq!(stats.flushes, 4);
+            assert_eq!(stats.operation_count(), 204);
+
+            // Every flush follows another quarter of the inserts, whatever runs in between.
+            let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+            let mut inserts = 0;
+            let mut flushes = Vec::new();
+            for line in &lines {
+                match line.as_bytes()[0] {
+                    b'I' => inserts += 1,
+                    b'F' => {
+                        assert_eq!(line, "F");
+                        flushes.push(inserts);
+                    }
+                    _ => {}
+                }
+            }
+            assert_eq!(flushes, [25, 50, 75, 100]);
+            if !interleave_groups {
+                // The last flush ends its group.
+                assert_eq!(lines[103], "F");
+                assert!(lines[104].starts_with("P "));
+            }
+        }
+    }
+
+    #[test]
+    fn val_patterns() {
+        use spec::{Inserts, Updates, ValPattern, WorkloadSpecGroup};
+
+        let spec = WorkloadSpec::builder()
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(Inserts::new(100, 8, 64).val_pattern(ValPattern::Repeated(b'x'))),
+            )
+            .add_group(WorkloadSpecGroup::new().updates(
+                Updates::new(100, 64).val_pattern(ValPattern::LowEntropy { distinct_bytes: 3 }),
+            ))
+            .build();
+        let bytes_count = spec.bytes_count();
+        let buf = generate_to_vec(&spec).unwrap();
+        assert_eq!(buf.len(), bytes_count);
+
+        let mut update_bytes = HashSet::new();
+        for line in buf.lines() {
+            let line = line.unwrap();
+            let fields: Vec<&str> = line.split(' ').collect();
+            assert_eq!(fields[2].len(), 64);
+            match fields[0] {
+                "I" => assert!(fields[2].bytes().all(|b| b == b'x'), "{line}"),
+                "U" => update_bytes.extend(fields[2].bytes()),
+                _ => unreachable!("{line}"),
+            }
+        }
+        assert!(update_bytes.len() <= 3, "{update_bytes:?}");
+        assert!(update_bytes.iter().all(|b| b"012".contains(b)));
+
+        for val_pattern in [
+            ValPattern::Repeated(b' '),
+            ValPattern::LowEntropy { distinct_bytes: 0 },
+            ValPattern::LowEntropy { distinct_bytes: 63 },
+        ] {
+            let spec = WorkloadSpec::builder()
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .inserts(Inserts::new(10, 8, 8).val_pattern(val_pattern)),
+                )
+                .build();
+            let err = generate_to_vec(&spec).unwrap_err();
+            assert!(matches!(err, WorkloadError::InvalidSpec(_)), "{err}");
+        }
+    }
+
+    #[test]
+    fn operation_counts() {
+        use spec::{
+            CountRangeQueries, Deletes, Flushes, Inserts, PointQueries, RangeQueries,
+            ReadModifyWrites, WorkloadSpecGroup,
+        };
+
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(100, 8, 8)))
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .deletes(Deletes::new(10))
+                    .point_queries(PointQueries::new(20))
+                    .range_queries(RangeQueries::new(5, 0.1))
+                    .count_range_queries(CountRangeQueries::new(5, 3))
+                    .read_modify_writes(ReadModifyWrites::new(30, 8))
+                    .flushes(Flushes::new(2)),
+            )
+            .add_section()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(50, 8, 8)))
+            .build();
+        let counts = spec.operation_counts();
+        assert_eq!(counts.operation_count(), spec.operation_count());
+        assert_eq!(spec.group_counts(), [2, 1]);
+
+        let mut buf = Vec::new();
+        let stats =
+            write_operations_with_options(&mut buf, &spec, GenerateOptions::default(), |_, _| {})
+                .unwrap();
+        let by_type = |stats: &WorkloadStats| {
+            return [
+                stats.inserts,
+                stats.updates,
+                stats.deletes,
+                stats.point_queries,
+                stats.empty_point_queries,
+                stats.range_queries,
+                stats.range_deletes,
+                stats.think_times,
+                stats.flushes,
+                stats.read_modify_writes,
+            ];
+        };
+        assert_eq!(by_type(&counts), by_type(&stats));
+        assert_eq!(counts.distinct_keys, 0);
+    }
+
+    #[test]
+    fn bulk_load() {
+        use spec::{Deletes, Inserts, PointQueries, Updates, WorkloadSpecGroup};
+
+        for interleave_groups in [false, true] {
+            let spec = WorkloadSpec::builder()
+                .bulk_load(true)
+                .interleave_groups(interleave_groups)
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .inserts(Inserts::new(300, 8, 8))
+                        .point_queries(PointQueries::new(100)),
+                )
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .inserts(Inserts::new(200, 8, 8))
+                        .updates(Updates::new(100, 8))
+                        .deletes(Deletes { amount: 50 }),
+                )
+                .build();
+            let buf = generate_to_vec(&spec).unwrap();
+            let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+            assert_eq!(lines.len(), 750);
+
+            // Every insert comes first, sorted by key, then the rest of the groups.
+            let (load, run) = lines.split_at(500);
+            assert!(load.iter().all(|line| line.starts_with("I ")));
+            assert!(run.iter().all(|line| !line.starts_with("I ")));
+            let keys: Vec<&str> = load
+                .iter()
+                .map(|line| line.split(' ').nth(1).unwrap())
+                .collect();
+            assert!(keys.is_sorted());
+            let loaded: HashSet<&str> = keys.into_iter().collect();
+            assert!(run
+                .iter()
+                .all(|line| loaded.contains(line.split(' ').nth(1).unwrap())));
+        }
+    }
+
+    #[test]
+    fn warmup_point_queries() {
+        use spec::{Deletes, Inserts, PointQueries, WorkloadSpecGroup};
+
+        for bulk_load in [false, true] {
+            let spec = WorkloadSpec::builder()
+                .bulk_load(bulk_load)
+                .warmup_point_queries(50)
+                .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(200, 8, 8)))
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .inserts(Inserts::new(100, 8, 8))
+                        .point_queries(PointQueries::new(100))
+                        .deletes(Deletes { amount: 20 }),
+                )
+                .build();
+            assert_eq!(spec.operation_count(), 470);
+            let buf = generate_to_vec(&spec).unwrap();
+            let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+            assert_eq!(lines.len(), 472);
+
+            // The inserts of the second group also run before its other operations, so the warmup
+            // follows all 300 inserts.
+            let start = lines.iter().position(|line| line == "# warmup-start");
+            let end = lines.iter().position(|line| line == "# warmup-end");
+            let (start, end) = (start.unwrap(), end.unwrap());
+            assert_eq!((start, end), (300, 351));
+            assert!(lines[..start].iter().all(|line| line.starts_with("I ")));
+            let loaded: HashSet<&str> = lines[..start]
+                .iter()
+                .map(|line| line.split(' ').nth(1).unwrap())
+                .collect();
+            assert!(lines[start + 1..end].iter().all(|line| line
+                .strip_prefix("P ")
+                .is_some_and(|key| loaded.contains(key))));
+            assert!(lines[end + 1..].iter().all(|line| !line.starts_with('#')));
+        }
+
+        // A section of only inserts warms up at its end.
+        let spec = WorkloadSpec::builder()
+            .warmup_point_queries(5)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8)))
+            .build();
+        let buf = generate_to_vec(&spec).unwrap();
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        assert_eq!(lines[10], "# warmup-start");
+        assert_eq!(lines[16], "# warmup-end");
+        assert_eq!(buf.len(), spec.bytes_count());
+
+        // Reversing would move the warmup out of its fence, which fails before the first section
+        // is written.
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8)))
+            .add_section()
+            .warmup_point_queries(5)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8)))
+            .build();
+        let options = GenerateOptions {
+            reverse: true,
+            ..GenerateOptions::default()
+        };
+        let mut buf = Vec::new();
+        let err = write_operations_with_options(&mut buf, &spec, options, |_, _| {}).unwrap_err();
+        assert!(matches!(err, WorkloadError::InvalidSpec(_)), "{err}");
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn read_modify_writes() {
+        use spec::{Inserts, ReadModifyWrites, ThinkTimes, WorkloadSpecGroup};
+
+        for interleave_groups in [false, true] {
+            let spec = WorkloadSpec::builder()
+                .interleave_groups(interleave_groups)
+                .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(100, 8, 8)))
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .read_modify_writes(ReadModifyWrites::new(200, 16))
+                        .think_times(ThinkTimes::new(50, 1)),
+                )
+                .build();
+            assert_eq!(spec.operation_count(), 550);
+            let mut buf = Vec::new();
+            let stats = write_operations_with_options(
+                &mut buf,
+                &spec,
+                GenerateOptions::default(),
+                |_, _| {},
+            )
+            .unwrap();
+            assert_eq!(stats.read_modify_writes, 200);
+            assert_eq!((stats.point_queries, stats.updates), (200, 200));
+            assert_eq!(stats.operation_count(), 550);
+
+            let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+            assert_eq!(lines.len(), 550);
+            let inserted: HashSet<&str> = lines
+                .iter()
+                .filter(|line| line.starts_with("I "))
+                .map(|line| line.split(' ').nth(1).unwrap())
+                .collect();
+            // Every point query is the read of a pair, directly followed by its write.
+            let mut pairs = 0;
+            for (i, line) in lines.iter().enumerate() {
+                let Some(key) = line.strip_prefix("P ") else {
+                    continue;
+                };
+                let fields: Vec<&str> = lines[i + 1].split(' ').collect();
+                assert_eq!(fields[..2], ["U", key]);
+                assert_eq!(fields[2].len(), 16);
+                assert!(inserted.contains(key));
+                pairs += 1;
+            }
+            assert_eq!(pairs, 200);
+        }
+
+        // `max_ops` may cut the last pair after its read.
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8)))
+            .add_group(WorkloadSpecGroup::new().read_modify_writes(ReadModifyWrites::new(10, 8)))
+            .build();
+        let options = GenerateOptions {
+            max_ops: Some(15),
+            ..GenerateOptions::default()
+        };
+        let mut buf = Vec::new();
+        let stats = write_operations_with_options(&mut buf, &spec, options, |_, _| {}).unwrap();
+        assert_eq!(buf.lines().count(), 15);
+        assert_eq!((stats.read_modify_writes, stats.point_queries), (2, 3));
+    }
+
+    #[test]
+    fn group_by_key() {
+        use spec::{
+            Deletes, Inserts, PointQueries, RangeQueries, ThinkTimes, Updates, WorkloadSpecGroup,
+        };
+
+        let spec = |group_by_key: bool| {
+            WorkloadSpec::builder()
+                .seed(5)
+                .group_by_key(group_by_key)
+                .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(200, 8, 8)))
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .updates(Updates::new(300, 8))
+                        .deletes(Deletes { amount: 50 })
+                        .point_queries(PointQueries::new(300))
+                        .range_queries(RangeQueries::new(50, 0.1))
+                        .think_times(ThinkTimes::new(20, 1)),
+                )
+                .build()
+        };
+        let lines = |spec: &WorkloadSpec| -> Vec<String> {
+            let buf = generate_to_vec(spec).unwrap();
+            buf.lines().map(|line| line.unwrap()).collect()
+        };
+        let grouped = lines(&spec(true));
+        let mut ungrouped = lines(&spec(false));
+        assert_eq!(grouped.len(), 920);
+
+        // The operations on every key are contiguous.
+        let mut seen = HashSet::new();
+        let mut last = None;
+        for line in grouped.iter().filter(|line| !line.starts_with("T ")) {
+            let key = line.split(' ').nth(1).unwrap();
+            if last != Some(key) {
+                assert!(seen.insert(key), "{key} is used again after other keys");
+                last = Some(key);
+            }
+        }
+        // Same operations, only reordered.
+        let mut sorted = grouped.clone();
+        sorted.sort();
+        ungrouped.sort();
+        assert_eq!(sorted, ungrouped);
+    }
+
+    #[test]
+    fn reverse_operations() {
+        use spec::{
+            Deletes, EmptyPointQueries, Inserts, PointQueries, RangeDeletes, RangeQueries,
+            ThinkTimes, Updates, WorkloadSpecGroup,
+        };
+
+        let spec = WorkloadSpec::builder()
+            .seed(11)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(300, 8, 8)))
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .updates(Updates::new(300, 8))
+                    .deletes(Deletes { amount: 50 })
+                    .range_deletes(RangeDeletes::new(2, 0.05))
+                    .point_queries(PointQueries::new(300))
+                    .empty_point_queries(EmptyPointQueries::new(50, 8))
+                    .range_queries(RangeQueries::new(50, 0.1))
+                    .think_times(ThinkTimes::new(20, 1)),
+            )
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(Inserts::new(50, 8, 8))
+                    .point_queries(PointQueries::new(100)),
+            )
+            .build();
+        let generate = |reverse: bool| {
+            let options = GenerateOptions {
+                reverse,
+                ..GenerateOptions::default()
             };
+            let mut buf = Vec::new();
+            let stats = write_operations_with_options(&mut buf, &spec, options, |_, _| {}).unwrap();
+            let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+            return (lines, stats);
+        };
+        let (forward, forward_stats) = generate(false);
+        let (reversed, stats) = generate(true);
+        assert_eq!(stats, forward_stats);
+
+        // The loaded keys are distinct, so their inserts are written in reverse.
+        let loaded: HashSet<&String> = forward[..300].iter().collect();
+        let reversed_load: Vec<&String> = reversed
+            .iter()
+            .filter(|line| loaded.contains(line))
+            .collect();
+        assert!(reversed_load.into_iter().rev().eq(forward[..300].iter()));
+
+        // Every operation still sees the valid keys it was generated for: updates, deletes and
+        // range queries are on valid keys, and point queries hit or miss like before.
+        let mut valid: BTreeMap<&str, usize> = BTreeMap::new();
+        let (mut hits, mut misses) = (0, 0);
+        for line in &reversed {
+            let fields: Vec<&str> = line.split(' ').collect();
+            match fields[0] {
+                "I" => *valid.entry(fields[1]).or_default() += 1,
+                "U" => assert!(valid.contains_key(fields[1]), "{line}"),
+                "D" => {
+                    let count = valid.get_mut(fields[1]).expect("deleted key to be valid");
+                    *count -= 1;
+                    if *count == 0 {
+                        valid.remove(fields[1]);
+                    }
+                }
+                "DR" => valid.retain(|key, _| !(fields[1]..fields[2]).contains(key)),
+                "P" if valid.contains_key(fields[1]) => hits += 1,
+                "P" => misses += 1,
+                "R" => assert!(valid.contains_key(fields[1]) && valid.contains_key(fields[2])),
+                _ => assert_eq!(fields[0], "T"),
+            }
         }
+        assert_eq!((hits, misses), (400, 50));
 
-        fn len(&self) -> usize {
-            return self.keys.len();
+        // Same operations, only reordered.
+        let (mut forward, mut reversed) = (forward, reversed);
+        assert_ne!(forward, reversed);
+        forward.sort();
+        reversed.sort();
+        assert_eq!(forward, reversed);
+    }
+
+    #[test]
+    fn interleave_groups() {
+        use spec::{Deletes, Inserts, PointQueries, WorkloadSpecGroup};
+
+        // The groups insert values of different lengths, so every insert shows its group.
+        let spec = WorkloadSpec::builder()
+            .interleave_groups(true)
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(Inserts::new(500, 8, 8))
+                    .point_queries(PointQueries::new(500)),
+            )
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(Inserts::new(500, 8, 12))
+                    .deletes(Deletes::new(200)),
+            )
+            .build();
+        let bytes_count = spec.bytes_count();
+        let mut buf = Vec::with_capacity(bytes_count);
+        let stats =
+            write_operations_with_options(&mut buf, &spec, GenerateOptions::default(), |_, _| {})
+                .unwrap();
+        assert_eq!(buf.len(), bytes_count);
+        assert_eq!(stats.live_keys, 800);
+
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        assert_eq!(lines.len(), 1700);
+        assert!(lines[0].starts_with("I "));
+        let val_len = |line: &String| line.split(' ').nth(2).unwrap().len();
+        let first_insert = |val_len_of_group: usize| {
+            lines
+                .iter()
+                .position(|line| line.starts_with("I ") && val_len(line) == val_len_of_group)
+                .unwrap()
+        };
+        let last_insert = |val_len_of_group: usize| {
+            lines
+                .iter()
+                .rposition(|line| line.starts_with("I ") && val_len(line) == val_len_of_group)
+                .unwrap()
+        };
+        // Block-sequential groups would write every insert of one group before the other's.
+        assert!(first_insert(12) < last_insert(8));
+        assert!(first_insert(8) < last_insert(12));
+        // Each group keeps its own order, inserts before the operations on them.
+        let first_query = lines
+            .iter()
+            .position(|line| line.starts_with("P "))
+            .unwrap();
+        let first_delete = lines
+            .iter()
+            .position(|line| line.starts_with("D "))
+            .unwrap();
+        assert!(last_insert(8) < first_query);
+        assert!(last_insert(12) < first_delete);
+
+        let spec = WorkloadSpec::builder()
+            .interleave_groups(true)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8)))
+            .add_group(WorkloadSpecGroup::new().deletes(Deletes::new(20)))
+            .build();
+        let err = write_operations(&mut sink(), &spec).unwrap_err();
+        assert!(err.to_string().contains("only 10 keys are inserted"));
+
+        // The deletes may come before every point query.
+        let spec = WorkloadSpec::builder()
+            .interleave_groups(true)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8)))
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .deletes(Deletes::new(10))
+                    .point_queries(PointQueries::new(5)),
+            )
+            .build();
+        let err = write_operations(&mut sink(), &spec).unwrap_err();
+        assert!(matches!(err, WorkloadError::NoValidKeys(_)), "{err}");
+
+        // Another group's deletes can take every key before an overwrite, so an insert behind it
+        // goes first.
+        for seed in 0..200 {
+            let spec = WorkloadSpec::builder()
+                .seed(seed)
+                .interleave_groups(true)
+                .add_group(
+                    WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8).overwrite_ratio(0.5)),
+                )
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .deletes(Deletes::new(4))
+                        .point_queries(PointQueries::new(10)),
+                )
+                .build();
+            let buf = generate_to_vec(&spec).unwrap();
+            assert_eq!(buf.lines().count(), 24, "seed {seed}");
         }
+    }
 
-        fn is_empty(&self) -> bool {
-            return self.keys.is_empty();
+    #[test]
+    fn partition_sections() {
+        // Both sections insert every two digit key, so only the section tags keep them apart.
+        let spec_str = include_str!("../test_specs/partition_sections.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let bytes_count = spec.bytes_count();
+        let mut buf = Vec::with_capacity(bytes_count);
+        write_operations(&mut buf, &spec).unwrap();
+        assert_eq!(buf.len(), bytes_count);
+        let keys: Vec<String> = buf
+            .lines()
+            .map(|line| line.unwrap().split(' ').nth(1).unwrap().to_string())
+            .collect();
+        let first: HashSet<&String> = keys[..100].iter().collect();
+        let second: HashSet<&String> = keys[100..].iter().collect();
+        assert_eq!(first.len(), 100);
+        assert_eq!(second.len(), 100);
+        assert_eq!(first.intersection(&second).count(), 0);
+        assert!(first.iter().all(|key| key.starts_with("0~")));
+        assert!(second.iter().all(|key| key.starts_with("1~")));
+    }
+
+    #[test]
+    fn exponential_val_len() {
+        let spec_str = r#"{ "sections": [{ "groups": [{ "inserts": {
+            "amount": 100000, "key_len": 8, "val_len": { "exponential": { "mean": 100, "max": 10000 } }
+        } }] }] }"#;
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let mut buf = Vec::new();
+        write_operations(&mut buf, &spec).unwrap();
+        let val_lens: Vec<usize> = buf
+            .lines()
+            .map(|line| line.unwrap().split(' ').nth(2).unwrap().len())
+            .collect();
+        let mean = val_lens.iter().sum::<usize>() as f64 / val_lens.len() as f64;
+        assert!((mean - 100.0).abs() < 2.0, "{mean}");
+        assert!(val_lens.iter().any(|&len| len > 300));
+        let estimate = spec.bytes_count() as f64;
+        assert!((buf.len() as f64 - estimate).abs() / estimate < 0.02);
+    }
+
+    #[test]
+    fn workload_stats() {
+        let spec_str = include_str!("../test_specs/all_operations.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let stats =
+            write_operations_with_options(&mut sink(), &spec, Default::default(), |_, _| {})
+                .unwrap();
+        assert_eq!(stats.operation_count(), spec.operation_count());
+        assert_eq!(stats.inserts, 1000);
+        assert_eq!(stats.deletes, 100);
+        assert_eq!(stats.range_queries, 100);
+        assert_eq!(stats.live_keys, stats.inserts - stats.deletes);
+        assert_eq!(stats.distinct_keys, 1000);
+        assert_eq!(stats.key_len_histogram, BTreeMap::from([(16, 1000)]));
+
+        let spec_str = r#"{ "sections": [
+            { "groups": [{ "inserts": { "amount": 30, "key_len": 8, "val_len": 8 } }] },
+            { "groups": [{ "inserts": { "amount": 20, "key_len": 12, "val_len": 8 } }] }
+        ] }"#;
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let stats =
+            write_operations_with_options(&mut sink(), &spec, Default::default(), |_, _| {})
+                .unwrap();
+        assert_eq!(stats.key_len_histogram, BTreeMap::from([(8, 30), (12, 20)]));
+        assert_eq!(stats.live_keys, 50);
+    }
+
+    #[test]
+    fn reverse_range_queries() {
+        let spec_str = include_str!("../test_specs/reverse_range_queries.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let bytes_count = spec.bytes_count();
+        let mut buf = Vec::with_capacity(bytes_count);
+        write_operations(&mut buf, &spec).unwrap();
+        assert_eq!(buf.lines().count(), 11_000);
+        assert_eq!(buf.len(), bytes_count);
+        for line in buf.lines().skip(10_000) {
+            let line = line.unwrap();
+            let mut parts = line.split(' ');
+            assert_eq!(parts.next(), Some("R"));
+            let key_first = parts.next().unwrap();
+            let key_second = parts.next().unwrap();
+            assert!(key_first > key_second);
         }
+    }
 
-        fn push(&mut self, key: Key) {
-            if self.sorted && self.keys.last().is_some_and(|last_key| last_key > &key) {
-                self.sorted = false;
+    #[test]
+    fn jsonl_round_trip() {
+        let spec_str = include_str!("../test_specs/all_operations.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let mut buf = Vec::new();
+        write_operations_with_format(&mut buf, &spec, OutputFormat::Jsonl).unwrap();
+        assert_eq!(buf.lines().count(), spec.operation_count());
+        for line in buf.lines() {
+            let op: serde_json::Value = serde_json::from_str(&line.unwrap()).unwrap();
+            let fields: &[&str] = match op["op"].as_str().unwrap() {
+                "insert" | "update" => &["key", "val"],
+                "delete" | "point_query" => &["key"],
+                "range_query" => &["start", "end"],
+                other => panic!("unexpected op {other}"),
+            };
+            for field in fields {
+                assert!(op[field].is_string());
             }
-            self.keys.push(key.clone());
-            self.key_set.insert(key);
         }
+    }
 
-        fn remove(&mut self, idx: usize) -> Key {
-            let key = self.keys.remove(idx);
-            self.key_set.remove(&key);
-            return key;
+    #[test]
+    fn msgpack_round_trip() {
+        use serde::Deserialize;
+        use writer::MsgpackOp;
+
+        let spec_str = include_str!("../test_specs/all_operations.json");
+        let mut spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        spec.seed = Some(7);
+        let mut msgpack = Vec::new();
+        write_operations_with_format(&mut msgpack, &spec, OutputFormat::Msgpack).unwrap();
+        assert!(!msgpack.is_empty());
+
+        let mut de = rmp_serde::Deserializer::from_read_ref(&msgpack);
+        let mut ascii = Vec::new();
+        let op_writer = AsciiWriter::default();
+        for _ in 0..spec.operation_count() {
+            let key = |bytes: &[u8]| Key::from(bytes);
+            match MsgpackOp::deserialize(&mut de).unwrap() {
+                MsgpackOp::Insert { key: k, val } => {
+                    op_writer.write_insert(&mut ascii, &key(&k), &key(&val))
+                }
+                MsgpackOp::Update { key: k, val } => {
+                    op_writer.write_update(&mut ascii, &key(&k), &key(&val))
+                }
+                MsgpackOp::Delete { key: k } => op_writer.write_delete(&mut ascii, &key(&k)),
+                MsgpackOp::PointQuery { key: k } => {
+                    op_writer.write_point_query(&mut ascii, &key(&k))
+                }
+                MsgpackOp::RangeQuery { start, end } => {
+                    op_writer.write_range_query(&mut ascii, &key(&start), &key(&end))
+                }
+                other => panic!("unexpected op {other:?}"),
+            }
+            .unwrap();
         }
+        // The stream holds exactly one map per operation.
+        assert!(MsgpackOp::deserialize(&mut de).is_err());
 
-        fn get(&self, idx: usize) -> Option<&Key> {
-            return self.keys.get(idx);
+        // Decoded back into ascii, the operations match the ascii workload of the same seed.
+        let mut expected = Vec::new();
+        write_operations(&mut expected, &spec).unwrap();
+        assert_eq!(ascii, expected);
+    }
+
+    #[test]
+    fn format_registry() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use writer::FormatRegistry;
+
+        /// Counts the operations instead of writing them.
+        struct CountingOpWriter {
+            ops: Arc<AtomicUsize>,
         }
 
-        fn get_random(&self, rng: &mut Xoshiro256Plus) -> &Key {
-            return self
-                .keys
-                .get(rng.random_range(0..self.keys.len()))
-                .expect("KeySet to not be empty");
+        impl CountingOpWriter {
+            fn count(&self) -> Result<()> {
+                self.ops.fetch_add(1, Ordering::Relaxed);
+                return Ok(());
+            }
         }
 
-        fn contains(&self, key: &Key) -> bool {
-            return self.key_set.contains(key);
+        impl OpWriter for CountingOpWriter {
+            fn write_insert(&self, _: &mut impl Write, _: &Key, _: &Key) -> Result<()> {
+                return self.count();
+            }
+
+            fn write_update(&self, _: &mut impl Write, _: &Key, _: &Key) -> Result<()> {
+                return self.count();
+            }
+
+            fn write_delete(&self, _: &mut impl Write, _: &Key) -> Result<()> {
+                return self.count();
+            }
+
+            fn write_point_query(&self, _: &mut impl Write, _: &Key) -> Result<()> {
+                return self.count();
+            }
+
+            fn write_range_query(&self, _: &mut impl Write, _: &Key, _: &Key) -> Result<()> {
+                return self.count();
+            }
+
+            fn write_seek_query(&self, _: &mut impl Write, _: &Key) -> Result<()> {
+                return self.count();
+            }
+
+            fn write_count_range_query(&self, _: &mut impl Write, _: &Key, _: usize) -> Result<()> {
+                return self.count();
+            }
+
+            fn write_range_delete(&self, _: &mut impl Write, _: &Key, _: &Key) -> Result<()> {
+                return self.count();
+            }
+
+            fn write_think_time(&self, _: &mut impl Write, _: u64) -> Result<()> {
+                return self.count();
+            }
+
+            fn write_flush(&self, _: &mut impl Write) -> Result<()> {
+                return self.count();
+            }
         }
 
-        fn sort(&mut self) {
-            if !self.sorted {
-                self.keys.sort();
-                self.sorted = true;
+        let spec_str = include_str!("../test_specs/all_operations.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let ops = Arc::new(AtomicUsize::new(0));
+        let mut registry = FormatRegistry::new();
+        let op_writer = CountingOpWriter { ops: ops.clone() };
+        registry.register("count", op_writer);
+        let names: Vec<&str> = registry.names().collect();
+        assert_eq!(names, ["ascii", "count", "jsonl", "msgpack"]);
+
+        let options = GenerateOptions::default();
+        let mut ascii = Vec::new();
+        let ascii_stats = registry
+            .write("ascii", &mut ascii, &spec, options, |_, _| {})
+            .unwrap();
+        let mut buf = Vec::new();
+        let stats = registry
+            .write("count", &mut buf, &spec, options, |_, _| {})
+            .unwrap();
+        assert!(buf.is_empty());
+        assert_eq!(stats, ascii_stats);
+        // Called once for every operation written.
+        assert_eq!(ops.load(Ordering::Relaxed), ascii.lines().count());
+
+        let err = registry
+            .write("csv", &mut buf, &spec, options, |_, _| {})
+            .unwrap_err();
+        assert!(
+            matches!(err, WorkloadError::UnregisteredFormat { .. }),
+            "{err}"
+        );
+        assert!(err.to_string().contains("`count`"), "{err}");
+    }
+
+    #[test]
+    fn iter_operations_counts() {
+        use spec::{Deletes, Inserts, WorkloadSpecGroup};
+
+        let spec_str = include_str!("../test_specs/all_operations.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let ops: Vec<Operation> = iter_operations(&spec).map(|op| op.unwrap()).collect();
+        assert_eq!(ops.len(), spec.operation_count());
+
+        let count = |pred: fn(&Operation) -> bool| ops.iter().filter(|op| pred(op)).count();
+        assert_eq!(count(|op| matches!(op, Operation::Insert { .. })), 1000);
+        assert_eq!(count(|op| matches!(op, Operation::Update { .. })), 100);
+        assert_eq!(count(|op| matches!(op, Operation::Delete { .. })), 100);
+        // Point queries and empty point queries.
+        assert_eq!(count(|op| matches!(op, Operation::PointQuery { .. })), 200);
+        assert_eq!(count(|op| matches!(op, Operation::RangeQuery { .. })), 100);
+        assert!(ops.iter().all(|op| match op {
+            Operation::RangeQuery { lo, hi } => lo <= hi,
+            _ => true,
+        }));
+
+        // Stopping early doesn't wait for the rest of the workload.
+        let spec_str = include_str!("../test_specs/1m_i.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        assert_eq!(iter_operations(&spec).take(10).count(), 10);
+
+        // A failure is the last item.
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8)))
+            .add_group(WorkloadSpecGroup::new().deletes(Deletes::new(11)))
+            .build();
+        let ops: Vec<_> = iter_operations(&spec).collect();
+        assert_eq!(ops.len(), 1);
+        assert!(matches!(ops[0], Err(WorkloadError::TooManyDeletes { .. })));
+    }
+
+    #[test]
+    fn key_set_selection() {
+        let select = |spec_str: &str| {
+            let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+            return KeySetKind::select(&spec.sections[0]);
+        };
+        assert_eq!(
+            select(include_str!("../test_specs/1m_i.json")),
+            KeySetKind::Vec
+        );
+        assert_eq!(
+            select(include_str!("../test_specs/deletes.json")),
+            KeySetKind::Vec
+        );
+        assert_eq!(
+            select(include_str!("../test_specs/1m_i-1m_rq.json")),
+            KeySetKind::Vec
+        );
+        assert_eq!(
+            select(include_str!("../test_specs/empty_point_queries.json")),
+            KeySetKind::VecHashSet
+        );
+        assert_eq!(
+            select(include_str!(
+                "../test_specs/empty_point_queries_numeric.json"
+            )),
+            KeySetKind::Roaring
+        );
+        assert_eq!(
+            select(include_str!("../test_specs/all_operations.json")),
+            KeySetKind::VecHashSet
+        );
+        assert_eq!(
+            select(include_str!("../test_specs/complex.json")),
+            KeySetKind::OrderStat
+        );
+        assert_eq!(
+            select(include_str!("../test_specs/bloom_filter_fp_rate.json")),
+            KeySetKind::VecBloomFilter
+        );
+        assert_eq!(
+            select(include_str!("../test_specs/duplicate_keys.json")),
+            KeySetKind::VecHashSet
+        );
+    }
+
+    #[test]
+    fn sequential_keys_with_later_inserts() {
+        use spec::{Inserts, KeyDistribution, PointQueries, WorkloadSpecGroup};
+
+        let group = || {
+            return WorkloadSpecGroup::new()
+                .inserts(Inserts::new(100, 8, 8))
+                .point_queries(
+                    PointQueries::new(100).key_distribution(KeyDistribution::Sequential),
+                );
+        };
+        // Inserts only before the first sequential picks keep the keys sorted once.
+        let spec = WorkloadSpec::builder()
+            .add_section()
+            .add_group(group())
+            .build();
+        assert_eq!(KeySetKind::select(&spec.sections[0]), KeySetKind::Vec);
+
+        // Inserts between sequential picks would re-sort the keys on every later pick.
+        for interleave_groups in [false, true] {
+            let spec = WorkloadSpec::builder()
+                .add_section()
+                .interleave_groups(interleave_groups)
+                .add_group(group())
+                .add_group(group())
+                .build();
+            assert_eq!(KeySetKind::select(&spec.sections[0]), KeySetKind::OrderStat);
+            let mut buf = Vec::new();
+            let stats = write_operations_with_options(
+                &mut buf,
+                &spec,
+                GenerateOptions::default(),
+                |_, _| {},
+            )
+            .unwrap();
+            assert_eq!(stats.operation_count(), 400);
+        }
+    }
+
+    #[cfg(feature = "sst")]
+    #[test]
+    fn sst_ingest() {
+        let spec_str = include_str!("../test_specs/updates_existing.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let dir = std::env::temp_dir().join(format!("workload-gen-sst-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let sst_path = dir.join("inserts.sst");
+
+        let key_count = sst::write_sst(&spec, &sst_path).unwrap();
+        assert_eq!(key_count, 1000);
+
+        let db = rocksdb::DB::open_default(dir.join("db")).unwrap();
+        db.ingest_external_file(vec![&sst_path]).unwrap();
+        assert_eq!(db.iterator(rocksdb::IteratorMode::Start).count(), key_count);
+        drop(db);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn key_prefix() {
+        let spec_str = include_str!("../test_specs/key_prefix.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let bytes_count = spec.bytes_count();
+        let mut buf = Vec::with_capacity(bytes_count);
+        write_operations(&mut buf, &spec).unwrap();
+        assert_eq!(buf.len(), bytes_count);
+        for line in buf.lines() {
+            let line = line.unwrap();
+            let mut parts = line.split(' ');
+            let op = parts.next().unwrap();
+            let keys = if op == "R" { 2 } else { 1 };
+            for key in parts.take(keys) {
+                assert!(key.starts_with("tenant-42:"), "{line}");
             }
         }
     }
-    pub struct VecBloomFilterKeySet {
-        keys: Vec<Key>,
-        bf: BloomFilter,
-        sorted: bool,
+
+    #[test]
+    fn length_prefixed_keys() {
+        let spec_str = include_str!("../test_specs/length_prefixed_keys.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let mut buf = Vec::new();
+        write_operations(&mut buf, &spec).unwrap();
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        let mut keys: Vec<&str> = lines[..1000]
+            .iter()
+            .map(|line| line.split(' ').nth(1).unwrap())
+            .collect();
+        assert!(keys[..500].iter().all(|key| key.starts_with("12")));
+        assert!(keys[500..].iter().all(|key| key.starts_with("04")));
+
+        // Short keys sort before long ones, and each range query covers half of the keys in order.
+        keys.sort();
+        assert!(keys[..500].iter().all(|key| key.len() == 6));
+        for line in &lines[1000..] {
+            let mut parts = line.split(' ').skip(1);
+            let (start, end) = (parts.next().unwrap(), parts.next().unwrap());
+            let covered =
+                keys.partition_point(|k| *k <= end) - keys.partition_point(|k| *k < start);
+            assert_eq!(covered, 502, "{line}");
+        }
     }
 
-    impl KeySet for VecBloomFilterKeySet {
-        fn new(capacity: usize) -> Self {
-            return Self {
-                keys: Vec::with_capacity(capacity),
-                bf: BloomFilter::with_rate(0.01, capacity as u32),
-                sorted: true,
+    #[test]
+    fn builder_matches_json() {
+        use spec::{
+            Deletes, EmptyPointQueries, Inserts, PointQueries, RangeQueries, Updates,
+            WorkloadSpecGroup,
+        };
+
+        let spec_str = include_str!("../test_specs/key_prefix.json");
+        let json_spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let spec = WorkloadSpec::builder()
+            .add_section()
+            .key_prefix("tenant-42:")
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 8, 16)))
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .updates(Updates::new(100, 16).existing(false))
+                    .deletes(Deletes::new(100))
+                    .point_queries(PointQueries::new(100))
+                    .empty_point_queries(EmptyPointQueries::new(100, 8))
+                    .range_queries(RangeQueries::new(100, 0.1)),
+            )
+            .build();
+        assert_eq!(format!("{spec:?}"), format!("{json_spec:?}"));
+
+        let mut buf = Vec::new();
+        let stats =
+            write_operations_with_options(&mut buf, &spec, GenerateOptions::default(), |_, _| {})
+                .unwrap();
+        let mut json_buf = Vec::new();
+        let json_stats = write_operations_with_options(
+            &mut json_buf,
+            &json_spec,
+            GenerateOptions::default(),
+            |_, _| {},
+        )
+        .unwrap();
+        assert_eq!(buf.len(), json_buf.len());
+        let ops = |buf: &[u8]| -> Vec<String> {
+            buf.lines()
+                .map(|line| line.unwrap().split(' ').next().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(ops(&buf), ops(&json_buf));
+        assert_eq!(stats.operation_count(), json_stats.operation_count());
+    }
+
+    #[test]
+    fn range_queries_contain_selected_keys() {
+        use keyset::{OrderStatKeySet, VecKeySet};
+        use spec::{Inserts, RangeQueries, WorkloadSpecGroup};
+
+        // With 3 keys, a selectivity of 0.9 can only fit 1 key between the endpoints, and a tiny
+        // selectivity still spans 1 key.
+        assert_eq!(range_query_len(0.0001, 100), 1);
+        for (inserts, selectivity, expected) in [(1000, 0.1, 100), (3, 0.9, 1), (100, 0.0001, 1)] {
+            let spec = WorkloadSpec::builder()
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .inserts(Inserts::new(inserts, 16, 8).allow_duplicate_keys(false)),
+                )
+                .add_group(
+                    WorkloadSpecGroup::new().range_queries(RangeQueries::new(100, selectivity)),
+                )
+                .build();
+            let mut buf = Vec::new();
+            write_operations(&mut buf, &spec).unwrap();
+            let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+
+            let mut keys = VecKeySet::new(inserts);
+            let mut tree_keys = OrderStatKeySet::new(inserts);
+            for line in &lines[..inserts] {
+                let key: Key = line.split(' ').nth(1).unwrap().as_bytes().into();
+                keys.push(key.clone());
+                tree_keys.push(key);
+            }
+            keys.sort();
+            for line in &lines[inserts..] {
+                let mut parts = line.split(' ').skip(1);
+                let start: Key = parts.next().unwrap().as_bytes().into();
+                let end: Key = parts.next().unwrap().as_bytes().into();
+                assert_eq!(keys.count_in_range(&start, &end), expected, "{line}");
+                assert_eq!(tree_keys.count_in_range(&start, &end), expected, "{line}");
+            }
+        }
+    }
+
+    #[test]
+    fn range_width_histogram() {
+        use spec::{Inserts, RangeQueries, WorkloadSpecGroup};
+
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 16, 8)))
+            .add_group(WorkloadSpecGroup::new().range_queries(RangeQueries::new(200, 0.1)))
+            .build();
+        let options = GenerateOptions {
+            range_width_histogram: true,
+            ..GenerateOptions::default()
+        };
+        let stats = write_operations_with_options(&mut sink(), &spec, options, |_, _| {}).unwrap();
+        assert_eq!(stats.range_widths.values().sum::<usize>(), 200);
+        let mean = stats
+            .range_widths
+            .iter()
+            .map(|(width, count)| width * count)
+            .sum::<usize>() as f64
+            / 200.0;
+        let expected = 0.1 * stats.live_keys as f64;
+        assert!((mean - expected).abs() <= 1.0, "{mean} vs {expected}");
+
+        // Not recorded by default.
+        let stats = write_operations_with_options(
+            &mut sink(),
+            &spec,
+            GenerateOptions::default(),
+            |_, _| {},
+        )
+        .unwrap();
+        assert!(stats.range_widths.is_empty());
+    }
+
+    #[test]
+    fn domain_based_range_widths() {
+        use keyset::VecKeySet;
+
+        // 900 keys packed in the first 3 letters of the domain, and 100 keys spread over the
+        // other 23.
+        let mut keys = VecKeySet::new(1000);
+        for i in 0..900 {
+            let key = format!("{}{:03}", (b'a' + (i / 300) as u8) as char, i % 300);
+            keys.push(key.as_bytes().into());
+        }
+        for i in 0..100 {
+            let key = format!("{}{i:03}", (b'd' + i as u8 % 23) as char);
+            keys.push(key.as_bytes().into());
+        }
+        keys.sort();
+        let key = |idx: usize| keys.get(idx).unwrap();
+        let (lo, hi) = (key_position(key(0), 0), key_position(key(999), 0));
+        let width = |(start, end): (usize, usize)| {
+            (key_position(key(end), 0) - key_position(key(start), 0)) / (hi - lo)
+        };
+        let count = |(start, end): (usize, usize)| keys.count_in_range(key(start), key(end));
+
+        let mut rng = RngAlgorithm::default().seed_from_u64(0);
+        let index_ranges: Vec<_> = (0..1000)
+            .map(|_| index_range(&mut rng, keys.len(), 0.1))
+            .collect();
+        let domain_ranges: Vec<_> = (0..1000)
+            .map(|_| domain_range(&mut rng, &keys, 0.1))
+            .collect();
+
+        // Index-based ranges hold the same number of keys, but ranges over the sparse keys span
+        // most of the domain.
+        assert!(index_ranges.iter().all(|&range| count(range) == 100));
+        assert!(index_ranges.iter().any(|&range| width(range) > 0.5));
+        // Domain-based ranges never span more than their selectivity of the domain, so ranges over
+        // the packed keys hold most keys, and ranges over the sparse keys hold few.
+        assert!(domain_ranges.iter().all(|&range| width(range) <= 0.1));
+        assert!(domain_ranges.iter().any(|&range| count(range) > 500));
+        assert!(domain_ranges.iter().any(|&range| count(range) < 20));
+
+        for selectivity in [-0.1, 1.5] {
+            let spec = WorkloadSpec::builder()
+                .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(100, 8, 8)))
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .range_queries(RangeQueries::new(100, selectivity).domain_based(true)),
+                )
+                .build();
+            let err = spec.validate().unwrap_err();
+            assert!(err.to_string().contains("selectivity"), "{err}");
+        }
+    }
+
+    #[test]
+    fn range_query_min_gap() {
+        use keyset::VecKeySet;
+        use spec::RangeQueries;
+
+        // 10 clusters of 100 nearly identical keys, spread over the domain.
+        let mut keys = VecKeySet::new(1000);
+        for i in 0..1000 {
+            let key = format!("{}{:03}", (b'a' + (i / 100) as u8 * 2) as char, i % 100);
+            keys.push(key.as_bytes().into());
+        }
+        keys.sort();
+        let domain = KeyDomain::new(&keys);
+
+        // Without a gap, most small ranges stay within a cluster.
+        let mut rng = RngAlgorithm::default().seed_from_u64(0);
+        let rs = RangeQueries::new(1000, 0.01);
+        let gaps: Vec<f64> = (0..1000)
+            .map(|_| {
+                let (start, end) = range_query_range(&mut rng, &keys, &rs).unwrap();
+                domain.gap(&keys, start, end)
+            })
+            .collect();
+        assert!(gaps.iter().filter(|&&gap| gap < 0.05).count() > 500);
+
+        // Domain-based ranges need to be wider than the space between clusters to span two.
+        let domain_based = RangeQueries::new(1000, 0.2).domain_based(true);
+        for rs in [rs, rs.reverse(true), domain_based] {
+            let rs = rs.min_gap(0.05);
+            for _ in 0..1000 {
+                let (start, end) = range_query_range(&mut rng, &keys, &rs).unwrap();
+                assert!(domain.gap(&keys, start, end) >= 0.05);
+            }
+        }
+
+        // Ranges of 1% of the domain never span 10% of it.
+        let rs = RangeQueries::new(1000, 0.01)
+            .domain_based(true)
+            .min_gap(0.1);
+        let err = range_query_range(&mut rng, &keys, &rs).unwrap_err();
+        assert!(matches!(err, WorkloadError::NoValidKeys(_)), "{err}");
+
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(100, 8, 8)))
+            .add_group(WorkloadSpecGroup::new().range_queries(rs.min_gap(1.5)))
+            .build();
+        let err = spec.validate().unwrap_err();
+        assert!(matches!(err, WorkloadError::InvalidSpec(_)), "{err}");
+    }
+
+    /// Checks that every count range query of a workload with 1000 inserts starts at a valid key
+    /// with at most `count` keys after it, returning the emitted counts.
+    fn check_count_range_queries(spec_str: &str) -> Vec<usize> {
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let mut buf = Vec::new();
+        write_operations(&mut buf, &spec).unwrap();
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        let mut keys: Vec<&str> = lines[..1000]
+            .iter()
+            .map(|line| line.split(' ').nth(1).unwrap())
+            .collect();
+        keys.sort();
+
+        let mut counts = Vec::new();
+        for line in &lines[1000..] {
+            let [op, start, count] = line.split(' ').collect::<Vec<_>>()[..] else {
+                panic!("malformed range query {line}");
             };
+            assert_eq!(op, "R");
+            let count: usize = count.strip_prefix('+').unwrap().parse().unwrap();
+            let start_idx = keys.binary_search(&start).unwrap();
+            assert!(start_idx + count <= keys.len(), "{line}");
+            counts.push(count);
+        }
+        return counts;
+    }
+
+    #[test]
+    fn count_range_queries() {
+        let spec_str = include_str!("../test_specs/count_range_queries.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let mut buf = Vec::new();
+        write_operations(&mut buf, &spec).unwrap();
+        assert_eq!(buf.len(), spec.bytes_count());
+        assert!(check_count_range_queries(spec_str)
+            .iter()
+            .all(|&count| count == 100));
+
+        // Counts past the number of valid keys are clamped.
+        let spec_str = spec_str.replace("\"count\": 100", "\"count\": 5000");
+        assert!(check_count_range_queries(&spec_str)
+            .iter()
+            .all(|&count| count == 1000));
+    }
+
+    #[test]
+    fn empty_point_queries() {
+        let spec_str = include_str!("../test_specs/empty_point_queries.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(&spec_str).unwrap();
+        let bytes_count = spec.bytes_count();
+        let mut buf = Vec::with_capacity(bytes_count);
+        write_operations(&mut buf, &spec).unwrap();
+        assert_eq!(buf.lines().count(), 101_000);
+        assert_eq!(buf.len(), bytes_count);
+    }
+
+    proptest::proptest! {
+        /// Random specs generate without panicking or failing, one line per operation besides
+        /// comments and column family directives.
+        #[test]
+        fn arbitrary_specs(seed in proptest::prelude::any::<u64>()) {
+            use spec::RngAlgorithm;
+
+            let spec = WorkloadSpec::arbitrary(&mut RngAlgorithm::default().seed_from_u64(seed));
+            let mut buf = Vec::new();
+            let stats = write_operations_with_options(
+                &mut buf,
+                &spec,
+                GenerateOptions::default(),
+                |_, _| {},
+            );
+            let stats = stats.map_err(|err| format!("{err} for {spec:#?}"));
+            proptest::prop_assert!(stats.is_ok(), "{}", stats.unwrap_err());
+            let ops = buf
+                .lines()
+                .map(|line| line.unwrap())
+                .filter(|line| !line.starts_with('#') && !line.starts_with("CF "))
+                .count();
+            proptest::prop_assert_eq!(ops, spec.operation_count());
+            proptest::prop_assert_eq!(stats.unwrap().operation_count(), spec.operation_count());
+        }
+    }
+
+    #[test]
+    fn seek_queries() {
+        use spec::{Inserts, SeekQueries, WorkloadSpecGroup};
+
+        let spec = WorkloadSpec::builder()
+            .seed(3)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 12, 8)))
+            .add_group(WorkloadSpecGroup::new().seek_queries(SeekQueries::new(400).hit_ratio(0.25)))
+            .build();
+        let mut buf = Vec::new();
+        let stats =
+            write_operations_with_options(&mut buf, &spec, GenerateOptions::default(), |_, _| {})
+                .unwrap();
+        assert_eq!(stats.seek_queries, 400);
+        assert_eq!(spec.operation_counts().seek_queries, 400);
+        assert_eq!(buf.len(), spec.bytes_count());
+
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        let inserted: HashSet<&str> = lines[..1000]
+            .iter()
+            .map(|line| line.split(' ').nth(1).unwrap())
+            .collect();
+        let mut hits = 0;
+        for line in &lines[1000..] {
+            let fields: Vec<&str> = line.split(' ').collect();
+            assert_eq!(fields.len(), 2, "{line}");
+            assert_eq!(fields[0], "SK");
+            // Seeks target keys of the inserted format, valid or absent.
+            assert_eq!(fields[1].len(), 12);
+            assert!(fields[1].bytes().all(|b| b.is_ascii_alphanumeric()));
+            hits += usize::from(inserted.contains(fields[1]));
+        }
+        assert_eq!(hits, 100);
+    }
+
+    #[test]
+    fn column_family() {
+        use spec::{Inserts, PointQueries, WorkloadSpecGroup};
+        use writer::convert;
+
+        let spec = WorkloadSpec::builder()
+            .seed(4)
+            .column_family("users")
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(100, 8, 8)))
+            .add_group(WorkloadSpecGroup::new().point_queries(PointQueries::new(50)))
+            .add_section()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(20, 8, 8)))
+            .add_section()
+            .column_family("orders")
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(30, 8, 8)))
+            .build();
+        let buf = generate_to_vec(&spec).unwrap();
+        assert_eq!(buf.len(), spec.bytes_count());
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        assert_eq!(lines.len(), 203);
+        // The operations of a tagged section follow its column family directive.
+        assert_eq!(lines[0], "CF users");
+        assert!(lines[1..151].iter().all(|line| !line.starts_with("CF")));
+        // The untagged section goes to the default column family, not to the one before it.
+        assert_eq!(lines[151], "CF default");
+        assert!(lines[152..172].iter().all(|line| line.starts_with("I ")));
+        assert_eq!(lines[172], "CF orders");
+        assert!(lines[173..].iter().all(|line| line.starts_with("I ")));
+
+        // Both phases route their operations of the section.
+        let (mut load, mut run) = (Vec::new(), Vec::new());
+        let options = GenerateOptions::default();
+        write_phases_with_options(&mut load, &mut run, &spec, options, |_, _| {}).unwrap();
+        assert!(load.starts_with(b"CF users\nI "));
+        assert!(run.starts_with(b"CF users\nP "));
+
+        let mut jsonl = Vec::new();
+        let ascii_format = AsciiFormat::default();
+        convert(
+            &mut buf.as_slice(),
+            OutputFormat::Ascii,
+            &mut jsonl,
+            OutputFormat::Jsonl,
+            ascii_format,
+        )
+        .unwrap();
+        assert!(jsonl.starts_with(br#"{"op":"column_family","name":"users"}"#));
+        let mut round_trip = Vec::new();
+        let ops = convert(
+            &mut jsonl.as_slice(),
+            OutputFormat::Jsonl,
+            &mut round_trip,
+            OutputFormat::Ascii,
+            ascii_format,
+        )
+        .unwrap();
+        assert_eq!(ops, spec.operation_count());
+        assert_eq!(round_trip, buf);
+
+        // A name has to be one field of its directive.
+        for name in ["user data", "users\n", "users\t"] {
+            let spec = WorkloadSpec::builder()
+                .column_family(name)
+                .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(10, 8, 8)))
+                .build();
+            let err = spec.validate().unwrap_err();
+            assert!(
+                matches!(&err, WorkloadError::InvalidSpec(msg) if msg.contains("whitespace")),
+                "{err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn near_miss_empty_point_queries() {
+        use spec::{Deletes, EmptyPointQueries, Inserts, WorkloadSpecGroup};
+
+        let spec = WorkloadSpec::builder()
+            .key_prefix("tenant:")
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 12, 8)))
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .empty_point_queries(EmptyPointQueries::new(500, 4).near_miss(true)),
+            )
+            .build();
+        let buf = generate_to_vec(&spec).unwrap();
+        assert_eq!(buf.len(), spec.bytes_count());
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        let key = |line: &String| line.split(' ').nth(1).unwrap().as_bytes().to_vec();
+        let inserted: HashSet<Vec<u8>> = lines[..1000].iter().map(key).collect();
+        for query in lines[1000..].iter().map(key) {
+            assert!(!inserted.contains(&query));
+            assert!(query.starts_with(b"tenant:"));
+            // All but one byte match an inserted key.
+            assert!(inserted.iter().any(|key| {
+                key.len() == query.len()
+                    && key.iter().zip(&query).filter(|(a, b)| a != b).count() == 1
+            }));
+        }
+
+        // A near miss needs a valid key to start from.
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1, 8, 8)))
+            .add_group(WorkloadSpecGroup::new().deletes(Deletes { amount: 1 }))
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .empty_point_queries(EmptyPointQueries::new(1, 8).near_miss(true)),
+            )
+            .build();
+        let err = generate_to_vec(&spec).unwrap_err();
+        assert!(matches!(err, WorkloadError::NoValidKeys(_)), "{err}");
+
+        // Keys that are only the prefix have no byte to change.
+        let spec = WorkloadSpec::builder()
+            .key_prefix("tenant:")
+            .add_group(
+                WorkloadSpecGroup::new().inserts(Inserts::new(1, 0, 8).key_lens(&[(0, 1.0)])),
+            )
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .empty_point_queries(EmptyPointQueries::new(1, 8).near_miss(true)),
+            )
+            .build();
+        let err = generate_to_vec(&spec).unwrap_err();
+        assert!(matches!(err, WorkloadError::KeySpaceExhausted(_)), "{err}");
+    }
+
+    #[test]
+    fn empty_point_queries_exhausted_key_space() {
+        let spec_str = include_str!("../test_specs/empty_point_queries_exhausted.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let err = write_operations(&mut sink(), &spec).unwrap_err();
+        assert!(err.to_string().contains("key space only has 62 keys"));
+    }
+
+    #[test]
+    fn empty_point_queries_max_attempts() {
+        use spec::{EmptyPointQueries, Inserts, KeySpace, WorkloadSpecGroup};
+
+        // 9 of the 10 numeric keys of length 1 are valid, so an attempt finds the absent key with
+        // a probability of 1/10.
+        let spec = |epqs: EmptyPointQueries| {
+            return WorkloadSpec::builder()
+                .seed(7)
+                .key_space(KeySpace::Numeric)
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .inserts(Inserts::new(9, 1, 8).allow_duplicate_keys(false)),
+                )
+                .add_group(WorkloadSpecGroup::new().empty_point_queries(epqs))
+                .build();
+        };
+        let buf = generate_to_vec(&spec(EmptyPointQueries::new(20, 1))).unwrap();
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        let absent = &lines[9];
+        assert!(lines[10..].iter().all(|line| line == absent));
+
+        let err =
+            generate_to_vec(&spec(EmptyPointQueries::new(20, 1).max_attempts(1))).unwrap_err();
+        assert!(matches!(err, WorkloadError::KeySpaceExhausted(_)));
+        let message = err.to_string();
+        assert!(
+            message.contains("absent from 9 valid keys after 1 attempts"),
+            "{message}"
+        );
+        assert!(message.contains("key space only has 10 keys"), "{message}");
+
+        let err =
+            generate_to_vec(&spec(EmptyPointQueries::new(20, 1).max_attempts(0))).unwrap_err();
+        assert!(matches!(err, WorkloadError::InvalidSpec(_)));
+    }
+
+    #[test]
+    fn point_query_hit_ratio() {
+        use spec::{Inserts, PointQueries, WorkloadSpecGroup};
+
+        let spec = |hit_ratio: f32| {
+            WorkloadSpec::builder()
+                .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 8, 8)))
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .point_queries(PointQueries::new(1000).hit_ratio(hit_ratio)),
+                )
+                .build()
+        };
+        let spec_70 = spec(0.7);
+        let mut buf = Vec::new();
+        let stats = write_operations_with_options(
+            &mut buf,
+            &spec_70,
+            GenerateOptions::default(),
+            |_, _| {},
+        )
+        .unwrap();
+        assert_eq!(buf.len(), spec_70.bytes_count());
+        let lines: Vec<String> = buf.lines().map(|line| line.unwrap()).collect();
+        let key = |line: &String| line.split(' ').nth(1).unwrap().to_string();
+        let inserted: HashSet<String> = lines[..1000].iter().map(key).collect();
+        let hits = lines[1000..]
+            .iter()
+            .filter(|line| inserted.contains(&key(line)))
+            .count();
+        assert!((690..=710).contains(&hits), "{hits} hits");
+        assert_eq!(
+            (stats.point_queries, stats.empty_point_queries),
+            (hits, 1000 - hits)
+        );
+        let counts = spec_70.operation_counts();
+        assert_eq!(
+            (counts.point_queries, counts.empty_point_queries),
+            (hits, 1000 - hits)
+        );
+        // Misses are spread over the queries instead of coming first.
+        let last_misses = lines[1900..]
+            .iter()
+            .filter(|line| !inserted.contains(&key(line)))
+            .count();
+        assert!((20..=40).contains(&last_misses), "{last_misses} misses");
+
+        let err = write_operations(&mut sink(), &spec(1.5)).unwrap_err();
+        assert!(matches!(err, WorkloadError::InvalidSpec(_)));
+    }
+
+    #[test]
+    fn uuid_key_space() {
+        let spec_str = include_str!("../test_specs/uuid.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let bytes_count = spec.bytes_count();
+        let mut buf = Vec::with_capacity(bytes_count);
+        write_operations(&mut buf, &spec).unwrap();
+        assert_eq!(buf.lines().count(), 2_000);
+        assert_eq!(buf.len(), bytes_count);
+        for line in buf.lines() {
+            let line = line.unwrap();
+            let key = line.split(' ').nth(1).unwrap();
+            let uuid = uuid::Uuid::try_parse(key).unwrap();
+            assert_eq!(uuid.get_version(), Some(uuid::Version::Random));
+            assert_eq!(uuid.hyphenated().to_string(), key);
+        }
+    }
+
+    #[test]
+    fn custom_charset_key_space() {
+        let spec_str = r#"{"sections": [{"key_space": {"custom": {"charset": "ACGT"}}, "groups": [
+            {"inserts": {"amount": 1000, "key_len": 12, "val_len": 8}},
+            {"empty_point_queries": {"amount": 100, "key_len": 12}}
+        ]}]}"#;
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let mut buf = Vec::new();
+        write_operations(&mut buf, &spec).unwrap();
+        assert_eq!(buf.lines().count(), 1100);
+        for line in buf.lines() {
+            let line = line.unwrap();
+            let key = line.split(' ').nth(1).unwrap();
+            assert_eq!(key.len(), 12);
+            assert!(key.chars().all(|c| "ACGT".contains(c)), "{key}");
         }
 
-        fn len(&self) -> usize {
-            return self.keys.len();
+        for charset in ["", "ÄCGT", "AC\\nGT", "AC\\tGT", "AC\\u0000GT"] {
+            let spec_str = spec_str.replace("ACGT", charset);
+            let spec = serde_json::from_str::<WorkloadSpec>(&spec_str).unwrap();
+            let err = write_operations(&mut sink(), &spec).unwrap_err();
+            assert!(matches!(err, WorkloadError::InvalidSpec(_)), "{err}");
         }
 
-        fn is_empty(&self) -> bool {
-            return self.keys.is_empty();
+        // A `+` could start the end key of a range query, which then reads as a count.
+        let with_plus = spec_str.replace("ACGT", "0123+");
+        let spec = serde_json::from_str::<WorkloadSpec>(&with_plus).unwrap();
+        write_operations(&mut sink(), &spec).unwrap();
+        let range_queries = with_plus.replace(
+            "{\"empty_point_queries\": {\"amount\": 100, \"key_len\": 12}}",
+            "{\"range_queries\": {\"amount\": 100, \"selectivity\": 0.01}}",
+        );
+        let spec = serde_json::from_str::<WorkloadSpec>(&range_queries).unwrap();
+        let err = write_operations(&mut sink(), &spec).unwrap_err();
+        assert!(matches!(err, WorkloadError::InvalidSpec(_)), "{err}");
+    }
+
+    #[test]
+    fn numeric_range_key_space() {
+        let spec_str = r#"{"sections": [{"key_space": {"numeric_range": {"min": 40, "max": 139}}, "groups": [
+            {"inserts": {"amount": 1000, "key_len": 5, "val_len": 8}},
+            {"updates": {"amount": 500, "val_len": 8}}
+        ]}]}"#;
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let mut buf = Vec::new();
+        write_operations(&mut buf, &spec).unwrap();
+        assert_eq!(buf.lines().count(), 1500);
+        let mut keys = HashSet::new();
+        for line in buf.lines() {
+            let line = line.unwrap();
+            let key = line.split(' ').nth(1).unwrap();
+            assert_eq!(key.len(), 5);
+            assert!((40..=139).contains(&key.parse::<u64>().unwrap()), "{key}");
+            keys.insert(key.to_string());
         }
+        // 1000 inserts into 100 keys must collide.
+        assert_eq!(keys.len(), 100);
 
-        fn push(&mut self, key: Key) {
-            if self.sorted && self.keys.last().is_some_and(|last_key| last_key > &key) {
-                self.sorted = false;
-            }
-            self.bf.insert(&key);
-            self.keys.push(key);
+        let distinct = spec_str.replacen(
+            "\"val_len\": 8}}",
+            "\"val_len\": 8, \"allow_duplicate_keys\": false}}",
+            1,
+        );
+        let spec = serde_json::from_str::<WorkloadSpec>(&distinct).unwrap();
+        let err = write_operations(&mut sink(), &spec).unwrap_err();
+        assert!(
+            err.to_string().contains("key space only has 100 keys"),
+            "{err}"
+        );
+
+        for invalid in [
+            spec_str.replace("\"key_len\": 5", "\"key_len\": 2"),
+            spec_str.replace("\"min\": 40", "\"min\": 140"),
+        ] {
+            let spec = serde_json::from_str::<WorkloadSpec>(&invalid).unwrap();
+            let err = write_operations(&mut sink(), &spec).unwrap_err();
+            assert!(matches!(err, WorkloadError::InvalidSpec(_)), "{err}");
         }
+    }
 
-        fn remove(&mut self, idx: usize) -> Key {
-            let key = self.keys.remove(idx);
-            self.bf.clear();
-            for k in &self.keys {
-                self.bf.insert(k);
+    #[test]
+    fn uuid_key_space_wrong_key_len() {
+        let spec_str = include_str!("../test_specs/uuid.json").replace("36", "16");
+        let spec = serde_json::from_str::<WorkloadSpec>(&spec_str).unwrap();
+        let err = write_operations(&mut sink(), &spec).unwrap_err();
+        assert!(err.to_string().contains("are 36 bytes long"));
+    }
+
+    #[test]
+    fn order_stat_key_set() {
+        use keyset::OrderStatKeySet;
+
+        let mut rng = RngAlgorithm::default().seed_from_u64(0);
+        let mut key_set = OrderStatKeySet::new(0);
+        let mut expected: Vec<Key> = Vec::new();
+        for i in 0..5_000 {
+            if i % 3 == 2 {
+                let idx = rng.random_range(0..expected.len());
+                assert_eq!(key_set.remove(idx), expected.remove(idx));
+            } else {
+                let key = gen_string(&mut rng, 2);
+                key_set.push(key.clone());
+                let idx = expected.partition_point(|k| k < &key);
+                expected.insert(idx, key);
             }
-            return key;
         }
 
-        fn get(&self, idx: usize) -> Option<&Key> {
-            return self.keys.get(idx);
+        assert_eq!(key_set.len(), expected.len());
+        for (idx, key) in expected.iter().enumerate() {
+            assert_eq!(key_set.get(idx), Some(key));
+            assert!(key_set.contains(key));
         }
+        assert_eq!(key_set.get(expected.len()), None);
+        assert!(!key_set.contains(&gen_string(&mut rng, 3)));
+    }
 
-        fn get_random(&self, rng: &mut Xoshiro256Plus) -> &Key {
-            return self
-                .keys
-                .get(rng.random_range(0..self.keys.len()))
-                .expect("KeySet to not be empty");
-        }
+    fn check_sample_random<K: KeySet>() {
+        use std::collections::HashSet;
 
-        fn contains(&self, key: &Key) -> bool {
-            return self.bf.contains(key);
+        let mut rng = RngAlgorithm::default().seed_from_u64(0);
+        let mut key_set = K::new(1_000);
+        for _ in 0..1_000 {
+            key_set.push(gen_string(&mut rng, 16));
         }
-
-        fn sort(&mut self) {
-            if !self.sorted {
-                self.keys.sort();
-                self.sorted = true;
-            }
+        for n in [0, 1, 100, 1_000, 2_000] {
+            let sample = key_set.sample_random(&mut rng, n);
+            assert_eq!(sample.len(), n.min(1_000));
+            let distinct: HashSet<&Key> = sample.iter().copied().collect();
+            assert_eq!(distinct.len(), sample.len());
+            assert!(sample.iter().all(|key| key_set.contains(key)));
         }
     }
 
-    pub struct VecHashMapIndexKeySet {
-        keys: Vec<Key>,
-        key_to_index: HashMap<Key, usize>,
-        sorted: bool,
+    #[test]
+    fn sample_random_keys() {
+        check_sample_random::<keyset::VecKeySet>();
+        check_sample_random::<keyset::VecHashSetKeySet>();
+        check_sample_random::<keyset::OrderStatKeySet>();
     }
 
-    impl KeySet for VecHashMapIndexKeySet {
-        fn new(capacity: usize) -> Self {
-            return Self {
-                keys: Vec::with_capacity(capacity),
-                key_to_index: HashMap::with_capacity(capacity),
-                sorted: true,
-            };
+    /// Checks the memory estimate of an exact key set grows with pushes and drops with removes.
+    fn check_memory_bytes<K: KeySet>() {
+        let mut rng = RngAlgorithm::default().seed_from_u64(0);
+        let mut key_set = K::new(0);
+        let empty = key_set.memory_bytes();
+        for _ in 0..10_000 {
+            key_set.push(keygen::NumericKeyGen.generate(&mut rng, 16));
         }
-
-        fn len(&self) -> usize {
-            return self.keys.len();
+        assert!(key_set.capacity() >= key_set.len());
+        let full = key_set.memory_bytes();
+        // At least the bytes of the keys themselves.
+        assert!(full >= empty + 10_000 * 16);
+        for _ in 0..5_000 {
+            key_set.remove(rng.random_range(0..key_set.len()));
         }
+        let half = key_set.memory_bytes();
+        assert!(half < full);
+        key_set.shrink_to_fit();
+        assert!(key_set.memory_bytes() <= half);
+        assert!(key_set.capacity() >= key_set.len());
+    }
 
-        fn is_empty(&self) -> bool {
-            return self.keys.is_empty();
-        }
+    #[test]
+    fn key_set_memory_bytes() {
+        check_memory_bytes::<keyset::VecKeySet>();
+        check_memory_bytes::<keyset::VecHashSetKeySet>();
+        check_memory_bytes::<keyset::VecHashMapIndexKeySet>();
+        check_memory_bytes::<keyset::RoaringKeySet>();
+        check_memory_bytes::<keyset::OrderStatKeySet>();
+    }
 
-        fn push(&mut self, key: Key) {
-            if !self.key_to_index.contains_key(&key) {
-                self.key_to_index.insert(key.clone(), self.keys.len());
-                self.keys.push(key);
+    #[test]
+    fn bloom_filter_fp_rate() {
+        use keyset::VecBloomFilterKeySet;
+
+        const KEYS: usize = 10_000;
+        const PROBES: usize = 100_000;
+        let mut rng = RngAlgorithm::default().seed_from_u64(0);
+        for fp_rate in [0.1, 0.01] {
+            let mut key_set = VecBloomFilterKeySet::new_with_rate(KEYS, fp_rate);
+            for _ in 0..KEYS {
+                key_set.push(gen_string(&mut rng, 16));
             }
+            // Probes are a different length, so they are never in the set.
+            let false_positives = (0..PROBES)
+                .filter(|_| key_set.contains(&gen_string(&mut rng, 17)))
+                .count();
+            let empirical_rate = false_positives as f32 / PROBES as f32;
+            assert!(
+                empirical_rate < fp_rate * 1.5,
+                "configured {fp_rate}, measured {empirical_rate}"
+            );
         }
+    }
 
-        fn remove(&mut self, idx: usize) -> Key {
-            assert!(idx < self.keys.len());
+    #[test]
+    fn bloom_filter_fp_rate_out_of_range() {
+        use spec::{EmptyPointQueries, Inserts, WorkloadSpecGroup};
 
-            // Swap with last, pop, and update hashmap
-            let swap_idx = self.keys.len() - 1;
-            self.keys.swap(idx, swap_idx);
-            let removed = self.keys.pop().unwrap();
-            self.key_to_index.remove(&removed);
+        for fp_rate in [0.0, 1.0, -0.1, f64::NAN] {
+            let spec = WorkloadSpec::builder()
+                .bloom_filter_fp_rate(fp_rate)
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .inserts(Inserts::new(10, 8, 8))
+                        .empty_point_queries(EmptyPointQueries::new(10, 8)),
+                )
+                .build();
+            let err = generate_to_vec(&spec).unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains("bloom_filter_fp_rate must be between 0 and 1"),
+                "{fp_rate}: {err}"
+            );
+        }
+    }
 
-            // Update index of swapped element if necessary
-            if idx < self.keys.len() {
-                let swapped_key = &self.keys[idx];
-                self.key_to_index.insert(swapped_key.clone(), idx);
-            }
+    #[test]
+    fn cuckoo_filter() {
+        use keyset::VecCuckooFilterKeySet;
+        use spec::{Deletes, EmptyPointQueries, Inserts, WorkloadSpecGroup};
 
-            return removed;
+        // Starts small, so the filter is rebuilt as it grows.
+        let mut rng = RngAlgorithm::default().seed_from_u64(0);
+        let mut key_set = VecCuckooFilterKeySet::new(100);
+        let keys: Vec<Key> = (0..10_000).map(|_| gen_string(&mut rng, 16)).collect();
+        for key in &keys {
+            key_set.push(key.clone());
         }
-
-        fn get(&self, idx: usize) -> Option<&Key> {
-            return self.keys.get(idx);
+        assert!(keys.iter().all(|key| key_set.contains(key)));
+        for _ in 0..5_000 {
+            key_set.remove(rng.random_range(0..key_set.len()));
         }
+        let valid: HashSet<&Key> = (0..key_set.len()).filter_map(|i| key_set.get(i)).collect();
+        assert!(valid.iter().all(|key| key_set.contains(key)));
+        // Deleted keys and probes of a different length are only contained by false positives.
+        let deleted = keys.iter().filter(|key| !valid.contains(key));
+        assert!(deleted.filter(|key| key_set.contains(key)).count() < 5);
+        let false_positives = (0..100_000)
+            .filter(|_| key_set.contains(&gen_string(&mut rng, 17)))
+            .count();
+        assert!(false_positives < 50, "{false_positives} false positives");
 
-        fn get_random(&self, rng: &mut Xoshiro256Plus) -> &Key {
-            let idx = rng.random_range(0..self.keys.len());
-            return &self.keys[idx];
+        // Copies beyond the 8 fingerprints that fit in a key's buckets are still contained until
+        // the last one is removed.
+        let mut key_set = VecCuckooFilterKeySet::new(100);
+        let key = gen_string(&mut rng, 16);
+        for _ in 0..20 {
+            key_set.push(key.clone());
         }
-
-        fn contains(&self, key: &Key) -> bool {
-            return self.key_to_index.contains_key(key);
+        for copies in (1..20).rev() {
+            key_set.remove(0);
+            assert_eq!(key_set.len(), copies);
+            assert!(key_set.contains(&key), "{copies} copies left");
         }
+        key_set.remove(0);
+        assert!(!key_set.contains(&key));
 
-        fn sort(&mut self) {
-            self.keys.sort();
-            self.key_to_index.clear();
-            for (i, key) in self.keys.iter().enumerate() {
-                self.key_to_index.insert(key.clone(), i);
-            }
-        }
+        let spec = |bloom_filter: bool| {
+            let builder = WorkloadSpec::builder().cuckoo_filter(true);
+            let builder = if bloom_filter {
+                builder.bloom_filter_fp_rate(0.01)
+            } else {
+                builder
+            };
+            builder
+                .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 8, 8)))
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .deletes(Deletes { amount: 500 })
+                        .empty_point_queries(EmptyPointQueries::new(500, 8)),
+                )
+                .build()
+        };
+        let spec_ok = spec(false);
+        assert_eq!(
+            KeySetKind::select(&spec_ok.sections[0]),
+            KeySetKind::VecCuckooFilter
+        );
+        let buf = generate_to_vec(&spec_ok).unwrap();
+        assert_eq!(buf.lines().count(), 2000);
+        let err = generate_to_vec(&spec(true)).unwrap_err();
+        assert!(matches!(err, WorkloadError::InvalidSpec(_)), "{err}");
     }
-}
-
-use crate::keyset::KeySet;
-pub use crate::schema::generate_workload_spec_schema;
-use crate::spec::WorkloadSpec;
 
-type Key = Box<[u8]>;
+    /// Pushes and removes keys from a tiny key space, so most keys are duplicates, checking that
+    /// every key stays contained until its last copy is removed. Approximate key sets may contain
+    /// keys without copies.
+    fn check_duplicate_keys<K: KeySet>(exact: bool) {
+        use std::collections::HashMap;
 
-struct AsciiWriter;
-impl AsciiWriter {
-    fn write_insert(w: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
-        w.write_all("I ".as_bytes())?;
-        w.write_all(key)?;
-        w.write_all(" ".as_bytes())?;
-        w.write_all(val)?;
-        w.write_all("\n".as_bytes())?;
-
-        return Ok(());
+        let mut rng = RngAlgorithm::default().seed_from_u64(0);
+        let mut key_set = K::new(12);
+        let mut expected: HashMap<Key, usize> = HashMap::new();
+        for _ in 0..2_000 {
+            // At most 12 keys, so counters of the counting bloom filter never saturate.
+            if key_set.len() == 12 || (!key_set.is_empty() && rng.random_bool(0.5)) {
+                let idx = rng.random_range(0..key_set.len());
+                let key = key_set.remove(idx);
+                *expected.get_mut(&key).unwrap() -= 1;
+            } else {
+                let key = keygen::NumericKeyGen.generate(&mut rng, 1);
+                key_set.push(key.clone());
+                *expected.entry(key).or_default() += 1;
+            }
+            assert_eq!(key_set.len(), expected.values().sum::<usize>());
+            for (key, &count) in &expected {
+                if exact || count > 0 {
+                    assert_eq!(
+                        key_set.contains(key),
+                        count > 0,
+                        "{key:?} has {count} copies"
+                    );
+                }
+            }
+        }
     }
-    fn write_update(w: &mut impl Write, key: &Key, val: &Key) -> Result<()> {
-        w.write_all("U ".as_bytes())?;
-        w.write_all(key)?;
-        w.write_all(" ".as_bytes())?;
-        w.write_all(val)?;
-        w.write_all("\n".as_bytes())?;
 
-        return Ok(());
+    #[test]
+    fn duplicate_keys_key_sets() {
+        check_duplicate_keys::<keyset::VecKeySet>(true);
+        check_duplicate_keys::<keyset::VecHashSetKeySet>(true);
+        check_duplicate_keys::<keyset::VecBloomFilterKeySet>(false);
+        check_duplicate_keys::<keyset::VecCuckooFilterKeySet>(false);
+        check_duplicate_keys::<keyset::VecHashMapIndexKeySet>(true);
+        check_duplicate_keys::<keyset::RoaringKeySet>(true);
+        check_duplicate_keys::<keyset::OrderStatKeySet>(true);
     }
-    fn write_delete(w: &mut impl Write, key: &Key) -> Result<()> {
-        w.write_all("D ".as_bytes())?;
-        w.write_all(key)?;
-        w.write_all("\n".as_bytes())?;
 
-        return Ok(());
+    #[test]
+    fn duplicate_keys_disallowed() {
+        let spec_str = include_str!("../test_specs/duplicate_keys.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let mut buf = Vec::new();
+        write_operations(&mut buf, &spec).unwrap();
+        let keys: HashSet<String> = buf
+            .lines()
+            .map(|line| line.unwrap().split(' ').nth(1).unwrap().to_string())
+            .collect();
+        assert_eq!(keys.len(), 100);
+
+        // Only 100 two digit keys exist, so a 101st insert can't find a fresh key.
+        let spec_str = spec_str.replace("\"amount\": 100", "\"amount\": 101");
+        let spec = serde_json::from_str::<WorkloadSpec>(&spec_str).unwrap();
+        let err = write_operations(&mut sink(), &spec).unwrap_err();
+        assert!(err.to_string().contains("key space only has 100 keys"));
     }
-    fn write_point_query(w: &mut impl Write, key: &Key) -> Result<()> {
-        w.write_all("P ".as_bytes())?;
-        w.write_all(key)?;
-        w.write_all("\n".as_bytes())?;
 
-        return Ok(());
+    #[test]
+    fn empty_point_queries_numeric() {
+        let spec_str = include_str!("../test_specs/empty_point_queries_numeric.json");
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let bytes_count = spec.bytes_count();
+        let mut buf = Vec::with_capacity(bytes_count);
+        write_operations(&mut buf, &spec).unwrap();
+        assert_eq!(buf.lines().count(), 20_000);
+        assert_eq!(buf.len(), bytes_count);
+        for line in buf.lines() {
+            let line = line.unwrap();
+            let key = line.split(' ').nth(1).unwrap();
+            assert!(key.bytes().all(|b| b.is_ascii_digit()));
+        }
     }
-    fn write_range_query(w: &mut impl Write, key1: &Key, key2: &Key) -> Result<()> {
-        w.write_all("R ".as_bytes())?;
-        w.write_all(key1)?;
-        w.write_all(" ".as_bytes())?;
-        w.write_all(key2)?;
-        w.write_all("\n".as_bytes())?;
 
-        return Ok(());
+    #[test]
+    fn roaring_memory_bytes() {
+        use keyset::{RoaringKeySet, VecHashSetKeySet};
+
+        // The keys of `100k_i-100k_eqp_numeric.json`.
+        let mut rng = RngAlgorithm::default().seed_from_u64(0);
+        let mut roaring = RoaringKeySet::new(100_000);
+        let mut hash_set = VecHashSetKeySet::new(100_000);
+        for _ in 0..100_000 {
+            let key = keygen::NumericKeyGen.generate(&mut rng, 12);
+            roaring.push(key.clone());
+            hash_set.push(key);
+        }
+        assert!(roaring.memory_bytes() < hash_set.memory_bytes());
     }
-}
 
-#[derive(Debug, Copy, Clone, Eq, Ord, PartialOrd, PartialEq)]
-enum OpMarker {
-    Insert,
-    Update,
-    Delete,
-    PointQuery,
-    EmptyPointQuery,
-    RangeQuery,
-}
+    #[test]
+    fn write_phases() {
+        use spec::{Inserts, PointQueries, Updates, WorkloadSpecGroup};
 
-#[inline]
-fn gen_string(rng: &mut Xoshiro256Plus, len: usize) -> Key {
-    return rng.sample_iter(Alphanumeric).take(len).collect();
-}
+        for group_by_key in [false, true] {
+            let spec = WorkloadSpec::builder()
+                .seed(4)
+                .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(100, 8, 8)))
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .updates(Updates::new(50, 8))
+                        .point_queries(PointQueries::new(50)),
+                )
+                .add_section()
+                .group_by_key(group_by_key)
+                .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(20, 8, 8)))
+                .add_group(WorkloadSpecGroup::new().point_queries(PointQueries::new(30)))
+                .build();
+            let (mut load, mut run) = (Vec::new(), Vec::new());
+            let stats = write_phases_with_options(
+                &mut load,
+                &mut run,
+                &spec,
+                GenerateOptions::default(),
+                |_, _| {},
+            )
+            .unwrap();
+            assert_eq!(stats.operation_count(), 250);
 
-pub fn write_operations(mut writer: &mut impl Write, workload: &WorkloadSpec) -> Result<()> {
-    let mut rng = Xoshiro256Plus::from_os_rng();
+            let load: Vec<String> = load.lines().map(|line| line.unwrap()).collect();
+            let run: Vec<String> = run.lines().map(|line| line.unwrap()).collect();
+            assert!(load.iter().all(|line| line.starts_with("I ")));
+            assert_eq!(load.len() + run.len(), 250);
+            // Grouping by key moves the operations on a key right after its insert, so the load
+            // phase of the second section ends after its first insert.
+            let run_inserts = run.iter().filter(|line| line.starts_with("I ")).count();
+            let section_load = if group_by_key { 1 } else { 20 };
+            assert_eq!(load.len(), 100 + section_load);
+            assert_eq!(run_inserts, 20 - section_load);
+        }
+    }
 
-    for section in &workload.sections {
-        let mut keys_valid = keyset::VecBloomFilterKeySet::new(section.insert_count());
+    #[test]
+    fn reinsert_ratio() {
+        use spec::{Deletes, Inserts, WorkloadSpecGroup};
 
-        for group in &section.groups {
-            let rng_ref = &mut rng;
-            let mut markers: Vec<OpMarker> = Vec::with_capacity(group.operation_count());
+        let spec = |reinsert_ratio: f32| {
+            return WorkloadSpec::builder()
+                .seed(9)
+                .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 16, 8)))
+                .add_group(WorkloadSpecGroup::new().deletes(Deletes::new(200)))
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .inserts(Inserts::new(100, 16, 8).reinsert_ratio(reinsert_ratio)),
+                )
+                .build();
+        };
+        for (reinsert_ratio, reinserts) in [(0.0, 0), (0.5, 50), (1.0, 100)] {
+            let mut buf = Vec::new();
+            let stats = write_operations_with_options(
+                &mut buf,
+                &spec(reinsert_ratio),
+                GenerateOptions::default(),
+                |_, _| {},
+            )
+            .unwrap();
+            assert_eq!(stats.inserts, 1100);
+            assert_eq!(stats.live_keys, 900);
+            assert_eq!(stats.distinct_keys, 1100 - reinserts);
 
-            if let Some(ds) = group.deletes {
-                if ds.amount > keys_valid.len() {
-                    bail!("Cannot have more deletes than existing valid keys.");
+            let mut deleted = HashSet::new();
+            let mut reinserted = 0;
+            for line in buf.lines().map(|line| line.unwrap()) {
+                let mut fields = line.split(' ');
+                let (op, key) = (fields.next().unwrap(), fields.next().unwrap().to_string());
+                match op {
+                    "D" => assert!(deleted.insert(key)),
+                    "I" if deleted.remove(&key) => reinserted += 1,
+                    _ => {}
                 }
             }
+            assert_eq!(reinserted, reinserts);
+        }
 
-            // A group must have at least 1 valid key before any other operation can occur.
-            // TODO: handle empty point queries
-            if (group.inserts.is_some()
-                || group.updates.is_some()
-                || group.deletes.is_some()
-                || group.point_queries.is_some()
-                || group.range_queries.is_some())
-                && keys_valid.is_empty()
-            {
-                if let Some(is) = group.inserts {
-                    markers.append(&mut vec![OpMarker::Insert; is.amount - 1]);
+        // At most `max_tracked_keys` deleted keys are held back for reinserts, and the collected
+        // keys hold every reinserted key once.
+        let group = || {
+            return WorkloadSpecGroup::new()
+                .inserts(Inserts::new(40, 16, 8))
+                .deletes(Deletes::new(40));
+        };
+        let spec = WorkloadSpec::builder()
+            .seed(9)
+            .max_tracked_keys(50)
+            .add_group(group())
+            .add_group(group())
+            .add_group(
+                WorkloadSpecGroup::new().inserts(Inserts::new(100, 16, 8).reinsert_ratio(1.0)),
+            )
+            .build();
+        let options = GenerateOptions {
+            collect_keys: true,
+            ..GenerateOptions::default()
+        };
+        let stats = write_operations_with_options(&mut sink(), &spec, options, |_, _| {}).unwrap();
+        assert_eq!(stats.distinct_keys, 130);
+        assert_eq!(stats.keys.len(), 130);
+    }
 
-                    let key = gen_string(rng_ref, is.key_len);
-                    let val = gen_string(rng_ref, is.val_len);
-                    AsciiWriter::write_insert(&mut writer, &key, &val)?;
-                    keys_valid.push(key);
-                } else {
-                    eprintln!("{workload:#?}");
-                    bail!("Invalid workload spec. Group must have existing valid keys or have insert operations.");
-                }
-            } else if let Some(is) = group.inserts {
-                markers.append(&mut vec![OpMarker::Insert; is.amount]);
-            }
+    #[test]
+    fn mmap_output() {
+        let dir = std::env::temp_dir().join(format!("workload-gen-mmap-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let generate = |spec_str: &str, mmap: bool, max_ops: Option<usize>| {
+            let path = dir.join(format!("{mmap}.txt"));
+            let options = GenerateOptions {
+                mmap,
+                max_ops,
+                ..GenerateOptions::default()
+            };
+            generate_workload_with_progress(spec_str, path.clone(), options, |_, _| {}).unwrap();
+            return std::fs::read(path).unwrap();
+        };
 
-            if let Some(us) = group.updates {
-                markers.append(&mut vec![OpMarker::Update; us.amount]);
-            }
-            if let Some(ds) = group.deletes {
-                markers.append(&mut vec![OpMarker::Delete; ds.amount]);
-            }
-            if let Some(pqs) = group.point_queries {
-                markers.append(&mut vec![OpMarker::PointQuery; pqs.amount]);
-            }
-            if let Some(epqs) = group.empty_point_queries {
-                markers.append(&mut vec![OpMarker::EmptyPointQuery; epqs.amount]);
-            }
-            if let Some(rqs) = group.range_queries {
-                markers.append(&mut vec![OpMarker::RangeQuery; rqs.amount]);
-            }
+        let spec_str = r#"{ "seed": 1, "sections": [{ "groups": [
+            { "inserts": { "amount": 1000, "key_len": 8, "val_len": 16 } },
+            { "updates": { "amount": 500, "val_len": 4 }, "point_queries": { "amount": 500 } },
+            { "count_range_queries": { "amount": 100, "count": 10 } }
+        ] }] }"#;
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        assert!(spec.bytes_count_is_exact());
+        let buffered = generate(spec_str, false, None);
+        assert_eq!(buffered.len(), spec.bytes_count());
+        assert_eq!(generate(spec_str, true, None), buffered);
+        // Stopping early truncates the file to the bytes written.
+        assert_eq!(
+            generate(spec_str, true, Some(10)),
+            generate(spec_str, false, Some(10))
+        );
 
-            for marker in markers.iter() {
-                match marker {
-                    OpMarker::Insert => {
-                        let is = group
-                            .inserts
-                            .context("Insert marker can only appear when inserts is not None")?;
-                        let key = gen_string(rng_ref, is.key_len);
-                        let val = gen_string(rng_ref, is.val_len);
-                        AsciiWriter::write_insert(writer, &key, &val)?;
-                        keys_valid.push(key);
-                    }
-                    OpMarker::Update => {
-                        let us = group
-                            .updates
-                            .context("Update marker can only appear when updates is not None")?;
-                        let key = keys_valid.get_random(rng_ref);
-                        let val = gen_string(rng_ref, us.val_len);
+        // Sampled value lengths fall back to buffered writes.
+        let spec_str = r#"{ "seed": 1, "sections": [{ "groups": [
+            { "inserts": { "amount": 1000, "key_len": 8, "val_len": { "exponential": { "mean": 100, "max": 10000 } } } }
+        ] }] }"#;
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        assert!(!spec.bytes_count_is_exact());
+        let options = GenerateOptions {
+            mmap: true,
+            ..GenerateOptions::default()
+        };
+        assert_eq!(options.mmap_len(&spec), None);
+        assert_eq!(
+            generate(spec_str, true, None),
+            generate(spec_str, false, None)
+        );
 
-                        AsciiWriter::write_update(writer, key, &val)?;
-                    }
-                    OpMarker::Delete => {
-                        let idx = rng_ref.random_range(0..keys_valid.len());
-                        let key = keys_valid.remove(idx);
+        // Neither an invalid spec nor a failed workload leaves a file behind.
+        let path = dir.join("failed.txt");
+        for spec_str in [
+            r#"{ "sections": [{ "groups": [
+                { "inserts": { "amount": 10, "key_len": 8, "val_len": 8, "overwrite_ratio": 2.0 } }
+            ] }] }"#,
+            r#"{ "sections": [{ "groups": [{ "point_queries": { "amount": 10 } }] }] }"#,
+        ] {
+            let options = GenerateOptions {
+                mmap: true,
+                ..GenerateOptions::default()
+            };
+            generate_workload_with_progress(spec_str, path.clone(), options, |_, _| {})
+                .unwrap_err();
+            assert!(!path.exists());
+        }
 
-                        AsciiWriter::write_delete(writer, &key)?;
-                    }
-                    OpMarker::PointQuery => {
-                        let key = keys_valid
-                            .get(rng_ref.random_range(0..keys_valid.len()))
-                            .unwrap();
-                        AsciiWriter::write_point_query(writer, key)?
-                    }
-                    OpMarker::EmptyPointQuery => {
-                        let epq = group.empty_point_queries.context(
-                            "EmptyPointQuery marker can only appear when point_queries is not None",
-                        )?;
-                        let key = loop {
-                            let key = gen_string(rng_ref, epq.key_len);
-                            if !keys_valid.contains(&key) {
-                                break key;
-                            }
-                        };
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 
-                        AsciiWriter::write_point_query(writer, &key)?
-                    }
-                    OpMarker::RangeQuery => {
-                        let rs = group.range_queries.context(
-                            "RangeQuery marker can only appear when range_queries is not None",
-                        )?;
+    #[test]
+    fn key_set_estimates() {
+        use keyset::{
+            OrderStatKeySet, RoaringKeySet, VecBloomFilterKeySet, VecCuckooFilterKeySet,
+            VecHashSetKeySet, VecKeySet,
+        };
+        use spec::{Deletes, Inserts, KeySpace, PointQueries, WorkloadSpecGroup};
+
+        // The peak follows the inserts and deletes of every group, and the last group ends at it.
+        let spec = WorkloadSpec::builder()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 16, 8)))
+            .add_group(WorkloadSpecGroup::new().deletes(Deletes::new(300)))
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(Inserts::new(500, 16, 8))
+                    .point_queries(PointQueries::new(100)),
+            )
+            .add_section()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 16, 8)))
+            .add_group(WorkloadSpecGroup::new().deletes(Deletes::new(600)))
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(100, 16, 8)))
+            .build();
+        assert_eq!(spec.peak_live_keys(), vec![1200, 1000]);
+        let stats = write_operations_with_options(
+            &mut sink(),
+            &spec,
+            GenerateOptions::default(),
+            |_, _| {},
+        )
+        .unwrap();
+        assert_eq!(stats.live_keys, 1200 + 500);
+
+        fn memory_bytes<K: KeySet>(keys: &[Key]) -> usize {
+            let mut key_set = K::new(keys.len());
+            for key in keys {
+                key_set.push(key.clone());
+            }
+            return key_set.memory_bytes();
+        }
+        let spec = WorkloadSpec::builder()
+            .key_space(KeySpace::Numeric)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(20_000, 12, 8)))
+            .build();
+        let options = GenerateOptions {
+            collect_keys: true,
+            ..GenerateOptions::default()
+        };
+        let keys = write_operations_with_options(&mut sink(), &spec, options, |_, _| {})
+            .unwrap()
+            .keys;
+        assert_eq!(keys.len(), 20_000);
+        for kind in KeySetKind::ALL {
+            let actual = match kind {
+                KeySetKind::Vec => memory_bytes::<VecKeySet>(&keys),
+                KeySetKind::VecHashSet => memory_bytes::<VecHashSetKeySet>(&keys),
+                KeySetKind::VecBloomFilter => memory_bytes::<VecBloomFilterKeySet>(&keys),
+                KeySetKind::VecCuckooFilter => memory_bytes::<VecCuckooFilterKeySet>(&keys),
+                KeySetKind::Roaring => memory_bytes::<RoaringKeySet>(&keys),
+                KeySetKind::OrderStat => memory_bytes::<OrderStatKeySet>(&keys),
+            };
+            let estimate = spec.key_set_memory_bytes(kind)[0];
+            let error = estimate.abs_diff(actual) as f64 / actual as f64;
+            assert!(
+                error < 0.1,
+                "{kind:?}: estimate {estimate}, actual {actual}"
+            );
+        }
 
-                        keys_valid.sort();
-                        // It would be better to use `from` and `try_from` instead of `as` here.
-                        // Maybe the `num_traits` crate could help.
-                        // https://doc.rust-lang.org/reference/expressions/operator-expr.html#r-expr.as.numeric.float-as-int
-                        let num_items =
-                            (rs.selectivity * (keys_valid.len() as f32).floor()) as usize;
-                        let start_range = 0..keys_valid.len() - num_items;
+        // A numeric range packs its keys into a few roaring containers.
+        let spec = WorkloadSpec::builder()
+            .seed(1)
+            .key_space(KeySpace::NumericRange {
+                min: 0,
+                max: 39_999,
+            })
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(Inserts::new(20_000, 12, 8).allow_duplicate_keys(false)),
+            )
+            .build();
+        let keys = write_operations_with_options(&mut sink(), &spec, options, |_, _| {})
+            .unwrap()
+            .keys;
+        let actual = memory_bytes::<RoaringKeySet>(&keys);
+        let estimate = spec.key_set_memory_bytes(KeySetKind::Roaring)[0];
+        let error = estimate.abs_diff(actual) as f64 / actual as f64;
+        assert!(error < 0.02, "estimate {estimate}, actual {actual}");
 
-                        let start_idx = rng_ref.random_range(start_range);
-                        let key1 = &keys_valid.get(start_idx).expect("index to be in range");
-                        let key2 = &keys_valid
-                            .get(start_idx + num_items)
-                            .expect("index to be in range");
+        // Keys repeating more often than the cuckoo filter holds fill its saturated map.
+        let spec = WorkloadSpec::builder()
+            .key_space(KeySpace::Numeric)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(100, 1, 8)))
+            .build();
+        let keys: Vec<Key> = (0..100)
+            .map(|i| (i % 10).to_string().into_bytes().into())
+            .collect();
+        let actual = memory_bytes::<VecCuckooFilterKeySet>(&keys);
+        let estimate = spec.key_set_memory_bytes(KeySetKind::VecCuckooFilter)[0];
+        let error = estimate.abs_diff(actual) as f64 / actual as f64;
+        assert!(error < 0.02, "estimate {estimate}, actual {actual}");
+    }
 
-                        AsciiWriter::write_range_query(writer, key1, key2)?
-                    }
+    #[test]
+    fn separators_in_keys() {
+        use spec::{Inserts, KeySpace, PointQueries, WorkloadSpecGroup};
+
+        // A charset of `a`, a space, and a backslash.
+        let spec = WorkloadSpec::builder()
+            .key_space(KeySpace::Custom {
+                charset: "a \\".to_string(),
+            })
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(100, 8, 8)))
+            .add_group(WorkloadSpecGroup::new().point_queries(PointQueries::new(100)))
+            .build();
+        // Fails before writing anything.
+        let mut buf = Vec::new();
+        let err = write_operations(&mut buf, &spec).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("keys of section 0 can contain the ascii separator ' '"),
+            "{err}"
+        );
+        assert!(buf.is_empty());
+        // So do prefixes and column families with a separator.
+        let tabs = GenerateOptions {
+            ascii_format: AsciiFormat {
+                field_sep: b'\t',
+                ..AsciiFormat::default()
+            },
+            ..GenerateOptions::default()
+        };
+        let spec_tab = WorkloadSpec::builder()
+            .key_prefix("tenant\t")
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(100, 8, 8)))
+            .build();
+        let err =
+            write_operations_with_options(&mut sink(), &spec_tab, tabs, |_, _| {}).unwrap_err();
+        assert!(err.to_string().contains("key_prefix of section 0"), "{err}");
+        write_operations_with_options(&mut sink(), &spec, tabs, |_, _| {}).unwrap();
+
+        let options = GenerateOptions {
+            ascii_format: AsciiFormat {
+                escaping: FieldEscaping::Backslash,
+                ..AsciiFormat::default()
+            },
+            ..GenerateOptions::default()
+        };
+        let mut buf = Vec::new();
+        write_operations_with_options(&mut buf, &spec, options, |_, _| {}).unwrap();
+        for line in buf.lines().map(|line| line.unwrap()) {
+            // Splits at unescaped spaces, dropping the escaping backslashes.
+            let mut fields = vec![String::new()];
+            let mut chars = line.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => fields.last_mut().unwrap().push(chars.next().unwrap()),
+                    ' ' => fields.push(String::new()),
+                    c => fields.last_mut().unwrap().push(c),
                 }
             }
+            let expected_fields = if fields[0] == "I" { 3 } else { 2 };
+            assert_eq!(fields.len(), expected_fields, "{line}");
+            assert_eq!(fields[1].len(), 8, "{line}");
+            assert!(fields[1].chars().all(|c| "a \\".contains(c)));
         }
     }
 
-    return Ok(());
-}
+    #[test]
+    fn blind_inserts_untracked() {
+        use spec::{Inserts, PointQueries, WorkloadSpecGroup};
 
-/// Takes in a JSON representation of a workload specification and writes the workload to a file.
-pub fn generate_workload(workload_spec_string: &str, output_file: PathBuf) -> Result<()> {
-    let workload_spec: WorkloadSpec =
-        serde_json::from_str(workload_spec_string).context("parsing json file")?;
-    let mut buf_writer = BufWriter::with_capacity(1024 * 1024, File::create(output_file)?);
-    write_operations(&mut buf_writer, &workload_spec)?;
-    buf_writer.flush()?;
+        let spec = WorkloadSpec::builder()
+            .seed(3)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 4, 8)))
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(500, 4, 8).sorted(true)))
+            .add_section()
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 16, 8)))
+            .add_group(WorkloadSpecGroup::new().point_queries(PointQueries::new(100)))
+            .build();
+        assert!(!spec.sections[0].needs_valid_keys());
+        assert!(spec.sections[1].needs_valid_keys());
 
-    Ok(())
-}
+        // Collecting the keys tracks them, which must not change the workload or its counts.
+        let generate = |collect_keys| {
+            let options = GenerateOptions {
+                collect_keys,
+                ..GenerateOptions::default()
+            };
+            let mut buf = Vec::new();
+            let stats = write_operations_with_options(&mut buf, &spec, options, |_, _| {}).unwrap();
+            return (buf, stats);
+        };
+        let (untracked, untracked_stats) = generate(false);
+        let (tracked, tracked_stats) = generate(true);
+        assert_eq!(untracked, tracked);
+        assert_eq!(untracked_stats.live_keys, 2500);
+        assert_eq!(untracked_stats.live_keys, tracked_stats.live_keys);
+        assert_eq!(untracked_stats.distinct_keys, tracked_stats.distinct_keys);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::BufRead;
+    #[test]
+    fn deterministic_keys() {
+        use spec::{Inserts, PointQueries, WorkloadSpecGroup};
+
+        // The sections draw differently from their rngs, with other values and point queries.
+        let spec = |deterministic_keys| {
+            WorkloadSpec::builder()
+                .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(500, 16, 8)))
+                .section_seed(3)
+                .deterministic_keys(deterministic_keys)
+                .add_section()
+                .add_group(
+                    WorkloadSpecGroup::new()
+                        .inserts(Inserts::new(200, 16, 32))
+                        .point_queries(PointQueries::new(300)),
+                )
+                .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(300, 16, 4)))
+                .section_seed(3)
+                .deterministic_keys(deterministic_keys)
+                .build()
+        };
+        let insert_keys = |spec: &WorkloadSpec| -> (Vec<Key>, Vec<Key>) {
+            let keys: Vec<Key> = iter_operations(spec)
+                .filter_map(|op| match op.unwrap() {
+                    Operation::Insert { key, .. } => Some(key),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(keys.len(), 1000);
+            return (keys[..500].to_vec(), keys[500..].to_vec());
+        };
+
+        let (first, second) = insert_keys(&spec(true));
+        assert_eq!(first, second);
+        assert_eq!((first, second), insert_keys(&spec(true)));
+        let (first, second) = insert_keys(&spec(false));
+        assert_ne!(first, second);
+    }
 
     #[test]
-    fn workload_1m_i() {
-        let spec_str = include_str!("../test_specs/1m_i.json");
-        let spec = serde_json::from_str::<WorkloadSpec>(&spec_str).unwrap();
-        let bytes_count = spec.bytes_count();
-        let mut buf = Vec::with_capacity(bytes_count);
-        write_operations(&mut buf, &spec).unwrap();
-        assert_eq!(buf.lines().count(), 1_000_000);
-        assert_eq!(buf.len(), bytes_count);
+    fn throttled() {
+        use spec::{Inserts, PointQueries, WorkloadSpecGroup};
+        use std::time::{Duration, Instant};
+
+        let spec = WorkloadSpec::builder()
+            .seed(2)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(500, 8, 8)))
+            .add_group(WorkloadSpecGroup::new().point_queries(PointQueries::new(500)))
+            .build();
+        let start = Instant::now();
+        let mut buf = Vec::new();
+        write_operations_throttled(&mut buf, &spec, 1000.0).unwrap();
+        let elapsed = start.elapsed();
+        // The last of 1000 operations is due after 999ms, give or take a skipped short sleep. A
+        // loaded machine only makes it later.
+        assert!(elapsed >= Duration::from_millis(990), "{elapsed:?}");
+        assert_eq!(buf, generate_to_vec(&spec).unwrap());
+
+        let err = write_operations_throttled(&mut Vec::new(), &spec, 0.0).unwrap_err();
+        assert!(matches!(err, WorkloadError::InvalidOpsPerSec(_)), "{err}");
     }
 
     #[test]
-    fn workload_1m_i_1m_rq() {
+    fn scaled_to() {
+        use spec::{Inserts, PointQueries, ReadModifyWrites, WorkloadSpecGroup};
+
         let spec_str = include_str!("../test_specs/1m_i-1m_rq.json");
-        let spec = serde_json::from_str::<WorkloadSpec>(&spec_str).unwrap();
-        let bytes_count = spec.bytes_count();
-        let mut buf = Vec::with_capacity(bytes_count);
-        write_operations(&mut buf, &spec).unwrap();
+        let spec = serde_json::from_str::<WorkloadSpec>(spec_str).unwrap();
+        let scaled = spec.scaled_to(100_000);
+        let counts = scaled.operation_counts();
+        assert_eq!(scaled.operation_count(), 100_000);
+        assert_eq!(counts.inserts, 50_000);
+        assert_eq!(counts.range_queries, 50_000);
+        let mut buf = Vec::new();
+        write_operations(&mut buf, &scaled).unwrap();
+        assert_eq!(buf.lines().count(), 100_000);
 
-        assert_eq!(buf.lines().count(), 2_000_000);
-        assert_eq!(buf.len(), bytes_count);
+        // Rounded amounts still add up, and the rare point queries keep one.
+        let spec = WorkloadSpec::builder()
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(Inserts::new(1000, 8, 8))
+                    .point_queries(PointQueries::new(1)),
+            )
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(333, 8, 8)))
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(333, 8, 8)))
+            .build();
+        for total in [7, 10, 101, 999, 12_345] {
+            let scaled = spec.scaled_to(total);
+            assert_eq!(scaled.operation_count(), total);
+            assert!(scaled.operation_counts().point_queries > 0);
+        }
+
+        // Read-modify-writes count as two operations.
+        let spec = WorkloadSpec::builder()
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .inserts(Inserts::new(100, 8, 8))
+                    .read_modify_writes(ReadModifyWrites::new(100, 8)),
+            )
+            .build();
+        let scaled = spec.scaled_to(31);
+        assert_eq!(scaled.operation_count(), 31);
+        assert!(scaled.operation_counts().updates > 0);
     }
 
     #[test]
-    fn deletes() {
-        let spec_str = include_str!("../test_specs/deletes.json");
-        let spec = serde_json::from_str::<WorkloadSpec>(&spec_str).unwrap();
-        let bytes_count = spec.bytes_count();
-        let mut buf = Vec::with_capacity(bytes_count);
-        write_operations(&mut buf, &spec).unwrap();
-        assert_eq!(buf.lines().count(), 1_100_000);
-        assert_eq!(buf.len(), bytes_count);
+    fn convert_round_trip() {
+        use spec::{
+            CountRangeQueries, Deletes, Flushes, Inserts, KeySpace, PointQueries, RangeDeletes,
+            RangeQueries, SeekQueries, ThinkTimes, Updates, WorkloadSpecGroup,
+        };
+        use writer::convert;
+
+        let spec = WorkloadSpec::builder()
+            .seed(5)
+            .warmup_point_queries(10)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(500, 8, 8)))
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .updates(Updates::new(50, 8))
+                    .deletes(Deletes { amount: 20 })
+                    .point_queries(PointQueries::new(50))
+                    .range_queries(RangeQueries::new(20, 0.01))
+                    .count_range_queries(CountRangeQueries::new(20, 5))
+                    .seek_queries(SeekQueries::new(20).hit_ratio(0.5))
+                    .range_deletes(RangeDeletes::new(5, 0.01))
+                    .think_times(ThinkTimes::new(10, 100))
+                    .flushes(Flushes::new(3)),
+            )
+            .build();
+        let ascii = generate_to_vec(&spec).unwrap();
+        let ascii_format = AsciiFormat::default();
+        let mut input = ascii.as_slice();
+        let mut msgpack = Vec::new();
+        let ops = convert(
+            &mut input,
+            OutputFormat::Ascii,
+            &mut msgpack,
+            OutputFormat::Msgpack,
+            ascii_format,
+        )
+        .unwrap();
+        assert_eq!(ops, spec.operation_count());
+        let mut jsonl = Vec::new();
+        convert(
+            &mut msgpack.as_slice(),
+            OutputFormat::Msgpack,
+            &mut jsonl,
+            OutputFormat::Jsonl,
+            ascii_format,
+        )
+        .unwrap();
+        let mut round_trip = Vec::new();
+        convert(
+            &mut jsonl.as_slice(),
+            OutputFormat::Jsonl,
+            &mut round_trip,
+            OutputFormat::Ascii,
+            ascii_format,
+        )
+        .unwrap();
+        // The warmup fences are comments, which every format keeps.
+        assert_eq!(round_trip, ascii);
+
+        // Escaped separators are unescaped by the reader and escaped again by the writer.
+        let spec = WorkloadSpec::builder()
+            .key_space(KeySpace::Custom {
+                charset: "a \\".to_string(),
+            })
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(100, 8, 8)))
+            .build();
+        let ascii_format = AsciiFormat {
+            escaping: FieldEscaping::Backslash,
+            record_sep: b' ',
+            field_sep: b'\t',
+        };
+        let options = GenerateOptions {
+            ascii_format,
+            ..GenerateOptions::default()
+        };
+        let mut ascii = Vec::new();
+        write_operations_with_options(&mut ascii, &spec, options, |_, _| {}).unwrap();
+        let mut msgpack = Vec::new();
+        let (from, to) = (OutputFormat::Ascii, OutputFormat::Msgpack);
+        convert(&mut ascii.as_slice(), from, &mut msgpack, to, ascii_format).unwrap();
+        let mut round_trip = Vec::new();
+        convert(
+            &mut msgpack.as_slice(),
+            to,
+            &mut round_trip,
+            from,
+            ascii_format,
+        )
+        .unwrap();
+        assert_eq!(round_trip, ascii);
+
+        let err = convert(
+            &mut "I a b\nX c\n".as_bytes(),
+            OutputFormat::Ascii,
+            &mut Vec::new(),
+            OutputFormat::Jsonl,
+            AsciiFormat::default(),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, WorkloadError::MalformedOperation { record: 2, .. }),
+            "{err}"
+        );
     }
 
     #[test]
-    fn empty_point_queries() {
-        let spec_str = include_str!("../test_specs/empty_point_queries.json");
-        let spec = serde_json::from_str::<WorkloadSpec>(&spec_str).unwrap();
-        let bytes_count = spec.bytes_count();
-        let mut buf = Vec::with_capacity(bytes_count);
-        write_operations(&mut buf, &spec).unwrap();
-        assert_eq!(buf.lines().count(), 101_000);
-        assert_eq!(buf.len(), bytes_count);
+    fn shards() {
+        use spec::{
+            Deletes, Flushes, Inserts, PointQueries, RangeQueries, SeekQueries, ThinkTimes,
+            Updates, WorkloadSpecGroup,
+        };
+        use writer::shard_of;
+
+        let spec = WorkloadSpec::builder()
+            .seed(9)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 8, 8)))
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .updates(Updates::new(100, 8))
+                    .deletes(Deletes { amount: 100 })
+                    .point_queries(PointQueries::new(100))
+                    .range_queries(RangeQueries::new(20, 0.01))
+                    .seek_queries(SeekQueries::new(10))
+                    .think_times(ThinkTimes::new(10, 100))
+                    .flushes(Flushes::new(4)),
+            )
+            .build();
+        for keyless_shard in [None, Some(1)] {
+            let mut shards = vec![Vec::new(); 3];
+            let options = GenerateOptions::default();
+            write_shards_with_options(&mut shards, &spec, options, keyless_shard, |_, _| {})
+                .unwrap();
+            let mut ops = 0;
+            let mut keyless = vec![0; 3];
+            let mut ranges = vec![0; 3];
+            let mut seeks = vec![0; 3];
+            for (idx, shard) in shards.iter().enumerate() {
+                for line in shard.lines().map(|line| line.unwrap()) {
+                    ops += 1;
+                    // Every operation on a key is in the key's shard, ranges and seeks are in
+                    // every shard.
+                    if line.starts_with("R ") {
+                        ranges[idx] += 1;
+                        continue;
+                    }
+                    if line.starts_with("SK ") {
+                        seeks[idx] += 1;
+                        continue;
+                    }
+                    match line.split(' ').nth(1).filter(|_| !line.starts_with("T ")) {
+                        Some(key) => assert_eq!(shard_of(key.as_bytes(), 3), idx, "{line}"),
+                        None => keyless[idx] += 1,
+                    }
+                }
+            }
+            assert_eq!(ranges, [20, 20, 20]);
+            assert_eq!(seeks, [10, 10, 10]);
+            match keyless_shard {
+                None => {
+                    assert_eq!(keyless, [14, 14, 14]);
+                    assert_eq!(ops, spec.operation_count() + 2 * (14 + 20 + 10));
+                }
+                Some(_) => {
+                    assert_eq!(keyless, [0, 14, 0]);
+                    assert_eq!(ops, spec.operation_count() + 2 * (20 + 10));
+                }
+            }
+            assert!(shards.iter().all(|shard| shard.len() > 1000));
+        }
+
+        let err = write_shards_with_options(
+            &mut [Vec::new()],
+            &spec,
+            GenerateOptions::default(),
+            Some(1),
+            |_, _| {},
+        )
+        .unwrap_err();
+        assert!(matches!(err, WorkloadError::InvalidShards { .. }), "{err}");
+    }
+
+    #[test]
+    fn shards_replay_like_unsharded() {
+        use spec::{Deletes, Inserts, RangeDeletes, Updates, WorkloadSpecGroup};
+
+        // Replays the writes of an ascii workload into the key-value state it leaves behind.
+        let replay = |buf: &[u8]| {
+            let mut state: BTreeMap<String, String> = BTreeMap::new();
+            for line in buf.lines().map(|line| line.unwrap()) {
+                let fields: Vec<&str> = line.split(' ').collect();
+                match fields[..] {
+                    ["I" | "U", key, val] => {
+                        state.insert(key.to_string(), val.to_string());
+                    }
+                    ["D", key] => {
+                        state.remove(key);
+                    }
+                    ["DR", start, end] => state.retain(|key, _| !(start..end).contains(&&**key)),
+                    _ => {}
+                }
+            }
+            return state;
+        };
+        let spec = WorkloadSpec::builder()
+            .seed(5)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 8, 8)))
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .updates(Updates::new(200, 8))
+                    .deletes(Deletes::new(100))
+                    .range_deletes(RangeDeletes::new(5, 0.05)),
+            )
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(200, 8, 8)))
+            .build();
+        let unsharded = replay(&generate_to_vec(&spec).unwrap());
+        assert!(unsharded.len() < 1000);
+
+        let mut shards = vec![Vec::new(); 4];
+        write_shards_with_options(
+            &mut shards,
+            &spec,
+            GenerateOptions::default(),
+            None,
+            |_, _| {},
+        )
+        .unwrap();
+        let mut sharded = BTreeMap::new();
+        for shard in &shards {
+            sharded.append(&mut replay(shard));
+        }
+        assert_eq!(sharded, unsharded);
+    }
+
+    #[test]
+    fn binary_keys() {
+        use spec::{Deletes, Inserts, KeySpace, PointQueries, RangeQueries, WorkloadSpecGroup};
+        use writer::{MsgpackReader, OpReader};
+
+        let spec = WorkloadSpec::builder()
+            .seed(4)
+            .key_space(KeySpace::Binary)
+            .add_group(WorkloadSpecGroup::new().inserts(Inserts::new(1000, 16, 8)))
+            .add_group(
+                WorkloadSpecGroup::new()
+                    .deletes(Deletes { amount: 100 })
+                    .point_queries(PointQueries::new(100))
+                    .range_queries(RangeQueries::new(10, 0.01)),
+            )
+            .build();
+        for format in [OutputFormat::Ascii, OutputFormat::Jsonl] {
+            let err = write_operations_with_format(&mut Vec::new(), &spec, format).unwrap_err();
+            assert!(matches!(err, WorkloadError::BinaryKeys { .. }), "{err}");
+        }
+
+        let mut msgpack = Vec::new();
+        write_operations_with_format(&mut msgpack, &spec, OutputFormat::Msgpack).unwrap();
+        let ops = Mutex::new(Vec::new());
+        let collector = OperationWriter {
+            emit: |op| {
+                ops.lock().unwrap().push(op);
+                return Ok(());
+            },
+        };
+        let read = MsgpackReader
+            .read_into(&mut msgpack.as_slice(), &collector, &mut io::sink())
+            .unwrap();
+        assert_eq!(read, spec.operation_count());
+        let ops = ops.into_inner().unwrap();
+        let expected: Vec<Operation> = iter_operations(&spec).map(|op| op.unwrap()).collect();
+        assert_eq!(ops, expected);
+        let keys: Vec<&Key> = ops.iter().filter_map(|op| op.key()).collect();
+        assert!(keys.iter().all(|key| key.len() == 16));
+        assert!(keys.iter().any(|key| std::str::from_utf8(key).is_err()));
     }
 }